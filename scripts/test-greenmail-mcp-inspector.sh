@@ -14,6 +14,7 @@ fi
 
 GREENMAIL_HOST="${GREENMAIL_HOST:-127.0.0.1}"
 GREENMAIL_SMTP_PORT="${GREENMAIL_SMTP_PORT:-4025}"
+GREENMAIL_SMTPS_PORT="${GREENMAIL_SMTPS_PORT:-4465}"
 GREENMAIL_IMAP_PORT="${GREENMAIL_IMAP_PORT:-4143}"
 GREENMAIL_USER="${GREENMAIL_USER:-test@localhost}"
 GREENMAIL_PASS="${GREENMAIL_PASS:-test}"
@@ -25,7 +26,11 @@ GREENMAIL_OPTS="${GREENMAIL_OPTS:-$GREENMAIL_OPTS_DEFAULT}"
 started_local_container=0
 GREENMAIL_TLS_DIR=""
 GREENMAIL_CA_CERT=""
+MAIL_EXPORT_DIR=""
 cleanup() {
+  if [[ -n "$MAIL_EXPORT_DIR" ]]; then
+    rm -rf "$MAIL_EXPORT_DIR" >/dev/null 2>&1 || true
+  fi
   if [[ -n "$GREENMAIL_TLS_DIR" ]]; then
     rm -rf "$GREENMAIL_TLS_DIR" >/dev/null 2>&1 || true
   fi
@@ -173,6 +178,7 @@ else
     -v "$GREENMAIL_PRELOAD_DIR:/greenmail-preload:ro" \
     -v "$GREENMAIL_TLS_DIR:/greenmail-tls:ro" \
     -p "$GREENMAIL_SMTP_PORT:3025" \
+    -p "$GREENMAIL_SMTPS_PORT:3465" \
     -p "$GREENMAIL_IMAP_PORT:3993" \
     "$IMAGE"
 
@@ -229,6 +235,14 @@ export MAIL_IMAP_DEFAULT_USER="$GREENMAIL_USER"
 export MAIL_IMAP_DEFAULT_PASS="$GREENMAIL_PASS"
 export MAIL_IMAP_CA_CERT_PATH="$GREENMAIL_CA_CERT"
 export MAIL_IMAP_WRITE_ENABLED="true"
+export MAIL_IMAP_DEFAULT_SMTP_HOST="$GREENMAIL_HOST"
+export MAIL_IMAP_DEFAULT_SMTP_PORT="$GREENMAIL_SMTPS_PORT"
+export MAIL_IMAP_DEFAULT_SMTP_SECURITY="tls"
+export MAIL_IMAP_SEND_ENABLED="true"
+MAIL_EXPORT_DIR="$(make_temp_dir mail-export)"
+export MAIL_IMAP_EXPORT_DIR="$MAIL_EXPORT_DIR"
+export MAIL_IMAP_IMPORT_DIR="$MAIL_EXPORT_DIR"
+export MAIL_IMAP_ATTACHMENT_DIR="$MAIL_EXPORT_DIR"
 
 run_inspector() {
   npx --yes @modelcontextprotocol/inspector "$SERVER_BIN" --cli "$@"
@@ -393,11 +407,19 @@ assert_json "all expected tools are listed" "$TOOLS_JSON" '
       "imap_list_accounts",
       "imap_list_mailboxes",
       "imap_search_messages",
+      "imap_mailbox_stats",
       "imap_get_message",
       "imap_get_message_raw",
+      "imap_get_attachment",
+      "imap_get_message_structure",
       "imap_apply_to_messages",
       "imap_update_message_flags",
       "imap_manage_mailbox",
+      "imap_export_messages",
+      "imap_import_messages",
+      "imap_save_draft",
+      "imap_create_reply_draft",
+      "imap_send_message",
       "imap_get_operation",
       "imap_cancel_operation"
     ]
@@ -469,6 +491,17 @@ assert_tool_schema "imap_search_messages" "search parameter contract" '
     and ($schema.properties.snippet_max_chars.maximum == 500)
     and (($schema.properties | has("include_snippet") | not))
 '
+assert_tool_schema "imap_mailbox_stats" "mailbox_stats parameter contract" '
+  .tools[] | select(.name == $name) | .inputSchema as $schema
+  | ($schema.type == "object")
+    and (($schema.required // []) | index("mailbox") != null)
+    and (($schema.properties | has("account_id")))
+    and (($schema.properties | has("from")))
+    and (($schema.properties | has("last_days")))
+    and ($schema.properties.top_n.minimum == 1)
+    and ($schema.properties.top_n.maximum == 50)
+    and (($schema.properties | has("cursor") | not))
+'
 assert_tool_schema "imap_get_message" "get_message parameter contract" '
   .tools[] | select(.name == $name) | .inputSchema as $schema
   | ($schema.type == "object")
@@ -496,6 +529,22 @@ assert_tool_schema "imap_get_message_raw" "get_message_raw parameter contract" '
     and ($schema.properties.max_bytes.minimum == 1024)
     and ($schema.properties.max_bytes.maximum == 1000000)
 '
+assert_tool_schema "imap_get_attachment" "get_attachment parameter contract" '
+  .tools[] | select(.name == $name) | .inputSchema as $schema
+  | ($schema.type == "object")
+    and (($schema.required // []) | index("message_id") != null)
+    and (($schema.required // []) | index("part_id") != null)
+    and (($schema.properties | has("account_id") | not))
+    and (($schema.properties | has("offset_bytes")))
+    and ($schema.properties.max_bytes.minimum == 1)
+    and ($schema.properties.max_bytes.maximum == 64000)
+'
+assert_tool_schema "imap_get_message_structure" "get_message_structure parameter contract" '
+  .tools[] | select(.name == $name) | .inputSchema as $schema
+  | ($schema.type == "object")
+    and (($schema.required // []) | index("message_id") != null)
+    and (($schema.properties | has("account_id") | not))
+'
 assert_tool_schema "imap_apply_to_messages" "apply_to_messages parameter contract" '
   .tools[] | select(.name == $name) | .inputSchema as $schema
   | ($schema.type == "object")
@@ -507,7 +556,7 @@ assert_tool_schema "imap_apply_to_messages" "apply_to_messages parameter contrac
     and ($schema.properties.message_ids.maxItems == 250)
     and ($schema.properties.message_ids.items.type == "string")
     and (($schema.properties | has("action")))
-    and ($schema.properties.action.enum == ["move", "copy", "delete"])
+    and ($schema.properties.action.enum == ["move", "copy", "delete", "permanent_delete"])
     and (($schema.properties | has("destination_mailbox")))
     and (($schema.properties | has("dry_run") | not))
 '
@@ -541,6 +590,50 @@ assert_tool_schema "imap_manage_mailbox" "manage_mailbox parameter contract" '
     and (($schema.properties | has("mailbox")))
     and (($schema.properties | has("destination_mailbox")))
 '
+assert_tool_schema "imap_export_messages" "export_messages parameter contract" '
+  .tools[] | select(.name == $name) | .inputSchema as $schema
+  | ($schema.type == "object")
+    and (($schema.required // []) | index("format") != null)
+    and (($schema.required // []) | index("destination") != null)
+    and (($schema.properties | has("message_ids")))
+    and (($schema.properties | has("cursor")))
+    and ($schema.properties.format.enum == ["eml", "mbox", "maildir"])
+'
+assert_tool_schema "imap_import_messages" "import_messages parameter contract" '
+  .tools[] | select(.name == $name) | .inputSchema as $schema
+  | ($schema.type == "object")
+    and (($schema.required // []) | index("mailbox") != null)
+    and (($schema.required // []) | index("format") != null)
+    and (($schema.required // []) | index("source") != null)
+    and (($schema.properties | has("account_id")))
+    and ($schema.properties.format.enum == ["eml", "mbox", "maildir"])
+'
+assert_tool_schema "imap_save_draft" "save_draft parameter contract" '
+  .tools[] | select(.name == $name) | .inputSchema as $schema
+  | ($schema.type == "object")
+    and (($schema.required // []) | length == 0)
+    and (($schema.properties | has("from")))
+    and (($schema.properties | has("to")))
+    and (($schema.properties | has("body_html")))
+    and ($schema.properties.attachments.maxItems == 20)
+'
+assert_tool_schema "imap_create_reply_draft" "create_reply_draft parameter contract" '
+  .tools[] | select(.name == $name) | .inputSchema as $schema
+  | ($schema.type == "object")
+    and (($schema.required // []) | index("message_id") != null)
+    and (($schema.properties | has("mode")))
+    and (($schema.properties | has("quote_original")))
+    and (($schema.properties | has("forward_as")))
+'
+assert_tool_schema "imap_send_message" "send_message parameter contract" '
+  .tools[] | select(.name == $name) | .inputSchema as $schema
+  | ($schema.type == "object")
+    and (($schema.required // []) | length == 0)
+    and (($schema.properties | has("draft_message_id")))
+    and (($schema.properties | has("bcc")))
+    and ($schema.properties.save_to_sent.type == "boolean")
+    and ($schema.properties.delete_draft.type == "boolean")
+'
 assert_tool_schema "imap_get_operation" "get_operation parameter contract" '
   .tools[] | select(.name == $name) | .inputSchema as $schema
   | ($schema.type == "object")
@@ -646,13 +739,43 @@ assert_json "imap_search_messages first page output contract" "$SEARCH_JSON" '
     and ($data.messages[0].mailbox == "INBOX")
     and (($data.messages[0].uidvalidity | type) == "number")
     and (($data.messages[0].uid | type) == "number")
+    and (($data.messages[0].is_bulk | type) == "boolean")
     and (($data.messages[0].date | type) == "string")
-    and (($data.messages[0].from | type) == "string")
+    and (($data.messages[0].from | type) == "array")
+    and (($data.messages[0].from[0].email | type) == "string")
     and (($data.messages[0].subject | type) == "string")
     and (($data.messages[0].flags | type) == "array")
     and (($data.messages[0].snippet | type) == "string")
 '
 
+echo "Checking imap_mailbox_stats output contract"
+STATS_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_mailbox_stats \
+  --tool-arg account_id=default \
+  --tool-arg mailbox=INBOX \
+  --tool-arg top_n=3)
+assert_json "imap_mailbox_stats output contract" "$STATS_JSON" '
+  (.structuredContent.data // .data) as $data
+  | (.isError != true)
+    and ($data.status == "ok")
+    and ($data.account_id == "default")
+    and ($data.mailbox == "INBOX")
+    and ($data.total > 0)
+    and ($data.scanned == $data.total)
+    and ($data.truncated == false)
+    and (($data.unread | type) == "number")
+    and (($data.by_sender | length) <= 3)
+    and (($data.by_sender[0].key | type) == "string")
+    and (($data.by_sender[0].count | type) == "number")
+    and (($data.by_sender[0].unread | type) == "number")
+    and (($data.by_sender_domain | type) == "array")
+    and (($data.by_day | type) == "array")
+    and (($data.by_flag | type) == "array")
+    and (($data.by_size | map(.count) | add) == $data.scanned)
+    and ($data.next_action.tool == "imap_search_messages")
+'
+
 MESSAGE_ID=$(printf '%s\n' "$SEARCH_JSON" | jq -r '(.structuredContent.data // .data).messages[0].message_id // empty')
 if [[ -z "$MESSAGE_ID" ]]; then
   echo "Failed to capture search message id" >&2
@@ -680,7 +803,8 @@ GET_JSON=$(run_inspector \
   --tool-arg body_max_chars=500 \
   --tool-arg include_headers=true \
   --tool-arg include_all_headers=true \
-  --tool-arg include_html=true)
+  --tool-arg include_html=true \
+  --tool-arg include_links=true)
 assert_json "imap_get_message base output contract" "$GET_JSON" '
   def envelope_ok:
     (.isError != true)
@@ -699,13 +823,21 @@ assert_json "imap_get_message base output contract" "$GET_JSON" '
     and (($data.message.uidvalidity | type) == "number")
     and (($data.message.uid | type) == "number")
     and (($data.message.date | type) == "string")
-    and (($data.message.from | type) == "string")
+    and (($data.message.from | type) == "array")
+    and (($data.message.from[0].email | type) == "string")
+    and (($data.message.to | type) == "array")
     and (($data.message.subject | type) == "string")
     and (($data.message.headers | type) == "array")
     and (($data.message.headers | length) > 0)
     and (($data.message.body_text | type) == "string")
     and (($data.message.body_html == null) or (($data.message.body_html | type) == "string"))
     and (($data.message.attachments | type) == "array")
+    and (($data.message.authentication.dkim | type) == "string")
+    and (($data.message.authentication.results | type) == "array")
+    and (($data.message.security.signed | type) == "boolean")
+    and (($data.message.security.layers | type) == "array")
+    and (($data.message.links | type) == "array")
+    and (($data.message.remote_images | type) == "array")
 '
 
 GET_ATTACHMENT_JSON=$(run_inspector \
@@ -732,9 +864,56 @@ assert_json "imap_get_message attachment output contract" "$GET_ATTACHMENT_JSON"
     and (($data.message.attachments[0].size_bytes | type) == "number")
     and (($data.message.attachments[0].part_id | type) == "string")
     and ($data.message.attachments[0] | has("extracted_text"))
+    and ($data.message.attachments[0] | has("archive_entries"))
+'
+
+echo "Checking imap_get_attachment output contract"
+ATTACHMENT_PART_ID=$(printf '%s\n' "$GET_ATTACHMENT_JSON" | jq -r '(.structuredContent.data // .data).message.attachments[0].part_id // empty')
+ATTACHMENT_CONTENT_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_get_attachment \
+  --tool-arg "message_id=${ATTACHMENT_MESSAGE_ID}" \
+  --tool-arg "part_id=${ATTACHMENT_PART_ID}" \
+  --tool-arg max_bytes=4096)
+assert_json "imap_get_attachment output contract" "$ATTACHMENT_CONTENT_JSON" '
+  def envelope_ok:
+    (.isError != true)
+    and ((.structuredContent.summary // .summary) | type == "string")
+    and (((.structuredContent.meta // .meta).now_utc | type) == "string")
+    and (((.structuredContent.meta // .meta).duration_ms | type) == "number");
+  (.structuredContent.data // .data) as $data
+  | envelope_ok
+    and ($data.status == "ok")
+    and (($data.part_id | type) == "string")
+    and (($data.content_type | type) == "string")
+    and (($data.total_size_bytes | type) == "number")
+    and ($data.returned_bytes <= 4096)
+    and ($data.offset_bytes == 0)
+    and (($data.truncated | type) == "boolean")
+    and (($data.content_base64 | type) == "string")
 '
 
-echo "Checking imap_get_message_raw output contract"
+echo "Checking imap_get_message_structure output contract"
+STRUCTURE_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_get_message_structure \
+  --tool-arg "message_id=${ATTACHMENT_MESSAGE_ID}")
+assert_json "imap_get_message_structure output contract" "$STRUCTURE_JSON" '
+  def envelope_ok:
+    (.isError != true)
+    and ((.structuredContent.summary // .summary) | type == "string")
+    and (((.structuredContent.meta // .meta).now_utc | type) == "string")
+    and (((.structuredContent.meta // .meta).duration_ms | type) == "number");
+  (.structuredContent.data // .data) as $data
+  | envelope_ok
+    and ($data.status == "ok")
+    and (($data.size_bytes | type) == "number")
+    and (($data.structure.content_type | type) == "string")
+    and (($data.structure.parameters | type) == "object")
+    and (($data.structure.parts | type) == "array")
+    and ([$data.structure | .. | objects | select(has("part_id")) | .part_id]
+      | index($attachment_part_id) != null)
+' --arg attachment_part_id "$ATTACHMENT_PART_ID"
 RAW_JSON=$(run_inspector \
   --method tools/call \
   --tool-name imap_get_message_raw \
@@ -764,6 +943,8 @@ MAILBOX_BASE="Inspector MCP $(date +%s)"
 MAILBOX_COPY="${MAILBOX_BASE}/Copied"
 MAILBOX_CREATE="${MAILBOX_BASE}/Child"
 MAILBOX_RENAME="${MAILBOX_BASE}/Renamed"
+MAILBOX_TRASH="${MAILBOX_BASE}/Trash"
+export MAIL_IMAP_DEFAULT_TRASH_MAILBOX="$MAILBOX_TRASH"
 
 echo "Checking imap_manage_mailbox output contracts"
 MANAGE_COPY_CREATE_JSON=$(run_inspector \
@@ -848,6 +1029,14 @@ if [[ -z "$COPIED_MESSAGE_ID" ]]; then
   exit 1
 fi
 
+MANAGE_TRASH_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_manage_mailbox \
+  --tool-arg account_id=default \
+  --tool-arg action=create \
+  --tool-arg "mailbox=${MAILBOX_TRASH}")
+wait_for_terminal_operation_json "imap_manage_mailbox create trash" "$MANAGE_TRASH_JSON" >/dev/null
+
 APPLY_DELETE_JSON=$(run_inspector \
   --method tools/call \
   --tool-name imap_apply_to_messages \
@@ -867,8 +1056,38 @@ assert_json "imap_apply_to_messages delete terminal result contract" "$APPLY_DEL
     and (($result.results | length) == 1)
     and (($result.results[0].issues | type) == "array")
     and (($result.results[0].source_mailbox | type) == "string")
-    and (($result.results[0].destination_mailbox == null) or ($result.results[0].destination_mailbox == ""))
-'
+    and ($result.results[0].destination_mailbox == $trash)
+' --arg trash "$MAILBOX_TRASH"
+
+TRASHED_MESSAGE_SEARCH_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_search_messages \
+  --tool-arg account_id=default \
+  --tool-arg "mailbox=${MAILBOX_TRASH}" \
+  --tool-arg limit=1)
+TRASHED_MESSAGE_ID=$(printf '%s\n' "$TRASHED_MESSAGE_SEARCH_JSON" | jq -r '(.structuredContent.data // .data).messages[0].message_id // empty')
+if [[ -z "$TRASHED_MESSAGE_ID" ]]; then
+  echo "Failed to capture trashed message id from ${MAILBOX_TRASH}" >&2
+  printf '%s\n' "$TRASHED_MESSAGE_SEARCH_JSON" >&2
+  exit 1
+fi
+expect_failure_with_text "permanent deletion is disabled; set MAIL_IMAP_PERMANENT_DELETE_ENABLED=true" \
+  --method tools/call \
+  --tool-name imap_apply_to_messages \
+  --tool-arg "message_ids=[\"${TRASHED_MESSAGE_ID}\"]" \
+  --tool-arg action=permanent_delete
+APPLY_EXPUNGE_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_apply_to_messages \
+  --tool-arg "message_ids=[\"${TRASHED_MESSAGE_ID}\"]" \
+  --tool-arg action=delete)
+APPLY_EXPUNGE_TERMINAL_JSON=$(wait_for_terminal_operation_json "imap_apply_to_messages delete from trash" "$APPLY_EXPUNGE_JSON")
+assert_json "imap_apply_to_messages delete from trash expunges" "$APPLY_EXPUNGE_TERMINAL_JSON" '
+  (.structuredContent.data // .data).result as $result
+  | ($result.status == "ok")
+    and ($result.results[0].source_mailbox == $trash)
+    and ($result.results[0].destination_mailbox == null)
+' --arg trash "$MAILBOX_TRASH"
 
 echo "Checking imap_update_message_flags output contracts"
 UPDATE_FLAGS_JSON=$(run_inspector \
@@ -896,6 +1115,212 @@ assert_json "imap_update_message_flags terminal result contract" "$UPDATE_FLAGS_
     and (($result.results[0].flags == null) or (($result.results[0].flags | type) == "array"))
 '
 
+echo "Checking imap_export_messages output contracts"
+EXPORT_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_export_messages \
+  --tool-arg "message_ids=[\"${MESSAGE_ID}\"]" \
+  --tool-arg format=eml \
+  --tool-arg destination=inspector/eml)
+EXPORT_TERMINAL_JSON=$(wait_for_terminal_operation_json "imap_export_messages" "$EXPORT_JSON")
+assert_json "imap_export_messages terminal result contract" "$EXPORT_TERMINAL_JSON" '
+  (.structuredContent.data // .data).result as $result
+  | ($result.status == "ok")
+    and ($result.account_id == "default")
+    and ($result.format == "eml")
+    and ($result.destination == "inspector/eml")
+    and ($result.matched == 1)
+    and ($result.succeeded == 1)
+    and (($result.results | length) == 1)
+    and ($result.results[0].message_id == $message_id)
+    and ($result.results[0].path | startswith("inspector/eml/"))
+    and ($result.results[0].path | endswith(".eml"))
+    and (($result.results[0].sha256 | test("^[0-9a-f]{64}$")))
+    and (($result.results[0].size_bytes | type) == "number")
+' --arg message_id "$MESSAGE_ID"
+EXPORT_PATH=$(printf '%s\n' "$EXPORT_TERMINAL_JSON" | jq -r '(.structuredContent.data // .data).result.results[0].path')
+EXPORT_SHA256=$(printf '%s\n' "$EXPORT_TERMINAL_JSON" | jq -r '(.structuredContent.data // .data).result.results[0].sha256')
+if [[ "$(sha256sum "$MAIL_EXPORT_DIR/$EXPORT_PATH" | cut -d' ' -f1)" != "$EXPORT_SHA256" ]]; then
+  echo "Exported file digest does not match reported sha256" >&2
+  exit 1
+fi
+echo "Checking imap_import_messages output contracts"
+IMPORT_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_import_messages \
+  --tool-arg account_id=default \
+  --tool-arg mailbox=INBOX \
+  --tool-arg format=eml \
+  --tool-arg source=inspector/eml)
+IMPORT_TERMINAL_JSON=$(wait_for_terminal_operation_json "imap_import_messages" "$IMPORT_JSON")
+assert_json "imap_import_messages terminal result contract" "$IMPORT_TERMINAL_JSON" '
+  (.structuredContent.data // .data).result as $result
+  | ($result.status == "ok")
+    and ($result.account_id == "default")
+    and ($result.mailbox == "INBOX")
+    and ($result.format == "eml")
+    and ($result.source == "inspector/eml")
+    and ($result.matched == 1)
+    and (($result.imported + $result.skipped) == 1)
+    and ($result.failed == 0)
+    and ($result.results[0].source_path == $path)
+    and (($result.results[0].duplicate | type) == "boolean")
+    and (($result.results[0].flags | type) == "array")
+' --arg path "$EXPORT_PATH"
+
+echo "Checking imap_save_draft output contract"
+MAILBOX_DRAFTS="${MAILBOX_BASE}/Drafts"
+MANAGE_DRAFTS_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_manage_mailbox \
+  --tool-arg account_id=default \
+  --tool-arg action=create \
+  --tool-arg "mailbox=${MAILBOX_DRAFTS}")
+wait_for_terminal_operation_json "imap_manage_mailbox create drafts" "$MANAGE_DRAFTS_JSON" >/dev/null
+SAVE_DRAFT_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_save_draft \
+  --tool-arg account_id=default \
+  --tool-arg "from=Inspector <inspector@example.com>" \
+  --tool-arg 'to=["Jürgen <jurgen@example.com>"]' \
+  --tool-arg "subject=Inspector draft Grüße" \
+  --tool-arg "body_text=Draft body" \
+  --tool-arg "body_html=<p>Draft body</p>" \
+  --tool-arg "attachments=[{\"path\":\"${EXPORT_PATH}\",\"content_type\":\"message/rfc822\"},{\"message_id\":\"${ATTACHMENT_MESSAGE_ID}\",\"part_id\":\"${ATTACHMENT_PART_ID}\"}]")
+assert_json "imap_save_draft output contract" "$SAVE_DRAFT_JSON" '
+  (.structuredContent.data // .data) as $data
+  | ($data.status == "ok")
+    and ($data.account_id == "default")
+    and ($data.mailbox == $mailbox)
+    and ($data.message_id | startswith("imap:default:" + $mailbox + ":"))
+    and (($data.message_uri | type) == "string")
+    and ($data.message_id_header | endswith("@example.com>"))
+    and ($data.size_bytes > 0)
+' --arg mailbox "$MAILBOX_DRAFTS"
+DRAFT_MESSAGE_ID=$(printf '%s\n' "$SAVE_DRAFT_JSON" | jq -r '(.structuredContent.data // .data).message_id')
+DRAFT_GET_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_get_message \
+  --tool-arg "message_id=${DRAFT_MESSAGE_ID}")
+assert_json "imap_save_draft round trip" "$DRAFT_GET_JSON" '
+  (.structuredContent.data // .data).message as $message
+  | ($message.subject == "Inspector draft Grüße")
+    and ($message.to[0].email == "jurgen@example.com")
+    and (($message.flags // []) | index("\\Draft") != null)
+    and (($message.attachments // []) | length == 2)
+'
+
+echo "Checking imap_create_reply_draft output contract"
+REPLY_DRAFT_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_create_reply_draft \
+  --tool-arg "message_id=${MESSAGE_ID}" \
+  --tool-arg mode=reply_all \
+  --tool-arg "body=Inspector reply" \
+  --tool-arg 'cc=["observer@example.com"]')
+assert_json "imap_create_reply_draft output contract" "$REPLY_DRAFT_JSON" '
+  (.structuredContent.data // .data) as $data
+  | ($data.status == "ok")
+    and ($data.mailbox == $mailbox)
+    and ($data.subject | test("^re:"; "i"))
+    and ($data.cc | index("observer@example.com") != null)
+    and (($data.to + $data.cc) | all(. != "test@localhost"))
+' --arg mailbox "$MAILBOX_DRAFTS"
+FORWARD_DRAFT_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_create_reply_draft \
+  --tool-arg "message_id=${MESSAGE_ID}" \
+  --tool-arg mode=forward \
+  --tool-arg forward_as=attachment \
+  --tool-arg 'to=["Forward Target <forward@example.com>"]')
+FORWARD_MESSAGE_ID=$(printf '%s\n' "$FORWARD_DRAFT_JSON" | jq -r '(.structuredContent.data // .data).message_id')
+FORWARD_GET_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_get_message \
+  --tool-arg "message_id=${FORWARD_MESSAGE_ID}")
+assert_json "imap_create_reply_draft forward round trip" "$FORWARD_GET_JSON" '
+  (.structuredContent.data // .data).message as $message
+  | ($message.subject | test("^fwd:"; "i"))
+    and ($message.to[0].email == "forward@example.com")
+    and (($message.attachments // []) | length == 1)
+'
+expect_failure_with_text "forwards need at least one" \
+  --method tools/call \
+  --tool-name imap_create_reply_draft \
+  --tool-arg "message_id=${MESSAGE_ID}" \
+  --tool-arg mode=forward
+
+echo "Checking imap_send_message output contract"
+MAILBOX_SENT="${MAILBOX_BASE}/Sent"
+MANAGE_SENT_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_manage_mailbox \
+  --tool-arg account_id=default \
+  --tool-arg action=create \
+  --tool-arg "mailbox=${MAILBOX_SENT}")
+wait_for_terminal_operation_json "imap_manage_mailbox create sent" "$MANAGE_SENT_JSON" >/dev/null
+SEND_SUBJECT="Inspector send $(date +%s)"
+SEND_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_send_message \
+  --tool-arg account_id=default \
+  --tool-arg "from=Inspector <${GREENMAIL_USER}>" \
+  --tool-arg "to=[\"${GREENMAIL_USER}\"]" \
+  --tool-arg 'bcc=["hidden@example.com"]' \
+  --tool-arg "subject=${SEND_SUBJECT}" \
+  --tool-arg "body_text=Sent body")
+assert_json "imap_send_message output contract" "$SEND_JSON" '
+  (.structuredContent.data // .data) as $data
+  | ($data.status == "ok")
+    and ($data.sent == true)
+    and ($data.envelope_from == $user)
+    and ($data.recipients == [$user, "hidden@example.com"])
+    and ($data.sent_mailbox == $mailbox)
+    and ($data.sent_message_id | startswith("imap:default:" + $mailbox + ":"))
+    and ($data.draft_deleted == false)
+' --arg user "$GREENMAIL_USER" --arg mailbox "$MAILBOX_SENT"
+SEND_SEARCH_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_search_messages \
+  --tool-arg account_id=default \
+  --tool-arg mailbox=INBOX \
+  --tool-arg "subject=${SEND_SUBJECT}")
+assert_json "imap_send_message delivery" "$SEND_SEARCH_JSON" '
+  (.structuredContent.data // .data).messages as $messages
+  | ($messages | length == 1)
+'
+SEND_DRAFT_JSON=$(run_inspector \
+  --method tools/call \
+  --tool-name imap_send_message \
+  --tool-arg account_id=default \
+  --tool-arg "draft_message_id=${FORWARD_MESSAGE_ID}" \
+  --tool-arg save_to_sent=false)
+assert_json "imap_send_message draft contract" "$SEND_DRAFT_JSON" '
+  (.structuredContent.data // .data) as $data
+  | ($data.status == "ok")
+    and ($data.recipients == ["forward@example.com"])
+    and ($data.sent_mailbox == null)
+    and ($data.draft_deleted == true)
+'
+expect_failure_with_text "cannot be combined" \
+  --method tools/call \
+  --tool-name imap_send_message \
+  --tool-arg account_id=default \
+  --tool-arg "draft_message_id=${DRAFT_MESSAGE_ID}" \
+  --tool-arg "subject=Override"
+expect_failure_with_text "must be in the Drafts mailbox" \
+  --method tools/call \
+  --tool-name imap_send_message \
+  --tool-arg account_id=default \
+  --tool-arg "draft_message_id=${MESSAGE_ID}"
+
+expect_failure_with_text "path must be relative" \
+  --method tools/call \
+  --tool-name imap_export_messages \
+  --tool-arg "message_ids=[\"${MESSAGE_ID}\"]" \
+  --tool-arg format=mbox \
+  --tool-arg destination=../escape.mbox
+
 MANAGE_RENAME_JSON=$(run_inspector \
   --method tools/call \
   --tool-name imap_manage_mailbox \
@@ -931,6 +1356,16 @@ assert_json "imap_manage_mailbox delete terminal result contract" "$MANAGE_DELET
     and (($result.destination_mailbox == null) or ($result.destination_mailbox == ""))
 ' --arg mailbox "$MAILBOX_RENAME"
 
+echo "Checking send policy enforcement over MCP"
+export MAIL_IMAP_SEND_ENABLED="false"
+
+expect_failure_with_text "sending is disabled; set MAIL_IMAP_SEND_ENABLED=true" \
+  --method tools/call \
+  --tool-name imap_send_message \
+  --tool-arg account_id=default \
+  --tool-arg 'to=["test@localhost"]' \
+  --tool-arg "subject=Disabled"
+
 echo "Checking write-path policy enforcement over MCP"
 export MAIL_IMAP_WRITE_ENABLED="false"
 
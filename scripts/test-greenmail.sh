@@ -5,6 +5,7 @@ IMAGE="greenmail/standalone:2.1.8"
 NAME="mail-imap-mcp-rs-greenmail-test"
 GREENMAIL_HOST="${GREENMAIL_HOST:-127.0.0.1}"
 GREENMAIL_SMTP_PORT="${GREENMAIL_SMTP_PORT:-3025}"
+GREENMAIL_SMTPS_PORT="${GREENMAIL_SMTPS_PORT:-3465}"
 GREENMAIL_IMAP_PORT="${GREENMAIL_IMAP_PORT:-3143}"
 GREENMAIL_USER="${GREENMAIL_USER:-test@localhost}"
 GREENMAIL_PASS="${GREENMAIL_PASS:-test}"
@@ -59,6 +60,7 @@ if ! probe_greenmail >/dev/null 2>&1; then
     -e GREENMAIL_OPTS="$GREENMAIL_OPTS" \
     -v "$GREENMAIL_PRELOAD_DIR:/greenmail-preload:ro" \
     -p "$GREENMAIL_SMTP_PORT:3025" \
+    -p "$GREENMAIL_SMTPS_PORT:3465" \
     -p "$GREENMAIL_IMAP_PORT:3993" \
     "$IMAGE"
 
@@ -76,6 +78,7 @@ wait_for_greenmail
 
 GREENMAIL_HOST="$GREENMAIL_HOST" \
 GREENMAIL_SMTP_PORT="$GREENMAIL_SMTP_PORT" \
+GREENMAIL_SMTPS_PORT="$GREENMAIL_SMTPS_PORT" \
 GREENMAIL_IMAP_PORT="$GREENMAIL_IMAP_PORT" \
 GREENMAIL_USER="$GREENMAIL_USER" \
 GREENMAIL_PASS="$GREENMAIL_PASS" \
@@ -0,0 +1,801 @@
+//! Outgoing message composition
+//!
+//! Builds RFC 5322 messages with MIME bodies from structured fields. Text is
+//! sent as UTF-8, quoted-printable when it is not plain 7-bit, attachments as
+//! base64, and non-ASCII header text as RFC 2047 encoded words.
+
+use base64::Engine;
+use chrono::{DateTime, FixedOffset};
+use mailparse::{
+    DispositionType, MailAddr, MailHeaderMap, ParsedMail, addrparse, addrparse_header,
+    parse_headers, parse_mail,
+};
+use uuid::Uuid;
+
+use crate::errors::{AppError, AppResult};
+use crate::mailstore::normalize_crlf;
+
+/// Preferred maximum line length for folded headers and encoded bodies
+const LINE_LENGTH: usize = 76;
+/// Hard line length limit from RFC 5322, excluding CRLF
+const MAX_LINE_LENGTH: usize = 998;
+/// UTF-8 bytes per RFC 2047 encoded word, keeping each word under 75 chars
+const ENCODED_WORD_BYTES: usize = 45;
+/// Most `References` ids kept in a reply; the thread root is always kept
+const MAX_REFERENCES: usize = 20;
+
+/// One address with an optional display name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mailbox {
+    /// Display name, if any
+    pub name: Option<String>,
+    /// Bare `local@domain` address
+    pub address: String,
+}
+
+impl Mailbox {
+    /// Parse a single address such as `Jane <jane@example.com>`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidInput` unless `raw` holds exactly one valid address.
+    pub fn parse(raw: &str) -> AppResult<Self> {
+        match Self::parse_list(raw)?.as_slice() {
+            [mailbox] => Ok(mailbox.clone()),
+            _ => Err(AppError::InvalidInput(format!(
+                "'{raw}' must contain exactly one email address"
+            ))),
+        }
+    }
+
+    /// Parse an address list header value, flattening groups.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidInput` if the list cannot be parsed or an address is
+    /// malformed.
+    pub fn parse_list(raw: &str) -> AppResult<Vec<Self>> {
+        if raw.contains(['\r', '\n']) {
+            return Err(AppError::InvalidInput(
+                "email addresses must not contain line breaks".to_owned(),
+            ));
+        }
+        let parsed = addrparse(raw)
+            .map_err(|e| AppError::InvalidInput(format!("invalid address '{raw}': {e}")))?;
+        let mut mailboxes = Vec::new();
+        for addr in parsed.iter() {
+            let singles = match addr {
+                MailAddr::Single(single) => std::slice::from_ref(single),
+                MailAddr::Group(group) => group.addrs.as_slice(),
+            };
+            for single in singles {
+                validate_address(&single.addr)?;
+                mailboxes.push(Self {
+                    name: single
+                        .display_name
+                        .as_deref()
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .map(str::to_owned),
+                    address: single.addr.clone(),
+                });
+            }
+        }
+        Ok(mailboxes)
+    }
+
+    /// Build a mailbox from an already-split name and address.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidInput` if the address is malformed.
+    pub fn new(name: Option<String>, address: &str) -> AppResult<Self> {
+        validate_address(address)?;
+        Ok(Self {
+            name: name
+                .map(|name| name.replace(['\r', '\n'], " ").trim().to_owned())
+                .filter(|name| !name.is_empty()),
+            address: address.to_owned(),
+        })
+    }
+
+    /// Whether both mailboxes name the same address, ignoring case
+    pub fn same_address(&self, other: &Self) -> bool {
+        self.address.eq_ignore_ascii_case(&other.address)
+    }
+
+    /// Domain part of the address
+    pub fn domain(&self) -> &str {
+        self.address
+            .rsplit_once('@')
+            .map_or("localhost", |(_, domain)| domain)
+    }
+
+    fn header_tokens(&self) -> Vec<String> {
+        let address = format!("<{}>", self.address);
+        let Some(name) = &self.name else {
+            return vec![address];
+        };
+        let mut tokens = if !name.is_ascii() {
+            encoded_words(name)
+        } else if name.chars().any(|c| "()<>[]:;@\\,.\"".contains(c)) {
+            vec![format!(
+                "\"{}\"",
+                name.replace('\\', "\\\\").replace('"', "\\\"")
+            )]
+        } else {
+            name.split(' ').map(str::to_owned).collect()
+        };
+        tokens.push(address);
+        tokens
+    }
+}
+
+/// File attached to a composed message
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    /// Filename offered to the recipient
+    pub filename: String,
+    /// Lowercase MIME type
+    pub content_type: String,
+    /// Decoded content
+    pub data: Vec<u8>,
+}
+
+/// Structured fields of a message to compose
+#[derive(Debug, Clone, Default)]
+pub struct Draft {
+    /// Author; omitted from the headers when `None`
+    pub from: Option<Mailbox>,
+    /// Primary recipients
+    pub to: Vec<Mailbox>,
+    /// Carbon-copy recipients
+    pub cc: Vec<Mailbox>,
+    /// Blind-copy recipients, written as a `Bcc` header
+    pub bcc: Vec<Mailbox>,
+    /// Unstructured subject text
+    pub subject: String,
+    /// Plain text body
+    pub text: String,
+    /// Optional HTML alternative to `text`
+    pub html: Option<String>,
+    /// `Message-ID` of the message being replied to
+    pub in_reply_to: Option<String>,
+    /// Thread ancestry, oldest first
+    pub references: Vec<String>,
+    /// Files appended after the body
+    pub attachments: Vec<Attachment>,
+}
+
+/// Generate a fresh `Message-ID` value, including angle brackets.
+pub fn new_message_id(from: &Mailbox) -> String {
+    format!("<{}@{}>", Uuid::new_v4().simple(), from.domain())
+}
+
+/// Validate a MIME type such as `application/pdf` and return it lowercased.
+///
+/// # Errors
+///
+/// Returns `InvalidInput` unless the value is a `type/subtype` token pair.
+pub fn normalize_content_type(raw: &str) -> AppResult<String> {
+    let is_token = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c))
+    };
+    match raw.trim().split_once('/') {
+        Some((kind, subtype)) if is_token(kind) && is_token(subtype) => {
+            Ok(raw.trim().to_ascii_lowercase())
+        }
+        _ => Err(AppError::InvalidInput(format!(
+            "content_type '{raw}' must look like type/subtype"
+        ))),
+    }
+}
+
+/// Serialize a draft as RFC 5322 bytes with CRLF line endings.
+///
+/// `message_id` is written as-is and must include angle brackets.
+pub fn build_message(draft: &Draft, date: DateTime<FixedOffset>, message_id: &str) -> Vec<u8> {
+    let mut out = String::new();
+    push_header(&mut out, "Date", vec![date.to_rfc2822()]);
+    if let Some(from) = &draft.from {
+        push_header(&mut out, "From", from.header_tokens());
+    }
+    for (name, mailboxes) in [("To", &draft.to), ("Cc", &draft.cc), ("Bcc", &draft.bcc)] {
+        if !mailboxes.is_empty() {
+            push_header(&mut out, name, address_list_tokens(mailboxes));
+        }
+    }
+    push_header(&mut out, "Subject", text_tokens(&draft.subject));
+    push_header(&mut out, "Message-ID", vec![message_id.to_owned()]);
+    if let Some(in_reply_to) = &draft.in_reply_to {
+        push_header(&mut out, "In-Reply-To", vec![in_reply_to.clone()]);
+    }
+    if !draft.references.is_empty() {
+        push_header(&mut out, "References", draft.references.clone());
+    }
+    out.push_str("MIME-Version: 1.0\r\n");
+
+    let mut body = text_part("text/plain", &draft.text).into_bytes();
+    if let Some(html) = &draft.html {
+        let html = text_part("text/html", html).into_bytes();
+        body = multipart("alternative", vec![body, html]);
+    }
+    if !draft.attachments.is_empty() {
+        let mut parts = vec![body];
+        parts.extend(draft.attachments.iter().map(attachment_part));
+        body = multipart("mixed", parts);
+    }
+    let mut message = out.into_bytes();
+    message.extend(body);
+    message
+}
+
+/// Addresses from every `name` header of a raw message, flattening groups
+///
+/// # Errors
+///
+/// Returns `InvalidInput` if the headers cannot be parsed or hold a malformed
+/// address.
+pub fn header_mailboxes(raw: &[u8], name: &str) -> AppResult<Vec<Mailbox>> {
+    let (headers, _) = parse_headers(raw)
+        .map_err(|e| AppError::InvalidInput(format!("message headers are malformed: {e}")))?;
+    let mut mailboxes = Vec::new();
+    for header in headers.get_all_headers(name) {
+        let parsed = addrparse_header(header)
+            .map_err(|e| AppError::InvalidInput(format!("{name} header is malformed: {e}")))?;
+        for addr in parsed.iter() {
+            let singles = match addr {
+                MailAddr::Single(single) => std::slice::from_ref(single),
+                MailAddr::Group(group) => group.addrs.as_slice(),
+            };
+            for single in singles {
+                mailboxes.push(Mailbox::new(single.display_name.clone(), &single.addr)?);
+            }
+        }
+    }
+    Ok(mailboxes)
+}
+
+/// Remove every `name` field, with its folded lines, from a message's header
+/// block, leaving the body untouched
+pub fn strip_header(raw: &[u8], name: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(raw.len());
+    let mut offset = 0;
+    let mut skipping = false;
+    while offset < raw.len() {
+        let end = raw[offset..]
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(raw.len(), |position| offset + position + 1);
+        let line = &raw[offset..end];
+        if line == b"\r\n" || line == b"\n" {
+            break;
+        }
+        if !matches!(line.first(), Some(b' ' | b'\t')) {
+            skipping = line.iter().position(|b| *b == b':').is_some_and(|colon| {
+                line[..colon]
+                    .trim_ascii()
+                    .eq_ignore_ascii_case(name.as_bytes())
+            });
+        }
+        if !skipping {
+            out.extend_from_slice(line);
+        }
+        offset = end;
+    }
+    out.extend_from_slice(&raw[offset..]);
+    out
+}
+
+/// Subject for a reply, adding `Re: ` unless a reply prefix is present
+pub fn reply_subject(subject: &str) -> String {
+    prefixed_subject(subject, "Re:", &["re:"])
+}
+
+/// Subject for a forward, adding `Fwd: ` unless a forward prefix is present
+pub fn forward_subject(subject: &str) -> String {
+    prefixed_subject(subject, "Fwd:", &["fwd:", "fw:"])
+}
+
+fn prefixed_subject(subject: &str, prefix: &str, existing: &[&str]) -> String {
+    let subject = subject.trim();
+    let lower = subject.to_lowercase();
+    if existing.iter().any(|known| lower.starts_with(known)) {
+        subject.to_owned()
+    } else if subject.is_empty() {
+        prefix.to_owned()
+    } else {
+        format!("{prefix} {subject}")
+    }
+}
+
+/// `References` for a reply: the parent's ancestry plus the parent itself.
+///
+/// Ancestry comes from `References`, or `In-Reply-To` when that is missing.
+/// Long threads keep the root and the most recent ids.
+pub fn reply_references(
+    references: Option<&str>,
+    in_reply_to: Option<&str>,
+    parent: &str,
+) -> Vec<String> {
+    let mut ids = references
+        .map(message_ids)
+        .filter(|ids| !ids.is_empty())
+        .or_else(|| in_reply_to.map(message_ids))
+        .unwrap_or_default();
+    ids.retain(|id| id != parent);
+    ids.push(parent.to_owned());
+    if ids.len() > MAX_REFERENCES {
+        ids.drain(1..=ids.len() - MAX_REFERENCES);
+    }
+    ids
+}
+
+/// Angle-bracketed message ids in a header value, in order
+pub fn message_ids(value: &str) -> Vec<String> {
+    let mut ids = Vec::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let id = &rest[start..=start + end];
+        if id.len() > 2 && !id[1..id.len() - 1].contains(['<', ' ', '\t', '\r', '\n']) {
+            ids.push(id.to_owned());
+        }
+        rest = &rest[start + end + 1..];
+    }
+    ids
+}
+
+/// Prefix every line with `>` for quoting in a reply
+pub fn quote_text(text: &str) -> String {
+    text.trim_end()
+        .lines()
+        .map(|line| {
+            if line.is_empty() || line.starts_with('>') {
+                format!(">{line}")
+            } else {
+                format!("> {line}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Files attached to an existing message, for carrying into a forward
+///
+/// Collects leaf parts with an attachment disposition, or with a filename
+/// outside the text body. Embedded messages are kept whole.
+///
+/// # Errors
+///
+/// Returns `Internal` if the message cannot be parsed.
+pub fn message_attachments(raw: &[u8]) -> AppResult<Vec<Attachment>> {
+    let parsed =
+        parse_mail(raw).map_err(|e| AppError::Internal(format!("failed to parse message: {e}")))?;
+    let mut attachments = Vec::new();
+    collect_attachments(&parsed, &mut attachments)?;
+    Ok(attachments)
+}
+
+fn collect_attachments(part: &ParsedMail<'_>, out: &mut Vec<Attachment>) -> AppResult<()> {
+    if !part.subparts.is_empty() {
+        for subpart in &part.subparts {
+            collect_attachments(subpart, out)?;
+        }
+        return Ok(());
+    }
+    let disposition = part.get_content_disposition();
+    let filename = disposition
+        .params
+        .get("filename")
+        .or_else(|| part.ctype.params.get("name"))
+        .cloned();
+    let content_type = part.ctype.mimetype.to_ascii_lowercase();
+    let is_attachment = disposition.disposition == DispositionType::Attachment
+        || (filename.is_some() && !content_type.starts_with("text/"));
+    if !is_attachment {
+        return Ok(());
+    }
+    let data = part
+        .get_body_raw()
+        .map_err(|e| AppError::Internal(format!("failed to decode attachment: {e}")))?;
+    out.push(Attachment {
+        filename: filename.unwrap_or_else(|| {
+            if content_type == "message/rfc822" {
+                "forwarded.eml".to_owned()
+            } else {
+                format!("attachment-{}", out.len() + 1)
+            }
+        }),
+        content_type,
+        data,
+    });
+    Ok(())
+}
+
+/// Reject addresses that cannot be written safely into a header
+fn validate_address(address: &str) -> AppResult<()> {
+    let valid = address.split_once('@').is_some_and(|(local, domain)| {
+        !local.is_empty() && !domain.is_empty() && !domain.contains('@')
+    }) && !address
+        .chars()
+        .any(|c| c.is_whitespace() || c.is_control() || "<>()[],;\\\"".contains(c));
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::InvalidInput(format!(
+            "'{address}' is not a valid email address"
+        )))
+    }
+}
+
+fn address_list_tokens(mailboxes: &[Mailbox]) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for (index, mailbox) in mailboxes.iter().enumerate() {
+        let mut mailbox_tokens = mailbox.header_tokens();
+        if index + 1 < mailboxes.len()
+            && let Some(last) = mailbox_tokens.last_mut()
+        {
+            last.push(',');
+        }
+        tokens.extend(mailbox_tokens);
+    }
+    tokens
+}
+
+/// Split unstructured header text into foldable words, encoding non-ASCII text
+fn text_tokens(value: &str) -> Vec<String> {
+    let value = value.replace(['\r', '\n'], " ");
+    if value.is_ascii() && !value.contains("=?") {
+        value.split(' ').map(str::to_owned).collect()
+    } else {
+        encoded_words(&value)
+    }
+}
+
+/// Encode text as RFC 2047 base64 words without splitting UTF-8 sequences
+fn encoded_words(value: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for c in value.chars() {
+        if chunk.len() + c.len_utf8() > ENCODED_WORD_BYTES {
+            words.push(encoded_word(&chunk));
+            chunk.clear();
+        }
+        chunk.push(c);
+    }
+    if !chunk.is_empty() || words.is_empty() {
+        words.push(encoded_word(&chunk));
+    }
+    words
+}
+
+fn encoded_word(text: &str) -> String {
+    format!(
+        "=?UTF-8?B?{}?=",
+        base64::engine::general_purpose::STANDARD.encode(text)
+    )
+}
+
+/// Write `name: tokens` folding between tokens to keep lines short
+fn push_header(out: &mut String, name: &str, tokens: Vec<String>) {
+    out.push_str(name);
+    out.push(':');
+    let mut line_length = name.len() + 1;
+    for (index, token) in tokens.into_iter().enumerate() {
+        if index > 0 && line_length + 1 + token.len() > LINE_LENGTH {
+            out.push_str("\r\n");
+            line_length = 0;
+        }
+        out.push(' ');
+        out.push_str(&token);
+        line_length += 1 + token.len();
+    }
+    out.push_str("\r\n");
+}
+
+/// Body part for UTF-8 text, as headers, blank line, and content
+fn text_part(content_type: &str, text: &str) -> String {
+    let lines = text
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect::<Vec<_>>();
+    let seven_bit = text.is_ascii()
+        && lines
+            .iter()
+            .all(|line| line.len() <= MAX_LINE_LENGTH && !line.contains('\r'));
+    let (encoding, content) = if seven_bit {
+        ("7bit", lines.join("\r\n"))
+    } else {
+        ("quoted-printable", quoted_printable(&lines))
+    };
+    format!(
+        "Content-Type: {content_type}; charset=utf-8\r\nContent-Transfer-Encoding: {encoding}\r\n\r\n{content}\r\n"
+    )
+}
+
+fn quoted_printable(lines: &[&str]) -> String {
+    let mut out = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        if index > 0 {
+            out.push_str("\r\n");
+        }
+        let bytes = line.as_bytes();
+        let mut line_length = 0;
+        for (position, &byte) in bytes.iter().enumerate() {
+            let last = position + 1 == bytes.len();
+            let literal =
+                matches!(byte, 33..=60 | 62..=126) || (matches!(byte, b' ' | b'\t') && !last);
+            let piece = if literal {
+                char::from(byte).to_string()
+            } else {
+                format!("={byte:02X}")
+            };
+            if line_length + piece.len() > LINE_LENGTH - 1 {
+                out.push_str("=\r\n");
+                line_length = 0;
+            }
+            out.push_str(&piece);
+            line_length += piece.len();
+        }
+    }
+    out
+}
+
+/// Attachment as a MIME part; embedded messages stay unencoded per RFC 2046
+fn attachment_part(attachment: &Attachment) -> Vec<u8> {
+    let filename = filename_parameter("filename", &attachment.filename);
+    if attachment.content_type == "message/rfc822" {
+        let encoding = if attachment.data.is_ascii() {
+            "7bit"
+        } else {
+            "8bit"
+        };
+        let mut part = format!(
+            "Content-Type: message/rfc822\r\nContent-Transfer-Encoding: {encoding}\r\nContent-Disposition: attachment; {filename}\r\n\r\n"
+        )
+        .into_bytes();
+        part.extend(normalize_crlf(&attachment.data));
+        if !part.ends_with(b"\r\n") {
+            part.extend_from_slice(b"\r\n");
+        }
+        return part;
+    }
+    let name = filename_parameter("name", &attachment.filename);
+    let encoded = base64::engine::general_purpose::STANDARD.encode(&attachment.data);
+    let mut content = String::with_capacity(encoded.len() + encoded.len() / LINE_LENGTH * 2);
+    for (index, line) in encoded.as_bytes().chunks(LINE_LENGTH).enumerate() {
+        if index > 0 {
+            content.push_str("\r\n");
+        }
+        content.push_str(std::str::from_utf8(line).unwrap_or_default());
+    }
+    format!(
+        "Content-Type: {}; {name}\r\nContent-Transfer-Encoding: base64\r\nContent-Disposition: attachment; {filename}\r\n\r\n{content}\r\n",
+        attachment.content_type
+    )
+    .into_bytes()
+}
+
+/// `key="value"` for ASCII names, RFC 2231 `key*=utf-8''...` otherwise
+fn filename_parameter(key: &str, filename: &str) -> String {
+    if filename.is_ascii() && !filename.chars().any(char::is_control) {
+        return format!(
+            "{key}=\"{}\"",
+            filename.replace('\\', "\\\\").replace('"', "\\\"")
+        );
+    }
+    let mut encoded = String::new();
+    for byte in filename.bytes() {
+        if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    format!("{key}*=utf-8''{encoded}")
+}
+
+/// Join parts under a fresh boundary; `=_` cannot occur in encoded content
+fn multipart(subtype: &str, parts: Vec<Vec<u8>>) -> Vec<u8> {
+    let boundary = format!("=_{}", Uuid::new_v4().simple());
+    let mut out =
+        format!("Content-Type: multipart/{subtype}; boundary=\"{boundary}\"\r\n\r\n").into_bytes();
+    for part in parts {
+        out.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+        out.extend(part);
+    }
+    out.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use mailparse::{MailHeaderMap, parse_mail};
+
+    use super::{
+        Attachment, Draft, Mailbox, build_message, forward_subject, header_mailboxes,
+        message_attachments, quote_text, reply_references, reply_subject, strip_header,
+    };
+
+    fn sample_date() -> DateTime<chrono::FixedOffset> {
+        DateTime::parse_from_rfc3339("2026-03-01T09:30:00+01:00").expect("valid date")
+    }
+
+    #[test]
+    fn parses_display_names_and_rejects_bad_addresses() {
+        let mailbox = Mailbox::parse("Jane Doe <jane@example.com>").expect("valid address");
+        assert_eq!(mailbox.name.as_deref(), Some("Jane Doe"));
+        assert_eq!(mailbox.address, "jane@example.com");
+        assert_eq!(mailbox.domain(), "example.com");
+
+        assert!(Mailbox::parse("a@example.com, b@example.com").is_err());
+        assert!(Mailbox::parse("not an address").is_err());
+        assert!(Mailbox::parse("x@example.com\r\nBcc: y@example.com").is_err());
+        assert_eq!(
+            Mailbox::parse_list("Team: a@example.com, b@example.com;")
+                .expect("group")
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn builds_parseable_message_with_alternative_and_attachment() {
+        let draft = Draft {
+            from: Some(Mailbox::parse("Jürgen <jurgen@example.com>").expect("from")),
+            to: vec![Mailbox {
+                name: Some("Doe, Jane".to_owned()),
+                address: "jane@example.com".to_owned(),
+            }],
+            bcc: vec![Mailbox::parse("audit@example.com").expect("bcc")],
+            subject: "Grüße aus Köln — quarterly numbers".to_owned(),
+            text: "Hallo,\nsee attached. Größe = 3\n".to_owned(),
+            html: Some("<p>Hallo</p>".to_owned()),
+            in_reply_to: Some("<parent@example.com>".to_owned()),
+            references: vec![
+                "<root@example.com>".to_owned(),
+                "<parent@example.com>".to_owned(),
+            ],
+            attachments: vec![Attachment {
+                filename: "Bericht ü.pdf".to_owned(),
+                content_type: "application/pdf".to_owned(),
+                data: vec![0, 1, 2, 255],
+            }],
+            ..Draft::default()
+        };
+        let raw = build_message(&draft, sample_date(), "<id@example.com>");
+        assert!(raw.split(|b| *b == b'\n').all(|line| line.len() <= 999));
+
+        let parsed = parse_mail(&raw).expect("composed message parses");
+        let headers = parsed.get_headers();
+        assert_eq!(
+            headers.get_first_value("Subject").as_deref(),
+            Some("Grüße aus Köln — quarterly numbers")
+        );
+        assert_eq!(
+            headers.get_first_value("From").as_deref(),
+            Some("Jürgen <jurgen@example.com>")
+        );
+        assert!(
+            headers
+                .get_first_value("To")
+                .is_some_and(|to| to.contains("\"Doe, Jane\" <jane@example.com>"))
+        );
+        assert_eq!(
+            headers.get_first_value("References").as_deref(),
+            Some("<root@example.com> <parent@example.com>")
+        );
+        assert_eq!(parsed.ctype.mimetype, "multipart/mixed");
+        let alternative = &parsed.subparts[0];
+        assert_eq!(alternative.ctype.mimetype, "multipart/alternative");
+        assert_eq!(
+            alternative.subparts[0].get_body().expect("text body"),
+            "Hallo,\r\nsee attached. Größe = 3\r\n"
+        );
+        let attachment = &parsed.subparts[1];
+        assert_eq!(
+            attachment.get_body_raw().expect("bytes"),
+            vec![0, 1, 2, 255]
+        );
+        assert_eq!(
+            attachment
+                .get_content_disposition()
+                .params
+                .get("filename")
+                .map(String::as_str),
+            Some("Bericht ü.pdf")
+        );
+    }
+
+    #[test]
+    fn embeds_messages_without_reencoding_bytes() {
+        let draft = Draft {
+            from: Some(Mailbox::parse("a@example.com").expect("from")),
+            attachments: vec![Attachment {
+                filename: "original.eml".to_owned(),
+                content_type: "message/rfc822".to_owned(),
+                data: b"Subject: Caf\xe9\n\nLatin-1 body \xe9\n".to_vec(),
+            }],
+            ..Draft::default()
+        };
+        let raw = build_message(&draft, sample_date(), "<id@example.com>");
+        let embedded = b"Content-Transfer-Encoding: 8bit\r\nContent-Disposition: attachment; filename=\"original.eml\"\r\n\r\nSubject: Caf\xe9\r\n\r\nLatin-1 body \xe9\r\n--";
+        assert!(
+            raw.windows(embedded.len())
+                .any(|window| window == embedded.as_slice())
+        );
+    }
+
+    #[test]
+    fn quoted_printable_wraps_long_lines() {
+        let draft = Draft {
+            from: Some(Mailbox::parse("a@example.com").expect("from")),
+            text: "é".repeat(200),
+            ..Draft::default()
+        };
+        let raw = build_message(&draft, sample_date(), "<id@example.com>");
+        assert!(raw.split(|b| *b == b'\n').all(|line| line.len() <= 77));
+        let parsed = parse_mail(&raw).expect("composed message parses");
+        assert_eq!(parsed.get_body().expect("body").trim_end(), "é".repeat(200));
+    }
+
+    #[test]
+    fn reply_helpers_prefix_subjects_and_extend_references() {
+        assert_eq!(reply_subject("Lunch"), "Re: Lunch");
+        assert_eq!(reply_subject("RE: Lunch"), "RE: Lunch");
+        assert_eq!(forward_subject("Fw: Lunch"), "Fw: Lunch");
+        assert_eq!(forward_subject("Re: Lunch"), "Fwd: Re: Lunch");
+        assert_eq!(reply_subject(""), "Re:");
+
+        assert_eq!(
+            reply_references(Some("<a@x> <b@x>"), Some("<b@x>"), "<c@x>"),
+            vec!["<a@x>", "<b@x>", "<c@x>"]
+        );
+        assert_eq!(
+            reply_references(None, Some("<b@x>"), "<c@x>"),
+            vec!["<b@x>", "<c@x>"]
+        );
+        let long = (0..30).map(|i| format!("<{i}@x>")).collect::<Vec<_>>();
+        let references = reply_references(Some(&long.join(" ")), None, "<new@x>");
+        assert_eq!(references.len(), 20);
+        assert_eq!(references[0], "<0@x>");
+        assert_eq!(references[1], "<12@x>");
+        assert_eq!(references[19], "<new@x>");
+
+        assert_eq!(quote_text("Hi\n\n> earlier\n"), "> Hi\n>\n>> earlier");
+    }
+
+    #[test]
+    fn collects_attachments_for_forwarding() {
+        let raw = b"Content-Type: multipart/mixed; boundary=b\r\n\r\n--b\r\nContent-Type: text/plain\r\n\r\nbody\r\n--b\r\nContent-Type: application/pdf; name=a.pdf\r\nContent-Transfer-Encoding: base64\r\n\r\nAAEC\r\n--b\r\nContent-Type: text/csv\r\nContent-Disposition: attachment; filename=rows.csv\r\n\r\nx,y\r\n--b--\r\n";
+        let attachments = message_attachments(raw).expect("attachments");
+        assert_eq!(attachments.len(), 2);
+        assert_eq!(attachments[0].filename, "a.pdf");
+        assert_eq!(attachments[0].data, vec![0, 1, 2]);
+        assert_eq!(attachments[1].content_type, "text/csv");
+    }
+
+    #[test]
+    fn strips_folded_headers_and_reads_address_lists() {
+        let raw = b"To: a@example.com\r\nBcc: hidden@example.com,\r\n  other@example.com\r\nCc: =?UTF-8?B?RG9lLCBKYW5l?= <jane@example.com>\r\n\r\nBcc: body text\r\n";
+        let stripped = strip_header(raw, "bcc");
+        assert_eq!(
+            stripped,
+            b"To: a@example.com\r\nCc: =?UTF-8?B?RG9lLCBKYW5l?= <jane@example.com>\r\n\r\nBcc: body text\r\n".to_vec()
+        );
+
+        let bcc = header_mailboxes(raw, "Bcc").expect("bcc parses");
+        assert_eq!(bcc.len(), 2);
+        assert_eq!(bcc[1].address, "other@example.com");
+        let cc = header_mailboxes(raw, "Cc").expect("cc parses");
+        assert_eq!(cc[0].name.as_deref(), Some("Doe, Jane"));
+        assert!(header_mailboxes(&stripped, "Bcc").expect("none").is_empty());
+    }
+}
@@ -7,14 +7,23 @@
 use std::collections::BTreeMap;
 use std::env;
 use std::env::VarError;
+use std::fmt;
 use std::fs::File;
 use std::io::BufReader;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
+use std::sync::Arc;
 
+use pgp::composed::{Deserializable, SignedPublicKey, SignedSecretKey};
 use regex::Regex;
+use rsa::RsaPrivateKey;
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::DecodePrivateKey;
 use rustls_pki_types::CertificateDer;
 use secrecy::SecretString;
+use x509_cert::Certificate;
 
+use crate::compose::Mailbox;
 use crate::errors::{AppError, AppResult};
 
 /// IMAP account configuration
@@ -35,6 +44,75 @@ pub struct AccountConfig {
     pub user: String,
     /// Password stored in a type that prevents accidental logging
     pub pass: SecretString,
+    /// Keys and certificates for signed or encrypted mail
+    pub message_keys: Arc<MessageKeys>,
+    /// Addresses the account writes as; the first is the default `From`
+    pub identities: Vec<Mailbox>,
+    /// Submission server used to send mail, when configured
+    pub smtp: Option<SmtpConfig>,
+    /// Mailbox `delete` moves messages to, overriding `\Trash` detection
+    pub trash_mailbox: Option<String>,
+}
+
+/// SMTP submission settings for one account
+///
+/// Credentials default to the account's IMAP login.
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    /// Submission server hostname
+    pub host: String,
+    /// Submission port (465 for implicit TLS, 587 for STARTTLS)
+    pub port: u16,
+    /// How the connection is secured
+    pub security: SmtpSecurity,
+    /// Username for `AUTH`
+    pub user: String,
+    /// Password for `AUTH`
+    pub pass: SecretString,
+}
+
+/// Transport security for SMTP submission; plaintext is not supported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    /// TLS from the first byte (RFC 8314)
+    Tls,
+    /// Plain connection upgraded with `STARTTLS` before authenticating
+    StartTls,
+}
+
+/// Local S/MIME and OpenPGP key material for one account
+///
+/// Used to decrypt messages addressed to the account and to verify
+/// signatures. `Debug` only reports which keys are present.
+#[derive(Clone, Default)]
+pub struct MessageKeys {
+    /// Account S/MIME certificate, matched against encrypted message recipients
+    pub smime_certificate: Option<Certificate>,
+    /// RSA private key for `smime_certificate`
+    pub smime_private_key: Option<RsaPrivateKey>,
+    /// Certificates trusted as S/MIME signers or signer issuers
+    pub smime_trusted_certificates: Vec<Certificate>,
+    /// OpenPGP secret keys used for decryption
+    pub openpgp_secret_keys: Vec<SignedSecretKey>,
+    /// Passphrase unlocking `openpgp_secret_keys`
+    pub openpgp_passphrase: Option<SecretString>,
+    /// OpenPGP public keys trusted for signature verification
+    pub openpgp_public_keys: Vec<SignedPublicKey>,
+}
+
+impl fmt::Debug for MessageKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageKeys")
+            .field("smime_certificate", &self.smime_certificate.is_some())
+            .field("smime_private_key", &self.smime_private_key.is_some())
+            .field(
+                "smime_trusted_certificates",
+                &self.smime_trusted_certificates.len(),
+            )
+            .field("openpgp_secret_keys", &self.openpgp_secret_keys.len())
+            .field("openpgp_public_keys", &self.openpgp_public_keys.len())
+            .finish_non_exhaustive()
+    }
 }
 
 /// Server-wide configuration
@@ -49,6 +127,10 @@ pub struct ServerConfig {
     pub trusted_ca_certs: Vec<CertificateDer<'static>>,
     /// Whether write operations (copy, move, delete, flag updates) are enabled
     pub write_enabled: bool,
+    /// Whether `imap_send_message` may submit mail over SMTP
+    pub send_enabled: bool,
+    /// Whether `permanent_delete` may expunge messages outside Trash
+    pub permanent_delete_enabled: bool,
     /// TCP connection timeout in milliseconds
     pub connect_timeout_ms: u64,
     /// IMAP greeting/TLS handshake timeout in milliseconds
@@ -59,12 +141,24 @@ pub struct ServerConfig {
     pub cursor_ttl_seconds: u64,
     /// Maximum number of cursors to retain (LRU eviction when exceeded)
     pub cursor_max_entries: usize,
+    /// HMAC key for stateless signed cursors. `None` keeps cursors in memory.
+    pub cursor_signing_key: Option<SecretString>,
     /// Time-to-live for cached read-only IMAP sessions in seconds
     pub read_session_cache_ttl_seconds: u64,
     /// Maximum idle read-only IMAP sessions cached per account
     pub read_session_cache_max_per_account: usize,
     /// Maximum number of completed write operations to retain in memory
     pub operation_max_entries: usize,
+    /// DKIM key records keyed by `<selector>._domainkey.<domain>`, from a key file
+    pub dkim_keys: BTreeMap<String, String>,
+    /// DNS resolver queried for DKIM keys missing from `dkim_keys`
+    pub dkim_dns_server: Option<SocketAddr>,
+    /// Sandbox directory that message exports are written under
+    pub export_dir: Option<PathBuf>,
+    /// Sandbox directory that message imports are read from
+    pub import_dir: Option<PathBuf>,
+    /// Sandbox directory that draft attachments are read from
+    pub attachment_dir: Option<PathBuf>,
 }
 
 impl ServerConfig {
@@ -120,11 +214,14 @@ impl ServerConfig {
             accounts,
             trusted_ca_certs: load_ca_certs_env("MAIL_IMAP_CA_CERT_PATH")?,
             write_enabled: parse_bool_env("MAIL_IMAP_WRITE_ENABLED", false)?,
+            send_enabled: parse_bool_env("MAIL_IMAP_SEND_ENABLED", false)?,
+            permanent_delete_enabled: parse_bool_env("MAIL_IMAP_PERMANENT_DELETE_ENABLED", false)?,
             connect_timeout_ms: parse_u64_env("MAIL_IMAP_CONNECT_TIMEOUT_MS", 30_000)?,
             greeting_timeout_ms: parse_u64_env("MAIL_IMAP_GREETING_TIMEOUT_MS", 15_000)?,
             socket_timeout_ms: parse_u64_env("MAIL_IMAP_SOCKET_TIMEOUT_MS", 300_000)?,
             cursor_ttl_seconds: parse_u64_env("MAIL_IMAP_CURSOR_TTL_SECONDS", 600)?,
             cursor_max_entries: parse_usize_env("MAIL_IMAP_CURSOR_MAX_ENTRIES", 512)?,
+            cursor_signing_key: load_cursor_signing_key_env("MAIL_IMAP_CURSOR_SIGNING_KEY")?,
             read_session_cache_ttl_seconds: parse_u64_env(
                 "MAIL_IMAP_READ_SESSION_CACHE_TTL_SECONDS",
                 120,
@@ -134,6 +231,11 @@ impl ServerConfig {
                 4,
             )?,
             operation_max_entries: parse_usize_env("MAIL_IMAP_OPERATION_MAX_ENTRIES", 256)?,
+            dkim_keys: load_dkim_key_file_env("MAIL_IMAP_DKIM_KEY_FILE")?,
+            dkim_dns_server: parse_socket_addr_env("MAIL_IMAP_DKIM_DNS_SERVER", 53)?,
+            export_dir: load_dir_env("MAIL_IMAP_EXPORT_DIR")?,
+            import_dir: load_dir_env("MAIL_IMAP_IMPORT_DIR")?,
+            attachment_dir: load_dir_env("MAIL_IMAP_ATTACHMENT_DIR")?,
         })
     }
 
@@ -151,7 +253,9 @@ impl ServerConfig {
 
 /// Load a single account configuration from environment
 ///
-/// Reads `MAIL_IMAP_<SEGMENT>_HOST`, `_USER`, `_PASS`, `_PORT`, and `_SECURE`.
+/// Reads `MAIL_IMAP_<SEGMENT>_HOST`, `_USER`, `_PASS`, `_PORT`, `_SECURE`,
+/// `_IDENTITIES`, and `_TRASH_MAILBOX`, plus the optional SMTP settings and
+/// S/MIME and OpenPGP key paths.
 /// Normalizes the segment name to lowercase for `account_id` (except `DEFAULT`
 /// becomes `default`).
 fn load_account(segment: &str) -> AppResult<AccountConfig> {
@@ -159,6 +263,8 @@ fn load_account(segment: &str) -> AppResult<AccountConfig> {
     let host = required_env(&format!("{prefix}HOST"))?;
     let user = required_env(&format!("{prefix}USER"))?;
     let pass = required_env(&format!("{prefix}PASS"))?;
+    let identities = load_identities(&prefix, &user)?;
+    let smtp = load_smtp(&prefix, &user, &pass)?;
 
     Ok(AccountConfig {
         account_id: if segment == "DEFAULT" {
@@ -171,9 +277,267 @@ fn load_account(segment: &str) -> AppResult<AccountConfig> {
         secure: parse_bool_env(&format!("{prefix}SECURE"), true)?,
         user,
         pass: SecretString::new(pass.into()),
+        message_keys: Arc::new(load_message_keys(&prefix)?),
+        identities,
+        smtp,
+        trash_mailbox: env::var(format!("{prefix}TRASH_MAILBOX"))
+            .ok()
+            .map(|mailbox| mailbox.trim().to_owned())
+            .filter(|mailbox| !mailbox.is_empty()),
     })
 }
 
+/// Load an account's optional SMTP submission settings
+///
+/// Reads `<prefix>SMTP_HOST`, `<prefix>SMTP_PORT`, `<prefix>SMTP_SECURITY`
+/// (`tls` or `starttls`), `<prefix>SMTP_USER`, and `<prefix>SMTP_PASS`.
+/// Sending is unavailable when the host is unset. Without an explicit
+/// security mode, port 587 uses STARTTLS and any other port implicit TLS; the
+/// port defaults to 465 for `tls` and 587 for `starttls`. The user and password
+/// default to the IMAP login.
+///
+/// # Errors
+///
+/// Returns `InvalidInput` if a value is malformed.
+fn load_smtp(prefix: &str, user: &str, pass: &str) -> AppResult<Option<SmtpConfig>> {
+    let host = match env::var(format!("{prefix}SMTP_HOST")) {
+        Ok(host) if !host.trim().is_empty() => host.trim().to_owned(),
+        _ => return Ok(None),
+    };
+    let security_key = format!("{prefix}SMTP_SECURITY");
+    let security = match env::var(&security_key) {
+        Ok(value) => match value.trim().to_ascii_lowercase().as_str() {
+            "tls" | "ssl" => Some(SmtpSecurity::Tls),
+            "starttls" => Some(SmtpSecurity::StartTls),
+            _ => {
+                return Err(AppError::InvalidInput(format!(
+                    "environment variable {security_key} must be tls or starttls"
+                )));
+            }
+        },
+        Err(_) => None,
+    };
+    let port_key = format!("{prefix}SMTP_PORT");
+    let (port, security) = match security {
+        Some(SmtpSecurity::Tls) => (parse_u16_env(&port_key, 465)?, SmtpSecurity::Tls),
+        Some(SmtpSecurity::StartTls) => (parse_u16_env(&port_key, 587)?, SmtpSecurity::StartTls),
+        None => {
+            let port = parse_u16_env(&port_key, 465)?;
+            let security = if port == 587 {
+                SmtpSecurity::StartTls
+            } else {
+                SmtpSecurity::Tls
+            };
+            (port, security)
+        }
+    };
+    let non_empty = |key: String| env::var(key).ok().filter(|v| !v.trim().is_empty());
+    Ok(Some(SmtpConfig {
+        host,
+        port,
+        security,
+        user: non_empty(format!("{prefix}SMTP_USER")).unwrap_or_else(|| user.to_owned()),
+        pass: SecretString::new(
+            non_empty(format!("{prefix}SMTP_PASS"))
+                .unwrap_or_else(|| pass.to_owned())
+                .into(),
+        ),
+    }))
+}
+
+/// Load the addresses an account writes as
+///
+/// Reads the comma-separated `<prefix>IDENTITIES` address list. When unset,
+/// the login is used if it is an email address.
+///
+/// # Errors
+///
+/// Returns `InvalidInput` if the list is empty, not unicode, or malformed.
+fn load_identities(prefix: &str, user: &str) -> AppResult<Vec<Mailbox>> {
+    let key = format!("{prefix}IDENTITIES");
+    match env::var(&key) {
+        Ok(value) => {
+            let identities = Mailbox::parse_list(value.trim()).map_err(|e| {
+                AppError::InvalidInput(format!("environment variable {key} is invalid: {e}"))
+            })?;
+            if identities.is_empty() {
+                return Err(AppError::InvalidInput(format!(
+                    "environment variable {key} must not be empty"
+                )));
+            }
+            Ok(identities)
+        }
+        Err(VarError::NotPresent) => Ok(Mailbox::parse(user).map(|m| vec![m]).unwrap_or_default()),
+        Err(VarError::NotUnicode(_)) => Err(AppError::InvalidInput(format!(
+            "environment variable {key} contains non-unicode data"
+        ))),
+    }
+}
+
+/// Load an account's optional S/MIME and OpenPGP keys
+///
+/// Reads `<prefix>SMIME_CERT_PATH` and `<prefix>SMIME_KEY_PATH` (set together),
+/// `<prefix>SMIME_TRUST_PATH`, `<prefix>PGP_SECRET_KEY_PATH`,
+/// `<prefix>PGP_PASSPHRASE`, and `<prefix>PGP_PUBLIC_KEYS_PATH`.
+///
+/// # Errors
+///
+/// Returns `InvalidInput` if a file cannot be read or parsed, or only one of
+/// the S/MIME certificate and key is set.
+fn load_message_keys(prefix: &str) -> AppResult<MessageKeys> {
+    let cert_key = format!("{prefix}SMIME_CERT_PATH");
+    let private_key = format!("{prefix}SMIME_KEY_PATH");
+    let smime_certificate = match read_optional_file_env(&cert_key)? {
+        Some((path, bytes)) => Some(
+            parse_pem_certificates(&bytes)
+                .and_then(|certs| {
+                    certs
+                        .into_iter()
+                        .next()
+                        .ok_or_else(|| "no certificate".into())
+                })
+                .map_err(|e| key_file_error(&cert_key, &path, &e))?,
+        ),
+        None => None,
+    };
+    let smime_private_key = match read_optional_file_env(&private_key)? {
+        Some((path, bytes)) => Some(
+            parse_rsa_private_key(&bytes).map_err(|e| key_file_error(&private_key, &path, &e))?,
+        ),
+        None => None,
+    };
+    if smime_certificate.is_some() != smime_private_key.is_some() {
+        return Err(AppError::InvalidInput(format!(
+            "{cert_key} and {private_key} must be set together"
+        )));
+    }
+
+    let trust_key = format!("{prefix}SMIME_TRUST_PATH");
+    let smime_trusted_certificates = match read_optional_file_env(&trust_key)? {
+        Some((path, bytes)) => {
+            parse_pem_certificates(&bytes).map_err(|e| key_file_error(&trust_key, &path, &e))?
+        }
+        None => Vec::new(),
+    };
+
+    let secret_key = format!("{prefix}PGP_SECRET_KEY_PATH");
+    let openpgp_secret_keys = match read_optional_file_env(&secret_key)? {
+        Some((path, bytes)) => SignedSecretKey::from_armor_many(bytes.as_slice())
+            .and_then(|(keys, _)| keys.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| key_file_error(&secret_key, &path, &e.to_string()))?,
+        None => Vec::new(),
+    };
+    let public_keys = format!("{prefix}PGP_PUBLIC_KEYS_PATH");
+    let openpgp_public_keys = match read_optional_file_env(&public_keys)? {
+        Some((path, bytes)) => SignedPublicKey::from_armor_many(bytes.as_slice())
+            .and_then(|(keys, _)| keys.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| key_file_error(&public_keys, &path, &e.to_string()))?,
+        None => Vec::new(),
+    };
+    let openpgp_passphrase = env::var(format!("{prefix}PGP_PASSPHRASE"))
+        .ok()
+        .map(|value| SecretString::new(value.into()));
+
+    Ok(MessageKeys {
+        smime_certificate,
+        smime_private_key,
+        smime_trusted_certificates,
+        openpgp_secret_keys,
+        openpgp_passphrase,
+        openpgp_public_keys,
+    })
+}
+
+/// Read the file named by an optional path environment variable
+///
+/// # Errors
+///
+/// Returns `InvalidInput` if the variable is empty or not unicode, or the
+/// file cannot be read.
+fn read_optional_file_env(key: &str) -> AppResult<Option<(PathBuf, Vec<u8>)>> {
+    let path = match env::var(key) {
+        Ok(value) if !value.trim().is_empty() => PathBuf::from(value.trim()),
+        Ok(_) => {
+            return Err(AppError::InvalidInput(format!(
+                "environment variable {key} must not be empty"
+            )));
+        }
+        Err(VarError::NotPresent) => return Ok(None),
+        Err(VarError::NotUnicode(_)) => {
+            return Err(AppError::InvalidInput(format!(
+                "environment variable {key} contains non-unicode data"
+            )));
+        }
+    };
+    let bytes = std::fs::read(&path).map_err(|e| {
+        AppError::InvalidInput(format!("failed to read {key} at {}: {e}", path.display()))
+    })?;
+    Ok(Some((path, bytes)))
+}
+
+/// Load an optional sandbox directory
+///
+/// Returns `None` if unset. The directory must already exist and is
+/// canonicalized so later path checks compare resolved locations.
+///
+/// # Errors
+///
+/// Returns `InvalidInput` if the variable is empty or not unicode, or the
+/// path is not an existing directory.
+fn load_dir_env(key: &str) -> AppResult<Option<PathBuf>> {
+    let path = match env::var(key) {
+        Ok(value) if !value.trim().is_empty() => PathBuf::from(value.trim()),
+        Ok(_) => {
+            return Err(AppError::InvalidInput(format!(
+                "environment variable {key} must not be empty"
+            )));
+        }
+        Err(VarError::NotPresent) => return Ok(None),
+        Err(VarError::NotUnicode(_)) => {
+            return Err(AppError::InvalidInput(format!(
+                "environment variable {key} contains non-unicode data"
+            )));
+        }
+    };
+    let path = path.canonicalize().map_err(|e| {
+        AppError::InvalidInput(format!(
+            "failed to resolve {key} at {}: {e}",
+            path.display()
+        ))
+    })?;
+    if !path.is_dir() {
+        return Err(AppError::InvalidInput(format!(
+            "{key} at {} is not a directory",
+            path.display()
+        )));
+    }
+    Ok(Some(path))
+}
+
+fn key_file_error(key: &str, path: &std::path::Path, error: &str) -> AppError {
+    AppError::InvalidInput(format!(
+        "failed to parse {key} at {}: {error}",
+        path.display()
+    ))
+}
+
+/// Parse one or more PEM certificates
+fn parse_pem_certificates(bytes: &[u8]) -> Result<Vec<Certificate>, String> {
+    let certs = Certificate::load_pem_chain(bytes).map_err(|e| e.to_string())?;
+    if certs.is_empty() {
+        return Err("no PEM certificates found".to_owned());
+    }
+    Ok(certs)
+}
+
+/// Parse an unencrypted PKCS#8 or PKCS#1 PEM RSA private key
+fn parse_rsa_private_key(bytes: &[u8]) -> Result<RsaPrivateKey, String> {
+    let pem = std::str::from_utf8(bytes).map_err(|_| "key file is not PEM text".to_owned())?;
+    RsaPrivateKey::from_pkcs8_pem(pem)
+        .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+        .map_err(|_| "expected an unencrypted PKCS#8 or PKCS#1 RSA private key".to_owned())
+}
+
 /// Read a required environment variable, returning error if missing or empty
 fn required_env(key: &str) -> AppResult<String> {
     match env::var(key) {
@@ -289,6 +653,120 @@ fn parse_usize_env(key: &str, default: usize) -> AppResult<usize> {
     }
 }
 
+/// Minimum accepted length of the cursor signing key in bytes
+const MIN_CURSOR_SIGNING_KEY_BYTES: usize = 32;
+
+/// Load the optional cursor signing key
+///
+/// Returns `None` if unset. When set, the key must be at least 32 bytes so
+/// that signed cursors cannot be forged by brute force.
+///
+/// # Errors
+///
+/// Returns `InvalidInput` if the key is empty, too short, or not unicode.
+fn load_cursor_signing_key_env(key: &str) -> AppResult<Option<SecretString>> {
+    match env::var(key) {
+        Ok(value) => {
+            let trimmed = value.trim();
+            if trimmed.len() < MIN_CURSOR_SIGNING_KEY_BYTES {
+                return Err(AppError::InvalidInput(format!(
+                    "environment variable {key} must be at least {MIN_CURSOR_SIGNING_KEY_BYTES} bytes"
+                )));
+            }
+            Ok(Some(SecretString::new(trimmed.into())))
+        }
+        Err(VarError::NotPresent) => Ok(None),
+        Err(VarError::NotUnicode(_)) => Err(AppError::InvalidInput(format!(
+            "environment variable {key} contains non-unicode data"
+        ))),
+    }
+}
+
+/// Parse an optional `ip` or `ip:port` environment variable
+///
+/// Returns `None` if unset; a bare IP address uses `default_port`.
+///
+/// # Errors
+///
+/// Returns `InvalidInput` if the variable is set but not a valid address.
+fn parse_socket_addr_env(key: &str, default_port: u16) -> AppResult<Option<SocketAddr>> {
+    match env::var(key) {
+        Ok(v) => {
+            let trimmed = v.trim();
+            trimmed
+                .parse::<SocketAddr>()
+                .or_else(|_| {
+                    trimmed
+                        .parse::<IpAddr>()
+                        .map(|ip| SocketAddr::new(ip, default_port))
+                })
+                .map(Some)
+                .map_err(|_| {
+                    AppError::InvalidInput(format!(
+                        "invalid socket address environment variable {key}: '{v}'"
+                    ))
+                })
+        }
+        Err(VarError::NotPresent) => Ok(None),
+        Err(VarError::NotUnicode(_)) => Err(AppError::InvalidInput(format!(
+            "environment variable {key} contains non-unicode data"
+        ))),
+    }
+}
+
+/// Load DKIM key records from an optional key file
+///
+/// Each non-empty line that does not start with `#` holds a record name and
+/// its TXT value, e.g. `sel._domainkey.example.com v=DKIM1; k=rsa; p=MIIB...`.
+/// Names are lowercased and a trailing dot is ignored.
+///
+/// # Errors
+///
+/// Returns `InvalidInput` if the file cannot be read or a line has no value.
+fn load_dkim_key_file_env(key: &str) -> AppResult<BTreeMap<String, String>> {
+    let path = match env::var(key) {
+        Ok(value) if !value.trim().is_empty() => PathBuf::from(value.trim()),
+        Ok(_) => {
+            return Err(AppError::InvalidInput(format!(
+                "environment variable {key} must not be empty"
+            )));
+        }
+        Err(VarError::NotPresent) => return Ok(BTreeMap::new()),
+        Err(VarError::NotUnicode(_)) => {
+            return Err(AppError::InvalidInput(format!(
+                "environment variable {key} contains non-unicode data"
+            )));
+        }
+    };
+    let contents = std::fs::read_to_string(&path).map_err(|e| {
+        AppError::InvalidInput(format!(
+            "failed to read DKIM key file {key} at {}: {e}",
+            path.display()
+        ))
+    })?;
+    parse_dkim_key_file(&contents).map_err(|line| {
+        AppError::InvalidInput(format!(
+            "DKIM key file {key} at {} has no record value on line {line}",
+            path.display()
+        ))
+    })
+}
+
+/// Parse key file contents, returning the 1-based number of a malformed line.
+fn parse_dkim_key_file(contents: &str) -> Result<BTreeMap<String, String>, usize> {
+    let mut keys = BTreeMap::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, record) = line.split_once(char::is_whitespace).ok_or(index + 1)?;
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        keys.insert(name, record.trim().to_owned());
+    }
+    Ok(keys)
+}
+
 fn load_ca_certs_env(key: &str) -> AppResult<Vec<CertificateDer<'static>>> {
     match env::var(key) {
         Ok(value) => {
@@ -336,13 +814,68 @@ fn load_ca_certs_env(key: &str) -> AppResult<Vec<CertificateDer<'static>>> {
 mod tests {
     use std::sync::{Mutex, OnceLock};
 
-    use super::{ServerConfig, load_ca_certs_env, parse_bool_value};
+    use secrecy::ExposeSecret;
+
+    use super::{
+        ServerConfig, SmtpSecurity, load_ca_certs_env, load_cursor_signing_key_env, load_dir_env,
+        load_identities, load_message_keys, load_smtp, parse_bool_value, parse_dkim_key_file,
+    };
 
     fn env_lock() -> &'static Mutex<()> {
         static ENV_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
         ENV_LOCK.get_or_init(|| Mutex::new(()))
     }
 
+    #[test]
+    fn parses_dkim_key_file_records() {
+        let keys = parse_dkim_key_file(
+            "# test keys\n\nSel1._DomainKey.Example.COM. v=DKIM1; k=ed25519; p=abc\n",
+        )
+        .expect("key file should parse");
+        assert_eq!(
+            keys.get("sel1._domainkey.example.com").map(String::as_str),
+            Some("v=DKIM1; k=ed25519; p=abc")
+        );
+        assert_eq!(parse_dkim_key_file("a.example\nbroken").err(), Some(1));
+    }
+
+    #[test]
+    fn loads_message_keys_and_requires_smime_pairs() {
+        let _guard = env_lock().lock().expect("env lock");
+        let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/secure");
+        let vars = [
+            ("MAIL_IMAP_KEYTEST_SMIME_CERT_PATH", "alice-cert.pem"),
+            ("MAIL_IMAP_KEYTEST_SMIME_KEY_PATH", "alice-key.pem"),
+            ("MAIL_IMAP_KEYTEST_SMIME_TRUST_PATH", "ca.pem"),
+            (
+                "MAIL_IMAP_KEYTEST_PGP_SECRET_KEY_PATH",
+                "alice-pgp-secret.asc",
+            ),
+            (
+                "MAIL_IMAP_KEYTEST_PGP_PUBLIC_KEYS_PATH",
+                "alice-pgp-public.asc",
+            ),
+        ];
+        for (key, file) in vars {
+            unsafe { std::env::set_var(key, format!("{fixtures}/{file}")) };
+        }
+
+        let keys = load_message_keys("MAIL_IMAP_KEYTEST_").expect("keys should load");
+        assert!(keys.smime_certificate.is_some() && keys.smime_private_key.is_some());
+        assert_eq!(keys.smime_trusted_certificates.len(), 1);
+        assert_eq!(keys.openpgp_secret_keys.len(), 1);
+        assert_eq!(keys.openpgp_public_keys.len(), 1);
+        assert!(format!("{keys:?}").contains("smime_private_key: true"));
+
+        unsafe { std::env::remove_var("MAIL_IMAP_KEYTEST_SMIME_KEY_PATH") };
+        let err = load_message_keys("MAIL_IMAP_KEYTEST_").expect_err("unpaired cert must fail");
+        assert!(err.to_string().contains("must be set together"));
+
+        for (key, _) in vars {
+            unsafe { std::env::remove_var(key) };
+        }
+    }
+
     #[test]
     fn parse_bool_value_accepts_common_truthy_and_falsy_values() {
         for truthy in ["1", "true", "TRUE", " yes ", "Y", "on"] {
@@ -373,6 +906,112 @@ mod tests {
         unsafe { std::env::remove_var(key) };
     }
 
+    #[test]
+    fn load_cursor_signing_key_env_rejects_short_key() {
+        let _guard = env_lock().lock().expect("env lock");
+        let key = "MAIL_IMAP_CURSOR_SIGNING_KEY";
+        unsafe { std::env::set_var(key, "too-short") };
+
+        let err = load_cursor_signing_key_env(key).expect_err("short signing key must fail");
+        assert!(err.to_string().contains("at least 32 bytes"));
+
+        unsafe { std::env::remove_var(key) };
+        assert!(
+            load_cursor_signing_key_env(key)
+                .expect("unset key is allowed")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn load_dir_env_requires_existing_directory() {
+        let _guard = env_lock().lock().expect("env lock");
+        let key = "MAIL_IMAP_EXPORT_DIR";
+        let dir = std::env::temp_dir();
+        unsafe { std::env::set_var(key, &dir) };
+        let loaded = load_dir_env(key).expect("directory loads");
+        assert_eq!(
+            loaded,
+            Some(dir.canonicalize().expect("canonical temp dir"))
+        );
+
+        let file = dir.join(format!("mail-imap-dir-env-{}", std::process::id()));
+        std::fs::write(&file, b"x").expect("write temp file");
+        unsafe { std::env::set_var(key, &file) };
+        let err = load_dir_env(key).expect_err("file path must fail");
+        assert!(err.to_string().contains("is not a directory"));
+
+        std::fs::remove_file(&file).expect("remove temp file");
+        unsafe { std::env::remove_var(key) };
+    }
+
+    #[test]
+    fn load_identities_defaults_to_email_login() {
+        let _guard = env_lock().lock().expect("env lock");
+        let key = "MAIL_IMAP_IDENTITYTEST_IDENTITIES";
+        unsafe { std::env::remove_var(key) };
+        let identities =
+            load_identities("MAIL_IMAP_IDENTITYTEST_", "user@example.com").expect("login loads");
+        assert_eq!(identities.len(), 1);
+        assert_eq!(identities[0].address, "user@example.com");
+        assert!(
+            load_identities("MAIL_IMAP_IDENTITYTEST_", "plainuser")
+                .expect("non-address login loads")
+                .is_empty()
+        );
+
+        unsafe { std::env::set_var(key, "Jane <jane@example.com>, alias@example.org") };
+        let identities =
+            load_identities("MAIL_IMAP_IDENTITYTEST_", "plainuser").expect("list loads");
+        assert_eq!(identities.len(), 2);
+        assert_eq!(identities[0].name.as_deref(), Some("Jane"));
+
+        unsafe { std::env::set_var(key, "not an address") };
+        assert!(load_identities("MAIL_IMAP_IDENTITYTEST_", "plainuser").is_err());
+        unsafe { std::env::remove_var(key) };
+    }
+
+    #[test]
+    fn load_smtp_derives_security_from_port_and_reuses_login() {
+        let _guard = env_lock().lock().expect("env lock");
+        let prefix = "MAIL_IMAP_SMTPTEST_";
+        let keys = ["SMTP_HOST", "SMTP_PORT", "SMTP_SECURITY", "SMTP_USER"]
+            .map(|suffix| format!("{prefix}{suffix}"));
+        for key in &keys {
+            unsafe { std::env::remove_var(key) };
+        }
+        assert!(load_smtp(prefix, "user", "pass").expect("unset").is_none());
+
+        unsafe { std::env::set_var(&keys[0], "smtp.example.com") };
+        let smtp = load_smtp(prefix, "user", "pass")
+            .expect("host only")
+            .expect("configured");
+        assert_eq!((smtp.port, smtp.security), (465, SmtpSecurity::Tls));
+        assert_eq!(smtp.user, "user");
+        assert_eq!(smtp.pass.expose_secret(), "pass");
+
+        unsafe { std::env::set_var(&keys[1], "587") };
+        let smtp = load_smtp(prefix, "user", "pass")
+            .expect("port 587")
+            .expect("configured");
+        assert_eq!(smtp.security, SmtpSecurity::StartTls);
+
+        unsafe { std::env::remove_var(&keys[1]) };
+        unsafe { std::env::set_var(&keys[2], "STARTTLS") };
+        unsafe { std::env::set_var(&keys[3], "sender@example.com") };
+        let smtp = load_smtp(prefix, "user", "pass")
+            .expect("explicit starttls")
+            .expect("configured");
+        assert_eq!((smtp.port, smtp.security), (587, SmtpSecurity::StartTls));
+        assert_eq!(smtp.user, "sender@example.com");
+
+        unsafe { std::env::set_var(&keys[2], "plain") };
+        assert!(load_smtp(prefix, "user", "pass").is_err());
+        for key in &keys {
+            unsafe { std::env::remove_var(key) };
+        }
+    }
+
     #[test]
     fn load_from_env_uses_read_session_cache_defaults() {
         let _guard = env_lock().lock().expect("env lock");
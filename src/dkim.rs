@@ -0,0 +1,675 @@
+//! DKIM signature verification (RFC 6376, RFC 8463)
+//!
+//! Verifies `DKIM-Signature` headers over the raw RFC822 source. Public keys
+//! come from the configured key file first and then from the configured DNS
+//! resolver; nothing is looked up unless one of them is set. Supports
+//! `rsa-sha256` and `ed25519-sha256` with simple and relaxed canonicalization.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use base64::Engine;
+use hickory_resolver::Resolver;
+use hickory_resolver::config::{ConnectionConfig, NameServerConfig, ResolverConfig};
+use hickory_resolver::net::runtime::TokioRuntimeProvider;
+use hickory_resolver::proto::rr::RData;
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::traits::PublicKeyParts;
+use rsa::{Pkcs1v15Sign, RsaPublicKey};
+use sha2::{Digest, Sha256};
+
+use crate::config::ServerConfig;
+use crate::errors::{AppError, AppResult};
+use crate::models::DkimSignatureResult;
+
+/// Maximum `DKIM-Signature` headers verified per message.
+pub const MAX_DKIM_SIGNATURES: usize = 5;
+
+/// Minimum accepted RSA key size (RFC 8301).
+const MIN_RSA_KEY_BITS: usize = 1024;
+
+/// Timeout for one DNS key lookup.
+const DNS_LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Canonicalization {
+    Simple,
+    Relaxed,
+}
+
+/// One header field with its raw bytes, including folding and trailing CRLF.
+struct HeaderField<'a> {
+    name: String,
+    raw: &'a [u8],
+}
+
+/// Parsed `DKIM-Signature` tags needed for verification.
+struct Signature {
+    field_index: usize,
+    tags: HashMap<String, String>,
+}
+
+impl Signature {
+    fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.get(name).map(String::as_str)
+    }
+
+    fn result(&self, result: &str, reason: Option<String>) -> DkimSignatureResult {
+        DkimSignatureResult {
+            domain: self.tag("d").map(str::to_owned),
+            selector: self.tag("s").map(str::to_owned),
+            algorithm: self.tag("a").map(str::to_owned),
+            result: result.to_owned(),
+            reason,
+            aligned: false,
+        }
+    }
+}
+
+/// Public key lookup outcome for one signature.
+enum KeyLookup {
+    Found(String),
+    /// No record exists; the signature cannot be valid
+    Missing(String),
+    /// The key could not be retrieved right now
+    Unavailable(String),
+}
+
+/// Check that a key file or DNS resolver is configured for verification
+///
+/// # Errors
+///
+/// Returns `InvalidInput` when neither `MAIL_IMAP_DKIM_KEY_FILE` nor
+/// `MAIL_IMAP_DKIM_DNS_SERVER` is configured.
+pub fn ensure_key_source(config: &ServerConfig) -> AppResult<()> {
+    if config.dkim_keys.is_empty() && config.dkim_dns_server.is_none() {
+        return Err(AppError::InvalidInput(
+            "verify_dkim requires MAIL_IMAP_DKIM_KEY_FILE or MAIL_IMAP_DKIM_DNS_SERVER".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+/// Verify every `DKIM-Signature` on a message (up to `MAX_DKIM_SIGNATURES`)
+///
+/// # Errors
+///
+/// Returns `InvalidInput` when neither `MAIL_IMAP_DKIM_KEY_FILE` nor
+/// `MAIL_IMAP_DKIM_DNS_SERVER` is configured.
+pub async fn verify_message(
+    config: &ServerConfig,
+    raw: &[u8],
+) -> AppResult<Vec<DkimSignatureResult>> {
+    ensure_key_source(config)?;
+
+    let (fields, body) = split_message(raw);
+    let signatures = signatures(&fields);
+    let mut results = Vec::with_capacity(signatures.len());
+    for signature in &signatures {
+        let (Some(domain), Some(selector)) = (signature.tag("d"), signature.tag("s")) else {
+            results.push(signature.result("fail", Some("missing d= or s= tag".to_owned())));
+            continue;
+        };
+        let name = format!("{selector}._domainkey.{domain}").to_ascii_lowercase();
+        let lookup = lookup_key(config, &name).await;
+        results.push(check_signature(&fields, body, signature, lookup));
+    }
+    Ok(results)
+}
+
+async fn lookup_key(config: &ServerConfig, name: &str) -> KeyLookup {
+    if let Some(record) = config.dkim_keys.get(name) {
+        return KeyLookup::Found(record.clone());
+    }
+    let Some(server) = config.dkim_dns_server else {
+        return KeyLookup::Unavailable(format!("no key for {name} in the key file"));
+    };
+    match lookup_txt(server, name).await {
+        Ok(Some(record)) => KeyLookup::Found(record),
+        Ok(None) => KeyLookup::Missing(format!("no DKIM key record at {name}")),
+        Err(message) => KeyLookup::Unavailable(message),
+    }
+}
+
+/// Query the configured resolver for a TXT record, joining its strings.
+async fn lookup_txt(server: SocketAddr, name: &str) -> Result<Option<String>, String> {
+    let mut connection = ConnectionConfig::udp();
+    connection.port = server.port();
+    let mut name_server = NameServerConfig::udp(server.ip());
+    name_server.connections = vec![connection];
+    let mut builder = Resolver::builder_with_config(
+        ResolverConfig::from_parts(None, Vec::new(), vec![name_server]),
+        TokioRuntimeProvider::default(),
+    );
+    builder.options_mut().timeout = DNS_LOOKUP_TIMEOUT;
+    builder.options_mut().attempts = 1;
+    let resolver = builder
+        .build()
+        .map_err(|e| format!("failed to build DNS resolver: {e}"))?;
+
+    match resolver.txt_lookup(format!("{name}.")).await {
+        Ok(lookup) => Ok(lookup
+            .answers()
+            .iter()
+            .find_map(|record| match &record.data {
+                RData::TXT(txt) => Some(
+                    txt.txt_data
+                        .iter()
+                        .map(|part| String::from_utf8_lossy(part))
+                        .collect::<String>(),
+                ),
+                _ => None,
+            })),
+        Err(error) if error.is_no_records_found() => Ok(None),
+        Err(error) => Err(format!("DNS lookup for {name} failed: {error}")),
+    }
+}
+
+/// Verify one signature against an already looked-up key record.
+fn check_signature(
+    fields: &[HeaderField<'_>],
+    body: &[u8],
+    signature: &Signature,
+    lookup: KeyLookup,
+) -> DkimSignatureResult {
+    let record = match lookup {
+        KeyLookup::Found(record) => record,
+        KeyLookup::Missing(reason) => return signature.result("fail", Some(reason)),
+        KeyLookup::Unavailable(reason) => return signature.result("neutral", Some(reason)),
+    };
+    match verify_signature(fields, body, signature, &record) {
+        Ok(0) => signature.result("pass", None),
+        Ok(unsigned) => signature.result(
+            "neutral",
+            Some(format!(
+                "{unsigned} body bytes after the signed length (l=) are not covered by the signature"
+            )),
+        ),
+        Err(reason) => signature.result("fail", Some(reason)),
+    }
+}
+
+/// Verify one signature, returning the canonical body bytes left unsigned by `l=`.
+fn verify_signature(
+    fields: &[HeaderField<'_>],
+    body: &[u8],
+    signature: &Signature,
+    record: &str,
+) -> Result<usize, String> {
+    if signature.tag("v") != Some("1") {
+        return Err("unsupported signature version".to_owned());
+    }
+    let algorithm = signature.tag("a").unwrap_or_default().to_ascii_lowercase();
+    if algorithm == "rsa-sha1" {
+        return Err("rsa-sha1 signatures are not accepted (RFC 8301)".to_owned());
+    }
+    if algorithm != "rsa-sha256" && algorithm != "ed25519-sha256" {
+        return Err(format!("unsupported algorithm {algorithm}"));
+    }
+    if let Some(expires) = signature.tag("x").and_then(|x| x.parse::<i64>().ok())
+        && expires < chrono::Utc::now().timestamp()
+    {
+        return Err("signature expired".to_owned());
+    }
+
+    let signed_headers = signature
+        .tag("h")
+        .unwrap_or_default()
+        .split(':')
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<_>>();
+    if !signed_headers.iter().any(|name| name == "from") {
+        return Err("From header is not signed".to_owned());
+    }
+    let (header_canon, body_canon) = parse_canonicalization(signature.tag("c"))?;
+
+    let mut canonical_body = canonicalize_body(body, body_canon);
+    let mut unsigned = 0;
+    if let Some(length) = signature.tag("l") {
+        let length = length
+            .parse::<usize>()
+            .map_err(|_| "invalid l= tag".to_owned())?;
+        if length > canonical_body.len() {
+            return Err("body is shorter than the signed length".to_owned());
+        }
+        unsigned = canonical_body.len() - length;
+        canonical_body.truncate(length);
+    }
+    let body_hash = decode_base64_tag(signature.tag("bh"), "bh")?;
+    if Sha256::digest(&canonical_body).as_slice() != body_hash.as_slice() {
+        return Err("body hash does not match".to_owned());
+    }
+
+    let mut hasher = Sha256::new();
+    let mut used = HashMap::<&str, usize>::new();
+    for name in &signed_headers {
+        let skip = used.entry(name).or_default();
+        // Repeated names are signed from the bottom of the header block up.
+        if let Some(field) = fields
+            .iter()
+            .rev()
+            .filter(|field| field.name == *name)
+            .nth(*skip)
+        {
+            hasher.update(canonicalize_header(field.raw, header_canon));
+        }
+        *skip += 1;
+    }
+    let own_field = fields[signature.field_index].raw;
+    let mut own = canonicalize_header(&remove_signature_value(own_field)?, header_canon);
+    if own.ends_with(b"\r\n") {
+        own.truncate(own.len() - 2);
+    }
+    hasher.update(&own);
+    let header_hash = hasher.finalize();
+
+    let signature_bytes = decode_base64_tag(signature.tag("b"), "b")?;
+    let key_tags = parse_tags(record);
+    if key_tags.get("v").is_some_and(|v| v != "DKIM1") {
+        return Err("key record has an unsupported version".to_owned());
+    }
+    let key_bytes = match key_tags.get("p") {
+        Some(p) if p.is_empty() => return Err("key has been revoked".to_owned()),
+        Some(p) => base64::engine::general_purpose::STANDARD
+            .decode(p)
+            .map_err(|_| "key record p= is not valid base64".to_owned())?,
+        None => return Err("key record has no p= tag".to_owned()),
+    };
+    let key_type = key_tags.get("k").map_or("rsa", String::as_str);
+
+    let verified = match (algorithm.as_str(), key_type) {
+        ("rsa-sha256", "rsa") => {
+            let key = RsaPublicKey::from_public_key_der(&key_bytes)
+                .or_else(|_| RsaPublicKey::from_pkcs1_der(&key_bytes))
+                .map_err(|_| "key record p= is not an RSA public key".to_owned())?;
+            if key.size() * 8 < MIN_RSA_KEY_BITS {
+                return Err("RSA key is shorter than 1024 bits".to_owned());
+            }
+            key.verify(
+                Pkcs1v15Sign::new::<Sha256>(),
+                &header_hash,
+                &signature_bytes,
+            )
+            .map_err(|_| "signature does not verify".to_owned())
+        }
+        ("ed25519-sha256", "ed25519") => {
+            let key_bytes: [u8; 32] = key_bytes
+                .try_into()
+                .map_err(|_| "ed25519 key must be 32 bytes".to_owned())?;
+            let key = ed25519_dalek::VerifyingKey::from_bytes(&key_bytes)
+                .map_err(|_| "invalid ed25519 key".to_owned())?;
+            let signature = ed25519_dalek::Signature::from_slice(&signature_bytes)
+                .map_err(|_| "invalid ed25519 signature".to_owned())?;
+            key.verify_strict(&header_hash, &signature)
+                .map_err(|_| "signature does not verify".to_owned())
+        }
+        _ => Err(format!(
+            "key type {key_type} does not match algorithm {algorithm}"
+        )),
+    };
+    verified.map(|()| unsigned)
+}
+
+/// Split raw message bytes into header fields and the body.
+fn split_message(raw: &[u8]) -> (Vec<HeaderField<'_>>, &[u8]) {
+    let mut fields: Vec<HeaderField<'_>> = Vec::new();
+    let mut position = 0;
+    while position < raw.len() {
+        let line_end = raw[position..]
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(raw.len(), |offset| position + offset + 1);
+        let line = &raw[position..line_end];
+        if line == b"\r\n" || line == b"\n" {
+            return (fields, &raw[line_end..]);
+        }
+        if matches!(line.first(), Some(b' ' | b'\t')) {
+            if let Some(last) = fields.last_mut() {
+                let start = last.raw.as_ptr() as usize - raw.as_ptr() as usize;
+                last.raw = &raw[start..line_end];
+            }
+        } else if let Some(colon) = line.iter().position(|byte| *byte == b':') {
+            fields.push(HeaderField {
+                name: String::from_utf8_lossy(&line[..colon])
+                    .trim()
+                    .to_ascii_lowercase(),
+                raw: line,
+            });
+        }
+        position = line_end;
+    }
+    (fields, &[])
+}
+
+fn signatures(fields: &[HeaderField<'_>]) -> Vec<Signature> {
+    fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| field.name == "dkim-signature")
+        .take(MAX_DKIM_SIGNATURES)
+        .map(|(field_index, field)| {
+            let raw = String::from_utf8_lossy(field.raw);
+            let value = raw.split_once(':').map_or("", |(_, value)| value);
+            Signature {
+                field_index,
+                tags: parse_tags(value),
+            }
+        })
+        .collect()
+}
+
+/// Parse a `tag=value; ...` list, removing folding whitespace from values.
+fn parse_tags(value: &str) -> HashMap<String, String> {
+    value
+        .split(';')
+        .filter_map(|tag| {
+            let (name, value) = tag.split_once('=')?;
+            let value = match name.trim() {
+                "b" | "bh" | "p" => value.split_whitespace().collect::<String>(),
+                _ => value.split_whitespace().collect::<Vec<_>>().join(" "),
+            };
+            Some((name.trim().to_owned(), value))
+        })
+        .collect()
+}
+
+fn parse_canonicalization(
+    value: Option<&str>,
+) -> Result<(Canonicalization, Canonicalization), String> {
+    let parse = |name: &str| match name {
+        "simple" => Ok(Canonicalization::Simple),
+        "relaxed" => Ok(Canonicalization::Relaxed),
+        other => Err(format!("unsupported canonicalization {other}")),
+    };
+    let value = value.unwrap_or("simple/simple").to_ascii_lowercase();
+    let (header, body) = value.split_once('/').unwrap_or((value.as_str(), "simple"));
+    Ok((parse(header)?, parse(body)?))
+}
+
+fn decode_base64_tag(value: Option<&str>, tag: &str) -> Result<Vec<u8>, String> {
+    let value = value.ok_or_else(|| format!("missing {tag}= tag"))?;
+    base64::engine::general_purpose::STANDARD
+        .decode(value)
+        .map_err(|_| format!("{tag}= tag is not valid base64"))
+}
+
+/// Blank the `b=` tag value (including surrounding whitespace) of a signature field.
+fn remove_signature_value(field: &[u8]) -> Result<Vec<u8>, String> {
+    let text = std::str::from_utf8(field).map_err(|_| "DKIM-Signature is not UTF-8".to_owned())?;
+    let colon = text
+        .find(':')
+        .ok_or_else(|| "malformed DKIM-Signature".to_owned())?;
+    let mut segment_start = colon + 1;
+    for segment in text[colon + 1..].split(';') {
+        let segment_end = segment_start + segment.len();
+        if let Some((name, _)) = segment.split_once('=')
+            && name.trim() == "b"
+        {
+            let value_start = segment_start + name.len() + 1;
+            let mut stripped = field[..value_start].to_vec();
+            stripped.extend_from_slice(&field[segment_end..]);
+            return Ok(stripped);
+        }
+        segment_start = segment_end + 1;
+    }
+    Err("missing b= tag".to_owned())
+}
+
+fn canonicalize_header(raw: &[u8], canon: Canonicalization) -> Vec<u8> {
+    if canon == Canonicalization::Simple {
+        return raw.to_vec();
+    }
+    let Some(colon) = raw.iter().position(|byte| *byte == b':') else {
+        return raw.to_vec();
+    };
+    let mut out = String::from_utf8_lossy(&raw[..colon])
+        .trim()
+        .to_ascii_lowercase()
+        .into_bytes();
+    out.push(b':');
+    let unfolded = raw[colon + 1..]
+        .iter()
+        .copied()
+        .filter(|byte| *byte != b'\r' && *byte != b'\n')
+        .collect::<Vec<_>>();
+    out.extend(collapse_whitespace(&unfolded).trim_ascii());
+    out.extend_from_slice(b"\r\n");
+    out
+}
+
+fn canonicalize_body(body: &[u8], canon: Canonicalization) -> Vec<u8> {
+    let mut lines = body
+        .split(|byte| *byte == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .map(|line| match canon {
+            Canonicalization::Simple => line.to_vec(),
+            Canonicalization::Relaxed => collapse_whitespace(line).trim_ascii_end().to_vec(),
+        })
+        .collect::<Vec<_>>();
+    while lines.last().is_some_and(Vec::is_empty) {
+        lines.pop();
+    }
+    if lines.is_empty() {
+        return match canon {
+            Canonicalization::Simple => b"\r\n".to_vec(),
+            Canonicalization::Relaxed => Vec::new(),
+        };
+    }
+    let mut out = Vec::with_capacity(body.len() + 2);
+    for line in lines {
+        out.extend(line);
+        out.extend_from_slice(b"\r\n");
+    }
+    out
+}
+
+/// Replace each run of spaces and tabs with a single space.
+fn collapse_whitespace(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    for byte in bytes {
+        if matches!(byte, b' ' | b'\t') {
+            if out.last() != Some(&b' ') {
+                out.push(b' ');
+            }
+        } else {
+            out.push(*byte);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use ed25519_dalek::{Signer, SigningKey};
+    use sha2::{Digest, Sha256};
+
+    use super::{
+        Canonicalization, KeyLookup, canonicalize_body, canonicalize_header, check_signature,
+        remove_signature_value, signatures, split_message,
+    };
+
+    const BODY: &str = "Hello  world \r\n\r\n\r\n";
+
+    /// Sign a message with relaxed/relaxed canonicalization, as a sender would.
+    fn signed_message(key: &SigningKey) -> String {
+        signed_message_with_length(key, None)
+    }
+
+    /// Sign a message, limiting the body hash to `length` bytes via `l=`.
+    fn signed_message_with_length(key: &SigningKey, length: Option<usize>) -> String {
+        let encode = |bytes: &[u8]| base64::engine::general_purpose::STANDARD.encode(bytes);
+        let mut canonical = canonicalize_body(BODY.as_bytes(), Canonicalization::Relaxed);
+        let length_tag = length.map_or_else(String::new, |length| {
+            canonical.truncate(length);
+            format!(" l={length};")
+        });
+        let body_hash = encode(&Sha256::digest(canonical));
+        let headers = "From: Alice <alice@example.com>\r\nSubject:  Quarterly   report\r\n";
+        let signature_field = format!(
+            "DKIM-Signature: v=1; a=ed25519-sha256; c=relaxed/relaxed; d=example.com;\r\n s=sel; h=from:subject;{length_tag} bh={body_hash};\r\n b=\r\n"
+        );
+
+        let mut hasher = Sha256::new();
+        for line in headers.split_inclusive("\r\n") {
+            hasher.update(canonicalize_header(
+                line.as_bytes(),
+                Canonicalization::Relaxed,
+            ));
+        }
+        let own = canonicalize_header(signature_field.as_bytes(), Canonicalization::Relaxed);
+        hasher.update(&own[..own.len() - 2]);
+        let signature = encode(&key.sign(&hasher.finalize()).to_bytes());
+        let signature_field = signature_field.replace(" b=\r\n", &format!(" b={signature}\r\n"));
+        format!("{signature_field}{headers}\r\n{BODY}")
+    }
+
+    fn key_record(key: &SigningKey) -> String {
+        let public =
+            base64::engine::general_purpose::STANDARD.encode(key.verifying_key().to_bytes());
+        format!("v=DKIM1; k=ed25519; p={public}")
+    }
+
+    #[test]
+    fn verifies_ed25519_signature_and_detects_tampering() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let message = signed_message(&key);
+
+        let (fields, body) = split_message(message.as_bytes());
+        let signature = &signatures(&fields)[0];
+        let result = check_signature(&fields, body, signature, KeyLookup::Found(key_record(&key)));
+        assert_eq!(result.result, "pass", "{:?}", result.reason);
+        assert_eq!(result.domain.as_deref(), Some("example.com"));
+
+        let tampered = message.replace("Quarterly", "Annual");
+        let (fields, body) = split_message(tampered.as_bytes());
+        let result = check_signature(
+            &fields,
+            body,
+            &signatures(&fields)[0],
+            KeyLookup::Found(key_record(&key)),
+        );
+        assert_eq!(result.result, "fail");
+        assert_eq!(result.reason.as_deref(), Some("signature does not verify"));
+
+        let tampered = message.replace("Hello", "Goodbye");
+        let (fields, body) = split_message(tampered.as_bytes());
+        let result = check_signature(
+            &fields,
+            body,
+            &signatures(&fields)[0],
+            KeyLookup::Found(key_record(&key)),
+        );
+        assert_eq!(result.reason.as_deref(), Some("body hash does not match"));
+
+        let (fields, body) = split_message(message.as_bytes());
+        let other = SigningKey::from_bytes(&[9; 32]);
+        let result = check_signature(
+            &fields,
+            body,
+            &signatures(&fields)[0],
+            KeyLookup::Found(key_record(&other)),
+        );
+        assert_eq!(result.result, "fail");
+        let result = check_signature(
+            &fields,
+            body,
+            &signatures(&fields)[0],
+            KeyLookup::Unavailable("timeout".to_owned()),
+        );
+        assert_eq!(result.result, "neutral");
+    }
+
+    #[test]
+    fn reports_content_appended_after_signed_length_as_neutral() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let message = signed_message_with_length(&key, Some("Hello world\r\n".len()));
+
+        let (fields, body) = split_message(message.as_bytes());
+        let result = check_signature(
+            &fields,
+            body,
+            &signatures(&fields)[0],
+            KeyLookup::Found(key_record(&key)),
+        );
+        assert_eq!(result.result, "pass", "{:?}", result.reason);
+
+        let appended = format!("{message}Click here to verify your account\r\n");
+        let (fields, body) = split_message(appended.as_bytes());
+        let result = check_signature(
+            &fields,
+            body,
+            &signatures(&fields)[0],
+            KeyLookup::Found(key_record(&key)),
+        );
+        assert_eq!(result.result, "neutral");
+        assert!(
+            result
+                .reason
+                .as_deref()
+                .is_some_and(|reason| reason.contains("not covered"))
+        );
+    }
+
+    #[test]
+    fn canonicalizes_headers_and_bodies() {
+        assert_eq!(
+            canonicalize_header(b"Subject: a  \t b \r\n c\r\n", Canonicalization::Relaxed),
+            b"subject:a b c\r\n"
+        );
+        assert_eq!(
+            canonicalize_body(b"a  b \r\n\r\n", Canonicalization::Relaxed),
+            b"a b\r\n"
+        );
+        assert_eq!(canonicalize_body(b"", Canonicalization::Simple), b"\r\n");
+        assert_eq!(
+            canonicalize_body(b"\r\n\r\n", Canonicalization::Relaxed),
+            b""
+        );
+        assert_eq!(
+            remove_signature_value(b"DKIM-Signature: v=1; bh=abc; b=xyz\r\n def; d=x\r\n")
+                .expect("b= tag is present"),
+            b"DKIM-Signature: v=1; bh=abc; b=; d=x\r\n"
+        );
+    }
+
+    #[test]
+    fn verifies_rfc8463_ed25519_example() {
+        let raw = concat!(
+            "DKIM-Signature: v=1; a=ed25519-sha256; c=relaxed/relaxed;\r\n",
+            " d=football.example.com; i=@football.example.com;\r\n",
+            " q=dns/txt; s=brisbane; t=1528637909; h=from : to :\r\n",
+            " subject : date : message-id : from : subject : date;\r\n",
+            " bh=2jUSOH9NhtVGCQWNr9BrIAPreKQjO6Sn7XIkfJVOzv8=;\r\n",
+            " b=/gCrinpcQOoIfuHNQIbq4pgh9kyIK3AQUdt9OdqQehSwhEIug4D11Bus\r\n",
+            " Fa3bT3FY5OsU7ZbnKELq+eXdp1Q1Dw==\r\n",
+            "From: Joe SixPack <joe@football.example.com>\r\n",
+            "To: Suzie Q <suzie@shopping.example.net>\r\n",
+            "Subject: Is dinner ready?\r\n",
+            "Date: Fri, 11 Jul 2003 21:00:37 -0700 (PDT)\r\n",
+            "Message-ID: <20030712040037.46341.5F8J@football.example.com>\r\n",
+            "\r\n",
+            "Hi.\r\n",
+            "\r\n",
+            "We lost the game.  Are you hungry yet?\r\n",
+            "\r\n",
+            "Joe.\r\n",
+        );
+        let (fields, body) = split_message(raw.as_bytes());
+        let signature = &signatures(&fields)[0];
+        let record = "v=DKIM1; k=ed25519; p=11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo=";
+
+        let result = check_signature(
+            &fields,
+            body,
+            signature,
+            KeyLookup::Found(record.to_owned()),
+        );
+
+        assert_eq!(result.result, "pass");
+        assert_eq!(result.selector.as_deref(), Some("brisbane"));
+    }
+}
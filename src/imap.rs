@@ -3,10 +3,14 @@
 //! Provides timeout-bounded wrappers around `async-imap` operations. All network
 //! calls are enforced to use TLS, and timeouts are derived from server config.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::time::Duration;
 
+use async_imap::imap_proto::types::{
+    BodyParams, BodyStructure, ContentEncoding, MessageSection, NameAttribute, Response,
+    ResponseCode, SectionPath, Status, UidSetMember,
+};
 use async_imap::types::{Fetch, Flag};
 use async_imap::{Client, Session};
 use futures::TryStreamExt;
@@ -14,18 +18,91 @@ use rustls::ClientConfig;
 use rustls::RootCertStore;
 use rustls_pki_types::ServerName;
 use secrecy::ExposeSecret;
+use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
 use tokio::time::timeout;
 use tokio_rustls::TlsConnector;
 
 use crate::config::{AccountConfig, ServerConfig};
 use crate::errors::{AppError, AppResult};
-use crate::mailbox_codec::encode_mailbox_name_for_command;
+use crate::mailbox_codec::{decode_mailbox_name_for_display, encode_mailbox_name_for_command};
+use crate::models::MimePart;
 
 #[derive(Debug, Clone)]
 pub struct HeaderAndFlags {
     pub header_bytes: Vec<u8>,
     pub flags: Vec<String>,
+    pub size_bytes: Option<u32>,
+}
+
+/// Location and encoding of a message's preferred readable text part
+///
+/// Derived from `BODYSTRUCTURE` so that callers can fetch only a prefix of
+/// the part instead of the whole message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextPartRef {
+    /// IMAP section number (e.g. `1`, `1.2`)
+    pub section: String,
+    /// Lowercase MIME type (`text/plain` or `text/html`)
+    pub mime_type: String,
+    /// Declared charset parameter, if any
+    pub charset: Option<String>,
+    /// Lowercase Content-Transfer-Encoding
+    pub transfer_encoding: String,
+}
+
+/// Metadata for one addressable body part, derived from `BODYSTRUCTURE`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BodyPartRef {
+    /// IMAP section number (e.g. `1`, `2.1`)
+    pub section: String,
+    /// Lowercase MIME type (e.g. `application/pdf`)
+    pub content_type: String,
+    /// Filename from `Content-Disposition` or the `name` type parameter
+    pub filename: Option<String>,
+    /// Lowercase Content-Transfer-Encoding
+    pub transfer_encoding: String,
+    /// Encoded part size in octets as reported by the server
+    pub encoded_size_bytes: usize,
+}
+
+impl BodyPartRef {
+    /// Decoded part size derived from the encoded octet count
+    ///
+    /// Base64 sizes assume standard 76-character lines and may exceed the
+    /// decoded size by the two padding bytes; other encodings report octets.
+    pub fn decoded_size_bytes(&self) -> usize {
+        match self.transfer_encoding.as_str() {
+            "base64" => {
+                let octets = self.encoded_size_bytes;
+                let chars = octets / 78 * 76 + (octets % 78).min(76);
+                chars / 4 * 3
+            }
+            _ => self.encoded_size_bytes,
+        }
+    }
+}
+
+/// Message bytes fetched for parsing
+///
+/// When only some body sections were downloaded, leaf parts that were not
+/// needed keep their MIME headers but have empty bodies, and their sizes come
+/// from `BODYSTRUCTURE` instead.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FetchedMessage {
+    /// RFC822 bytes, with omitted bodies left empty
+    pub raw: Vec<u8>,
+    /// Decoded size of each omitted leaf part, keyed by IMAP section
+    pub omitted_sizes: HashMap<String, usize>,
+}
+
+/// MIME tree and total size of one message
+#[derive(Debug, Clone)]
+pub struct MessageStructure {
+    /// `RFC822.SIZE` of the whole message
+    pub size_bytes: Option<u32>,
+    /// Top-level body part
+    pub root: MimePart,
 }
 
 /// Type alias for authenticated IMAP session over TLS
@@ -86,14 +163,7 @@ pub async fn connect_authenticated(
     .map_err(|_| AppError::Timeout("tcp connect timeout".to_owned()))
     .and_then(|r| r.map_err(|e| AppError::Internal(format!("tcp connect failed: {e}"))))?;
 
-    let mut roots = RootCertStore::empty();
-    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
-    roots.add_parsable_certificates(server.trusted_ca_certs.iter().cloned());
-    let tls_config = ClientConfig::builder()
-        .with_root_certificates(roots)
-        .with_no_client_auth();
-    let connector = TlsConnector::from(Arc::new(tls_config));
-
+    let connector = tls_connector(server);
     let server_name = ServerName::try_from(account.host.clone())
         .map_err(|_| AppError::InvalidInput("invalid IMAP host for TLS SNI".to_owned()))?;
     let tls_stream = timeout(greeting_duration, connector.connect(server_name, tcp))
@@ -131,6 +201,17 @@ pub async fn connect_authenticated(
     Ok(session)
 }
 
+/// TLS connector trusting the web PKI roots plus `MAIL_IMAP_CA_CERT_PATH`
+pub fn tls_connector(server: &ServerConfig) -> TlsConnector {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    roots.add_parsable_certificates(server.trusted_ca_certs.iter().cloned());
+    let tls_config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    TlsConnector::from(Arc::new(tls_config))
+}
+
 /// Verify that an authenticated session is still usable.
 pub async fn noop_session(server: &ServerConfig, session: &mut ImapSession) -> AppResult<()> {
     timeout(socket_timeout(server), session.noop())
@@ -185,6 +266,91 @@ pub async fn list_all_mailboxes(
         .and_then(|r| r.map_err(|e| AppError::Internal(format!("LIST stream failed: {e}"))))
 }
 
+/// RFC 6154 special-use mailbox roles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialUse {
+    /// `\Drafts`
+    Drafts,
+    /// `\Sent`
+    Sent,
+    /// `\Trash`
+    Trash,
+}
+
+impl SpecialUse {
+    /// Role name as used in the attribute, such as `Drafts`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Drafts => "Drafts",
+            Self::Sent => "Sent",
+            Self::Trash => "Trash",
+        }
+    }
+
+    fn attribute(self) -> NameAttribute<'static> {
+        match self {
+            Self::Drafts => NameAttribute::Drafts,
+            Self::Sent => NameAttribute::Sent,
+            Self::Trash => NameAttribute::Trash,
+        }
+    }
+
+    /// Conventional mailbox names used when no mailbox carries the attribute
+    fn fallback_names(self) -> &'static [&'static str] {
+        match self {
+            Self::Drafts => &["Drafts"],
+            Self::Sent => &["Sent", "Sent Items", "Sent Messages"],
+            Self::Trash => &["Trash", "Deleted Items", "Deleted Messages"],
+        }
+    }
+}
+
+/// Find the mailbox that serves a special-use role
+///
+/// Prefers a selectable mailbox carrying the RFC 6154 attribute, then one whose
+/// last hierarchy component matches the role's conventional name. Returns the
+/// decoded display name, or `None` when no mailbox qualifies.
+pub async fn find_special_use_mailbox(
+    server: &ServerConfig,
+    session: &mut ImapSession,
+    role: SpecialUse,
+) -> AppResult<Option<String>> {
+    let mailboxes = list_all_mailboxes(server, session).await?;
+    let candidates = mailboxes
+        .iter()
+        .map(|name| (name.attributes(), name.delimiter(), name.name()))
+        .collect::<Vec<_>>();
+    Ok(pick_special_use(role, &candidates).map(decode_mailbox_name_for_display))
+}
+
+fn pick_special_use<'a>(
+    role: SpecialUse,
+    mailboxes: &[(&[NameAttribute<'_>], Option<&str>, &'a str)],
+) -> Option<&'a str> {
+    let selectable = mailboxes
+        .iter()
+        .filter(|(attributes, _, _)| !attributes.contains(&NameAttribute::NoSelect));
+    let attribute = role.attribute();
+    selectable
+        .clone()
+        .find(|(attributes, _, _)| attributes.contains(&attribute))
+        .or_else(|| {
+            selectable.clone().find(|(_, delimiter, name)| {
+                let display = decode_mailbox_name_for_display(name);
+                let leaf = match delimiter {
+                    Some(delimiter) if !delimiter.is_empty() => {
+                        display.rsplit(delimiter).next().unwrap_or_default()
+                    }
+                    _ => display.as_str(),
+                };
+                role.fallback_names()
+                    .iter()
+                    .any(|fallback| leaf.eq_ignore_ascii_case(fallback))
+            })
+        })
+        .map(|(_, _, name)| *name)
+}
+
 /// Create a mailbox if it does not already exist.
 pub async fn create_mailbox_if_missing(
     server: &ServerConfig,
@@ -418,6 +584,32 @@ pub async fn fetch_raw_message(
     Ok(body.to_vec())
 }
 
+/// Full message source with the flags and arrival time needed to store it
+#[derive(Debug, Clone)]
+pub struct StoredMessage {
+    pub raw: Vec<u8>,
+    pub flags: Vec<String>,
+    pub internal_date: Option<chrono::DateTime<chrono::FixedOffset>>,
+}
+
+/// Fetch full message source, flags, and `INTERNALDATE` without setting `\Seen`
+pub async fn fetch_stored_message(
+    server: &ServerConfig,
+    session: &mut ImapSession,
+    uid: u32,
+) -> AppResult<StoredMessage> {
+    let fetch = fetch_one(server, session, uid, "(UID FLAGS INTERNALDATE BODY.PEEK[])").await?;
+    let raw = fetch
+        .body()
+        .ok_or_else(|| AppError::Internal("message has no full message body".to_owned()))?
+        .to_vec();
+    Ok(StoredMessage {
+        raw,
+        flags: flags_to_strings(&fetch),
+        internal_date: fetch.internal_date(),
+    })
+}
+
 /// Fetch curated headers and flags
 ///
 /// Returns standard headers (Date, From, To, CC, Subject) and message flags.
@@ -443,7 +635,10 @@ pub async fn fetch_headers_and_flags(
     Ok((header_bytes, flags_to_strings(&fetch)))
 }
 
-/// Fetch curated headers and flags for a UID set in one round trip.
+/// Fetch curated headers, flags, and sizes for a UID set in one round trip.
+///
+/// The headers include `List-Id`, `List-Unsubscribe`, and `Precedence` so
+/// summaries can report bulk mail.
 pub async fn fetch_headers_and_flags_by_uid_set(
     server: &ServerConfig,
     session: &mut ImapSession,
@@ -453,7 +648,7 @@ pub async fn fetch_headers_and_flags_by_uid_set(
         socket_timeout(server),
         session.uid_fetch(
             uid_set,
-            "(UID FLAGS BODY.PEEK[HEADER.FIELDS (DATE FROM TO CC SUBJECT)])",
+            "(UID FLAGS RFC822.SIZE BODY.PEEK[HEADER.FIELDS (DATE FROM TO CC SUBJECT LIST-ID LIST-UNSUBSCRIBE PRECEDENCE)])",
         ),
     )
     .await
@@ -477,12 +672,667 @@ pub async fn fetch_headers_and_flags_by_uid_set(
             HeaderAndFlags {
                 header_bytes,
                 flags: flags_to_strings(&fetch),
+                size_bytes: fetch.size,
             },
         );
     }
     Ok(by_uid)
 }
 
+/// Locate the preferred text part of each message in a UID set.
+///
+/// Runs `UID FETCH (UID BODYSTRUCTURE)`; messages without a readable inline
+/// text part are omitted from the result.
+pub async fn fetch_text_part_refs_by_uid_set(
+    server: &ServerConfig,
+    session: &mut ImapSession,
+    uid_set: &str,
+) -> AppResult<HashMap<u32, TextPartRef>> {
+    let fetches = uid_fetch_collect(server, session, uid_set, "(UID BODYSTRUCTURE)").await?;
+    let mut by_uid = HashMap::new();
+    for fetch in fetches {
+        let (Some(uid), Some(structure)) = (fetch.uid, fetch.bodystructure()) else {
+            continue;
+        };
+        if let Some(part) = preferred_text_part(structure) {
+            by_uid.insert(uid, part);
+        }
+    }
+    Ok(by_uid)
+}
+
+/// Fetch a bounded prefix of one body section for every UID in a set.
+///
+/// Uses `BODY.PEEK[<section>]<0.max_bytes>` so `\Seen` is not set and large
+/// attachments elsewhere in the message are never transferred.
+pub async fn fetch_partial_section_by_uid_set(
+    server: &ServerConfig,
+    session: &mut ImapSession,
+    uid_set: &str,
+    section: &str,
+    max_bytes: usize,
+) -> AppResult<HashMap<u32, Vec<u8>>> {
+    let section_path = SectionPath::Part(parse_section_path(section)?, None);
+    let query = format!("(UID BODY.PEEK[{section}]<0.{max_bytes}>)");
+    let fetches = uid_fetch_collect(server, session, uid_set, &query).await?;
+    let mut by_uid = HashMap::new();
+    for fetch in fetches {
+        let Some(uid) = fetch.uid else {
+            continue;
+        };
+        if let Some(bytes) = fetch.section(&section_path) {
+            by_uid.insert(uid, bytes.to_vec());
+        }
+    }
+    Ok(by_uid)
+}
+
+/// Look up one body part of a message by IMAP section number.
+///
+/// `root` asks for the message root itself, whose section is `1` only when it
+/// is not multipart. Returns `Ok(None)` when the message has no leaf part at
+/// `section`, or when `root` is set and the root is multipart.
+pub async fn fetch_body_part_ref(
+    server: &ServerConfig,
+    session: &mut ImapSession,
+    uid: u32,
+    section: &str,
+    root: bool,
+) -> AppResult<Option<BodyPartRef>> {
+    let path = parse_section_path(section)?;
+    let fetch = fetch_one(server, session, uid, "(UID BODYSTRUCTURE)").await?;
+    let structure = fetch
+        .bodystructure()
+        .ok_or_else(|| AppError::Internal("message has no body structure".to_owned()))?;
+    Ok(locate_part_ref(structure, &path, section, root))
+}
+
+/// Resolve `section` to a leaf part, treating a multipart root as absent
+/// when the caller asked for the root
+fn locate_part_ref(
+    structure: &BodyStructure<'_>,
+    path: &[u32],
+    section: &str,
+    root: bool,
+) -> Option<BodyPartRef> {
+    if root && matches!(structure, BodyStructure::Multipart { .. }) {
+        return None;
+    }
+    locate_body_part(structure, path).and_then(|part| body_part_ref(part, section))
+}
+
+/// Fetch one complete body section without setting `\Seen`.
+///
+/// Returns the section bytes still in their transfer encoding.
+pub async fn fetch_body_section(
+    server: &ServerConfig,
+    session: &mut ImapSession,
+    uid: u32,
+    section: &str,
+) -> AppResult<Vec<u8>> {
+    let section_path = SectionPath::Part(parse_section_path(section)?, None);
+    let query = format!("(UID BODY.PEEK[{section}])");
+    let fetch = fetch_one(server, session, uid, &query).await?;
+    let bytes = fetch
+        .section(&section_path)
+        .ok_or_else(|| AppError::Internal(format!("message has no body section {section}")))?;
+    Ok(bytes.to_vec())
+}
+
+/// Fetch a byte range of one body section without setting `\Seen`.
+///
+/// Uses `BODY.PEEK[<section>]<offset.max_bytes>` so only the range is
+/// transferred. Returns the bytes still in their transfer encoding; fewer than
+/// `max_bytes` come back at the end of the section.
+pub async fn fetch_body_section_range(
+    server: &ServerConfig,
+    session: &mut ImapSession,
+    uid: u32,
+    section: &str,
+    offset: usize,
+    max_bytes: usize,
+) -> AppResult<Vec<u8>> {
+    let section_path = SectionPath::Part(parse_section_path(section)?, None);
+    let query = format!("(UID {})", section_range_query(section, offset, max_bytes));
+    let fetch = fetch_one(server, session, uid, &query).await?;
+    let bytes = fetch
+        .section(&section_path)
+        .ok_or_else(|| AppError::Internal(format!("message has no body section {section}")))?;
+    Ok(bytes.to_vec())
+}
+
+/// Partial fetch item for `max_bytes` of a body section starting at `offset`.
+pub fn section_range_query(section: &str, offset: usize, max_bytes: usize) -> String {
+    format!("BODY.PEEK[{section}]<{offset}.{max_bytes}>")
+}
+
+/// Fetch only the body sections of a message that `needed` selects.
+///
+/// Runs `UID FETCH (UID BODYSTRUCTURE)`, then one `UID FETCH` of the header
+/// and MIME header sections plus the selected leaf bodies, and rebuilds the
+/// message with the remaining bodies left empty. Returns `Ok(None)` when
+/// every body is needed, or when the structure has signed or encrypted parts
+/// or a multipart without a boundary; fetch the full message instead.
+pub async fn fetch_message_parts(
+    server: &ServerConfig,
+    session: &mut ImapSession,
+    uid: u32,
+    needed: &(dyn Fn(&BodyPartRef) -> bool + Sync),
+) -> AppResult<Option<FetchedMessage>> {
+    let fetch = fetch_one(server, session, uid, "(UID BODYSTRUCTURE)").await?;
+    let structure = fetch
+        .bodystructure()
+        .ok_or_else(|| AppError::Internal("message has no body structure".to_owned()))?;
+    let Some(plan) = FetchPlan::new(structure, needed) else {
+        return Ok(None);
+    };
+    let fetch = fetch_one(server, session, uid, &plan.query()).await?;
+    let raw = plan.assemble(|path| fetch.section(path))?;
+    Ok(Some(FetchedMessage {
+        raw,
+        omitted_sizes: plan.omitted_sizes,
+    }))
+}
+
+/// Fetch a message's MIME tree without downloading any body content.
+///
+/// Runs `UID FETCH (UID RFC822.SIZE BODYSTRUCTURE)`.
+pub async fn fetch_message_structure(
+    server: &ServerConfig,
+    session: &mut ImapSession,
+    uid: u32,
+) -> AppResult<MessageStructure> {
+    let fetch = fetch_one(server, session, uid, "(UID RFC822.SIZE BODYSTRUCTURE)").await?;
+    let structure = fetch
+        .bodystructure()
+        .ok_or_else(|| AppError::Internal("message has no body structure".to_owned()))?;
+    Ok(MessageStructure {
+        size_bytes: fetch.size,
+        root: message_body_part(structure, &[]),
+    })
+}
+
+/// Describe the body of a message at `path` (empty for the top level).
+///
+/// Part ids follow `imap_get_message`: the top-level body is `1` and its
+/// children are `1.<section>`.
+fn message_body_part(body: &BodyStructure<'_>, path: &[u32]) -> MimePart {
+    if let BodyStructure::Multipart { .. } = body {
+        return mime_part(body, path, section_spec(path, SectionKind::Text), None);
+    }
+    let mut body_path = path.to_vec();
+    body_path.push(1);
+    let section = join_section(&body_path);
+    let part_id = if path.is_empty() {
+        "1".to_owned()
+    } else {
+        format!("1.{section}")
+    };
+    mime_part(body, &body_path, section, Some(part_id))
+}
+
+fn mime_part(
+    structure: &BodyStructure<'_>,
+    path: &[u32],
+    section: String,
+    part_id: Option<String>,
+) -> MimePart {
+    let (common, other, lines) = match structure {
+        BodyStructure::Basic { common, other, .. } => (common, Some(other), None),
+        BodyStructure::Text {
+            common,
+            other,
+            lines,
+            ..
+        }
+        | BodyStructure::Message {
+            common,
+            other,
+            lines,
+            ..
+        } => (common, Some(other), Some(*lines)),
+        BodyStructure::Multipart { common, .. } => (common, None, None),
+    };
+    let parts = match structure {
+        BodyStructure::Multipart { bodies, .. } => bodies
+            .iter()
+            .enumerate()
+            .map(|(index, body)| {
+                let mut child = path.to_vec();
+                child.push(index as u32 + 1);
+                let child_section = join_section(&child);
+                let child_id = (!matches!(body, BodyStructure::Multipart { .. }))
+                    .then(|| format!("1.{child_section}"));
+                mime_part(body, &child, child_section, child_id)
+            })
+            .collect(),
+        BodyStructure::Message { body, .. } => vec![message_body_part(body, path)],
+        BodyStructure::Basic { .. } | BodyStructure::Text { .. } => Vec::new(),
+    };
+    let parameters = param_map(&common.ty.params);
+    let disposition_parameters = common
+        .disposition
+        .as_ref()
+        .map(|disposition| param_map(&disposition.params))
+        .unwrap_or_default();
+    let filename = param_filename(&disposition_parameters, "filename")
+        .or_else(|| param_filename(&parameters, "name"));
+    MimePart {
+        part_id,
+        section,
+        content_type: format!("{}/{}", common.ty.ty, common.ty.subtype).to_ascii_lowercase(),
+        parameters,
+        disposition: common
+            .disposition
+            .as_ref()
+            .map(|disposition| disposition.ty.to_ascii_lowercase()),
+        disposition_parameters,
+        filename,
+        encoding: other.map(|other| content_encoding_label(&other.transfer_encoding)),
+        size_bytes: other.map(|other| other.octets),
+        lines,
+        content_id: other.and_then(|other| other.id.as_ref().map(|id| id.to_string())),
+        language: common
+            .language
+            .as_ref()
+            .map(|tags| tags.iter().map(|tag| tag.to_string()).collect()),
+        parts,
+    }
+}
+
+fn param_map(params: &BodyParams<'_>) -> BTreeMap<String, String> {
+    params
+        .iter()
+        .flatten()
+        .map(|(name, value)| (name.to_ascii_lowercase(), value.to_string()))
+        .collect()
+}
+
+/// Read a filename parameter, decoding RFC 2231 (`name*`) and RFC 2047 values.
+fn param_filename(params: &BTreeMap<String, String>, name: &str) -> Option<String> {
+    if let Some(value) = params.get(name) {
+        let decoded = mailparse::parse_header(format!("X: {value}").as_bytes())
+            .map(|(header, _)| header.get_value())
+            .unwrap_or_else(|_| value.clone());
+        return Some(decoded);
+    }
+    let extended = params.get(&format!("{name}*"))?;
+    let (charset, rest) = extended.split_once('\'')?;
+    let (_, encoded) = rest.split_once('\'')?;
+    if !matches!(
+        charset.to_ascii_lowercase().as_str(),
+        "utf-8" | "us-ascii" | ""
+    ) {
+        return Some(encoded.to_owned());
+    }
+    let bytes = urlencoding::decode_binary(encoded.as_bytes());
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Sections to download and how to splice them back into a message
+#[derive(Debug, Default)]
+struct FetchPlan {
+    pieces: Vec<Piece>,
+    omitted_sizes: HashMap<String, usize>,
+    full_fetch: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Section { path: Vec<u32>, kind: SectionKind },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SectionKind {
+    Header,
+    Mime,
+    Text,
+    Body,
+}
+
+impl FetchPlan {
+    /// Plan a sparse fetch, or `None` when the full message should be fetched.
+    fn new(structure: &BodyStructure<'_>, needed: &dyn Fn(&BodyPartRef) -> bool) -> Option<Self> {
+        let mut plan = Self::default();
+        plan.message(structure, &[], needed);
+        (!plan.full_fetch && !plan.omitted_sizes.is_empty()).then_some(plan)
+    }
+
+    /// Plan an RFC822 message (the top level or an embedded `message/rfc822`).
+    fn message(
+        &mut self,
+        body: &BodyStructure<'_>,
+        path: &[u32],
+        needed: &dyn Fn(&BodyPartRef) -> bool,
+    ) {
+        self.section(path, SectionKind::Header);
+        if let BodyStructure::Multipart { .. } = body {
+            self.multipart(body, path, needed);
+        } else {
+            let mut body_path = path.to_vec();
+            body_path.push(1);
+            self.leaf(body, &body_path, path, SectionKind::Text, needed);
+        }
+    }
+
+    fn multipart(
+        &mut self,
+        structure: &BodyStructure<'_>,
+        path: &[u32],
+        needed: &dyn Fn(&BodyPartRef) -> bool,
+    ) {
+        let BodyStructure::Multipart { common, bodies, .. } = structure else {
+            return;
+        };
+        let boundary = body_param(&common.ty.params, "boundary");
+        let subtype = common.ty.subtype.to_ascii_lowercase();
+        let Some(boundary) =
+            boundary.filter(|_| !matches!(subtype.as_str(), "signed" | "encrypted"))
+        else {
+            self.full_fetch = true;
+            return;
+        };
+        for (index, body) in bodies.iter().enumerate() {
+            let mut child = path.to_vec();
+            child.push(index as u32 + 1);
+            self.pieces
+                .push(Piece::Literal(format!("--{boundary}\r\n")));
+            self.section(&child, SectionKind::Mime);
+            match body {
+                BodyStructure::Multipart { .. } => self.multipart(body, &child, needed),
+                BodyStructure::Message { body, other, .. }
+                    if is_identity_encoding(&other.transfer_encoding) =>
+                {
+                    self.message(body, &child, needed);
+                }
+                _ => self.leaf(body, &child, &child, SectionKind::Body, needed),
+            }
+            self.pieces.push(Piece::Literal("\r\n".to_owned()));
+        }
+        self.pieces
+            .push(Piece::Literal(format!("--{boundary}--\r\n")));
+    }
+
+    /// Fetch a leaf body from `content_path`/`kind`, or record it as omitted.
+    fn leaf(
+        &mut self,
+        structure: &BodyStructure<'_>,
+        path: &[u32],
+        content_path: &[u32],
+        kind: SectionKind,
+        needed: &dyn Fn(&BodyPartRef) -> bool,
+    ) {
+        let section = join_section(path);
+        let Some(part) = body_part_ref(structure, &section) else {
+            self.full_fetch = true;
+            return;
+        };
+        if matches!(
+            part.content_type.as_str(),
+            "application/pkcs7-mime" | "application/x-pkcs7-mime"
+        ) {
+            self.full_fetch = true;
+        } else if needed(&part) {
+            self.section(content_path, kind);
+        } else {
+            self.omitted_sizes
+                .insert(section, part.decoded_size_bytes());
+        }
+    }
+
+    fn section(&mut self, path: &[u32], kind: SectionKind) {
+        self.pieces.push(Piece::Section {
+            path: path.to_vec(),
+            kind,
+        });
+    }
+
+    /// `UID FETCH` items for every planned section.
+    fn query(&self) -> String {
+        let items = self
+            .pieces
+            .iter()
+            .filter_map(|piece| match piece {
+                Piece::Section { path, kind } => {
+                    Some(format!("BODY.PEEK[{}]", section_spec(path, *kind)))
+                }
+                Piece::Literal(_) => None,
+            })
+            .collect::<Vec<_>>();
+        format!("(UID {})", items.join(" "))
+    }
+
+    /// Splice the fetched sections into RFC822 bytes.
+    fn assemble<'a>(
+        &self,
+        section: impl Fn(&SectionPath) -> Option<&'a [u8]>,
+    ) -> AppResult<Vec<u8>> {
+        let mut raw = Vec::new();
+        for piece in &self.pieces {
+            let (path, kind) = match piece {
+                Piece::Literal(text) => {
+                    raw.extend_from_slice(text.as_bytes());
+                    continue;
+                }
+                Piece::Section { path, kind } => (path, *kind),
+            };
+            let bytes = section(&section_path(path, kind)).ok_or_else(|| {
+                AppError::Internal(format!(
+                    "message has no body section {}",
+                    section_spec(path, kind)
+                ))
+            })?;
+            raw.extend_from_slice(bytes);
+            if matches!(kind, SectionKind::Header | SectionKind::Mime) {
+                terminate_header_block(&mut raw);
+            }
+        }
+        Ok(raw)
+    }
+}
+
+fn section_spec(path: &[u32], kind: SectionKind) -> String {
+    let keyword = match kind {
+        SectionKind::Header => "HEADER",
+        SectionKind::Mime => "MIME",
+        SectionKind::Text => "TEXT",
+        SectionKind::Body => return join_section(path),
+    };
+    if path.is_empty() {
+        keyword.to_owned()
+    } else {
+        format!("{}.{keyword}", join_section(path))
+    }
+}
+
+fn section_path(path: &[u32], kind: SectionKind) -> SectionPath {
+    let section = match kind {
+        SectionKind::Header => Some(MessageSection::Header),
+        SectionKind::Mime => Some(MessageSection::Mime),
+        SectionKind::Text => Some(MessageSection::Text),
+        SectionKind::Body => None,
+    };
+    match section {
+        Some(section) if path.is_empty() => SectionPath::Full(section),
+        section => SectionPath::Part(path.to_vec(), section),
+    }
+}
+
+fn join_section(path: &[u32]) -> String {
+    path.iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// End a fetched header block with exactly one blank line.
+fn terminate_header_block(raw: &mut Vec<u8>) {
+    if raw.ends_with(b"\r\n\r\n") || raw.ends_with(b"\n\n") {
+        return;
+    }
+    if raw.ends_with(b"\n") {
+        raw.extend_from_slice(b"\r\n");
+    } else {
+        raw.extend_from_slice(b"\r\n\r\n");
+    }
+}
+
+fn is_identity_encoding(encoding: &ContentEncoding<'_>) -> bool {
+    matches!(
+        encoding,
+        ContentEncoding::SevenBit | ContentEncoding::EightBit | ContentEncoding::Binary
+    )
+}
+
+fn parse_section_path(section: &str) -> AppResult<Vec<u32>> {
+    section
+        .split('.')
+        .map(|component| component.parse::<u32>().ok().filter(|value| *value > 0))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| AppError::InvalidInput(format!("invalid body section '{section}'")))
+}
+
+/// Resolve an IMAP section path against a body structure.
+///
+/// Follows RFC 3501 numbering: a non-multipart body is part `1` of its
+/// message, and parts of an encapsulated `message/rfc822` continue the path.
+fn locate_body_part<'a>(
+    structure: &'a BodyStructure<'a>,
+    path: &[u32],
+) -> Option<&'a BodyStructure<'a>> {
+    let (first, rest) = path.split_first()?;
+    let child = match structure {
+        BodyStructure::Multipart { bodies, .. } => bodies.get(*first as usize - 1)?,
+        _ if *first == 1 => structure,
+        _ => return None,
+    };
+    if rest.is_empty() {
+        return Some(child);
+    }
+    match child {
+        BodyStructure::Message { body, .. } => locate_body_part(body, rest),
+        BodyStructure::Multipart { .. } => locate_body_part(child, rest),
+        BodyStructure::Basic { .. } | BodyStructure::Text { .. } => None,
+    }
+}
+
+fn body_part_ref(structure: &BodyStructure<'_>, section: &str) -> Option<BodyPartRef> {
+    let (common, other) = match structure {
+        BodyStructure::Basic { common, other, .. }
+        | BodyStructure::Text { common, other, .. }
+        | BodyStructure::Message { common, other, .. } => (common, other),
+        BodyStructure::Multipart { .. } => return None,
+    };
+    let filename = common
+        .disposition
+        .as_ref()
+        .and_then(|disposition| body_param(&disposition.params, "filename"))
+        .or_else(|| body_param(&common.ty.params, "name"));
+    Some(BodyPartRef {
+        section: section.to_owned(),
+        content_type: format!("{}/{}", common.ty.ty, common.ty.subtype).to_ascii_lowercase(),
+        filename,
+        transfer_encoding: content_encoding_label(&other.transfer_encoding),
+        encoded_size_bytes: other.octets as usize,
+    })
+}
+
+/// Pick the first inline `text/plain` part, falling back to `text/html`.
+///
+/// Nested `message/rfc822` parts are not descended into.
+pub fn preferred_text_part(structure: &BodyStructure<'_>) -> Option<TextPartRef> {
+    let mut plain = None;
+    let mut html = None;
+    collect_text_parts(structure, &mut Vec::new(), &mut plain, &mut html);
+    plain.or(html)
+}
+
+fn collect_text_parts(
+    structure: &BodyStructure<'_>,
+    path: &mut Vec<u32>,
+    plain: &mut Option<TextPartRef>,
+    html: &mut Option<TextPartRef>,
+) {
+    match structure {
+        BodyStructure::Multipart { bodies, .. } => {
+            for (index, body) in bodies.iter().enumerate() {
+                path.push(index as u32 + 1);
+                collect_text_parts(body, path, plain, html);
+                path.pop();
+            }
+        }
+        BodyStructure::Text { common, other, .. } => {
+            let is_attachment = common
+                .disposition
+                .as_ref()
+                .is_some_and(|disposition| disposition.ty.eq_ignore_ascii_case("attachment"));
+            let subtype = common.ty.subtype.to_ascii_lowercase();
+            let slot = match subtype.as_str() {
+                "plain" => &mut *plain,
+                "html" => &mut *html,
+                _ => return,
+            };
+            if is_attachment || slot.is_some() {
+                return;
+            }
+            let section = if path.is_empty() {
+                "1".to_owned()
+            } else {
+                path.iter()
+                    .map(u32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(".")
+            };
+            let charset = body_param(&common.ty.params, "charset");
+            *slot = Some(TextPartRef {
+                section,
+                mime_type: format!("text/{subtype}"),
+                charset,
+                transfer_encoding: content_encoding_label(&other.transfer_encoding),
+            });
+        }
+        BodyStructure::Basic { .. } | BodyStructure::Message { .. } => {}
+    }
+}
+
+fn body_param(params: &BodyParams<'_>, key: &str) -> Option<String> {
+    params.as_ref().and_then(|params| {
+        params
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.to_string())
+    })
+}
+
+fn content_encoding_label(encoding: &ContentEncoding<'_>) -> String {
+    match encoding {
+        ContentEncoding::SevenBit => "7bit".to_owned(),
+        ContentEncoding::EightBit => "8bit".to_owned(),
+        ContentEncoding::Binary => "binary".to_owned(),
+        ContentEncoding::Base64 => "base64".to_owned(),
+        ContentEncoding::QuotedPrintable => "quoted-printable".to_owned(),
+        ContentEncoding::Other(other) => other.to_ascii_lowercase(),
+    }
+}
+
+/// Run `UID FETCH` and collect every response within the socket timeout.
+async fn uid_fetch_collect(
+    server: &ServerConfig,
+    session: &mut ImapSession,
+    uid_set: &str,
+    query: &str,
+) -> AppResult<Vec<Fetch>> {
+    let stream = timeout(socket_timeout(server), session.uid_fetch(uid_set, query))
+        .await
+        .map_err(|_| AppError::Timeout("UID FETCH timed out".to_owned()))
+        .and_then(|r| r.map_err(|e| AppError::Internal(format!("uid fetch failed: {e}"))))?;
+    timeout(socket_timeout(server), stream.try_collect())
+        .await
+        .map_err(|_| AppError::Timeout("UID FETCH stream timed out".to_owned()))
+        .and_then(|r| r.map_err(|e| AppError::Internal(format!("uid fetch stream failed: {e}"))))
+}
+
 /// Fetch message flags only
 ///
 /// Returns IMAP flags (e.g., `\Seen`, `\Flagged`, `\Draft`) as strings.
@@ -592,7 +1442,6 @@ pub async fn fetch_raw_message_range(
 ///
 /// Runs `UID STORE` with a flag query string. Use `+FLAGS.SILENT` to add
 /// flags or `-FLAGS.SILENT` to remove flags.
-#[cfg_attr(not(test), allow(dead_code))]
 pub async fn uid_store(
     server: &ServerConfig,
     session: &mut ImapSession,
@@ -685,7 +1534,6 @@ pub async fn uid_move_sequence(
 /// Permanently delete a message
 ///
 /// Runs `UID EXPUNGE` to immediately remove the message marked as `\Deleted`.
-#[cfg_attr(not(test), allow(dead_code))]
 pub async fn uid_expunge(
     server: &ServerConfig,
     session: &mut ImapSession,
@@ -715,25 +1563,149 @@ pub async fn uid_expunge_sequence(
 
 /// Append raw RFC822 message to mailbox
 ///
-/// Used for cross-account copy operations. Does not return the new UID
-/// directly (would require `UIDPLUS` capability).
-#[cfg_attr(not(test), allow(dead_code))]
+/// Sets `flags` and, when given, `INTERNALDATE` on the new message. Does not
+/// return the new UID directly (would require `UIDPLUS` capability).
 pub async fn append(
     server: &ServerConfig,
     session: &mut ImapSession,
     mailbox: &str,
+    flags: &[String],
+    internal_date: Option<chrono::DateTime<chrono::FixedOffset>>,
     content: &[u8],
 ) -> AppResult<()> {
     let encoded_mailbox = encode_mailbox_name_for_command(mailbox);
+    let flags = (!flags.is_empty()).then(|| format!("({})", flags.join(" ")));
+    let internal_date =
+        internal_date.map(|date| date.format("\"%d-%b-%Y %H:%M:%S %z\"").to_string());
     timeout(
         socket_timeout(server),
-        session.append(&encoded_mailbox, None, None, content),
+        session.append(
+            &encoded_mailbox,
+            flags.as_deref(),
+            internal_date.as_deref(),
+            content,
+        ),
     )
     .await
     .map_err(|_| AppError::Timeout("APPEND timed out".to_owned()))
     .and_then(|r| r.map_err(|e| AppError::Internal(format!("APPEND failed: {e}"))))
 }
 
+/// Append a message and report its UID via `UIDPLUS`
+///
+/// Runs `APPEND` directly because `async-imap` discards the tagged response
+/// code. Returns the `APPENDUID` UID validity and UID, or `None` when the
+/// server does not send `APPENDUID`.
+pub async fn append_with_uid(
+    server: &ServerConfig,
+    session: &mut ImapSession,
+    mailbox: &str,
+    flags: &[String],
+    content: &[u8],
+) -> AppResult<Option<(u32, u32)>> {
+    let encoded_mailbox = format!(
+        "\"{}\"",
+        escape_quoted(&encode_mailbox_name_for_command(mailbox))?
+    );
+    let command = format!(
+        "APPEND {encoded_mailbox} ({}) {{{}}}",
+        flags.join(" "),
+        content.len()
+    );
+    timeout(
+        socket_timeout(server),
+        run_append(session, &command, content),
+    )
+    .await
+    .map_err(|_| AppError::Timeout("APPEND timed out".to_owned()))?
+}
+
+async fn run_append(
+    session: &mut ImapSession,
+    command: &str,
+    content: &[u8],
+) -> AppResult<Option<(u32, u32)>> {
+    let id = session
+        .run_command(command)
+        .await
+        .map_err(|e| AppError::Internal(format!("APPEND failed: {e}")))?;
+    next_append_response(session, |response| match response {
+        Response::Continue { .. } => Some(Ok(())),
+        Response::Done {
+            tag, information, ..
+        } if *tag == id => Some(Err(AppError::Internal(format!(
+            "APPEND rejected: {}",
+            information.as_deref().unwrap_or("no reason given")
+        )))),
+        _ => None,
+    })
+    .await??;
+
+    let stream = session.get_mut();
+    let written = async {
+        stream.write_all(content).await?;
+        stream.write_all(b"\r\n").await?;
+        stream.flush().await
+    };
+    written
+        .await
+        .map_err(|e| AppError::Internal(format!("APPEND failed: {e}")))?;
+
+    next_append_response(session, |response| match response {
+        Response::Done {
+            tag,
+            status,
+            code,
+            information,
+        } if *tag == id => Some(if *status == Status::Ok {
+            Ok(match code {
+                Some(ResponseCode::AppendUid(uidvalidity, uids)) => match uids.as_slice() {
+                    [UidSetMember::Uid(uid)] => Some((*uidvalidity, *uid)),
+                    _ => None,
+                },
+                _ => None,
+            })
+        } else {
+            Err(AppError::Internal(format!(
+                "APPEND failed: {}",
+                information.as_deref().unwrap_or("no reason given")
+            )))
+        }),
+        _ => None,
+    })
+    .await?
+}
+
+/// Read responses until `handle` accepts one, skipping untagged data.
+async fn next_append_response<T>(
+    session: &mut ImapSession,
+    mut handle: impl FnMut(&Response<'_>) -> Option<T>,
+) -> AppResult<T> {
+    loop {
+        let response = session
+            .read_response()
+            .await
+            .map_err(|e| AppError::Internal(format!("APPEND failed: {e}")))?
+            .ok_or_else(|| AppError::Internal("APPEND failed: connection closed".to_owned()))?;
+        if let Some(value) = handle(response.parsed()) {
+            return Ok(value);
+        }
+    }
+}
+
+/// Escape a value for use inside an IMAP quoted string
+///
+/// Backslashes and double quotes are escaped; CR, LF, and NUL cannot appear
+/// in a quoted string (RFC 3501 section 4.3) and are rejected.
+pub fn escape_quoted(value: &str) -> AppResult<String> {
+    if value.contains(['\r', '\n', '\0']) {
+        return Err(AppError::InvalidInput(
+            "IMAP strings must not contain line breaks or NUL".to_owned(),
+        ));
+    }
+    Ok(value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeMap;
@@ -752,18 +1724,27 @@ mod tests {
 
     use crate::mailbox_codec::encode_mailbox_name_for_command;
 
+    use async_imap::imap_proto::types::{
+        BodyContentCommon, BodyContentSinglePart, BodyStructure, ContentDisposition,
+        ContentEncoding, ContentType, MessageSection, NameAttribute, SectionPath,
+    };
+
     use super::{
-        append, build_mailbox_parent_paths, fetch_flags, fetch_raw_message, list_all_mailboxes,
-        select_mailbox_readonly, select_mailbox_readwrite, socket_timeout, uid_copy, uid_expunge,
-        uid_move, uid_search, uid_store,
+        BodyPartRef, FetchPlan, SpecialUse, TextPartRef, append, append_with_uid, body_part_ref,
+        build_mailbox_parent_paths, escape_quoted, fetch_flags, fetch_raw_message,
+        list_all_mailboxes, locate_body_part, locate_part_ref, message_body_part, param_filename,
+        pick_special_use, preferred_text_part, select_mailbox_readonly, select_mailbox_readwrite,
+        socket_timeout, uid_copy, uid_expunge, uid_move, uid_search, uid_store,
     };
-    use crate::config::{AccountConfig, ServerConfig};
+    use crate::config::{AccountConfig, ServerConfig, SmtpConfig, SmtpSecurity};
+    use crate::smtp::Envelope;
 
     /// Holds connection details for a GreenMail test server instance.
     #[derive(Debug, Clone)]
     struct GreenmailEndpoints {
         host: String,
         smtp_port: u16,
+        smtps_port: u16,
         imap_port: u16,
         user: String,
         pass: String,
@@ -782,6 +1763,7 @@ mod tests {
         GreenmailEndpoints {
             host: std::env::var("GREENMAIL_HOST").unwrap_or_else(|_| "localhost".to_owned()),
             smtp_port: parse_test_port("GREENMAIL_SMTP_PORT", 3025),
+            smtps_port: parse_test_port("GREENMAIL_SMTPS_PORT", 3465),
             imap_port: parse_test_port("GREENMAIL_IMAP_PORT", 3143),
             user: std::env::var("GREENMAIL_USER").unwrap_or_else(|_| "test@localhost".to_owned()),
             pass: std::env::var("GREENMAIL_PASS").unwrap_or_else(|_| "test".to_owned()),
@@ -796,6 +1778,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn escape_quoted_escapes_and_rejects_line_breaks() {
+        assert_eq!(
+            escape_quoted(r#"Drafts "A" \ B"#).expect("quotable"),
+            r#"Drafts \"A\" \\ B"#
+        );
+        assert!(escape_quoted("Drafts\r\nA002 LOGOUT").is_err());
+        assert!(escape_quoted("Drafts\0").is_err());
+    }
+
     #[test]
     fn mailbox_parent_paths_uses_dot_when_no_slash_exists() {
         assert_eq!(
@@ -804,6 +1796,394 @@ mod tests {
         );
     }
 
+    fn text_part(
+        subtype: &'static str,
+        attachment: bool,
+        encoding: ContentEncoding<'static>,
+    ) -> BodyStructure<'static> {
+        BodyStructure::Text {
+            common: BodyContentCommon {
+                ty: ContentType {
+                    ty: "TEXT".into(),
+                    subtype: subtype.into(),
+                    params: Some(vec![("CHARSET".into(), "iso-8859-1".into())]),
+                },
+                disposition: attachment.then(|| ContentDisposition {
+                    ty: "ATTACHMENT".into(),
+                    params: None,
+                }),
+                language: None,
+                location: None,
+            },
+            other: BodyContentSinglePart {
+                id: None,
+                md5: None,
+                description: None,
+                transfer_encoding: encoding,
+                octets: 100,
+            },
+            lines: 3,
+            extension: None,
+        }
+    }
+
+    fn multipart(
+        subtype: &'static str,
+        bodies: Vec<BodyStructure<'static>>,
+    ) -> BodyStructure<'static> {
+        BodyStructure::Multipart {
+            common: BodyContentCommon {
+                ty: ContentType {
+                    ty: "MULTIPART".into(),
+                    subtype: subtype.into(),
+                    params: Some(vec![("BOUNDARY".into(), subtype.into())]),
+                },
+                disposition: None,
+                language: None,
+                location: None,
+            },
+            bodies,
+            extension: None,
+        }
+    }
+
+    fn video_attachment(octets: u32) -> BodyStructure<'static> {
+        BodyStructure::Basic {
+            common: BodyContentCommon {
+                ty: ContentType {
+                    ty: "VIDEO".into(),
+                    subtype: "MP4".into(),
+                    params: Some(vec![("NAME".into(), "clip.mp4".into())]),
+                },
+                disposition: Some(ContentDisposition {
+                    ty: "ATTACHMENT".into(),
+                    params: None,
+                }),
+                language: None,
+                location: None,
+            },
+            other: BodyContentSinglePart {
+                id: None,
+                md5: None,
+                description: None,
+                transfer_encoding: ContentEncoding::Base64,
+                octets,
+            },
+            extension: None,
+        }
+    }
+
+    #[test]
+    fn fetch_plan_skips_unneeded_bodies_and_rebuilds_message() {
+        let structure = multipart(
+            "MIXED",
+            vec![
+                multipart(
+                    "ALTERNATIVE",
+                    vec![
+                        text_part("PLAIN", false, ContentEncoding::SevenBit),
+                        text_part("HTML", false, ContentEncoding::SevenBit),
+                    ],
+                ),
+                video_attachment(78 * 1000),
+            ],
+        );
+        let needed = |part: &BodyPartRef| part.content_type.starts_with("text/");
+        let plan = FetchPlan::new(&structure, &needed).expect("video body is skipped");
+        assert_eq!(
+            plan.query(),
+            "(UID BODY.PEEK[HEADER] BODY.PEEK[1.MIME] BODY.PEEK[1.1.MIME] BODY.PEEK[1.1] BODY.PEEK[1.2.MIME] BODY.PEEK[1.2] BODY.PEEK[2.MIME])"
+        );
+        assert_eq!(plan.omitted_sizes.get("2"), Some(&57_000));
+
+        let sections: [(SectionPath, &[u8]); 7] = [
+            (
+                SectionPath::Full(MessageSection::Header),
+                &b"Subject: Clip\r\nContent-Type: multipart/mixed; boundary=MIXED\r\n\r\n"[..],
+            ),
+            (
+                SectionPath::Part(vec![1], Some(MessageSection::Mime)),
+                b"Content-Type: multipart/alternative; boundary=ALTERNATIVE\r\n",
+            ),
+            (
+                SectionPath::Part(vec![1, 1], Some(MessageSection::Mime)),
+                b"Content-Type: text/plain\r\n\r\n",
+            ),
+            (SectionPath::Part(vec![1, 1], None), b"Hello"),
+            (
+                SectionPath::Part(vec![1, 2], Some(MessageSection::Mime)),
+                b"Content-Type: text/html\r\n\r\n",
+            ),
+            (SectionPath::Part(vec![1, 2], None), b"<p>Hello</p>"),
+            (
+                SectionPath::Part(vec![2], Some(MessageSection::Mime)),
+                b"Content-Type: video/mp4; name=clip.mp4\r\nContent-Transfer-Encoding: base64\r\n\r\n",
+            ),
+        ];
+        let raw = plan
+            .assemble(|path| {
+                sections
+                    .iter()
+                    .find(|(section, _)| section == path)
+                    .map(|(_, bytes)| *bytes)
+            })
+            .expect("all sections present");
+        assert_eq!(
+            String::from_utf8(raw).expect("ascii message"),
+            "Subject: Clip\r\nContent-Type: multipart/mixed; boundary=MIXED\r\n\r\n\
+             --MIXED\r\nContent-Type: multipart/alternative; boundary=ALTERNATIVE\r\n\r\n\
+             --ALTERNATIVE\r\nContent-Type: text/plain\r\n\r\nHello\r\n\
+             --ALTERNATIVE\r\nContent-Type: text/html\r\n\r\n<p>Hello</p>\r\n\
+             --ALTERNATIVE--\r\n\r\n\
+             --MIXED\r\nContent-Type: video/mp4; name=clip.mp4\r\nContent-Transfer-Encoding: base64\r\n\r\n\r\n\
+             --MIXED--\r\n"
+        );
+        assert!(plan.assemble(|_| None).is_err());
+    }
+
+    #[test]
+    fn message_structure_numbers_parts_like_get_message() {
+        let structure = multipart(
+            "MIXED",
+            vec![
+                multipart(
+                    "ALTERNATIVE",
+                    vec![
+                        text_part("PLAIN", false, ContentEncoding::SevenBit),
+                        text_part("HTML", false, ContentEncoding::QuotedPrintable),
+                    ],
+                ),
+                video_attachment(4_096),
+            ],
+        );
+        let root = message_body_part(&structure, &[]);
+        assert_eq!(root.part_id, None);
+        assert_eq!(root.section, "TEXT");
+        assert_eq!(root.content_type, "multipart/mixed");
+        assert_eq!(
+            root.parameters.get("boundary").map(String::as_str),
+            Some("MIXED")
+        );
+        assert_eq!(root.size_bytes, None);
+
+        let alternative = &root.parts[0];
+        assert_eq!(alternative.part_id, None);
+        assert_eq!(alternative.section, "1");
+        let html = &alternative.parts[1];
+        assert_eq!(html.part_id.as_deref(), Some("1.1.2"));
+        assert_eq!(html.section, "1.2");
+        assert_eq!(html.encoding.as_deref(), Some("quoted-printable"));
+        assert_eq!(html.lines, Some(3));
+        assert_eq!(
+            html.parameters.get("charset").map(String::as_str),
+            Some("iso-8859-1")
+        );
+
+        let video = &root.parts[1];
+        assert_eq!(video.part_id.as_deref(), Some("1.2"));
+        assert_eq!(video.content_type, "video/mp4");
+        assert_eq!(video.disposition.as_deref(), Some("attachment"));
+        assert_eq!(video.filename.as_deref(), Some("clip.mp4"));
+        assert_eq!(video.size_bytes, Some(4_096));
+        assert_eq!(video.lines, None);
+        assert!(video.parts.is_empty());
+
+        let single = message_body_part(&video_attachment(100), &[]);
+        assert_eq!(single.part_id.as_deref(), Some("1"));
+        assert_eq!(single.section, "1");
+    }
+
+    #[test]
+    fn special_use_prefers_attribute_then_conventional_name() {
+        let drafts_attribute = [NameAttribute::Drafts];
+        let no_select = [NameAttribute::NoSelect, NameAttribute::Drafts];
+        let none: [NameAttribute<'static>; 0] = [];
+        let marked = [
+            (&none[..], Some("."), "INBOX.Drafts"),
+            (&no_select[..], Some("."), "Hidden"),
+            (&drafts_attribute[..], Some("."), "INBOX.Entw&APw-rfe"),
+        ];
+        assert_eq!(
+            pick_special_use(SpecialUse::Drafts, &marked),
+            Some("INBOX.Entw&APw-rfe")
+        );
+
+        let named = [
+            (&none[..], Some("/"), "Archive/Drafts old"),
+            (&none[..], Some("/"), "Work/drafts"),
+        ];
+        assert_eq!(
+            pick_special_use(SpecialUse::Drafts, &named),
+            Some("Work/drafts")
+        );
+        assert_eq!(pick_special_use(SpecialUse::Drafts, &named[..1]), None);
+
+        let sent = [
+            (&none[..], Some("/"), "Drafts"),
+            (&none[..], Some("/"), "Sent Items"),
+        ];
+        assert_eq!(
+            pick_special_use(SpecialUse::Sent, &sent),
+            Some("Sent Items")
+        );
+
+        let trash_attribute = [NameAttribute::Trash];
+        let trash = [
+            (&none[..], Some("/"), "Deleted Items"),
+            (&trash_attribute[..], Some("/"), "[Gmail]/Bin"),
+        ];
+        assert_eq!(
+            pick_special_use(SpecialUse::Trash, &trash),
+            Some("[Gmail]/Bin")
+        );
+        assert_eq!(
+            pick_special_use(SpecialUse::Trash, &trash[..1]),
+            Some("Deleted Items")
+        );
+    }
+
+    #[test]
+    fn param_filename_decodes_encoded_values() {
+        let params = BTreeMap::from([
+            (
+                "name".to_owned(),
+                "=?utf-8?q?R=C3=A9sum=C3=A9.pdf?=".to_owned(),
+            ),
+            (
+                "filename*".to_owned(),
+                "utf-8''%E4%BC%9A%E8%AD%B0.pdf".to_owned(),
+            ),
+        ]);
+        assert_eq!(
+            param_filename(&params, "name").as_deref(),
+            Some("Résumé.pdf")
+        );
+        assert_eq!(
+            param_filename(&params, "filename").as_deref(),
+            Some("会議.pdf")
+        );
+    }
+
+    #[test]
+    fn fetch_plan_falls_back_for_signed_or_fully_needed_messages() {
+        let needed = |part: &BodyPartRef| part.content_type.starts_with("text/");
+        let signed = multipart(
+            "SIGNED",
+            vec![
+                text_part("PLAIN", false, ContentEncoding::SevenBit),
+                video_attachment(100),
+            ],
+        );
+        assert!(FetchPlan::new(&signed, &needed).is_none());
+
+        let text_only = multipart(
+            "ALTERNATIVE",
+            vec![
+                text_part("PLAIN", false, ContentEncoding::SevenBit),
+                text_part("HTML", false, ContentEncoding::SevenBit),
+            ],
+        );
+        assert!(FetchPlan::new(&text_only, &needed).is_none());
+
+        let plan = FetchPlan::new(&video_attachment(100), &needed).expect("single part plan");
+        assert_eq!(plan.query(), "(UID BODY.PEEK[HEADER])");
+        assert_eq!(plan.omitted_sizes.get("1"), Some(&72));
+    }
+
+    #[test]
+    fn preferred_text_part_prefers_nested_plain_over_html_and_attachments() {
+        let structure = multipart(
+            "MIXED",
+            vec![
+                text_part("PLAIN", true, ContentEncoding::Base64),
+                multipart(
+                    "ALTERNATIVE",
+                    vec![
+                        text_part("HTML", false, ContentEncoding::QuotedPrintable),
+                        text_part("PLAIN", false, ContentEncoding::QuotedPrintable),
+                    ],
+                ),
+            ],
+        );
+        assert_eq!(
+            preferred_text_part(&structure),
+            Some(TextPartRef {
+                section: "2.2".to_owned(),
+                mime_type: "text/plain".to_owned(),
+                charset: Some("iso-8859-1".to_owned()),
+                transfer_encoding: "quoted-printable".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn preferred_text_part_uses_section_one_for_single_part_html() {
+        let structure = text_part("HTML", false, ContentEncoding::SevenBit);
+        let part = preferred_text_part(&structure).expect("html part should be selected");
+        assert_eq!(part.section, "1");
+        assert_eq!(part.mime_type, "text/html");
+    }
+
+    #[test]
+    fn locate_body_part_resolves_nested_sections() {
+        let structure = multipart(
+            "MIXED",
+            vec![
+                multipart(
+                    "ALTERNATIVE",
+                    vec![
+                        text_part("PLAIN", false, ContentEncoding::SevenBit),
+                        text_part("HTML", false, ContentEncoding::SevenBit),
+                    ],
+                ),
+                text_part("CSV", true, ContentEncoding::Base64),
+            ],
+        );
+        let part = locate_body_part(&structure, &[2])
+            .and_then(|part| body_part_ref(part, "2"))
+            .expect("attachment part should resolve");
+        assert_eq!(part.content_type, "text/csv");
+        assert_eq!(part.transfer_encoding, "base64");
+        assert_eq!(part.encoded_size_bytes, 100);
+        assert!(
+            locate_body_part(&structure, &[1, 2])
+                .and_then(|part| body_part_ref(part, "1.2"))
+                .is_some_and(|part| part.content_type == "text/html")
+        );
+        assert!(
+            locate_body_part(&structure, &[1])
+                .and_then(|part| body_part_ref(part, "1"))
+                .is_none()
+        );
+        assert!(locate_body_part(&structure, &[3]).is_none());
+
+        let single = text_part("PLAIN", false, ContentEncoding::SevenBit);
+        assert!(locate_body_part(&single, &[1]).is_some());
+        assert!(locate_body_part(&single, &[2]).is_none());
+    }
+
+    #[test]
+    fn root_part_of_multipart_does_not_resolve_to_first_child() {
+        let structure = multipart(
+            "MIXED",
+            vec![
+                text_part("PLAIN", false, ContentEncoding::SevenBit),
+                text_part("CSV", true, ContentEncoding::Base64),
+            ],
+        );
+        assert!(locate_part_ref(&structure, &[1], "1", true).is_none());
+        assert!(
+            locate_part_ref(&structure, &[1], "1", false)
+                .is_some_and(|part| part.content_type == "text/plain")
+        );
+
+        let single = text_part("CSV", true, ContentEncoding::Base64);
+        assert!(
+            locate_part_ref(&single, &[1], "1", true)
+                .is_some_and(|part| part.content_type == "text/csv")
+        );
+    }
+
     /// Constructs a ServerConfig for GreenMail integration tests.
     fn greenmail_test_config(endpoints: &GreenmailEndpoints) -> ServerConfig {
         let account = AccountConfig {
@@ -813,6 +2193,10 @@ mod tests {
             secure: true,
             user: endpoints.user.clone(),
             pass: SecretString::new(endpoints.pass.clone().into()),
+            message_keys: Default::default(),
+            identities: Vec::new(),
+            smtp: None,
+            trash_mailbox: None,
         };
 
         let mut accounts = BTreeMap::new();
@@ -822,14 +2206,22 @@ mod tests {
             accounts,
             trusted_ca_certs: Vec::new(),
             write_enabled: true,
+            send_enabled: false,
+            permanent_delete_enabled: false,
             connect_timeout_ms: 5_000,
             greeting_timeout_ms: 5_000,
             socket_timeout_ms: 15_000,
             cursor_ttl_seconds: 600,
             cursor_max_entries: 128,
+            cursor_signing_key: None,
             read_session_cache_ttl_seconds: 120,
             read_session_cache_max_per_account: 4,
             operation_max_entries: 256,
+            dkim_keys: BTreeMap::new(),
+            dkim_dns_server: None,
+            export_dir: None,
+            import_dir: None,
+            attachment_dir: None,
         }
     }
 
@@ -1150,9 +2542,16 @@ mod tests {
         let message = format!(
             "From: sender@example.com\r\nTo: user@example.com\r\nSubject: {subject}\r\n\r\nWrite-path body\r\n"
         );
-        append(&config, &mut session, "INBOX", message.as_bytes())
-            .await
-            .expect("APPEND should succeed");
+        append(
+            &config,
+            &mut session,
+            "INBOX",
+            &[],
+            None,
+            message.as_bytes(),
+        )
+        .await
+        .expect("APPEND should succeed");
 
         select_mailbox_readwrite(&config, &mut session, "INBOX")
             .await
@@ -1255,5 +2654,98 @@ mod tests {
             raw_text.contains(&subject),
             "remaining message should contain test subject"
         );
+
+        let draft_subject = format!("{subject} draft");
+        let draft =
+            format!("From: user@example.com\r\nSubject: {draft_subject}\r\n\r\nDraft body\r\n");
+        let appended = append_with_uid(
+            &config,
+            &mut session,
+            &destination_mailbox,
+            &["\\Draft".to_owned()],
+            draft.as_bytes(),
+        )
+        .await
+        .expect("APPEND with UID should succeed");
+        let uidvalidity = select_mailbox_readonly(&config, &mut session, &destination_mailbox)
+            .await
+            .expect("destination mailbox should be selectable after append");
+        let draft_uids = uid_search(
+            &config,
+            &mut session,
+            &format!("SUBJECT \"{draft_subject}\""),
+        )
+        .await
+        .expect("UID SEARCH for appended draft should succeed");
+        assert_eq!(draft_uids.len(), 1, "expected one appended draft");
+        if let Some(appended) = appended {
+            assert_eq!(appended, (uidvalidity, draft_uids[0]));
+        }
+    }
+
+    /// Submits a message over implicit-TLS SMTP and finds it in INBOX.
+    #[tokio::test]
+    #[ignore = "requires running GreenMail IMAP server"]
+    async fn greenmail_smtp_submission_test() {
+        let endpoints = greenmail_endpoints();
+        let config = greenmail_test_config(&endpoints);
+        wait_until_login_works(&config, &endpoints)
+            .await
+            .expect("greenmail did not become ready");
+
+        let smtp = SmtpConfig {
+            host: endpoints.host.clone(),
+            port: endpoints.smtps_port,
+            security: SmtpSecurity::Tls,
+            user: endpoints.user.clone(),
+            pass: SecretString::new(endpoints.pass.clone().into()),
+        };
+        let tls_config = ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoCertificateVerification))
+            .with_no_client_auth();
+        let connector = TlsConnector::from(Arc::new(tls_config));
+        let nonce = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("clock must be monotonic enough for test")
+            .as_nanos();
+        let subject = format!("Greenmail submission {nonce}");
+        let message = format!(
+            "From: {user}\r\nTo: {user}\r\nSubject: {subject}\r\n\r\n.leading dot\r\n",
+            user = endpoints.user
+        );
+        let envelope = Envelope {
+            from: endpoints.user.clone(),
+            recipients: vec![endpoints.user.clone()],
+        };
+        crate::smtp::send_message_with(&config, &smtp, &connector, &envelope, message.as_bytes())
+            .await
+            .expect("SMTP submission should succeed");
+
+        let mut session = connect_authenticated_greenmail(&config)
+            .await
+            .expect("imap login should work");
+        let mut found = Vec::new();
+        for _ in 0..10 {
+            select_mailbox_readonly(&config, &mut session, "INBOX")
+                .await
+                .expect("INBOX should be selectable");
+            found = uid_search(&config, &mut session, &format!("SUBJECT \"{subject}\""))
+                .await
+                .expect("UID SEARCH by SUBJECT should succeed");
+            if !found.is_empty() {
+                break;
+            }
+            sleep(Duration::from_millis(200)).await;
+        }
+        assert_eq!(found.len(), 1, "expected the submitted message in INBOX");
+        let raw = fetch_raw_message(&config, &mut session, found[0])
+            .await
+            .expect("submitted message should be fetchable");
+        let text = String::from_utf8_lossy(&raw);
+        assert!(
+            text.contains("\r\n.leading dot\r\n"),
+            "dot stuffing must be undone"
+        );
     }
 }
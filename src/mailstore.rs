@@ -0,0 +1,860 @@
+//! Local message stores for export and import
+//!
+//! Resolves paths inside a configured sandbox directory, writes messages as
+//! individual `.eml` files, an `mboxrd` mailbox, or a Maildir folder, and
+//! reads the same layouts back. Message bytes are written exactly as fetched,
+//! apart from the `mboxrd` `From ` quoting that the mbox format itself
+//! requires.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
+use mailparse::MailHeaderMap;
+use sha2::{Digest, Sha256};
+
+use crate::errors::{AppError, AppResult};
+
+/// Host part of generated Maildir file names
+const MAILDIR_HOST: &str = "mail-imap-mcp-rs";
+/// Largest single message read from an import source
+pub const MAX_IMPORT_MESSAGE_BYTES: usize = 50 * 1024 * 1024;
+/// Maildir info letters and the IMAP system flags they stand for
+const MAILDIR_FLAGS: [(char, &str); 5] = [
+    ('D', "\\Draft"),
+    ('F', "\\Flagged"),
+    ('R', "\\Answered"),
+    ('S', "\\Seen"),
+    ('T', "\\Deleted"),
+];
+
+/// On-disk layout of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreFormat {
+    /// One RFC822 file per message in a directory
+    Eml,
+    /// A single `mboxrd` file
+    Mbox,
+    /// A Maildir with `cur`, `new`, and `tmp` subdirectories
+    Maildir,
+}
+
+impl StoreFormat {
+    /// Parse the tool-facing format name
+    pub fn parse(value: &str) -> AppResult<Self> {
+        match value {
+            "eml" => Ok(Self::Eml),
+            "mbox" => Ok(Self::Mbox),
+            "maildir" => Ok(Self::Maildir),
+            _ => Err(AppError::InvalidInput(
+                "format must be one of: eml, mbox, maildir".to_owned(),
+            )),
+        }
+    }
+
+    /// Tool-facing format name
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Eml => "eml",
+            Self::Mbox => "mbox",
+            Self::Maildir => "maildir",
+        }
+    }
+}
+
+/// A message to be written, with the metadata the formats record
+pub struct StoreMessage<'a> {
+    /// Unique, filesystem-safe stem used for `.eml` and Maildir names
+    pub key: &'a str,
+    pub raw: &'a [u8],
+    pub flags: &'a [String],
+    pub internal_date: Option<DateTime<FixedOffset>>,
+}
+
+/// One message located in an import source
+#[derive(Debug, Clone)]
+pub struct SourceEntry {
+    /// File holding the message
+    pub path: PathBuf,
+    /// Byte offset and length of the entry within an mbox file
+    pub range: Option<(u64, usize)>,
+    /// Flags recorded by the source layout (Maildir info flags)
+    pub flags: Vec<String>,
+    pub internal_date: Option<DateTime<FixedOffset>>,
+}
+
+/// A message read back from an import source, ready to append
+#[derive(Debug, Clone)]
+pub struct SourceMessage {
+    pub raw: Vec<u8>,
+    pub flags: Vec<String>,
+    pub internal_date: Option<DateTime<FixedOffset>>,
+}
+
+/// Resolve a relative path inside the sandbox `root`
+///
+/// Rejects absolute paths and `.`/`..` components, and checks that the
+/// nearest existing ancestor does not leave `root` through a symlink.
+///
+/// # Errors
+///
+/// Returns `InvalidInput` if the path is empty or escapes the sandbox.
+pub fn resolve_sandbox_path(root: &Path, relative: &str) -> AppResult<PathBuf> {
+    let relative = relative.trim();
+    if relative.is_empty() {
+        return Err(AppError::InvalidInput("path must not be empty".to_owned()));
+    }
+    let relative_path = Path::new(relative);
+    if !relative_path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(AppError::InvalidInput(
+            "path must be relative and must not contain '.' or '..' components".to_owned(),
+        ));
+    }
+    let path = root.join(relative_path);
+    let mut existing = path.as_path();
+    while !existing.exists() {
+        existing = existing
+            .parent()
+            .ok_or_else(|| AppError::InvalidInput("path escapes the sandbox".to_owned()))?;
+    }
+    let resolved = existing
+        .canonicalize()
+        .map_err(|e| io_error("resolve", existing, &e))?;
+    if !resolved.starts_with(root) {
+        return Err(AppError::InvalidInput(
+            "path escapes the sandbox directory".to_owned(),
+        ));
+    }
+    Ok(path)
+}
+
+/// Create the export destination before any message is written
+///
+/// `.eml` and Maildir destinations are directories that may already exist;
+/// an mbox destination is a new file and must not exist yet.
+///
+/// # Errors
+///
+/// Returns `InvalidInput` if an mbox file already exists, or `Internal` on
+/// filesystem failures.
+pub fn prepare_export(format: StoreFormat, destination: &Path) -> AppResult<()> {
+    match format {
+        StoreFormat::Eml => create_dir(destination),
+        StoreFormat::Mbox => {
+            if let Some(parent) = destination.parent() {
+                create_dir(parent)?;
+            }
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(destination)
+                .map(|_| ())
+                .map_err(|e| {
+                    if e.kind() == std::io::ErrorKind::AlreadyExists {
+                        AppError::InvalidInput("destination mbox file already exists".to_owned())
+                    } else {
+                        io_error("create", destination, &e)
+                    }
+                })
+        }
+        StoreFormat::Maildir => {
+            create_dir(destination)?;
+            for sub in ["cur", "new", "tmp"] {
+                maildir_subdir(destination, sub)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Write one message to a prepared destination and return the file it went to
+///
+/// # Errors
+///
+/// Returns `Internal` on filesystem failures, or `Conflict` if an `.eml`
+/// file with the same name already exists.
+pub fn write_message(
+    format: StoreFormat,
+    destination: &Path,
+    message: &StoreMessage<'_>,
+) -> AppResult<PathBuf> {
+    match format {
+        StoreFormat::Eml => {
+            let path = destination.join(format!("{}.eml", message.key));
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .map_err(|e| {
+                    if e.kind() == std::io::ErrorKind::AlreadyExists {
+                        AppError::Conflict(format!("{} already exists", path.display()))
+                    } else {
+                        io_error("create", &path, &e)
+                    }
+                })?;
+            file.write_all(message.raw)
+                .and_then(|()| file.sync_all())
+                .map_err(|e| io_error("write", &path, &e))?;
+            Ok(path)
+        }
+        StoreFormat::Mbox => {
+            let mut file = OpenOptions::new()
+                .append(true)
+                .open(destination)
+                .map_err(|e| io_error("open", destination, &e))?;
+            file.write_all(&mboxrd_entry(message.raw, message.internal_date))
+                .and_then(|()| file.sync_all())
+                .map_err(|e| io_error("write", destination, &e))?;
+            Ok(destination.to_path_buf())
+        }
+        StoreFormat::Maildir => {
+            let seconds = message
+                .internal_date
+                .map_or_else(|| Utc::now().timestamp(), |date| date.timestamp());
+            let unique = format!("{seconds}.{}.{MAILDIR_HOST}", message.key);
+            let tmp_path = maildir_subdir(destination, "tmp")?.join(&unique);
+            let cur_path = maildir_subdir(destination, "cur")?
+                .join(format!("{unique}:2,{}", maildir_info_flags(message.flags)));
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&tmp_path)
+                .map_err(|e| io_error("create", &tmp_path, &e))?;
+            file.write_all(message.raw)
+                .and_then(|()| file.sync_all())
+                .map_err(|e| io_error("write", &tmp_path, &e))?;
+            fs::rename(&tmp_path, &cur_path).map_err(|e| io_error("rename", &tmp_path, &e))?;
+            Ok(cur_path)
+        }
+    }
+}
+
+/// List the messages in an import source without reading their bodies
+///
+/// `eml` accepts a single file or a directory of `*.eml` files; `maildir`
+/// reads `cur` and `new`; `mbox` splits on `From ` lines that start the file
+/// or follow a blank line.
+///
+/// # Errors
+///
+/// Returns `InvalidInput` if the source has the wrong shape or more than
+/// `max_entries` messages, or `Internal` on filesystem failures.
+pub fn list_source(
+    format: StoreFormat,
+    source: &Path,
+    max_entries: usize,
+) -> AppResult<Vec<SourceEntry>> {
+    let entries = match format {
+        StoreFormat::Eml if is_real_file(source) => vec![file_entry(source.to_path_buf(), None)?],
+        StoreFormat::Eml => {
+            require_dir(source)?;
+            sorted_files(source)?
+                .into_iter()
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("eml"))
+                })
+                .map(|path| file_entry(path, None))
+                .collect::<AppResult<Vec<_>>>()?
+        }
+        StoreFormat::Maildir => {
+            require_dir(source)?;
+            let mut entries = Vec::new();
+            for sub in ["cur", "new"] {
+                let dir = source.join(sub);
+                if !is_real_dir(&dir) {
+                    continue;
+                }
+                for path in sorted_files(&dir)? {
+                    let name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    entries.push(file_entry(path, Some(&name))?);
+                }
+            }
+            entries
+        }
+        StoreFormat::Mbox => {
+            if !is_real_file(source) {
+                return Err(AppError::InvalidInput(
+                    "mbox source must be a file, not a symlink".to_owned(),
+                ));
+            }
+            mbox_entries(source)?
+        }
+    };
+    if entries.len() > max_entries {
+        return Err(AppError::InvalidInput(format!(
+            "source contains {} messages; import at most {max_entries} at a time",
+            entries.len()
+        )));
+    }
+    Ok(entries)
+}
+
+/// Read one listed message, normalizing bare LF line endings to CRLF
+///
+/// mbox entries are unquoted per `mboxrd` and take flags from their
+/// `Status` and `X-Status` headers.
+///
+/// # Errors
+///
+/// Returns `InvalidInput` if the message exceeds `MAX_IMPORT_MESSAGE_BYTES`,
+/// or `Internal` on filesystem failures.
+pub fn read_source_message(entry: &SourceEntry) -> AppResult<SourceMessage> {
+    let mut file = File::open(&entry.path).map_err(|e| io_error("open", &entry.path, &e))?;
+    let (offset, length) = match entry.range {
+        Some(range) => range,
+        None => {
+            let length = file
+                .metadata()
+                .map_err(|e| io_error("stat", &entry.path, &e))?
+                .len();
+            (0, usize::try_from(length).unwrap_or(usize::MAX))
+        }
+    };
+    if length > MAX_IMPORT_MESSAGE_BYTES {
+        return Err(AppError::InvalidInput(format!(
+            "message is {length} bytes; the import limit is {MAX_IMPORT_MESSAGE_BYTES}"
+        )));
+    }
+    let mut raw = vec![0; length];
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.read_exact(&mut raw))
+        .map_err(|e| io_error("read", &entry.path, &e))?;
+    let mut flags = entry.flags.clone();
+    if entry.range.is_some() {
+        raw = mboxrd_unquote(&raw);
+        flags.extend(mbox_status_flags(&raw));
+    }
+    Ok(SourceMessage {
+        raw: normalize_crlf(&raw),
+        flags,
+        internal_date: entry.internal_date,
+    })
+}
+
+/// Trimmed `Message-ID` header value, if present
+pub fn message_id_header(raw: &[u8]) -> Option<String> {
+    let (headers, _) = mailparse::parse_headers(raw).ok()?;
+    headers
+        .get_first_value("Message-ID")
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty())
+}
+
+/// Lowercase hex SHA-256 of `bytes`
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Replace characters that are unsafe in file names with `_`
+pub fn file_name_component(value: &str) -> String {
+    value
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_') {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Encode one `mboxrd` entry: `From ` separator, quoted body, blank line
+///
+/// The entry uses LF line endings throughout: CRLF in the message becomes LF,
+/// as mbox readers expect. Import restores CRLF (see [`normalize_crlf`]).
+fn mboxrd_entry(raw: &[u8], internal_date: Option<DateTime<FixedOffset>>) -> Vec<u8> {
+    let date = internal_date.map_or_else(Utc::now, |date| date.with_timezone(&Utc));
+    let mut out = format!(
+        "From MAILER-DAEMON {}\n",
+        date.format("%a %b %e %H:%M:%S %Y")
+    )
+    .into_bytes();
+    for line in raw.split_inclusive(|byte| *byte == b'\n') {
+        let unquoted = line
+            .iter()
+            .position(|byte| *byte != b'>')
+            .unwrap_or(line.len());
+        if line[unquoted..].starts_with(b"From ") {
+            out.push(b'>');
+        }
+        match line.strip_suffix(b"\r\n") {
+            Some(content) => {
+                out.extend_from_slice(content);
+                out.push(b'\n');
+            }
+            None => out.extend_from_slice(line),
+        }
+    }
+    if !out.ends_with(b"\n") {
+        out.push(b'\n');
+    }
+    out.push(b'\n');
+    out
+}
+
+/// Maildir info flags for IMAP system flags, in the required ASCII order
+fn maildir_info_flags(flags: &[String]) -> String {
+    MAILDIR_FLAGS
+        .into_iter()
+        .filter(|(_, flag)| flags.iter().any(|value| value.eq_ignore_ascii_case(flag)))
+        .map(|(letter, _)| letter)
+        .collect()
+}
+
+/// Flags and delivery time encoded in a Maildir file name
+///
+/// The trashed letter (`T`) is not imported as `\Deleted`; restoring it
+/// would let the next expunge remove the imported copy.
+fn parse_maildir_name(name: &str) -> (Vec<String>, Option<DateTime<FixedOffset>>) {
+    let flags = name
+        .split_once(":2,")
+        .map(|(_, info)| {
+            MAILDIR_FLAGS
+                .into_iter()
+                .filter(|(letter, flag)| *flag != "\\Deleted" && info.contains(*letter))
+                .map(|(_, flag)| flag.to_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    let date = name
+        .split('.')
+        .next()
+        .and_then(|seconds| seconds.parse::<i64>().ok())
+        .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+        .map(|date| date.fixed_offset());
+    (flags, date)
+}
+
+/// IMAP flags from mbox `Status` (`R`) and `X-Status` (`A`, `F`, `T`) headers
+///
+/// `X-Status: D` is not imported as `\Deleted`, as with Maildir's `T`.
+fn mbox_status_flags(raw: &[u8]) -> Vec<String> {
+    let Ok((headers, _)) = mailparse::parse_headers(raw) else {
+        return Vec::new();
+    };
+    let status = headers.get_first_value("Status").unwrap_or_default();
+    let x_status = headers.get_first_value("X-Status").unwrap_or_default();
+    [
+        (status.contains('R'), "\\Seen"),
+        (x_status.contains('A'), "\\Answered"),
+        (x_status.contains('F'), "\\Flagged"),
+        (x_status.contains('T'), "\\Draft"),
+    ]
+    .into_iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, flag)| flag.to_owned())
+    .collect()
+}
+
+/// Split an mbox file into entries, dating each from its `From ` line
+///
+/// The blank line that precedes each separator belongs to the mbox framing
+/// and is not part of the message.
+fn mbox_entries(path: &Path) -> AppResult<Vec<SourceEntry>> {
+    let file = File::open(path).map_err(|e| io_error("open", path, &e))?;
+    let mut reader = BufReader::new(file);
+    let mut entries = Vec::new();
+    let mut current: Option<(u64, Option<DateTime<FixedOffset>>)> = None;
+    let mut offset = 0u64;
+    let mut blank_len = Some(0u64);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let read = reader
+            .read_until(b'\n', &mut line)
+            .map_err(|e| io_error("read", path, &e))? as u64;
+        if read == 0 {
+            break;
+        }
+        if let Some(separator_len) = blank_len
+            && line.starts_with(b"From ")
+        {
+            if let Some((start, date)) = current.take() {
+                entries.push(mbox_entry(path, start, offset - separator_len, date));
+            }
+            current = Some((offset + read, parse_from_line_date(&line)));
+        } else if current.is_none() {
+            return Err(AppError::InvalidInput(
+                "mbox source does not start with a 'From ' line".to_owned(),
+            ));
+        }
+        blank_len = (line == b"\n" || line == b"\r\n").then_some(read);
+        offset += read;
+    }
+    if let Some((start, date)) = current {
+        entries.push(mbox_entry(
+            path,
+            start,
+            offset - blank_len.unwrap_or(0),
+            date,
+        ));
+    }
+    Ok(entries)
+}
+
+fn mbox_entry(
+    path: &Path,
+    start: u64,
+    end: u64,
+    internal_date: Option<DateTime<FixedOffset>>,
+) -> SourceEntry {
+    SourceEntry {
+        path: path.to_path_buf(),
+        range: Some((
+            start,
+            usize::try_from(end.saturating_sub(start)).unwrap_or(usize::MAX),
+        )),
+        flags: Vec::new(),
+        internal_date,
+    }
+}
+
+/// Parse the asctime date that ends an mbox `From sender date` line
+fn parse_from_line_date(line: &[u8]) -> Option<DateTime<FixedOffset>> {
+    let line = String::from_utf8_lossy(line);
+    let words = line.split_whitespace().skip(2).collect::<Vec<_>>();
+    let date = words.get(..5)?.join(" ");
+    NaiveDateTime::parse_from_str(&date, "%a %b %e %H:%M:%S %Y")
+        .ok()
+        .map(|date| date.and_utc().fixed_offset())
+}
+
+/// Remove one level of `>` quoting from `>From ` lines
+fn mboxrd_unquote(raw: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(raw.len());
+    for line in raw.split_inclusive(|byte| *byte == b'\n') {
+        let quotes = line
+            .iter()
+            .position(|byte| *byte != b'>')
+            .unwrap_or(line.len());
+        if quotes > 0 && line[quotes..].starts_with(b"From ") {
+            out.extend_from_slice(&line[1..]);
+        } else {
+            out.extend_from_slice(line);
+        }
+    }
+    out
+}
+
+/// Convert bare LF line endings to CRLF as IMAP `APPEND` expects
+pub fn normalize_crlf(raw: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(raw.len() + raw.len() / 32);
+    let mut previous = 0u8;
+    for &byte in raw {
+        if byte == b'\n' && previous != b'\r' {
+            out.push(b'\r');
+        }
+        out.push(byte);
+        previous = byte;
+    }
+    out
+}
+
+fn file_entry(path: PathBuf, maildir_name: Option<&str>) -> AppResult<SourceEntry> {
+    let (flags, name_date) = maildir_name.map(parse_maildir_name).unwrap_or_default();
+    let internal_date = match name_date {
+        Some(date) => Some(date),
+        None => fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .map(|modified| DateTime::<Utc>::from(modified).fixed_offset()),
+    };
+    Ok(SourceEntry {
+        path,
+        range: None,
+        flags,
+        internal_date,
+    })
+}
+
+fn sorted_files(dir: &Path) -> AppResult<Vec<PathBuf>> {
+    let mut files = fs::read_dir(dir)
+        .map_err(|e| io_error("read", dir, &e))?
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry.file_type().is_ok_and(|file_type| file_type.is_file())
+                && !entry.file_name().to_string_lossy().starts_with('.')
+        })
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+fn require_dir(path: &Path) -> AppResult<()> {
+    if is_real_dir(path) {
+        Ok(())
+    } else {
+        Err(AppError::InvalidInput(
+            "source must be an existing directory, not a symlink".to_owned(),
+        ))
+    }
+}
+
+/// Whether `path` is a regular file, without following a final symlink
+fn is_real_file(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_file())
+}
+
+/// Whether `path` is a directory, without following a final symlink
+fn is_real_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir())
+}
+
+fn create_dir(path: &Path) -> AppResult<()> {
+    fs::create_dir_all(path).map_err(|e| io_error("create", path, &e))
+}
+
+/// Create or reuse a Maildir subdirectory, refusing anything but a real directory
+///
+/// A symlinked `tmp`, `cur`, or `new` could redirect writes outside the
+/// sandbox after the destination was resolved.
+fn maildir_subdir(destination: &Path, sub: &str) -> AppResult<PathBuf> {
+    let dir = destination.join(sub);
+    match fs::create_dir(&dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(io_error("create", &dir, &e)),
+    }
+    let metadata = fs::symlink_metadata(&dir).map_err(|e| io_error("stat", &dir, &e))?;
+    if !metadata.is_dir() {
+        return Err(AppError::InvalidInput(format!(
+            "Maildir {sub} must be a directory, not a symlink or file"
+        )));
+    }
+    Ok(dir)
+}
+
+fn io_error(action: &str, path: &Path, error: &std::io::Error) -> AppError {
+    AppError::Internal(format!("failed to {action} {}: {error}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        StoreFormat, StoreMessage, list_source, maildir_info_flags, mboxrd_entry,
+        message_id_header, parse_maildir_name, prepare_export, read_source_message,
+        resolve_sandbox_path, sha256_hex, write_message,
+    };
+    use chrono::DateTime;
+
+    fn sandbox(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("mailstore-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create sandbox");
+        dir.canonicalize().expect("canonical sandbox")
+    }
+
+    #[test]
+    fn sandbox_paths_reject_escapes() {
+        let root = sandbox("paths");
+        assert_eq!(
+            resolve_sandbox_path(&root, "backup/inbox.mbox").expect("nested path"),
+            root.join("backup/inbox.mbox")
+        );
+        for bad in ["", "/etc/passwd", "../outside", "a/../../b", "./a"] {
+            assert!(resolve_sandbox_path(&root, bad).is_err(), "{bad}");
+        }
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(std::env::temp_dir(), root.join("link"))
+                .expect("create symlink");
+            assert!(resolve_sandbox_path(&root, "link/file.eml").is_err());
+        }
+        std::fs::remove_dir_all(&root).expect("remove sandbox");
+    }
+
+    #[test]
+    fn mboxrd_quotes_from_lines() {
+        let date = DateTime::parse_from_rfc3339("2024-03-05T10:00:00+02:00").expect("date");
+        let entry = mboxrd_entry(
+            b"Subject: x\r\n\r\nFrom here\r\n>From there\r\nok",
+            Some(date),
+        );
+        assert!(!entry.contains(&b'\r'), "entries use LF line endings");
+        assert_eq!(
+            String::from_utf8(entry).expect("utf8"),
+            "From MAILER-DAEMON Tue Mar  5 08:00:00 2024\n\
+             Subject: x\n\n>From here\n>>From there\nok\n\n"
+        );
+    }
+
+    #[test]
+    fn writes_eml_and_maildir_files_byte_for_byte() {
+        let root = sandbox("write");
+        let raw = b"Subject: hi\r\n\r\nbody\r\n";
+        let flags = vec![
+            "\\Seen".to_owned(),
+            "\\Flagged".to_owned(),
+            "$Label".to_owned(),
+        ];
+        let message = StoreMessage {
+            key: "INBOX_7_42",
+            raw,
+            flags: &flags,
+            internal_date: None,
+        };
+
+        let eml_dir = root.join("eml");
+        prepare_export(StoreFormat::Eml, &eml_dir).expect("prepare eml");
+        let eml = write_message(StoreFormat::Eml, &eml_dir, &message).expect("write eml");
+        assert_eq!(std::fs::read(&eml).expect("read eml"), raw);
+        assert!(write_message(StoreFormat::Eml, &eml_dir, &message).is_err());
+
+        let maildir = root.join("maildir");
+        prepare_export(StoreFormat::Maildir, &maildir).expect("prepare maildir");
+        let stored = write_message(StoreFormat::Maildir, &maildir, &message).expect("write");
+        assert!(stored.to_string_lossy().ends_with(":2,FS"));
+        assert_eq!(std::fs::read(&stored).expect("read maildir"), raw);
+        assert_eq!(maildir_info_flags(&["\\Draft".to_owned()]), "D");
+
+        let mbox = root.join("out.mbox");
+        prepare_export(StoreFormat::Mbox, &mbox).expect("prepare mbox");
+        assert!(prepare_export(StoreFormat::Mbox, &mbox).is_err());
+
+        #[cfg(unix)]
+        {
+            let outside = sandbox("write-outside");
+            let linked = root.join("linked");
+            std::fs::create_dir_all(linked.join("cur")).expect("create cur");
+            std::os::unix::fs::symlink(&outside, linked.join("tmp")).expect("create symlink");
+            assert!(prepare_export(StoreFormat::Maildir, &linked).is_err());
+            assert!(write_message(StoreFormat::Maildir, &linked, &message).is_err());
+            assert_eq!(
+                std::fs::read_dir(&outside).expect("list outside").count(),
+                0
+            );
+            std::fs::remove_dir_all(&outside).expect("remove outside");
+        }
+
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        std::fs::remove_dir_all(&root).expect("remove sandbox");
+    }
+
+    #[test]
+    fn mbox_exports_read_back_byte_for_byte() {
+        let root = sandbox("mbox-roundtrip");
+        let mbox = root.join("out.mbox");
+        prepare_export(StoreFormat::Mbox, &mbox).expect("prepare mbox");
+        let date = DateTime::parse_from_rfc3339("2024-03-05T08:00:00+00:00").expect("date");
+        let first = b"Message-ID: <a@x>\r\n\r\nFrom the top\r\n\r\n".to_vec();
+        let second = b"Message-ID: <b@x>\r\n\r\n>From quoted\r\n".to_vec();
+        for (key, raw) in [("a", &first), ("b", &second)] {
+            let message = StoreMessage {
+                key,
+                raw,
+                flags: &[],
+                internal_date: Some(date),
+            };
+            write_message(StoreFormat::Mbox, &mbox, &message).expect("write mbox");
+        }
+
+        let entries = list_source(StoreFormat::Mbox, &mbox, 10).expect("list mbox");
+        assert_eq!(entries.len(), 2);
+        let read = entries
+            .iter()
+            .map(|entry| read_source_message(entry).expect("read entry"))
+            .collect::<Vec<_>>();
+        assert_eq!(read[0].raw, first);
+        assert_eq!(read[1].raw, second);
+        assert_eq!(read[0].internal_date, Some(date));
+        assert_eq!(message_id_header(&read[1].raw).as_deref(), Some("<b@x>"));
+        assert!(list_source(StoreFormat::Mbox, &mbox, 1).is_err());
+        std::fs::remove_dir_all(&root).expect("remove sandbox");
+    }
+
+    #[test]
+    fn import_sources_skip_symlinked_entries() {
+        let root = sandbox("import-links");
+        let eml_dir = root.join("eml");
+        std::fs::create_dir_all(&eml_dir).expect("create eml dir");
+        std::fs::write(eml_dir.join("a.eml"), "Subject: a\n\nbody\n").expect("write eml");
+        #[cfg(unix)]
+        {
+            let outside = sandbox("import-outside");
+            std::fs::write(outside.join("secret.eml"), "Subject: s\n\n").expect("write secret");
+            std::fs::write(outside.join("secret.mbox"), "From x\n\n").expect("write mbox");
+            let link = |target: &str, name: &std::path::Path| {
+                std::os::unix::fs::symlink(outside.join(target), name).expect("create symlink");
+            };
+            link("secret.eml", &eml_dir.join("b.eml"));
+            link("secret.eml", &root.join("single.eml"));
+            link("secret.mbox", &root.join("linked.mbox"));
+            std::fs::create_dir_all(root.join("maildir/new")).expect("create new");
+            std::os::unix::fs::symlink(&outside, root.join("maildir/cur")).expect("link cur");
+
+            let entries = list_source(StoreFormat::Eml, &eml_dir, 10).expect("list eml");
+            assert_eq!(entries.len(), 1);
+            assert!(entries[0].path.ends_with("a.eml"));
+            assert!(list_source(StoreFormat::Eml, &root.join("single.eml"), 10).is_err());
+            assert!(list_source(StoreFormat::Mbox, &root.join("linked.mbox"), 10).is_err());
+            let maildir =
+                list_source(StoreFormat::Maildir, &root.join("maildir"), 10).expect("list maildir");
+            assert!(maildir.is_empty());
+            std::fs::remove_dir_all(&outside).expect("remove outside");
+        }
+        std::fs::remove_dir_all(&root).expect("remove sandbox");
+    }
+
+    #[test]
+    fn reads_lf_mbox_status_and_maildir_flags() {
+        let root = sandbox("import");
+        let mbox = root.join("lf.mbox");
+        std::fs::write(
+            &mbox,
+            "From someone@example.com Thu Jan  4 09:30:00 2024\n\
+             Subject: hi\nStatus: RO\nX-Status: AF\n\nbody\n",
+        )
+        .expect("write mbox");
+        let entries = list_source(StoreFormat::Mbox, &mbox, 10).expect("list mbox");
+        let message = read_source_message(&entries[0]).expect("read entry");
+        assert_eq!(
+            message.raw,
+            b"Subject: hi\r\nStatus: RO\r\nX-Status: AF\r\n\r\nbody\r\n"
+        );
+        assert_eq!(message.flags, ["\\Seen", "\\Answered", "\\Flagged"]);
+
+        std::fs::write(root.join("not.mbox"), "Subject: x\n").expect("write file");
+        assert!(list_source(StoreFormat::Mbox, &root.join("not.mbox"), 10).is_err());
+
+        let (flags, date) = parse_maildir_name("1709625600.M1.host:2,FRS");
+        assert_eq!(flags, ["\\Flagged", "\\Answered", "\\Seen"]);
+        assert_eq!(date.map(|date| date.timestamp()), Some(1_709_625_600));
+        std::fs::remove_dir_all(&root).expect("remove sandbox");
+    }
+
+    #[test]
+    fn import_drops_deleted_flags() {
+        let root = sandbox("import-deleted");
+        let mbox = root.join("deleted.mbox");
+        std::fs::write(
+            &mbox,
+            "From someone@example.com Thu Jan  4 09:30:00 2024\n\
+             Subject: gone\nStatus: RO\nX-Status: DT\n\nbody\n",
+        )
+        .expect("write mbox");
+        let entries = list_source(StoreFormat::Mbox, &mbox, 10).expect("list mbox");
+        let message = read_source_message(&entries[0]).expect("read entry");
+        assert_eq!(message.flags, ["\\Seen", "\\Draft"]);
+
+        let (flags, _) = parse_maildir_name("1709625600.M1.host:2,ST");
+        assert_eq!(flags, ["\\Seen"]);
+        assert_eq!(maildir_info_flags(&["\\Deleted".to_owned()]), "T");
+        std::fs::remove_dir_all(&root).expect("remove sandbox");
+    }
+}
@@ -8,6 +8,7 @@
 //!
 //! - [`main`]: Process entry point with env loading and transport selection
 //! - [`config`]: Environment-driven configuration for accounts and server settings
+//! - [`dkim`]: DKIM signature verification against key-file or DNS public keys
 //! - [`errors`]: Application error model with MCP error mapping
 //! - [`imap`]: IMAP transport/session operations with timeout wrappers
 //! - [`server`]: MCP tool handlers with validation and business orchestration
@@ -16,15 +17,19 @@
 //! - [`message_id`]: Stable, opaque message ID parse/encode logic
 //! - [`pagination`]: Cursor storage with TTL and eviction behavior
 
+mod compose;
 mod config;
+mod dkim;
 mod errors;
 mod imap;
 mod mailbox_codec;
+mod mailstore;
 mod message_id;
 mod mime;
 mod models;
 mod pagination;
 mod server;
+mod smtp;
 
 use std::collections::BTreeMap;
 use std::io::{self, Write};
@@ -240,6 +245,12 @@ fn build_help_output(env_map: &BTreeMap<String, String>) -> String {
     out.push_str("  Optional per account section:\n");
     out.push_str("    MAIL_IMAP_<ACCOUNT>_PORT (default: 993)\n");
     out.push_str("    MAIL_IMAP_<ACCOUNT>_SECURE (default: true)\n");
+    out.push_str("    MAIL_IMAP_<ACCOUNT>_SMIME_CERT_PATH, MAIL_IMAP_<ACCOUNT>_SMIME_KEY_PATH\n");
+    out.push_str("    MAIL_IMAP_<ACCOUNT>_SMIME_TRUST_PATH\n");
+    out.push_str(
+        "    MAIL_IMAP_<ACCOUNT>_PGP_SECRET_KEY_PATH, MAIL_IMAP_<ACCOUNT>_PGP_PASSPHRASE\n",
+    );
+    out.push_str("    MAIL_IMAP_<ACCOUNT>_PGP_PUBLIC_KEYS_PATH\n");
     out.push_str(
         "  If no account section is discovered from environment, DEFAULT is used by convention.\n\n",
     );
@@ -279,6 +290,9 @@ fn build_help_output(env_map: &BTreeMap<String, String>) -> String {
     out.push_str("  MAIL_IMAP_SOCKET_TIMEOUT_MS=300000\n");
     out.push_str("  MAIL_IMAP_CURSOR_TTL_SECONDS=600\n");
     out.push_str("  MAIL_IMAP_CURSOR_MAX_ENTRIES=512\n");
+    out.push_str("  MAIL_IMAP_CURSOR_SIGNING_KEY=<unset>\n");
+    out.push_str("  MAIL_IMAP_DKIM_KEY_FILE=<unset>\n");
+    out.push_str("  MAIL_IMAP_DKIM_DNS_SERVER=<unset>\n");
     out.push_str("  MAIL_IMAP_OPERATION_MAX_ENTRIES=256\n\n");
 
     out.push_str("Send/write gate policy\n");
@@ -364,6 +378,10 @@ mod tests {
                 secure: true,
                 user: "user@example.com".to_owned(),
                 pass: SecretString::new("secret".to_owned().into()),
+                message_keys: Default::default(),
+                identities: Vec::new(),
+                smtp: None,
+                trash_mailbox: None,
             },
         );
 
@@ -371,14 +389,22 @@ mod tests {
             accounts,
             trusted_ca_certs: Vec::new(),
             write_enabled: false,
+            send_enabled: false,
+            permanent_delete_enabled: false,
             connect_timeout_ms: 30_000,
             greeting_timeout_ms: 15_000,
             socket_timeout_ms: 300_000,
             cursor_ttl_seconds: 600,
             cursor_max_entries: 512,
+            cursor_signing_key: None,
             read_session_cache_ttl_seconds: 120,
             read_session_cache_max_per_account: 4,
             operation_max_entries: 256,
+            dkim_keys: BTreeMap::new(),
+            dkim_dns_server: None,
+            export_dir: None,
+            import_dir: None,
+            attachment_dir: None,
         }
     }
 
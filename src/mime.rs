@@ -2,19 +2,69 @@
 //!
 //! Parses RFC822 messages using `mailparse`, extracts body text/HTML,
 //! and handles attachments. Sanitizes HTML, derives fallback text from HTML,
-//! and supports optional PDF text extraction.
+//! supports optional attachment text extraction, unwraps S/MIME and
+//! OpenPGP signed or encrypted parts, and inventories links and remote images.
 
-use std::collections::BTreeMap;
+mod archive;
+mod authentication;
+mod calendar;
+mod charset;
+mod extract;
+mod links;
+mod mailing_list;
+mod openpgp;
+mod smime;
+mod tnef;
+
+pub use authentication::apply_dkim_verification;
+pub use links::{MAX_LINKS, MAX_REMOTE_IMAGES};
+pub use mailing_list::is_bulk;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::LazyLock;
 
 use mailparse::body::Body;
-use mailparse::{DispositionType, MailHeader, ParsedMail};
+use mailparse::{DispositionType, MailAddr, MailHeader, ParsedMail};
+use regex::Regex;
 
+use crate::config::MessageKeys;
 use crate::errors::{AppError, AppResult};
-use crate::models::{AttachmentInfo, AttachmentMode, BodyMode};
+use crate::imap::{BodyPartRef, FetchedMessage};
+use crate::models::{
+    ArchiveEntry, AttachmentInfo, AttachmentMode, BodyMode, CalendarEvent, EmailAddress,
+    EmbeddedMessage, MailingList, MessageAuthentication, MessageLink, MessageSecurity, RemoteImage,
+    SecurityLayer, StrippedContent,
+};
 
 /// Maximum attachments collected during MIME parsing.
 pub const MAX_ATTACHMENTS: usize = 50;
 
+/// Maximum addresses returned per address-list header.
+pub const MAX_ADDRESSES_PER_HEADER: usize = 100;
+
+/// Maximum calendar events collected from a message's `text/calendar` parts.
+pub const MAX_CALENDAR_EVENTS: usize = 20;
+
+/// Maximum `message/rfc822` nesting depth that is parsed.
+pub const MAX_EMBEDDED_DEPTH: usize = 3;
+
+/// Maximum embedded messages reported per message, across all depths.
+pub const MAX_EMBEDDED_MESSAGES: usize = 10;
+
+/// Total body text characters shared by all embedded messages.
+const MAX_EMBEDDED_TEXT_CHARS: usize = 32_000;
+
+/// Maximum signed or encrypted layers unwrapped per message.
+const MAX_SECURITY_LAYERS: usize = 8;
+
+/// Part id component naming content decrypted or unwrapped from a CMS or
+/// OpenPGP layer, or decoded from a TNEF stream; such parts exist only
+/// locally and have no IMAP section.
+const UNWRAPPED_PART_COMPONENT: &str = "u";
+
+/// Maximum decoded attachment size eligible for text extraction.
+const MAX_EXTRACT_ATTACHMENT_BYTES: usize = 5_000_000;
+
 /// Parsed message representation
 ///
 /// Contains extracted headers, body content, and attachment metadata.
@@ -23,12 +73,10 @@ pub const MAX_ATTACHMENTS: usize = 50;
 pub struct ParsedMessage {
     /// Parsed Date header
     pub date: Option<String>,
-    /// Parsed From header
-    pub from: Option<String>,
-    /// Parsed To header
-    pub to: Option<String>,
-    /// Parsed Cc header
-    pub cc: Option<String>,
+    /// Parsed address-list headers
+    pub addresses: AddressHeaders,
+    /// Authentication verdicts from `Authentication-Results` and ARC headers
+    pub authentication: MessageAuthentication,
     /// Parsed Subject header
     pub subject: Option<String>,
     /// All headers as key-value pairs
@@ -37,93 +85,342 @@ pub struct ParsedMessage {
     pub body_text: Option<String>,
     /// Sanitized HTML body (untruncated)
     pub body_html_sanitized: Option<String>,
+    /// Markdown rendering of the body (untruncated; only for `BodyMode::Markdown`)
+    pub body_markdown: Option<String>,
+    /// Characters removed by `new_content_only` stripping, when requested
+    pub stripped_content: Option<StrippedContent>,
     /// Attachment metadata
     pub attachments: Vec<AttachmentInfo>,
     /// Whether attachment collection exceeded `MAX_ATTACHMENTS`
     pub attachments_truncated: bool,
+    /// Attachments whose text could not be extracted
+    pub attachment_issues: Vec<AttachmentIssue>,
+    /// Headers, bodies, and filenames decoded with a sniffed charset or lossily
+    pub charset_issues: Vec<CharsetIssue>,
+    /// Events parsed from `text/calendar` parts, deduplicated across copies
+    pub calendar_events: Vec<CalendarEvent>,
+    /// Messages embedded as `message/rfc822` parts, flattened in document order
+    pub embedded_messages: Vec<EmbeddedMessage>,
+    /// Whether embedded messages were dropped or cut by the depth, count, or text limits
+    pub embedded_messages_truncated: bool,
+    /// S/MIME and OpenPGP signature and encryption status
+    pub security: MessageSecurity,
+    /// Mailing-list metadata, when list headers are present
+    pub mailing_list: Option<MailingList>,
+    /// Links in the HTML body, in document order
+    pub links: Vec<MessageLink>,
+    /// Remote images in the HTML body, in document order
+    pub remote_images: Vec<RemoteImage>,
+    /// Whether links or images beyond `MAX_LINKS` or `MAX_REMOTE_IMAGES` were dropped
+    pub links_truncated: bool,
+}
+
+/// Per-attachment text extraction problem
+///
+/// Reported instead of failing the whole parse so callers can surface it as a
+/// tool issue while still returning the attachment metadata.
+#[derive(Debug, Clone)]
+pub struct AttachmentIssue {
+    /// Part ID of the affected attachment
+    pub part_id: String,
+    /// Filename of the affected attachment, if known
+    pub filename: Option<String>,
+    /// `limit_exceeded` or `extraction_failed`
+    pub code: &'static str,
+    /// Human-readable reason
+    pub message: String,
+}
+
+/// Text decoded with a detected charset or with replacement characters
+///
+/// Reported when the declared charset was missing, wrong, or unusable, or when
+/// malformed byte sequences were replaced with U+FFFD.
+#[derive(Debug, Clone)]
+pub struct CharsetIssue {
+    /// What was decoded, such as `headers`, `part 1.2 body`, or `part 1.3 filename`
+    pub location: String,
+    /// Charset label from the message, if any
+    pub declared: Option<String>,
+    /// Encoding the text was decoded with
+    pub detected: &'static str,
+    /// Characters replaced with U+FFFD
+    pub replaced_chars: usize,
+    /// Characters in the decoded text
+    pub total_chars: usize,
+}
+
+impl CharsetIssue {
+    fn new(location: String, report: charset::CharsetReport) -> Self {
+        Self {
+            location,
+            declared: report.declared,
+            detected: report.detected,
+            replaced_chars: report.replaced_chars,
+            total_chars: report.total_chars,
+        }
+    }
 }
 
-struct WalkConfig {
+struct WalkConfig<'a> {
     attachment_mode: AttachmentMode,
     include_html: bool,
     attachment_text_max_chars: usize,
+    new_content_only: bool,
+    /// Whether to inventory the HTML body's links and remote images
+    include_links: bool,
+    keys: &'a MessageKeys,
+    /// `From` addresses an S/MIME certificate or OpenPGP key must name
+    senders: &'a [String],
+    /// Decoded sizes of parts whose bodies were not downloaded, by IMAP section
+    omitted_sizes: &'a HashMap<String, usize>,
 }
 
+#[derive(Default)]
 struct WalkState {
     body_text: Option<String>,
     body_html: Option<String>,
+    /// Sanitized HTML body with quoted replies removed (`new_content_only`)
+    body_html_new_content: Option<String>,
     attachments: Vec<AttachmentInfo>,
     attachments_truncated: bool,
+    attachment_issues: Vec<AttachmentIssue>,
+    charset_issues: Vec<CharsetIssue>,
+    calendar_events: Vec<CalendarEvent>,
+    embedded_messages: Vec<EmbeddedMessage>,
+    embedded_messages_truncated: bool,
+    /// Part ID of the embedded message being walked, if any
+    embedded_parent: Option<String>,
+    security_layers: Vec<SecurityLayer>,
+    /// Links and remote images from the selected HTML body
+    link_inventory: links::LinkInventory,
 }
 
 /// Parse RFC822 message into structured representation
 ///
 /// Extracts headers, body text/HTML, and attachment info. Sanitizes
-/// HTML and optionally extracts text from PDF attachments.
+/// HTML, optionally extracts text from supported attachment formats, and
+/// verifies or decrypts S/MIME and OpenPGP parts with the account's keys, and
+/// on request lists the links and remote images of the HTML body. Attachments
+/// whose bodies were not fetched report their sizes from `BODYSTRUCTURE` and
+/// have no extracted text.
 ///
 /// # Parameters
 ///
-/// - `raw`: RFC822 message bytes
-/// - `body_max_chars`: Maximum characters for body text/HTML (caller truncates)
-/// - `include_html`: Whether to include HTML body
-/// - `extract_attachment_text`: Whether to extract text from PDFs
-/// - `attachment_text_max_chars`: Maximum characters for extracted PDF text
+/// - `message`: RFC822 message bytes and the sizes of omitted part bodies
+/// - `body_max_chars`: Maximum characters for each returned body
+/// - `body_mode`: Which bodies to return (text, sanitized HTML, or Markdown)
+/// - `attachment_mode`: Whether to list attachments and extract their text
+/// - `attachment_text_max_chars`: Maximum characters for extracted attachment text
+/// - `new_content_only`: Strip quoted replies and signatures from the bodies
+/// - `include_links`: List the links and remote images of the HTML body
+/// - `keys`: Account keys for signature verification and decryption
 ///
 /// # Errors
 ///
 /// - `Internal` if `mailparse` fails
+#[allow(clippy::too_many_arguments)]
 pub fn parse_message(
-    raw: &[u8],
+    message: &FetchedMessage,
     body_max_chars: usize,
     body_mode: BodyMode,
     attachment_mode: AttachmentMode,
     attachment_text_max_chars: usize,
+    new_content_only: bool,
+    include_links: bool,
+    keys: &MessageKeys,
 ) -> AppResult<ParsedMessage> {
-    let parsed = mailparse::parse_mail(raw)
-        .map_err(|e| AppError::Internal(format!("failed to parse RFC822 message: {e}")))?;
-
-    let headers = parse_all_headers(raw)?;
-    let mut state = WalkState {
-        body_text: None,
-        body_html: None,
-        attachments: Vec::new(),
-        attachments_truncated: false,
-    };
+    let senders: Vec<String> = parse_address_headers(&message.raw)
+        .from
+        .unwrap_or_default()
+        .into_iter()
+        .map(|address| address.email)
+        .collect();
     let config = WalkConfig {
         attachment_mode,
         include_html: matches!(body_mode, BodyMode::Html | BodyMode::Both),
         attachment_text_max_chars,
+        new_content_only,
+        include_links,
+        keys,
+        senders: &senders,
+        omitted_sizes: &message.omitted_sizes,
     };
+    parse_with_config(&message.raw, body_max_chars, body_mode, &config)
+}
+
+/// Whether `imap_get_message` must download a leaf part's body
+///
+/// Inline text, calendar, and embedded message parts are always read; TNEF
+/// parts when within the size limit; attachments only when their text is
+/// extracted and within the size limit.
+pub fn needs_part_body(part: &BodyPartRef, attachment_mode: AttachmentMode) -> bool {
+    let ctype = part.content_type.as_str();
+    let filename = part.filename.as_deref();
+    if matches!(ctype, "text/plain" | "text/html") && filename.is_none()
+        || matches!(ctype, "message/rfc822" | "message/global")
+        || is_calendar_part(ctype, filename)
+    {
+        return true;
+    }
+    if tnef::is_tnef(ctype, filename) {
+        return part.decoded_size_bytes() <= MAX_EXTRACT_ATTACHMENT_BYTES;
+    }
+    attachment_mode == AttachmentMode::ExtractText
+        && (extract::find_extractor(ctype, filename).is_some()
+            || archive::is_archive(ctype, filename))
+        && part.decoded_size_bytes() <= MAX_EXTRACT_ATTACHMENT_BYTES
+}
+
+fn parse_with_config(
+    raw: &[u8],
+    body_max_chars: usize,
+    body_mode: BodyMode,
+    config: &WalkConfig<'_>,
+) -> AppResult<ParsedMessage> {
+    let parsed = mailparse::parse_mail(raw)
+        .map_err(|e| AppError::Internal(format!("failed to parse RFC822 message: {e}")))?;
+
+    let mut state = WalkState::default();
+    let headers = parse_all_headers(raw, "headers", &mut state.charset_issues)?;
+    let new_content_only = config.new_content_only;
 
-    walk_parts(&parsed, "1".to_owned(), &mut state, &config)?;
+    walk_parts(&parsed, "1".to_owned(), &mut state, config, 0)?;
 
-    let text = if matches!(body_mode, BodyMode::Text | BodyMode::Both) {
-        select_body_text(state.body_text, state.body_html.as_deref())
-            .map(|t| truncate_chars(t, body_max_chars))
+    let mut text = if matches!(body_mode, BodyMode::Text | BodyMode::Both) {
+        select_body_text(state.body_text.clone(), state.body_html.as_deref())
+    } else {
+        None
+    };
+    let mut markdown = if body_mode == BodyMode::Markdown {
+        select_body_markdown(state.body_text.as_deref(), state.body_html.as_deref())
     } else {
         None
     };
-    let html = if config.include_html {
-        state.body_html.map(|h| truncate_chars(h, body_max_chars))
+    let mut html = if config.include_html {
+        state.body_html
     } else {
         None
     };
+    let mut stripped_content = None;
+    if new_content_only {
+        let new_markdown = if markdown.is_some() {
+            let new_plain = state
+                .body_text
+                .as_deref()
+                .map(|plain| strip_quotes_and_signature(plain).trim_end().to_owned());
+            select_body_markdown(new_plain.as_deref(), state.body_html_new_content.as_deref())
+        } else {
+            None
+        };
+        let new_text = if text.is_some() {
+            select_body_text(state.body_text, state.body_html_new_content.as_deref())
+                .map(|selected| strip_quotes_and_signature(&selected).trim_end().to_owned())
+                .filter(|selected| has_meaningful_content(selected))
+        } else {
+            None
+        };
+        let new_html = if html.is_some() {
+            state
+                .body_html_new_content
+                .filter(|selected| has_meaningful_content(selected))
+        } else {
+            None
+        };
+        stripped_content = Some(StrippedContent {
+            body_text_chars: text
+                .as_deref()
+                .map(|full| removed_chars(full, new_text.as_deref())),
+            body_html_chars: html
+                .as_deref()
+                .map(|full| removed_chars(full, new_html.as_deref())),
+            body_markdown_chars: markdown
+                .as_deref()
+                .map(|full| removed_chars(full, new_markdown.as_deref())),
+        });
+        text = new_text;
+        html = new_html;
+        markdown = new_markdown;
+    }
+    let text = text.map(|t| truncate_chars(t, body_max_chars));
+    let html = html.map(|h| truncate_chars(h, body_max_chars));
+    let markdown = markdown.map(|m| truncate_chars(m, body_max_chars));
 
+    let (embedded_messages, embedded_budget_exceeded) =
+        budget_embedded_messages(state.embedded_messages, body_max_chars);
     let header_map = to_header_map(&headers);
+    let addresses = parse_address_headers(raw);
     Ok(ParsedMessage {
         date: header_map.get("date").cloned(),
-        from: header_map.get("from").cloned(),
-        to: header_map.get("to").cloned(),
-        cc: header_map.get("cc").cloned(),
+        authentication: authentication::message_authentication(&headers, &addresses),
+        mailing_list: mailing_list::mailing_list(&headers),
+        addresses,
         subject: header_map.get("subject").cloned(),
         headers_all: headers,
         body_text: text,
         body_html_sanitized: html,
+        body_markdown: markdown,
+        stripped_content,
         attachments: state.attachments,
         attachments_truncated: state.attachments_truncated,
+        attachment_issues: state.attachment_issues,
+        charset_issues: state.charset_issues,
+        calendar_events: state.calendar_events,
+        embedded_messages,
+        embedded_messages_truncated: state.embedded_messages_truncated || embedded_budget_exceeded,
+        security: summarize_security(state.security_layers),
+        links: state.link_inventory.links,
+        remote_images: state.link_inventory.remote_images,
+        links_truncated: state.link_inventory.truncated,
     })
 }
 
+/// Summarize unwrapped layers into overall signed and encrypted flags.
+fn summarize_security(layers: Vec<SecurityLayer>) -> MessageSecurity {
+    let all_of_kind = |kind: &str, status: &str| {
+        let mut of_kind = layers.iter().filter(|layer| layer.kind == kind).peekable();
+        of_kind.peek().is_some() && of_kind.all(|layer| layer.status == status)
+    };
+    MessageSecurity {
+        encrypted: layers.iter().any(|layer| layer.kind == "encrypted"),
+        decrypted: all_of_kind("encrypted", "decrypted"),
+        signed: layers.iter().any(|layer| layer.kind == "signed"),
+        verified: all_of_kind("signed", "valid"),
+        layers,
+    }
+}
+
+/// Count characters dropped by stripping, ignoring carriage returns that
+/// line-based stripping normalizes away.
+fn removed_chars(full: &str, kept: Option<&str>) -> usize {
+    let count = |text: &str| text.chars().filter(|ch| *ch != '\r').count();
+    count(full).saturating_sub(kept.map_or(0, count))
+}
+
+/// Apply the embedded message count limit and shared body text budget
+///
+/// Each body is cut to `body_max_chars` like the outer body; the returned flag
+/// reports whether the count limit or the shared budget dropped anything.
+fn budget_embedded_messages(
+    mut embedded: Vec<EmbeddedMessage>,
+    body_max_chars: usize,
+) -> (Vec<EmbeddedMessage>, bool) {
+    let mut exceeded = embedded.len() > MAX_EMBEDDED_MESSAGES;
+    embedded.truncate(MAX_EMBEDDED_MESSAGES);
+    let mut remaining = MAX_EMBEDDED_TEXT_CHARS;
+    for message in &mut embedded {
+        message.body_text = message.body_text.take().and_then(|text| {
+            let len = text.chars().count();
+            if len > remaining && remaining < body_max_chars {
+                exceeded = true;
+            }
+            let text = truncate_chars(text, body_max_chars.min(remaining));
+            remaining -= text.chars().count();
+            (!text.is_empty()).then_some(text)
+        });
+    }
+    (embedded, exceeded)
+}
+
 /// Choose the message text body, preferring a meaningful text/plain part.
 ///
 /// Falls back to text derived from sanitized HTML when plain text is missing
@@ -147,6 +444,51 @@ fn html_to_text(html: &str) -> Option<String> {
     (!trimmed.is_empty()).then(|| trimmed.to_owned())
 }
 
+/// Choose the Markdown body, preferring the sanitized HTML part.
+///
+/// HTML carries the headings, links, and tables worth keeping; plain text is
+/// already readable Markdown and is returned unchanged when no meaningful HTML
+/// body exists.
+fn select_body_markdown(body_text: Option<&str>, body_html: Option<&str>) -> Option<String> {
+    body_html
+        .and_then(html_to_markdown)
+        .or_else(|| body_text.map(|text| text.trim().to_owned()))
+        .filter(|markdown| has_meaningful_content(markdown))
+}
+
+/// Convert sanitized HTML to compact Markdown.
+///
+/// Links become `[text](url)` and tables become Markdown tables. Images are
+/// reduced to their alt text, and runs of blank lines are collapsed.
+fn html_to_markdown(html: &str) -> Option<String> {
+    static CONVERTER: LazyLock<htmd::HtmlToMarkdown> = LazyLock::new(|| {
+        htmd::HtmlToMarkdown::builder()
+            .options(htmd::options::Options {
+                bullet_list_marker: htmd::options::BulletListMarker::Dash,
+                ..Default::default()
+            })
+            .add_handler(
+                vec!["img"],
+                |_: &dyn htmd::element_handler::Handlers, element: htmd::Element| {
+                    let alt = element
+                        .attrs
+                        .iter()
+                        .find(|attr| &*attr.name.local == "alt")
+                        .map(|attr| attr.value.trim().to_owned())
+                        .unwrap_or_default();
+                    Some(alt.into())
+                },
+            )
+            .build()
+    });
+    static BLANK_LINES: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\n[ \t]*(?:\n[ \t]*)+\n").expect("static regex is valid"));
+
+    let markdown = CONVERTER.convert(html).ok()?;
+    let markdown = BLANK_LINES.replace_all(markdown.trim(), "\n\n");
+    has_meaningful_content(&markdown).then(|| markdown.into_owned())
+}
+
 /// Return true when the body contains non-whitespace characters.
 fn has_meaningful_content(body: &str) -> bool {
     body.chars().any(|ch| !ch.is_whitespace())
@@ -160,71 +502,574 @@ fn walk_parts(
     part: &ParsedMail<'_>,
     part_id: String,
     state: &mut WalkState,
-    config: &WalkConfig,
+    config: &WalkConfig<'_>,
+    depth: usize,
 ) -> AppResult<()> {
+    if walk_protected_part(part, &part_id, state, config, depth)? {
+        return Ok(());
+    }
+
     if part.subparts.is_empty() {
         let ctype = part.ctype.mimetype.to_ascii_lowercase();
         let disp = part.get_content_disposition();
-        let filename = attachment_filename(part, &disp.params);
+        let filename = attachment_filename(part, &part_id, &disp.params, state);
         let is_attachment = disp.disposition == DispositionType::Attachment || filename.is_some();
+        let is_tnef = tnef::is_tnef(&ctype, filename.as_deref());
+
+        if matches!(ctype.as_str(), "message/rfc822" | "message/global") {
+            collect_embedded_message(part, &part_id, state, config, depth)?;
+        }
+
+        if is_calendar_part(&ctype, filename.as_deref())
+            && let Some(text) = decode_text_part(part, &part_id, state)
+        {
+            collect_calendar_events(&mut state.calendar_events, calendar::parse_calendar(&text));
+        }
 
         if !is_attachment {
             if ctype == "text/plain"
                 && state.body_text.is_none()
-                && let Ok(text) = part.get_body()
+                && let Some(text) = decode_text_part(part, &part_id, state)
             {
                 state.body_text = Some(text);
             }
 
             if ctype == "text/html"
                 && state.body_html.is_none()
-                && let Ok(html) = part.get_body()
+                && let Some(html) = decode_text_part(part, &part_id, state)
             {
-                state.body_html = Some(ammonia::clean(&html));
+                set_html_body(&html, state, config);
             }
         }
 
-        if is_attachment {
-            if state.attachments.len() >= MAX_ATTACHMENTS {
-                state.attachments_truncated = true;
-                return Ok(());
-            }
-
-            let size_bytes = attachment_size_bytes(part);
-            let mut extracted_text = None;
-            if config.attachment_mode == AttachmentMode::ExtractText
-                && ctype == "application/pdf"
-                && size_bytes <= 5_000_000
+        if is_attachment && state.attachments.len() >= MAX_ATTACHMENTS {
+            state.attachments_truncated = true;
+        } else if is_attachment {
+            let size_bytes = part_size_bytes(part, &part_id, config);
+            let extracted_text = if config.attachment_mode == AttachmentMode::ExtractText {
+                extract_attachment_text(part, &ctype, filename.as_deref(), size_bytes, config)
+                    .map_err(|(code, message)| {
+                        state.attachment_issues.push(AttachmentIssue {
+                            part_id: part_id.clone(),
+                            filename: filename.clone(),
+                            code,
+                            message,
+                        });
+                    })
+                    .ok()
+                    .flatten()
+            } else {
+                None
+            };
+            let archive_entries = if config.attachment_mode == AttachmentMode::ExtractText
+                && archive::is_archive(&ctype, filename.as_deref())
             {
-                let raw_body = part.get_body_raw().map_err(|e| {
-                    AppError::Internal(format!("failed decoding attachment body: {e}"))
-                })?;
-                if let Ok(text) = pdf_extract::extract_text_from_mem(&raw_body) {
-                    extracted_text = Some(truncate_chars(text, config.attachment_text_max_chars));
+                match check_extract_size(size_bytes) {
+                    Ok(()) => part.get_body_raw().ok().map(|bytes| {
+                        list_archive(&bytes, &part_id, filename.as_deref(), state, config)
+                    }),
+                    Err((code, message)) => {
+                        state.attachment_issues.push(AttachmentIssue {
+                            part_id: part_id.clone(),
+                            filename: filename.clone(),
+                            code,
+                            message,
+                        });
+                        None
+                    }
                 }
-            }
+            } else {
+                None
+            };
 
             if config.attachment_mode != AttachmentMode::None {
                 state.attachments.push(AttachmentInfo {
-                    filename,
+                    filename: filename.clone(),
                     content_type: ctype,
                     size_bytes,
-                    part_id,
+                    part_id: part_id.clone(),
                     extracted_text,
+                    archive_entries,
                 });
             }
         }
 
+        if is_tnef {
+            walk_tnef(part, &part_id, filename, state, config);
+        }
         return Ok(());
     }
 
     for (idx, sub) in part.subparts.iter().enumerate() {
         let next_id = format!("{part_id}.{}", idx + 1);
-        walk_parts(sub, next_id, state, config)?;
+        walk_parts(sub, next_id, state, config, depth)?;
+    }
+    Ok(())
+}
+
+/// Select an HTML body: sanitize it and record its links and quote-stripped copy.
+fn set_html_body(html: &str, state: &mut WalkState, config: &WalkConfig<'_>) {
+    if config.new_content_only {
+        state.body_html_new_content = Some(strip_quoted_html(html));
+    }
+    if config.include_links {
+        state.link_inventory = links::inventory(html);
+    }
+    state.body_html = Some(ammonia::clean(html));
+}
+
+/// Decoded size of a leaf part, including bodies omitted from the fetch.
+fn part_size_bytes(part: &ParsedMail<'_>, part_id: &str, config: &WalkConfig<'_>) -> usize {
+    imap_section_for_part_id(part_id)
+        .and_then(|section| config.omitted_sizes.get(&section).copied())
+        .unwrap_or_else(|| attachment_size_bytes(part))
+}
+
+/// Decode a TNEF (`winmail.dat`) part into bodies and attachments
+///
+/// The TNEF body fills in bodies no other part provided. Inner attachments
+/// are numbered under the TNEF part (`<part_id>.u.N`) and share the message's
+/// attachment count and extraction size limits; a TNEF part above the
+/// extraction limit is not decoded.
+fn walk_tnef(
+    part: &ParsedMail<'_>,
+    part_id: &str,
+    filename: Option<String>,
+    state: &mut WalkState,
+    config: &WalkConfig<'_>,
+) {
+    if let Err((code, message)) = check_extract_size(part_size_bytes(part, part_id, config)) {
+        state.attachment_issues.push(AttachmentIssue {
+            part_id: part_id.to_owned(),
+            filename,
+            code,
+            message,
+        });
+        return;
+    }
+    let decoded = part
+        .get_body_raw()
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| tnef::decode(&bytes));
+    let content = match decoded {
+        Ok(content) => content,
+        Err(reason) => {
+            state.attachment_issues.push(AttachmentIssue {
+                part_id: part_id.to_owned(),
+                filename,
+                code: "extraction_failed",
+                message: format!("failed decoding TNEF: {reason}"),
+            });
+            return;
+        }
+    };
+
+    if state.body_text.is_none() {
+        state.body_text = content.body_text;
+    }
+    if state.body_html.is_none()
+        && let Some(html) = content.body_html
+    {
+        set_html_body(&html, state, config);
+    }
+
+    for (idx, attachment) in content.attachments.into_iter().enumerate() {
+        if state.attachments.len() >= MAX_ATTACHMENTS {
+            state.attachments_truncated = true;
+            return;
+        }
+        let inner_id = format!("{part_id}.{UNWRAPPED_PART_COMPONENT}.{}", idx + 1);
+        let content_type = attachment
+            .content_type
+            .unwrap_or_else(|| "application/octet-stream".to_owned());
+        let size_bytes = attachment.data.len();
+        let extract_mode = config.attachment_mode == AttachmentMode::ExtractText;
+        let extracted_text = if extract_mode {
+            extract_text(
+                &attachment.data,
+                &content_type,
+                attachment.filename.as_deref(),
+                None,
+                config,
+            )
+            .map_err(|(code, message)| {
+                state.attachment_issues.push(AttachmentIssue {
+                    part_id: inner_id.clone(),
+                    filename: attachment.filename.clone(),
+                    code,
+                    message,
+                });
+            })
+            .ok()
+            .flatten()
+        } else {
+            None
+        };
+        let archive_entries =
+            if extract_mode && archive::is_archive(&content_type, attachment.filename.as_deref()) {
+                match check_extract_size(size_bytes) {
+                    Ok(()) => Some(list_archive(
+                        &attachment.data,
+                        &inner_id,
+                        attachment.filename.as_deref(),
+                        state,
+                        config,
+                    )),
+                    Err((code, message)) => {
+                        state.attachment_issues.push(AttachmentIssue {
+                            part_id: inner_id.clone(),
+                            filename: attachment.filename.clone(),
+                            code,
+                            message,
+                        });
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+        if config.attachment_mode != AttachmentMode::None {
+            state.attachments.push(AttachmentInfo {
+                filename: attachment.filename,
+                content_type,
+                size_bytes,
+                part_id: inner_id,
+                extracted_text,
+                archive_entries,
+            });
+        }
+    }
+}
+
+/// Unwrap an S/MIME or OpenPGP protected part and walk its content
+///
+/// Signed content keeps its IMAP part ids. Decrypted or opaque-signed
+/// content is numbered under the protected part like an embedded message:
+/// children of a multipart entity are `<part_id>.N`, a single part is
+/// `<part_id>.1`.
+/// Returns `false` when the part is not protected, uses an unknown protocol,
+/// could not be decrypted, or `MAX_SECURITY_LAYERS` is reached, so the
+/// caller walks it as an ordinary part.
+fn walk_protected_part(
+    part: &ParsedMail<'_>,
+    part_id: &str,
+    state: &mut WalkState,
+    config: &WalkConfig<'_>,
+    depth: usize,
+) -> AppResult<bool> {
+    if state.security_layers.len() >= MAX_SECURITY_LAYERS {
+        return Ok(false);
+    }
+    let ctype = part.ctype.mimetype.to_ascii_lowercase();
+    let protocol = part
+        .ctype
+        .params
+        .get("protocol")
+        .map(|protocol| protocol.to_ascii_lowercase());
+    match (ctype.as_str(), protocol.as_deref()) {
+        ("multipart/signed", Some(protocol)) if part.subparts.len() == 2 => {
+            let signature = part.subparts[1].get_body_raw().unwrap_or_default();
+            let signed = canonical_line_endings(part.subparts[0].raw_bytes);
+            let layer = match protocol {
+                "application/pkcs7-signature" | "application/x-pkcs7-signature" => {
+                    smime::verify_detached(
+                        part_id,
+                        &signed,
+                        &signature,
+                        config.keys,
+                        config.senders,
+                    )
+                }
+                "application/pgp-signature" => openpgp::verify_detached(
+                    part_id,
+                    &signed,
+                    &signature,
+                    config.keys,
+                    config.senders,
+                ),
+                _ => return Ok(false),
+            };
+            state.security_layers.push(layer);
+            walk_parts(
+                &part.subparts[0],
+                format!("{part_id}.1"),
+                state,
+                config,
+                depth,
+            )?;
+            Ok(true)
+        }
+        ("multipart/encrypted", Some("application/pgp-encrypted")) if part.subparts.len() == 2 => {
+            let armored = part.subparts[1].get_body_raw().unwrap_or_default();
+            let (layers, content) =
+                openpgp::decrypt(part_id, &armored, config.keys, config.senders);
+            state.security_layers.extend(layers);
+            walk_unwrapped_content(content, part_id, state, config, depth)
+        }
+        ("application/pkcs7-mime" | "application/x-pkcs7-mime", _) => {
+            let Ok(body) = part.get_body_raw() else {
+                return Ok(false);
+            };
+            let (layer, content) = smime::open(part_id, &body, config.keys, config.senders);
+            state.security_layers.push(layer);
+            walk_unwrapped_content(content, part_id, state, config, depth)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Walk a decrypted or unwrapped MIME entity as `<part_id>.u`.
+///
+/// The marker component keeps its parts from colliding with real IMAP
+/// sections (see [`is_unwrapped_part_id`]).
+fn walk_unwrapped_content(
+    content: Option<Vec<u8>>,
+    part_id: &str,
+    state: &mut WalkState,
+    config: &WalkConfig<'_>,
+    depth: usize,
+) -> AppResult<bool> {
+    let Some(content) = content else {
+        return Ok(false);
+    };
+    let Ok(inner) = mailparse::parse_mail(&content) else {
+        return Ok(false);
+    };
+    let inner_id = format!("{part_id}.{UNWRAPPED_PART_COMPONENT}");
+    walk_parts(&inner, inner_id, state, config, depth)?;
+    Ok(true)
+}
+
+/// Convert bare LF line endings to CRLF, the canonical form signatures cover.
+fn canonical_line_endings(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut previous = 0u8;
+    for &byte in bytes {
+        if byte == b'\n' && previous != b'\r' {
+            out.push(b'\r');
+        }
+        out.push(byte);
+        previous = byte;
+    }
+    out
+}
+
+/// Parse a `message/rfc822` part into an [`EmbeddedMessage`]
+///
+/// Inner part ids follow IMAP numbering: children of an embedded multipart
+/// are `<part_id>.N`, and a single-part embedded body is `<part_id>.1`. Parts
+/// that fail to parse or exceed `MAX_EMBEDDED_DEPTH` are skipped.
+fn collect_embedded_message(
+    part: &ParsedMail<'_>,
+    part_id: &str,
+    state: &mut WalkState,
+    config: &WalkConfig<'_>,
+    depth: usize,
+) -> AppResult<()> {
+    if depth >= MAX_EMBEDDED_DEPTH || state.embedded_messages.len() >= MAX_EMBEDDED_MESSAGES {
+        state.embedded_messages_truncated = true;
+        return Ok(());
+    }
+    let Ok(raw) = part.get_body_raw() else {
+        return Ok(());
+    };
+    let Ok(inner) = mailparse::parse_mail(&raw) else {
+        return Ok(());
+    };
+
+    let mut inner_state = WalkState {
+        embedded_parent: Some(part_id.to_owned()),
+        ..WalkState::default()
+    };
+    if inner.subparts.is_empty() {
+        walk_parts(
+            &inner,
+            format!("{part_id}.1"),
+            &mut inner_state,
+            config,
+            depth + 1,
+        )?;
+    } else {
+        for (idx, sub) in inner.subparts.iter().enumerate() {
+            let next_id = format!("{part_id}.{}", idx + 1);
+            walk_parts(sub, next_id, &mut inner_state, config, depth + 1)?;
+        }
+    }
+
+    let headers = parse_all_headers(
+        &raw,
+        &format!("part {part_id} headers"),
+        &mut state.charset_issues,
+    )
+    .unwrap_or_default();
+    let header_map = to_header_map(&headers);
+    let addresses = parse_address_headers(&raw);
+    state.embedded_messages.push(EmbeddedMessage {
+        part_id: part_id.to_owned(),
+        parent_part_id: state.embedded_parent.clone(),
+        depth: depth + 1,
+        date: header_map.get("date").cloned(),
+        from: addresses.from,
+        to: addresses.to,
+        cc: addresses.cc,
+        subject: header_map.get("subject").cloned(),
+        internet_message_id: header_map.get("message-id").cloned(),
+        body_text: select_body_text(inner_state.body_text, inner_state.body_html.as_deref()),
+        attachments: inner_state.attachments,
+    });
+    state
+        .embedded_messages
+        .extend(inner_state.embedded_messages);
+    state.embedded_messages_truncated |=
+        inner_state.embedded_messages_truncated || inner_state.attachments_truncated;
+    state
+        .attachment_issues
+        .extend(inner_state.attachment_issues);
+    state.charset_issues.extend(inner_state.charset_issues);
+    Ok(())
+}
+
+fn is_calendar_part(ctype: &str, filename: Option<&str>) -> bool {
+    matches!(ctype, "text/calendar" | "application/ics")
+        || filename.is_some_and(|name| name.to_ascii_lowercase().ends_with(".ics"))
+}
+
+/// Append parsed events, skipping duplicates of the same invite.
+///
+/// Invites commonly carry the same event both inline and as an `.ics`
+/// attachment; copies match on UID, SEQUENCE, and start time.
+fn collect_calendar_events(events: &mut Vec<CalendarEvent>, parsed: Vec<CalendarEvent>) {
+    for event in parsed {
+        if events.len() >= MAX_CALENDAR_EVENTS {
+            return;
+        }
+        let duplicate = event.uid.is_some()
+            && events.iter().any(|existing| {
+                existing.uid == event.uid
+                    && existing.sequence == event.sequence
+                    && existing.start.as_ref().map(|time| &time.local)
+                        == event.start.as_ref().map(|time| &time.local)
+            });
+        if !duplicate {
+            events.push(event);
+        }
+    }
+}
+
+/// Extract attachment text with the registered extractor for its format
+///
+/// Returns `Ok(None)` for unsupported formats and an issue code plus message
+/// when the attachment is too large or extraction fails.
+fn extract_attachment_text(
+    part: &ParsedMail<'_>,
+    ctype: &str,
+    filename: Option<&str>,
+    size_bytes: usize,
+    config: &WalkConfig<'_>,
+) -> Result<Option<String>, (&'static str, String)> {
+    if extract::find_extractor(ctype, filename).is_none() {
+        return Ok(None);
+    }
+    check_extract_size(size_bytes)?;
+    let bytes = part.get_body_raw().map_err(|e| {
+        (
+            "extraction_failed",
+            format!("failed decoding attachment body: {e}"),
+        )
+    })?;
+    let charset = part.ctype.params.get("charset").map(String::as_str);
+    extract_text(&bytes, ctype, filename, charset, config)
+}
+
+/// List an archive attachment and extract text from its supported entries
+///
+/// Entry texts share the `attachment_text_max_chars` budget; limit hits and
+/// entry failures are recorded as attachment issues.
+fn list_archive(
+    bytes: &[u8],
+    part_id: &str,
+    filename: Option<&str>,
+    state: &mut WalkState,
+    config: &WalkConfig<'_>,
+) -> Vec<ArchiveEntry> {
+    let extract_entry = |bytes: &[u8], path: &str| {
+        extract_text(bytes, "application/octet-stream", Some(path), None, config)
+    };
+    let listing = archive::list(
+        bytes,
+        filename,
+        config.attachment_text_max_chars,
+        &extract_entry,
+    );
+    state
+        .attachment_issues
+        .extend(
+            listing
+                .issues
+                .into_iter()
+                .map(|(code, message)| AttachmentIssue {
+                    part_id: part_id.to_owned(),
+                    filename: filename.map(str::to_owned),
+                    code,
+                    message,
+                }),
+        );
+    listing.entries
+}
+
+/// Extract text from decoded attachment bytes, within the extraction size limit
+fn extract_text(
+    bytes: &[u8],
+    ctype: &str,
+    filename: Option<&str>,
+    charset: Option<&str>,
+    config: &WalkConfig<'_>,
+) -> Result<Option<String>, (&'static str, String)> {
+    let Some(extractor) = extract::find_extractor(ctype, filename) else {
+        return Ok(None);
+    };
+    check_extract_size(bytes.len())?;
+    let content = extract::AttachmentContent { charset, bytes };
+    let text = extractor
+        .extract(&content)
+        .map_err(|e| ("extraction_failed", e.to_string()))?;
+    Ok(Some(truncate_chars(text, config.attachment_text_max_chars)))
+}
+
+fn check_extract_size(size_bytes: usize) -> Result<(), (&'static str, String)> {
+    if size_bytes > MAX_EXTRACT_ATTACHMENT_BYTES {
+        return Err((
+            "limit_exceeded",
+            format!(
+                "attachment is {size_bytes} bytes; text extraction is limited to {MAX_EXTRACT_ATTACHMENT_BYTES}"
+            ),
+        ));
     }
     Ok(())
 }
 
+/// Decode text bytes using a declared charset, defaulting to UTF-8.
+///
+/// Unknown charsets and invalid sequences decode lossily rather than failing.
+fn decode_charset(bytes: &[u8], charset: Option<&str>) -> String {
+    let charset = charset.filter(|value| {
+        value
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.' | ':'))
+    });
+    let Some(charset) = charset else {
+        return String::from_utf8_lossy(bytes).into_owned();
+    };
+    let mut synthetic = format!(
+        "Content-Type: text/plain; charset=\"{charset}\"\r\nContent-Transfer-Encoding: 8bit\r\n\r\n"
+    )
+    .into_bytes();
+    synthetic.extend_from_slice(bytes);
+    mailparse::parse_mail(&synthetic)
+        .and_then(|parsed| parsed.get_body())
+        .unwrap_or_else(|_| String::from_utf8_lossy(bytes).into_owned())
+}
+
 fn attachment_size_bytes(part: &ParsedMail<'_>) -> usize {
     match part.get_body_encoded() {
         Body::Base64(body) | Body::QuotedPrintable(body) => body
@@ -235,14 +1080,62 @@ fn attachment_size_bytes(part: &ParsedMail<'_>) -> usize {
     }
 }
 
+/// Decode a text part body, sniffing the charset when the label is missing or wrong
+///
+/// Fallbacks and replaced characters are recorded as charset issues.
+fn decode_text_part(part: &ParsedMail<'_>, part_id: &str, state: &mut WalkState) -> Option<String> {
+    let bytes = part.get_body_raw().ok()?;
+    let declared = part.ctype.params.get("charset").map(String::as_str);
+    let (text, report) = charset::decode(&bytes, declared);
+    if let Some(report) = report {
+        state
+            .charset_issues
+            .push(CharsetIssue::new(format!("part {part_id} body"), report));
+    }
+    Some(text)
+}
+
 /// Extract attachment filename from part
 ///
 /// Checks Content-Disposition parameter first, falls back to Content-Type
-/// name parameter.
+/// name parameter. Raw 8-bit parameters are re-decoded with a sniffed charset
+/// instead of the Latin-1 fallback `mailparse` applies.
 fn attachment_filename(
     part: &ParsedMail<'_>,
+    part_id: &str,
     disp_params: &BTreeMap<String, String>,
+    state: &mut WalkState,
 ) -> Option<String> {
+    let raw_header = |key: &str| {
+        part.headers
+            .iter()
+            .find(|header| header.get_key_ref().eq_ignore_ascii_case(key))
+            .and_then(|header| charset::decode_raw_header(header.get_value_raw()))
+    };
+    let sniffed = raw_header("content-disposition")
+        .and_then(|(value, report)| {
+            mailparse::parse_content_disposition(&value)
+                .params
+                .remove("filename")
+                .map(|name| (name, report))
+        })
+        .or_else(|| {
+            raw_header("content-type").and_then(|(value, report)| {
+                mailparse::parse_content_type(&value)
+                    .params
+                    .remove("name")
+                    .map(|name| (name, report))
+            })
+        });
+    if let Some((name, report)) = sniffed {
+        if let Some(report) = report {
+            state.charset_issues.push(CharsetIssue::new(
+                format!("part {part_id} filename"),
+                report,
+            ));
+        }
+        return Some(name);
+    }
     disp_params
         .get("filename")
         .cloned()
@@ -252,13 +1145,14 @@ fn attachment_filename(
 /// Return headers, either curated or all
 ///
 /// If `include_all=true`, returns all headers. Otherwise, returns only
-/// a safe subset (Date, From, To, Cc, Subject, Message-ID).
+/// a safe subset (Date, Subject, Message-ID); address headers are returned
+/// in structured form instead, with raw values kept for `include_all`.
 pub fn curated_headers(headers: &[(String, String)], include_all: bool) -> Vec<(String, String)> {
     if include_all {
         return headers.to_vec();
     }
 
-    let allowed = ["date", "from", "to", "cc", "subject", "message-id"];
+    let allowed = ["date", "subject", "message-id"];
     headers
         .iter()
         .filter(|(k, _)| allowed.contains(&k.to_ascii_lowercase().as_str()))
@@ -267,16 +1161,96 @@ pub fn curated_headers(headers: &[(String, String)], include_all: bool) -> Vec<(
 }
 
 /// Parse header bytes into key-value pairs
+///
+/// Raw 8-bit header values are decoded with a sniffed charset.
 pub fn parse_header_bytes(header_bytes: &[u8]) -> AppResult<Vec<(String, String)>> {
-    let (headers, _) = mailparse::parse_headers(header_bytes)
+    let (block, _) = charset::utf8_header_block(header_bytes);
+    let (headers, _) = mailparse::parse_headers(&block)
         .map_err(|e| AppError::Internal(format!("failed to parse message headers: {e}")))?;
     Ok(to_tuples(headers))
 }
 
+/// Address-list headers parsed into structured addresses
+///
+/// Each field is `None` when the header is absent or cannot be parsed; the raw
+/// value remains available through the header list.
+#[derive(Debug, Clone, Default)]
+pub struct AddressHeaders {
+    pub from: Option<Vec<EmailAddress>>,
+    pub to: Option<Vec<EmailAddress>>,
+    pub cc: Option<Vec<EmailAddress>>,
+    pub bcc: Option<Vec<EmailAddress>>,
+    pub reply_to: Option<Vec<EmailAddress>>,
+    pub sender: Option<Vec<EmailAddress>>,
+}
+
+/// Parse the address-list headers of a message or header block
+///
+/// Uses the first occurrence of each header. Display names are decoded from
+/// RFC 2047 encoded words, groups are flattened into their members, and each
+/// list is capped at `MAX_ADDRESSES_PER_HEADER` entries. Raw 8-bit display
+/// names are decoded with a sniffed charset.
+pub fn parse_address_headers(header_bytes: &[u8]) -> AddressHeaders {
+    let (block, _) = charset::utf8_header_block(header_bytes);
+    let Ok((headers, _)) = mailparse::parse_headers(&block) else {
+        return AddressHeaders::default();
+    };
+    let find = |key: &str| {
+        headers
+            .iter()
+            .find(|header| header.get_key_ref().eq_ignore_ascii_case(key))
+            .and_then(parse_address_header)
+    };
+    AddressHeaders {
+        from: find("from"),
+        to: find("to"),
+        cc: find("cc"),
+        bcc: find("bcc"),
+        reply_to: find("reply-to"),
+        sender: find("sender"),
+    }
+}
+
+fn parse_address_header(header: &MailHeader<'_>) -> Option<Vec<EmailAddress>> {
+    let list = mailparse::addrparse_header(header).ok()?;
+    let addresses = list
+        .iter()
+        .flat_map(|address| match address {
+            MailAddr::Single(info) => std::slice::from_ref(info),
+            MailAddr::Group(group) => group.addrs.as_slice(),
+        })
+        .filter_map(|info| {
+            let email = info.addr.trim();
+            (!email.is_empty()).then(|| EmailAddress {
+                name: info
+                    .display_name
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_owned),
+                email: email.to_owned(),
+            })
+        })
+        .take(MAX_ADDRESSES_PER_HEADER)
+        .collect();
+    Some(addresses)
+}
+
 /// Parse all headers from raw message
-fn parse_all_headers(raw: &[u8]) -> AppResult<Vec<(String, String)>> {
-    let (headers, _) = mailparse::parse_headers(raw)
+///
+/// Raw 8-bit header values are decoded with a sniffed charset, which is
+/// recorded in `charset_issues` under `location`.
+fn parse_all_headers(
+    raw: &[u8],
+    location: &str,
+    charset_issues: &mut Vec<CharsetIssue>,
+) -> AppResult<Vec<(String, String)>> {
+    let (block, report) = charset::utf8_header_block(raw);
+    let (headers, _) = mailparse::parse_headers(&block)
         .map_err(|e| AppError::Internal(format!("failed to parse message headers: {e}")))?;
+    if let Some(report) = report {
+        charset_issues.push(CharsetIssue::new(location.to_owned(), report));
+    }
     Ok(to_tuples(headers))
 }
 
@@ -312,58 +1286,520 @@ pub fn truncate_chars(input: String, max_chars: usize) -> String {
     input.chars().take(max_chars).collect()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::{
-        MAX_ATTACHMENTS, attachment_size_bytes, curated_headers, parse_message, truncate_chars,
-    };
-    use crate::models::{AttachmentMode, BodyMode};
-
-    /// Tests that Unicode strings are truncated by character, not byte.
-    #[test]
-    fn truncates_unicode_by_character() {
-        let input = "a😀b😀c".to_owned();
-        let out = truncate_chars(input, 4);
-        assert_eq!(out, "a😀b😀");
+/// Map an attachment `part_id` from [`parse_message`] to an IMAP section
+///
+/// Part ids number the root entity `1` and prefix every child with its
+/// parent's id, so a root multipart's second child is `1.2`. IMAP numbers that
+/// child `2`, while a non-multipart root body is section `1` in both schemes.
+/// A multipart root has no section of its own: IMAP section `1` is then its
+/// first child, so callers must reject the root id when the root is multipart
+/// (see [`is_root_part_id`]). Parts inside decrypted, opaque-signed, or
+/// TNEF content have no section and map to `None` (see [`is_unwrapped_part_id`]).
+pub fn imap_section_for_part_id(part_id: &str) -> Option<String> {
+    let valid = part_id.split('.').all(|component| {
+        !component.is_empty()
+            && component.bytes().all(|byte| byte.is_ascii_digit())
+            && !component.starts_with('0')
+    });
+    if !valid {
+        return None;
     }
+    match part_id.strip_prefix("1.") {
+        Some(section) => Some(section.to_owned()),
+        None if part_id == "1" => Some("1".to_owned()),
+        None => None,
+    }
+}
 
-    /// Tests that `curated_headers` filters headers unless `include_all` is true.
-    #[test]
-    fn curated_headers_filters_unless_include_all() {
-        let headers = vec![
-            (
-                "Date".to_owned(),
-                "Wed, 1 Jan 2025 00:00:00 +0000".to_owned(),
-            ),
-            ("From".to_owned(), "sender@example.com".to_owned()),
-            ("X-Custom".to_owned(), "value".to_owned()),
-        ];
+/// Whether `part_id` names the message root rather than one of its children
+pub fn is_root_part_id(part_id: &str) -> bool {
+    part_id == "1"
+}
 
-        let curated = curated_headers(&headers, false);
-        assert_eq!(curated.len(), 2);
-        assert!(curated.iter().any(|(k, _)| k.eq_ignore_ascii_case("date")));
-        assert!(curated.iter().any(|(k, _)| k.eq_ignore_ascii_case("from")));
+/// Whether `part_id` lies inside decrypted, opaque-signed, or TNEF content
+pub fn is_unwrapped_part_id(part_id: &str) -> bool {
+    part_id
+        .split('.')
+        .any(|component| component == UNWRAPPED_PART_COMPONENT)
+}
 
-        let all = curated_headers(&headers, true);
-        assert_eq!(all.len(), 3);
-    }
+/// Decode a body part's Content-Transfer-Encoding to its raw bytes
+///
+/// Unknown encodings are returned unchanged, matching identity encodings.
+pub fn decode_transfer_encoding(bytes: &[u8], transfer_encoding: &str) -> AppResult<Vec<u8>> {
+    let encoding_header = match transfer_encoding {
+        "base64" | "quoted-printable" => transfer_encoding,
+        _ => return Ok(bytes.to_vec()),
+    };
+    let mut synthetic = format!(
+        "Content-Type: application/octet-stream\r\nContent-Transfer-Encoding: {encoding_header}\r\n\r\n"
+    )
+    .into_bytes();
+    synthetic.extend_from_slice(bytes);
+    mailparse::parse_mail(&synthetic)
+        .and_then(|parsed| parsed.get_body_raw())
+        .map_err(|e| AppError::Internal(format!("failed to decode {transfer_encoding} body: {e}")))
+}
 
-    /// Tests parsing of a simple plain text message and verifies header and body extraction.
-    #[test]
-    fn parses_simple_plain_text_message() {
-        let raw = b"From: sender@example.com\r\nTo: user@example.com\r\nSubject: Hi\r\nDate: Wed, 1 Jan 2025 00:00:00 +0000\r\n\r\nHello there";
-        let parsed = parse_message(raw, 2000, BodyMode::Text, AttachmentMode::Metadata, 10000)
-            .expect("parse should succeed");
+/// Build a preview snippet from a partially fetched text part
+///
+/// `bytes` is a prefix of the part body as sent on the wire. Incomplete
+/// trailing encoding units are dropped before decoding the transfer encoding
+/// and charset; HTML is converted to text. Quoted replies and signatures are
+/// stripped and whitespace is collapsed. Returns `None` when nothing readable
+/// remains.
+pub fn snippet_from_partial_part(
+    bytes: &[u8],
+    mime_type: &str,
+    charset: Option<&str>,
+    transfer_encoding: &str,
+    max_chars: usize,
+) -> Option<String> {
+    let (encoding_header, body) = match transfer_encoding {
+        "base64" => ("base64", trim_partial_base64(bytes)),
+        "quoted-printable" => ("quoted-printable", trim_partial_quoted_printable(bytes)),
+        _ => ("7bit", bytes.to_vec()),
+    };
+    let mut synthetic = format!(
+        "Content-Type: application/octet-stream\r\nContent-Transfer-Encoding: {encoding_header}\r\n\r\n"
+    )
+    .into_bytes();
+    synthetic.extend_from_slice(&body);
+    let parsed = mailparse::parse_mail(&synthetic).ok()?;
+    let raw = parsed.get_body_raw().ok()?;
+    let (decoded, _) = charset::decode(trim_partial_utf8(&raw), charset);
+    let decoded = decoded.trim_end_matches('\u{FFFD}');
+    let text = if mime_type.eq_ignore_ascii_case("text/html") {
+        html_to_text(decoded)?
+    } else {
+        decoded.to_owned()
+    };
+    let collapsed = strip_quotes_and_signature(&text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!collapsed.is_empty()).then(|| truncate_chars(collapsed, max_chars))
+}
 
-        assert_eq!(parsed.subject.as_deref(), Some("Hi"));
-        assert_eq!(parsed.from.as_deref(), Some("sender@example.com"));
-        assert_eq!(parsed.to.as_deref(), Some("user@example.com"));
-        assert_eq!(parsed.body_text.as_deref(), Some("Hello there"));
-        assert!(parsed.attachments.is_empty());
-        assert!(!parsed.attachments_truncated);
+/// Remove quoted replies and trailing signatures from plain text
+///
+/// Drops `>`-quoted lines and cuts the text at the first signature delimiter
+/// (`-- `, mobile "Sent from my ..." footers), reply attribution
+/// (`On ... wrote:`, possibly wrapped), Outlook-style `From:`/`Sent:` header
+/// block, or forwarded/original message separator.
+pub fn strip_quotes_and_signature(text: &str) -> String {
+    let lines = text.lines().collect::<Vec<_>>();
+    let mut kept = Vec::new();
+    for (idx, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if is_quote_cutoff_line(line, trimmed) || starts_reply_header(&lines[idx..]) {
+            break;
+        }
+        if trimmed.starts_with('>') {
+            continue;
+        }
+        kept.push(*line);
     }
+    kept.join("\n")
+}
 
-    #[test]
+fn is_quote_cutoff_line(line: &str, trimmed: &str) -> bool {
+    line.trim_end_matches('\r') == "-- "
+        || trimmed == "--"
+        || (trimmed.starts_with("On ") && trimmed.ends_with("wrote:"))
+        || (trimmed.starts_with("-----")
+            && trimmed.to_ascii_lowercase().contains("original message"))
+        || (trimmed.starts_with("-----")
+            && trimmed.to_ascii_lowercase().contains("forwarded message"))
+        || (trimmed.len() >= 10 && trimmed.chars().all(|ch| ch == '_'))
+        || trimmed.starts_with("Sent from my ")
+        || trimmed.starts_with("Get Outlook for ")
+}
+
+/// Detect reply headers spanning several lines: an `On ... wrote:`
+/// attribution wrapped by the client, or an Outlook-style `From:` line
+/// followed by `Sent:` or `Date:` within the header block.
+fn starts_reply_header(lines: &[&str]) -> bool {
+    let unbold = |line: &str| line.trim().trim_start_matches('*').to_owned();
+    let first = unbold(lines[0]);
+    let following = lines.iter().skip(1).take(4).map(|line| unbold(line));
+    if first.starts_with("On ") {
+        return following.take(2).any(|line| line.ends_with("wrote:"));
+    }
+    if first.starts_with("From:") {
+        return following
+            .take_while(|line| !line.is_empty())
+            .any(|line| line.starts_with("Sent:") || line.starts_with("Date:"));
+    }
+    false
+}
+
+/// Remove quoted history and signatures from an HTML body and sanitize it
+///
+/// Drops `blockquote` elements and Gmail/Thunderbird quote, attribution, and
+/// signature containers, and cuts everything after an Outlook reply header
+/// (`divRplyFwdMsg`, `appendonsend`). Class and id attributes are kept through
+/// a first sanitizing pass so markers survive, then removed by the final pass.
+fn strip_quoted_html(html: &str) -> String {
+    static TAG: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"<(/?)(div|blockquote|hr)\b([^>]*)>").expect("static regex is valid")
+    });
+
+    let prepared = ammonia::Builder::default()
+        .add_generic_attributes(["class", "id"])
+        .clean(html)
+        .to_string();
+    let mut kept = String::with_capacity(prepared.len());
+    let mut position = 0;
+    let mut skipping: Option<(&str, usize)> = None;
+    for captures in TAG.captures_iter(&prepared) {
+        let (Some(tag), Some(name)) = (captures.get(0), captures.get(2)) else {
+            continue;
+        };
+        let closing = captures.get(1).is_some_and(|m| !m.is_empty());
+        let name = name.as_str();
+        if let Some((skip_name, depth)) = skipping.as_mut() {
+            if name == *skip_name && name != "hr" {
+                if closing {
+                    *depth -= 1;
+                } else {
+                    *depth += 1;
+                }
+                if *depth == 0 {
+                    skipping = None;
+                    position = tag.end();
+                }
+            }
+            continue;
+        }
+        if closing {
+            continue;
+        }
+        let attrs = captures.get(3).map_or("", |m| m.as_str());
+        let has_marker = |marker: &str| attrs.split(['"', ' ']).any(|token| token == marker);
+        if has_marker("divRplyFwdMsg") || has_marker("appendonsend") || has_marker("stopSpelling") {
+            kept.push_str(&prepared[position..tag.start()]);
+            return ammonia::clean(&kept);
+        }
+        if name == "blockquote"
+            || has_marker("gmail_quote")
+            || has_marker("gmail_signature")
+            || has_marker("moz-cite-prefix")
+            || has_marker("moz-signature")
+        {
+            kept.push_str(&prepared[position..tag.start()]);
+            skipping = Some((name, 1));
+        }
+    }
+    if skipping.is_none() {
+        kept.push_str(&prepared[position..]);
+    }
+    ammonia::clean(&kept)
+}
+
+/// Drop an incomplete UTF-8 sequence cut off at the end of a fetched prefix.
+fn trim_partial_utf8(bytes: &[u8]) -> &[u8] {
+    match std::str::from_utf8(bytes) {
+        Err(error) if error.error_len().is_none() => &bytes[..error.valid_up_to()],
+        _ => bytes,
+    }
+}
+
+/// Drop whitespace and any incomplete trailing quantum from base64 data.
+fn trim_partial_base64(bytes: &[u8]) -> Vec<u8> {
+    let mut cleaned = bytes
+        .iter()
+        .copied()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect::<Vec<_>>();
+    cleaned.truncate(cleaned.len() - cleaned.len() % 4);
+    cleaned
+}
+
+/// Drop a trailing soft break or escape sequence cut off by a partial fetch.
+fn trim_partial_quoted_printable(bytes: &[u8]) -> Vec<u8> {
+    let tail_start = bytes.len().saturating_sub(2);
+    match bytes[tail_start..].iter().position(|byte| *byte == b'=') {
+        Some(position) => bytes[..tail_start + position].to_vec(),
+        None => bytes.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{
+        MAX_ATTACHMENTS, MAX_EMBEDDED_DEPTH, MAX_EXTRACT_ATTACHMENT_BYTES, attachment_size_bytes,
+        curated_headers, decode_transfer_encoding, imap_section_for_part_id, is_unwrapped_part_id,
+        needs_part_body, parse_address_headers, parse_message, snippet_from_partial_part,
+        strip_quoted_html, strip_quotes_and_signature, truncate_chars,
+    };
+    use crate::config::MessageKeys;
+    use crate::imap::{BodyPartRef, FetchedMessage};
+    use crate::models::{AttachmentMode, BodyMode, EmailAddress};
+
+    fn fetched(raw: &[u8]) -> FetchedMessage {
+        FetchedMessage {
+            raw: raw.to_vec(),
+            ..FetchedMessage::default()
+        }
+    }
+
+    /// Tests that Unicode strings are truncated by character, not byte.
+    #[test]
+    fn truncates_unicode_by_character() {
+        let input = "a😀b😀c".to_owned();
+        let out = truncate_chars(input, 4);
+        assert_eq!(out, "a😀b😀");
+    }
+
+    /// Tests that part ids map to IMAP sections and unwrapped ids map to none.
+    #[test]
+    fn maps_part_ids_to_imap_sections() {
+        assert_eq!(imap_section_for_part_id("1").as_deref(), Some("1"));
+        assert_eq!(imap_section_for_part_id("1.2").as_deref(), Some("2"));
+        assert_eq!(imap_section_for_part_id("1.2.1").as_deref(), Some("2.1"));
+        assert_eq!(imap_section_for_part_id("2"), None);
+        assert_eq!(imap_section_for_part_id("1..2"), None);
+        assert_eq!(imap_section_for_part_id("1.0"), None);
+        assert_eq!(imap_section_for_part_id("../1"), None);
+        assert_eq!(imap_section_for_part_id("1.u.2"), None);
+        assert!(is_unwrapped_part_id("1.2.u.1"));
+        assert!(!is_unwrapped_part_id("1.2.1"));
+    }
+
+    /// Tests that base64, quoted-printable, and identity encodings decode to raw bytes.
+    #[test]
+    fn decodes_transfer_encodings() {
+        assert_eq!(
+            decode_transfer_encoding(b"aGVs\r\nbG8=\r\n", "base64").expect("base64 decodes"),
+            b"hello"
+        );
+        assert_eq!(
+            decode_transfer_encoding(b"caf=C3=A9=\r\n!", "quoted-printable")
+                .expect("quoted-printable decodes"),
+            "café!".as_bytes()
+        );
+        assert_eq!(
+            decode_transfer_encoding(b"raw\r\n", "7bit").expect("identity"),
+            b"raw\r\n"
+        );
+    }
+
+    /// Tests that a base64 prefix cut mid-quantum decodes with its declared charset.
+    #[test]
+    fn snippet_decodes_truncated_base64_with_charset() {
+        // "Caf\xe9 menu" in ISO-8859-1, base64 encoded and cut mid-quantum.
+        let snippet = snippet_from_partial_part(
+            b"Q2Fm6SBtZW51\r\nIG",
+            "text/plain",
+            Some("iso-8859-1"),
+            "base64",
+            200,
+        );
+        assert_eq!(snippet.as_deref(), Some("Café menu"));
+    }
+
+    /// Tests that a cut quoted-printable HTML prefix yields plain snippet text.
+    #[test]
+    fn snippet_handles_cut_quoted_printable_and_html() {
+        let snippet = snippet_from_partial_part(
+            b"<p>Hello <b>there</b></p><p>caf=C3=A9 =C3",
+            "text/html",
+            Some("utf-8"),
+            "quoted-printable",
+            200,
+        );
+        assert_eq!(snippet.as_deref(), Some("Hello there café"));
+    }
+
+    /// Tests that quoted lines, signatures, and reply attributions are stripped from snippets.
+    #[test]
+    fn strips_quoted_replies_and_signatures() {
+        let text = "Sounds good.\n> earlier text\nSee you then.\n-- \nAlice\nCEO";
+        assert_eq!(
+            strip_quotes_and_signature(text),
+            "Sounds good.\nSee you then."
+        );
+        let reply = "Yes.\n\nOn Mon, 2 Mar 2026, Bob <bob@example.com> wrote:\nOriginal";
+        assert_eq!(strip_quotes_and_signature(reply), "Yes.\n");
+    }
+
+    /// Tests that wrapped attributions, Outlook headers, and mobile signatures are stripped.
+    #[test]
+    fn strips_wrapped_attributions_and_outlook_headers() {
+        let wrapped = "Agreed.\nOn Mon, 2 Mar 2026 at 10:00, Bob Example\n<bob@example.com> wrote:\n> Original";
+        assert_eq!(strip_quotes_and_signature(wrapped), "Agreed.");
+        let outlook = "Approved.\n\nFrom: Bob Example <bob@example.com>\nSent: Monday, March 2, 2026 10:00 AM\nTo: Alice\nSubject: Budget\n\nPlease approve.";
+        assert_eq!(strip_quotes_and_signature(outlook), "Approved.\n");
+        let mobile = "On my way.\n\nSent from my iPhone";
+        assert_eq!(strip_quotes_and_signature(mobile), "On my way.\n");
+        let body = "From: the team, thanks for the update.\nNext steps below.";
+        assert_eq!(strip_quotes_and_signature(body), body);
+    }
+
+    /// Tests that Gmail and Outlook quote containers are removed from HTML.
+    #[test]
+    fn strips_quoted_html_containers() {
+        let gmail = concat!(
+            "<div dir=\"ltr\">Thanks!<div class=\"gmail_signature\">Alice</div></div>",
+            "<div class=\"gmail_quote\"><div class=\"gmail_attr\">On Mon, Bob wrote:</div>",
+            "<blockquote class=\"gmail_quote\"><div>Old text</div></blockquote></div>"
+        );
+        assert_eq!(strip_quoted_html(gmail), "<div>Thanks!</div>");
+
+        let outlook = concat!(
+            "<p>Approved.</p><hr style=\"display:inline-block\">",
+            "<div id=\"divRplyFwdMsg\"><b>From:</b> Bob</div><div>Please approve.</div>"
+        );
+        assert_eq!(strip_quoted_html(outlook), "<p>Approved.</p><hr>");
+    }
+
+    /// Tests that new_content_only reports how many characters were stripped.
+    #[test]
+    fn new_content_only_reports_stripped_characters() {
+        let raw = concat!(
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "Sounds good.\r\n",
+            "\r\n",
+            "On Mon, 2 Mar 2026, Bob <bob@example.com> wrote:\r\n",
+            "> Can we meet?\r\n",
+        );
+
+        let parsed = parse_message(
+            &fetched(raw.as_bytes()),
+            2000,
+            BodyMode::Text,
+            AttachmentMode::Metadata,
+            10000,
+            true,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
+        assert_eq!(parsed.body_text.as_deref(), Some("Sounds good."));
+        let stripped = parsed.stripped_content.expect("stripping is reported");
+        assert_eq!(stripped.body_text_chars, Some(66));
+        assert_eq!(stripped.body_html_chars, None);
+
+        let full = parse_message(
+            &fetched(raw.as_bytes()),
+            2000,
+            BodyMode::Text,
+            AttachmentMode::Metadata,
+            10000,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
+        assert!(full.stripped_content.is_none());
+        assert!(
+            full.body_text
+                .is_some_and(|text| text.contains("Can we meet?"))
+        );
+    }
+
+    /// Tests that `curated_headers` filters headers unless `include_all` is true.
+    #[test]
+    fn curated_headers_filters_unless_include_all() {
+        let headers = vec![
+            (
+                "Date".to_owned(),
+                "Wed, 1 Jan 2025 00:00:00 +0000".to_owned(),
+            ),
+            ("From".to_owned(), "sender@example.com".to_owned()),
+            ("X-Custom".to_owned(), "value".to_owned()),
+        ];
+
+        let curated = curated_headers(&headers, false);
+        assert_eq!(curated.len(), 1);
+        assert!(curated.iter().any(|(k, _)| k.eq_ignore_ascii_case("date")));
+
+        let all = curated_headers(&headers, true);
+        assert_eq!(all.len(), 3);
+    }
+
+    /// Tests that encoded names, quoted commas, and groups parse into address lists.
+    #[test]
+    fn parses_encoded_and_grouped_address_headers() {
+        let raw = concat!(
+            "From: =?UTF-8?Q?Doe=2C_J=C3=BCrgen?= <jurgen@example.com>\r\n",
+            "To: \"Smith, Ann\" <ann@example.com>, bob@example.com\r\n",
+            "Cc: Team: carol@example.com, Dan <dan@example.com>;\r\n",
+            "Bcc: undisclosed-recipients:;\r\n",
+            "Reply-To: <replies@example.com>\r\n",
+            "\r\n",
+            "Body\r\n",
+        );
+
+        let addresses = parse_address_headers(raw.as_bytes());
+        let address = |name: Option<&str>, email: &str| EmailAddress {
+            name: name.map(str::to_owned),
+            email: email.to_owned(),
+        };
+        assert_eq!(
+            addresses.from,
+            Some(vec![address(Some("Doe, Jürgen"), "jurgen@example.com")])
+        );
+        assert_eq!(
+            addresses.to,
+            Some(vec![
+                address(Some("Smith, Ann"), "ann@example.com"),
+                address(None, "bob@example.com"),
+            ])
+        );
+        assert_eq!(
+            addresses.cc,
+            Some(vec![
+                address(None, "carol@example.com"),
+                address(Some("Dan"), "dan@example.com"),
+            ])
+        );
+        assert_eq!(addresses.bcc, Some(Vec::new()));
+        assert_eq!(
+            addresses.reply_to,
+            Some(vec![address(None, "replies@example.com")])
+        );
+        assert_eq!(addresses.sender, None);
+    }
+
+    /// Tests parsing of a simple plain text message and verifies header and body extraction.
+    #[test]
+    fn parses_simple_plain_text_message() {
+        let raw = b"From: sender@example.com\r\nTo: user@example.com\r\nSubject: Hi\r\nDate: Wed, 1 Jan 2025 00:00:00 +0000\r\n\r\nHello there";
+        let parsed = parse_message(
+            &fetched(raw),
+            2000,
+            BodyMode::Text,
+            AttachmentMode::Metadata,
+            10000,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
+
+        assert_eq!(parsed.subject.as_deref(), Some("Hi"));
+        assert_eq!(
+            parsed.addresses.from,
+            Some(vec![EmailAddress {
+                name: None,
+                email: "sender@example.com".to_owned(),
+            }])
+        );
+        assert_eq!(
+            parsed.addresses.to.as_ref().map(|to| to[0].email.as_str()),
+            Some("user@example.com")
+        );
+        assert_eq!(parsed.body_text.as_deref(), Some("Hello there"));
+        assert!(parsed.attachments.is_empty());
+        assert!(!parsed.attachments_truncated);
+    }
+
+    #[test]
     fn derives_body_text_from_html_only_message() {
         let raw = concat!(
             "From: sender@example.com\r\n",
@@ -376,8 +1812,17 @@ mod tests {
         )
         .as_bytes();
 
-        let parsed = parse_message(raw, 2000, BodyMode::Text, AttachmentMode::Metadata, 10000)
-            .expect("parse should succeed");
+        let parsed = parse_message(
+            &fetched(raw),
+            2000,
+            BodyMode::Text,
+            AttachmentMode::Metadata,
+            10000,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
 
         assert_eq!(parsed.body_text.as_deref(), Some("Hello there"));
         assert_eq!(parsed.body_html_sanitized, None);
@@ -396,8 +1841,17 @@ mod tests {
         )
         .as_bytes();
 
-        let parsed = parse_message(raw, 2000, BodyMode::Both, AttachmentMode::Metadata, 10000)
-            .expect("parse should succeed");
+        let parsed = parse_message(
+            &fetched(raw),
+            2000,
+            BodyMode::Both,
+            AttachmentMode::Metadata,
+            10000,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
 
         assert_eq!(parsed.body_text.as_deref(), Some("Hello there"));
         assert_eq!(
@@ -427,8 +1881,17 @@ mod tests {
         )
         .as_bytes();
 
-        let parsed = parse_message(raw, 2000, BodyMode::Both, AttachmentMode::Metadata, 10000)
-            .expect("parse should succeed");
+        let parsed = parse_message(
+            &fetched(raw),
+            2000,
+            BodyMode::Both,
+            AttachmentMode::Metadata,
+            10000,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
 
         assert_eq!(parsed.body_text.as_deref(), Some("Hello from plain text"));
         assert_eq!(
@@ -458,8 +1921,17 @@ mod tests {
         )
         .as_bytes();
 
-        let parsed = parse_message(raw, 2000, BodyMode::Text, AttachmentMode::Metadata, 10000)
-            .expect("parse should succeed");
+        let parsed = parse_message(
+            &fetched(raw),
+            2000,
+            BodyMode::Text,
+            AttachmentMode::Metadata,
+            10000,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
 
         assert_eq!(parsed.body_text.as_deref(), Some("Hello from HTML"));
         assert_eq!(parsed.body_html_sanitized, None);
@@ -489,11 +1961,14 @@ mod tests {
         raw.push_str("--mix--\r\n");
 
         let parsed = parse_message(
-            raw.as_bytes(),
+            &fetched(raw.as_bytes()),
             2000,
             BodyMode::Text,
             AttachmentMode::Metadata,
             10000,
+            false,
+            false,
+            &MessageKeys::default(),
         )
         .expect("parse should succeed");
         assert_eq!(parsed.body_text.as_deref(), Some("hello"));
@@ -501,6 +1976,285 @@ mod tests {
         assert!(parsed.attachments_truncated);
     }
 
+    /// Tests that supported attachments get extracted text and failures become issues.
+    #[test]
+    fn extracts_text_attachments_and_reports_failures() {
+        let raw = concat!(
+            "Content-Type: multipart/mixed; boundary=\"mix\"\r\n",
+            "MIME-Version: 1.0\r\n",
+            "\r\n",
+            "--mix\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "See attached.\r\n",
+            "--mix\r\n",
+            "Content-Type: text/csv; charset=utf-8\r\n",
+            "Content-Disposition: attachment; filename=\"totals.csv\"\r\n",
+            "\r\n",
+            "item,cost\r\nwidget,12\r\n",
+            "--mix\r\n",
+            "Content-Type: application/octet-stream\r\n",
+            "Content-Disposition: attachment; filename=\"broken.docx\"\r\n",
+            "\r\n",
+            "not a zip\r\n",
+            "--mix--\r\n",
+        );
+
+        let parsed = parse_message(
+            &fetched(raw.as_bytes()),
+            2000,
+            BodyMode::Text,
+            AttachmentMode::ExtractText,
+            10,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
+        assert_eq!(parsed.attachments.len(), 2);
+        assert_eq!(
+            parsed.attachments[0].extracted_text.as_deref(),
+            Some("item,cost\r")
+        );
+        assert!(parsed.attachments[1].extracted_text.is_none());
+        assert_eq!(parsed.attachment_issues.len(), 1);
+        assert_eq!(parsed.attachment_issues[0].part_id, "1.3");
+        assert_eq!(parsed.attachment_issues[0].code, "extraction_failed");
+    }
+
+    /// Tests that attachments omitted from a partial fetch keep their structure sizes.
+    #[test]
+    fn reports_omitted_attachment_sizes_from_partial_fetch() {
+        let raw = concat!(
+            "Content-Type: multipart/mixed; boundary=\"mix\"\r\n",
+            "MIME-Version: 1.0\r\n",
+            "\r\n",
+            "--mix\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "Slides and recording attached.\r\n",
+            "--mix\r\n",
+            "Content-Type: application/pdf; name=\"slides.pdf\"\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "\r\n",
+            "--mix\r\n",
+            "Content-Type: video/mp4; name=\"recording.mp4\"\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "\r\n",
+            "--mix--\r\n",
+        );
+        let message = FetchedMessage {
+            raw: raw.as_bytes().to_vec(),
+            omitted_sizes: HashMap::from([
+                ("2".to_owned(), 7_000_000),
+                ("3".to_owned(), 25_000_000),
+            ]),
+        };
+
+        let parsed = parse_message(
+            &message,
+            2000,
+            BodyMode::Text,
+            AttachmentMode::ExtractText,
+            100,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
+        assert_eq!(
+            parsed.body_text.as_deref(),
+            Some("Slides and recording attached.")
+        );
+        let sizes = parsed
+            .attachments
+            .iter()
+            .map(|attachment| (attachment.part_id.as_str(), attachment.size_bytes))
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, [("1.2", 7_000_000), ("1.3", 25_000_000)]);
+        assert_eq!(parsed.attachments[0].extracted_text, None);
+        assert_eq!(parsed.attachment_issues.len(), 1);
+        assert_eq!(parsed.attachment_issues[0].part_id, "1.2");
+        assert_eq!(parsed.attachment_issues[0].code, "limit_exceeded");
+    }
+
+    /// Tests that an invite sent both inline and as an attachment is reported once.
+    #[test]
+    fn parses_calendar_invites_once_across_inline_and_attached_copies() {
+        let ics = "BEGIN:VCALENDAR\r\nMETHOD:REQUEST\r\nBEGIN:VEVENT\r\nUID:m-1\r\nSUMMARY:Sync\r\nDTSTART:20261020T150000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let raw = format!(
+            concat!(
+                "Content-Type: multipart/mixed; boundary=\"mix\"\r\n",
+                "MIME-Version: 1.0\r\n",
+                "\r\n",
+                "--mix\r\n",
+                "Content-Type: multipart/alternative; boundary=\"alt\"\r\n",
+                "\r\n",
+                "--alt\r\n",
+                "Content-Type: text/plain\r\n",
+                "\r\n",
+                "You are invited.\r\n",
+                "--alt\r\n",
+                "Content-Type: text/calendar; method=REQUEST; charset=utf-8\r\n",
+                "\r\n",
+                "{ics}",
+                "--alt--\r\n",
+                "--mix\r\n",
+                "Content-Type: application/ics; name=\"invite.ics\"\r\n",
+                "Content-Disposition: attachment; filename=\"invite.ics\"\r\n",
+                "\r\n",
+                "{ics}",
+                "--mix--\r\n",
+            ),
+            ics = ics
+        );
+
+        let parsed = parse_message(
+            &fetched(raw.as_bytes()),
+            2000,
+            BodyMode::Text,
+            AttachmentMode::Metadata,
+            10000,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
+        assert_eq!(parsed.body_text.as_deref(), Some("You are invited."));
+        assert_eq!(parsed.calendar_events.len(), 1);
+        assert_eq!(parsed.calendar_events[0].summary.as_deref(), Some("Sync"));
+        assert_eq!(
+            parsed.calendar_events[0]
+                .start
+                .as_ref()
+                .and_then(|start| start.utc.as_deref()),
+            Some("2026-10-20T15:00:00Z")
+        );
+        assert_eq!(parsed.attachments.len(), 1);
+    }
+
+    /// Tests that forwarded messages are parsed with part ids numbered under the embedding part.
+    #[test]
+    fn parses_forwarded_messages_with_addressable_part_ids() {
+        let raw = concat!(
+            "Subject: Fwd: Report\r\n",
+            "Content-Type: multipart/mixed; boundary=\"outer\"\r\n",
+            "MIME-Version: 1.0\r\n",
+            "\r\n",
+            "--outer\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "See below.\r\n",
+            "--outer\r\n",
+            "Content-Type: message/rfc822\r\n",
+            "Content-Disposition: attachment; filename=\"report.eml\"\r\n",
+            "\r\n",
+            "From: Alice <alice@example.com>\r\n",
+            "Subject: Report\r\n",
+            "Message-ID: <report@example.com>\r\n",
+            "Content-Type: multipart/mixed; boundary=\"inner\"\r\n",
+            "\r\n",
+            "--inner\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "Quarterly numbers attached.\r\n",
+            "--inner\r\n",
+            "Content-Type: text/csv; name=\"q3.csv\"\r\n",
+            "Content-Disposition: attachment; filename=\"q3.csv\"\r\n",
+            "\r\n",
+            "a,b\r\n",
+            "--inner\r\n",
+            "Content-Type: message/rfc822\r\n",
+            "\r\n",
+            "From: Bob <bob@example.com>\r\n",
+            "Subject: Original\r\n",
+            "\r\n",
+            "First draft.\r\n",
+            "--inner--\r\n",
+            "--outer--\r\n",
+        );
+
+        let parsed = parse_message(
+            &fetched(raw.as_bytes()),
+            2000,
+            BodyMode::Text,
+            AttachmentMode::Metadata,
+            10000,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
+        assert_eq!(parsed.body_text.as_deref(), Some("See below."));
+        assert_eq!(parsed.attachments.len(), 1);
+        assert_eq!(parsed.attachments[0].part_id, "1.2");
+        assert!(!parsed.embedded_messages_truncated);
+
+        let embedded = &parsed.embedded_messages;
+        assert_eq!(embedded.len(), 2);
+        assert_eq!(embedded[0].part_id, "1.2");
+        assert_eq!(embedded[0].parent_part_id, None);
+        assert_eq!(embedded[0].depth, 1);
+        assert_eq!(embedded[0].subject.as_deref(), Some("Report"));
+        assert_eq!(
+            embedded[0].internet_message_id.as_deref(),
+            Some("<report@example.com>")
+        );
+        assert_eq!(
+            embedded[0].body_text.as_deref(),
+            Some("Quarterly numbers attached.")
+        );
+        assert_eq!(embedded[0].attachments.len(), 1);
+        assert_eq!(embedded[0].attachments[0].part_id, "1.2.2");
+        assert_eq!(
+            imap_section_for_part_id(&embedded[0].attachments[0].part_id).as_deref(),
+            Some("2.2")
+        );
+
+        assert_eq!(embedded[1].part_id, "1.2.3");
+        assert_eq!(embedded[1].parent_part_id.as_deref(), Some("1.2"));
+        assert_eq!(embedded[1].depth, 2);
+        assert_eq!(
+            embedded[1].from,
+            Some(vec![EmailAddress {
+                name: Some("Bob".to_owned()),
+                email: "bob@example.com".to_owned(),
+            }])
+        );
+        assert_eq!(embedded[1].body_text.as_deref(), Some("First draft."));
+    }
+
+    /// Tests that embedded messages nested past the depth limit are not walked.
+    #[test]
+    fn stops_embedded_message_recursion_at_depth_limit() {
+        let mut raw = "Subject: innermost\r\n\r\nbottom\r\n".to_owned();
+        for level in 0..MAX_EMBEDDED_DEPTH + 2 {
+            raw = format!(
+                "Subject: level {level}\r\nContent-Type: multipart/mixed; boundary=\"b{level}\"\r\n\r\n--b{level}\r\nContent-Type: message/rfc822\r\n\r\n{raw}--b{level}--\r\n"
+            );
+        }
+
+        let parsed = parse_message(
+            &fetched(raw.as_bytes()),
+            2000,
+            BodyMode::Text,
+            AttachmentMode::Metadata,
+            10000,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
+        assert_eq!(parsed.embedded_messages.len(), MAX_EMBEDDED_DEPTH);
+        assert_eq!(
+            parsed.embedded_messages.last().map(|message| message.depth),
+            Some(MAX_EMBEDDED_DEPTH)
+        );
+        assert!(parsed.embedded_messages_truncated);
+    }
+
     #[test]
     fn attachment_size_uses_decoded_bytes_for_base64_parts() {
         let raw = concat!(
@@ -521,13 +2275,527 @@ mod tests {
         assert_eq!(attachment_size_bytes(attachment), 5);
 
         let parsed = parse_message(
-            raw.as_bytes(),
+            &fetched(raw.as_bytes()),
             2000,
             BodyMode::Text,
             AttachmentMode::Metadata,
             10000,
+            false,
+            false,
+            &MessageKeys::default(),
         )
         .expect("parse should succeed");
         assert_eq!(parsed.attachments[0].size_bytes, 5);
     }
+
+    fn secure_fixture(name: &str) -> Vec<u8> {
+        let path = format!(
+            "{}/tests/fixtures/secure/{name}",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        std::fs::read(path).expect("fixture should exist")
+    }
+
+    fn fixture_keys() -> MessageKeys {
+        use pgp::composed::{Deserializable, SignedPublicKey, SignedSecretKey};
+        use rsa::pkcs8::DecodePrivateKey;
+
+        let certificate = x509_cert::Certificate::load_pem_chain(&secure_fixture("alice-cert.pem"))
+            .expect("certificate should parse")
+            .remove(0);
+        let private_key = rsa::RsaPrivateKey::from_pkcs8_pem(
+            &String::from_utf8(secure_fixture("alice-key.pem")).expect("utf-8 key"),
+        )
+        .expect("private key should parse");
+        let (secret_key, _) =
+            SignedSecretKey::from_armor_single(secure_fixture("alice-pgp-secret.asc").as_slice())
+                .expect("secret key should parse");
+        let (public_key, _) =
+            SignedPublicKey::from_armor_single(secure_fixture("alice-pgp-public.asc").as_slice())
+                .expect("public key should parse");
+        MessageKeys {
+            smime_certificate: Some(certificate),
+            smime_private_key: Some(private_key),
+            smime_trusted_certificates: x509_cert::Certificate::load_pem_chain(&secure_fixture(
+                "ca.pem",
+            ))
+            .expect("trust anchor should parse"),
+            openpgp_secret_keys: vec![secret_key],
+            openpgp_passphrase: None,
+            openpgp_public_keys: vec![public_key],
+        }
+    }
+
+    fn parse_secure(raw: &[u8], keys: &MessageKeys) -> super::ParsedMessage {
+        parse_message(
+            &fetched(raw),
+            2000,
+            BodyMode::Text,
+            AttachmentMode::Metadata,
+            10000,
+            false,
+            false,
+            keys,
+        )
+        .expect("parse should succeed")
+    }
+
+    /// Tests that a trusted S/MIME signature verifies and hides the signature part.
+    #[test]
+    fn verifies_smime_signature_against_trust_anchor() {
+        let parsed = parse_secure(&secure_fixture("smime-signed.eml"), &fixture_keys());
+        assert!(parsed.security.signed && parsed.security.verified);
+        assert!(!parsed.security.encrypted);
+        let layer = &parsed.security.layers[0];
+        assert_eq!(
+            (layer.protocol.as_str(), layer.status.as_str()),
+            ("smime", "valid")
+        );
+        let signer = layer.signer.as_ref().expect("signer identity");
+        assert_eq!(signer.email.as_deref(), Some("alice@example.com"));
+        assert!(signer.trusted);
+        assert!(
+            parsed
+                .body_text
+                .as_deref()
+                .is_some_and(|body| body.contains("quarterly numbers"))
+        );
+        assert!(parsed.attachments.is_empty());
+    }
+
+    /// Tests that S/MIME signatures verify without keys but are not trusted.
+    #[test]
+    fn smime_signature_without_trust_anchor_is_untrusted() {
+        let parsed = parse_secure(&secure_fixture("smime-signed.eml"), &MessageKeys::default());
+        let layer = &parsed.security.layers[0];
+        assert_eq!(layer.status, "untrusted");
+        assert!(!layer.signer.as_ref().expect("signer identity").trusted);
+        assert!(parsed.security.signed && !parsed.security.verified);
+    }
+
+    /// Tests that a trusted signer for another address does not verify the From.
+    #[test]
+    fn smime_signer_must_match_from_address() {
+        let raw = String::from_utf8(secure_fixture("smime-signed.eml")).expect("utf-8 fixture");
+        let spoofed = raw.replace("From: alice@example.com", "From: ceo@victim.example");
+        let parsed = parse_secure(spoofed.as_bytes(), &fixture_keys());
+        let layer = &parsed.security.layers[0];
+        assert_eq!(layer.status, "untrusted");
+        assert!(layer.signer.as_ref().expect("signer identity").trusted);
+        assert!(!parsed.security.verified);
+    }
+
+    /// Tests that altering signed content fails verification.
+    #[test]
+    fn rejects_tampered_smime_content() {
+        let raw = String::from_utf8(secure_fixture("smime-signed.eml")).expect("utf-8 fixture");
+        let tampered = raw.replace("quarterly numbers", "quarterly figures");
+        let parsed = parse_secure(tampered.as_bytes(), &fixture_keys());
+        assert!(parsed.security.signed);
+        assert!(!parsed.security.verified);
+        assert_eq!(parsed.security.layers[0].status, "invalid");
+    }
+
+    /// Tests that signed-then-encrypted S/MIME is decrypted and verified.
+    #[test]
+    fn decrypts_and_verifies_smime_envelope() {
+        let parsed = parse_secure(
+            &secure_fixture("smime-signed-encrypted.eml"),
+            &fixture_keys(),
+        );
+        let statuses: Vec<(&str, &str)> = parsed
+            .security
+            .layers
+            .iter()
+            .map(|layer| (layer.kind.as_str(), layer.status.as_str()))
+            .collect();
+        assert_eq!(statuses, [("encrypted", "decrypted"), ("signed", "valid")]);
+        assert_eq!(parsed.security.layers[1].part_id, "1.u");
+        assert!(parsed.security.decrypted && parsed.security.verified);
+        assert!(
+            parsed
+                .body_text
+                .as_deref()
+                .is_some_and(|body| body.contains("launch code"))
+        );
+    }
+
+    /// Tests that encrypted content without a key is reported and listed as an attachment.
+    #[test]
+    fn reports_smime_envelope_without_key() {
+        let parsed = parse_secure(
+            &secure_fixture("smime-encrypted.eml"),
+            &MessageKeys::default(),
+        );
+        assert!(parsed.security.encrypted && !parsed.security.decrypted);
+        assert_eq!(parsed.security.layers[0].status, "no_key");
+        assert!(parsed.body_text.is_none());
+        assert_eq!(parsed.attachments.len(), 1);
+    }
+
+    /// Tests OpenPGP decryption with an embedded signature.
+    #[test]
+    fn decrypts_and_verifies_openpgp_message() {
+        let parsed = parse_secure(&secure_fixture("pgp-encrypted.eml"), &fixture_keys());
+        let statuses: Vec<(&str, &str)> = parsed
+            .security
+            .layers
+            .iter()
+            .map(|layer| (layer.kind.as_str(), layer.status.as_str()))
+            .collect();
+        assert_eq!(statuses, [("encrypted", "decrypted"), ("signed", "valid")]);
+        assert!(
+            parsed
+                .body_text
+                .as_deref()
+                .is_some_and(|body| body.contains("vault combination"))
+        );
+    }
+
+    /// Tests that decrypted OpenPGP data above the extraction limit is not read in full.
+    #[test]
+    fn limits_decrypted_openpgp_data() {
+        use pgp::composed::{ArmorOptions, MessageBuilder};
+        use pgp::crypto::sym::SymmetricKeyAlgorithm;
+        use pgp::types::CompressionAlgorithm;
+        use rsa::rand_core::OsRng;
+
+        let keys = fixture_keys();
+        let plaintext = vec![b'a'; MAX_EXTRACT_ATTACHMENT_BYTES + 1];
+        let mut builder = MessageBuilder::from_bytes("", plaintext)
+            .seipd_v1(OsRng, SymmetricKeyAlgorithm::AES256);
+        builder.compression(CompressionAlgorithm::ZLIB);
+        builder
+            .encrypt_to_key(OsRng, &keys.openpgp_public_keys[0].public_subkeys[0])
+            .expect("encrypt to fixture key");
+        let armored = builder
+            .to_armored_string(OsRng, ArmorOptions::default())
+            .expect("armor message");
+        let raw = format!(
+            "From: alice@example.com\r\n\
+             Content-Type: multipart/encrypted; protocol=\"application/pgp-encrypted\"; boundary=\"b\"\r\n\r\n\
+             --b\r\nContent-Type: application/pgp-encrypted\r\n\r\nVersion: 1\r\n\
+             --b\r\nContent-Type: application/octet-stream\r\n\r\n{armored}\r\n--b--\r\n"
+        );
+
+        let parsed = parse_secure(raw.as_bytes(), &keys);
+        assert_eq!(parsed.security.layers[0].status, "limit_exceeded");
+        assert!(!parsed.security.decrypted);
+        assert!(parsed.body_text.is_none());
+    }
+
+    /// Tests OpenPGP detached signatures with and without the signer's key.
+    #[test]
+    fn verifies_openpgp_detached_signature() {
+        let raw = secure_fixture("pgp-signed.eml");
+        let fingerprint = Some("8348B35606C014E9B79403FEAA5DD07D5BB6398D");
+
+        let parsed = parse_secure(&raw, &fixture_keys());
+        let layer = &parsed.security.layers[0];
+        assert_eq!(
+            (layer.protocol.as_str(), layer.status.as_str()),
+            ("openpgp", "valid")
+        );
+        let signer = layer.signer.as_ref().expect("signer identity");
+        assert_eq!(signer.fingerprint.as_deref(), fingerprint);
+        assert!(
+            parsed
+                .body_text
+                .as_deref()
+                .is_some_and(|body| body.contains("deploy window"))
+        );
+
+        let parsed = parse_secure(&raw, &MessageKeys::default());
+        let layer = &parsed.security.layers[0];
+        assert_eq!(layer.status, "unknown_key");
+        assert!(!parsed.security.verified);
+    }
+
+    /// Tests that an OpenPGP signer key without a user ID for the From address is untrusted.
+    #[test]
+    fn openpgp_signer_must_match_from_address() {
+        for fixture in ["pgp-signed.eml", "pgp-encrypted.eml"] {
+            let raw = String::from_utf8(secure_fixture(fixture)).expect("utf-8 fixture");
+            let spoofed = raw.replace(
+                "From: Alice Example <alice@example.com>",
+                "From: ceo@victim.example",
+            );
+            let parsed = parse_secure(spoofed.as_bytes(), &fixture_keys());
+            let layer = parsed
+                .security
+                .layers
+                .iter()
+                .find(|layer| layer.kind == "signed")
+                .expect("signed layer");
+            assert_eq!(layer.status, "untrusted", "{fixture}");
+            assert!(!parsed.security.verified, "{fixture}");
+        }
+    }
+
+    /// Tests that Markdown mode keeps headings, links, and tables from HTML.
+    #[test]
+    fn renders_html_body_as_markdown() {
+        let raw = concat!(
+            "Subject: Receipt\r\n",
+            "Content-Type: text/html; charset=utf-8\r\n",
+            "\r\n",
+            "<h1>Order 1042</h1><p>Thanks! <a href=\"https://shop.example/orders/1042\">View order</a></p>",
+            "<img src=\"https://t.shop.example/o.gif\" width=\"1\" height=\"1\">",
+            "<table><tr><th>Item</th><th>Price</th></tr><tr><td>Mug</td><td>$12</td></tr></table>",
+            "<script>alert(1)</script>"
+        );
+        let parsed = parse_message(
+            &fetched(raw.as_bytes()),
+            2000,
+            BodyMode::Markdown,
+            AttachmentMode::Metadata,
+            10000,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
+        let markdown = parsed.body_markdown.expect("markdown body");
+
+        assert!(markdown.starts_with("# Order 1042"), "{markdown}");
+        assert!(markdown.contains("[View order](https://shop.example/orders/1042)"));
+        assert!(markdown.contains("| Item | Price |"), "{markdown}");
+        assert!(markdown.contains("| Mug  | $12   |"), "{markdown}");
+        assert!(!markdown.contains("o.gif") && !markdown.contains("alert"));
+        assert!(parsed.body_text.is_none() && parsed.body_html_sanitized.is_none());
+    }
+
+    /// Tests that links and remote images are inventoried only when requested.
+    #[test]
+    fn inventories_links_only_when_requested() {
+        let raw = concat!(
+            "Subject: Receipt\r\n",
+            "Content-Type: text/html; charset=utf-8\r\n",
+            "\r\n",
+            "<a href=\"https://shop.example/orders/1042\">View order</a>",
+            "<img src=\"https://t.shop.example/o.gif\">",
+        );
+        let parse = |include_links| {
+            parse_message(
+                &fetched(raw.as_bytes()),
+                2000,
+                BodyMode::Text,
+                AttachmentMode::Metadata,
+                10000,
+                false,
+                include_links,
+                &MessageKeys::default(),
+            )
+            .expect("parse should succeed")
+        };
+
+        let parsed = parse(true);
+        assert_eq!(parsed.links.len(), 1);
+        assert_eq!(parsed.remote_images.len(), 1);
+        let parsed = parse(false);
+        assert!(parsed.links.is_empty() && parsed.remote_images.is_empty());
+    }
+
+    /// Tests that Markdown mode falls back to plain text and honors the character bound.
+    #[test]
+    fn markdown_mode_falls_back_to_plain_text() {
+        let raw = b"Subject: Hi\r\nContent-Type: text/plain\r\n\r\nSee you at 10.\r\n";
+        let parsed = parse_message(
+            &fetched(raw),
+            7,
+            BodyMode::Markdown,
+            AttachmentMode::Metadata,
+            10000,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
+        assert_eq!(parsed.body_markdown.as_deref(), Some("See you"));
+    }
+
+    fn parse_charset_fixture(name: &str) -> super::ParsedMessage {
+        let path = format!(
+            "{}/tests/fixtures/charset/{name}",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let raw = std::fs::read(path).expect("fixture should exist");
+        parse_message(
+            &fetched(&raw),
+            10000,
+            BodyMode::Text,
+            AttachmentMode::Metadata,
+            10000,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed")
+    }
+
+    /// Tests that unlabelled and mislabelled legacy bodies decode with the detected charset.
+    #[test]
+    fn decodes_charset_fixture_corpus() {
+        let cases = [
+            ("windows-1252-as-ascii.eml", "costs 28€", "windows-1252"),
+            (
+                "windows-1250-as-ascii.eml",
+                "Żółć, gęślą jaźń",
+                "windows-1250",
+            ),
+            (
+                "windows-1251-unlabelled.eml",
+                "Добрый день!",
+                "windows-1251",
+            ),
+            ("utf-8-as-iso-8859-1.eml", "Grüße aus München", "UTF-8"),
+            ("shift_jis-unlabelled.eml", "来週の会議の資料", "Shift_JIS"),
+            (
+                "iso-2022-jp-unlabelled.eml",
+                "来週の会議の資料",
+                "ISO-2022-JP",
+            ),
+            ("euc-kr-unlabelled.eml", "다음 주 회의 자료", "EUC-KR"),
+            ("gbk-unlabelled.eml", "下周会议的资料", "GBK"),
+            ("big5-unlabelled.eml", "下週會議的資料", "Big5"),
+        ];
+        for (name, expected, detected) in cases {
+            let parsed = parse_charset_fixture(name);
+            let body = parsed.body_text.as_deref().unwrap_or_default();
+            assert!(body.contains(expected), "{name}: {body}");
+            let issue = parsed
+                .charset_issues
+                .iter()
+                .find(|issue| issue.location == "part 1 body" || issue.location == "part 1.1 body")
+                .unwrap_or_else(|| panic!("{name}: body fallback is reported"));
+            assert_eq!(issue.detected, detected, "{name}");
+            assert_eq!(issue.replaced_chars, 0, "{name}");
+        }
+
+        let labelled = parse_charset_fixture("iso-8859-15-labelled.eml");
+        assert!(labelled.body_text.unwrap_or_default().contains("coûte 12€"));
+        assert!(labelled.charset_issues.is_empty());
+    }
+
+    /// Tests that raw Shift_JIS subjects and filenames are decoded and reported.
+    #[test]
+    fn decodes_raw_legacy_subject_and_filename() {
+        let parsed = parse_charset_fixture("shift_jis-unlabelled.eml");
+        assert_eq!(parsed.subject.as_deref(), Some("会議資料のご送付"));
+        assert_eq!(
+            parsed.attachments[0].filename.as_deref(),
+            Some("会議資料.txt")
+        );
+        let locations = parsed
+            .charset_issues
+            .iter()
+            .map(|issue| (issue.location.as_str(), issue.detected))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            locations,
+            [
+                ("headers", "Shift_JIS"),
+                ("part 1.1 body", "Shift_JIS"),
+                ("part 1.2 filename", "Shift_JIS"),
+            ]
+        );
+    }
+
+    /// Tests that TNEF bodies and attachments are surfaced with nested part ids.
+    #[test]
+    fn decodes_tnef_body_and_attachments() {
+        let path = format!(
+            "{}/tests/fixtures/tnef/outlook-rtf.eml",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let raw = std::fs::read(path).expect("fixture should exist");
+        let parsed = parse_message(
+            &fetched(&raw),
+            2000,
+            BodyMode::Text,
+            AttachmentMode::ExtractText,
+            10000,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
+
+        assert_eq!(parsed.body_text.as_deref(), Some("hello world"));
+        let attachments = parsed
+            .attachments
+            .iter()
+            .map(|attachment| {
+                (
+                    attachment.part_id.as_str(),
+                    attachment.filename.as_deref(),
+                    attachment.content_type.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            attachments,
+            [
+                ("1.1", Some("winmail.dat"), "application/ms-tnef"),
+                ("1.1.u.1", Some("Quarterly report.txt"), "text/plain"),
+                ("1.1.u.2", Some("résumé.csv"), "application/octet-stream"),
+            ]
+        );
+        assert_eq!(
+            parsed.attachments[1].extracted_text.as_deref(),
+            Some("Revenue: 42 million\r\nCafé expenses: 3\r\n")
+        );
+        assert_eq!(
+            parsed.attachments[2].extracted_text.as_deref(),
+            Some("name,total\r\nnorth,12\r\n")
+        );
+        assert!(parsed.attachment_issues.is_empty());
+    }
+
+    /// Tests that TNEF parts above the extraction limit are neither fetched nor decoded.
+    #[test]
+    fn skips_oversized_tnef() {
+        let part = BodyPartRef {
+            section: "2".to_owned(),
+            content_type: "application/ms-tnef".to_owned(),
+            filename: Some("winmail.dat".to_owned()),
+            transfer_encoding: "7bit".to_owned(),
+            encoded_size_bytes: MAX_EXTRACT_ATTACHMENT_BYTES + 1,
+        };
+        assert!(!needs_part_body(&part, AttachmentMode::Metadata));
+
+        let raw = concat!(
+            "Content-Type: multipart/mixed; boundary=\"mix\"\r\n",
+            "\r\n",
+            "--mix\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "See attached.\r\n",
+            "--mix\r\n",
+            "Content-Type: application/ms-tnef; name=\"winmail.dat\"\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "\r\n",
+            "--mix--\r\n",
+        );
+        let message = FetchedMessage {
+            raw: raw.as_bytes().to_vec(),
+            omitted_sizes: HashMap::from([("2".to_owned(), MAX_EXTRACT_ATTACHMENT_BYTES + 1)]),
+        };
+        let parsed = parse_message(
+            &message,
+            2000,
+            BodyMode::Text,
+            AttachmentMode::Metadata,
+            100,
+            false,
+            false,
+            &MessageKeys::default(),
+        )
+        .expect("parse should succeed");
+        assert_eq!(parsed.attachments.len(), 1);
+        assert_eq!(parsed.attachment_issues.len(), 1);
+        assert_eq!(parsed.attachment_issues[0].part_id, "1.2");
+        assert_eq!(parsed.attachment_issues[0].code, "limit_exceeded");
+    }
 }
@@ -0,0 +1,537 @@
+//! Archive attachment listing (ZIP, gzip, tar)
+//!
+//! Lists archive entries and extracts text from entries the attachment
+//! extractors support, opening nested archives up to `MAX_ARCHIVE_DEPTH`.
+//! Entry count, total decompressed bytes, compression ratio, and nesting
+//! depth are bounded so archive bombs stop early; each limit hit is reported
+//! as an issue.
+
+use std::io::{Cursor, Read};
+
+use flate2::read::GzDecoder;
+use zip::ZipArchive;
+
+use crate::models::ArchiveEntry;
+
+use super::extract;
+
+/// Maximum entries listed per archive attachment, including nested archives.
+pub(super) const MAX_ARCHIVE_ENTRIES: usize = 200;
+
+/// Maximum archive nesting opened; the attachment itself is level 1.
+pub(super) const MAX_ARCHIVE_DEPTH: usize = 3;
+
+/// Maximum bytes decompressed per archive attachment.
+const MAX_ARCHIVE_TOTAL_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Maximum ratio of decompressed to compressed size for an entry.
+const MAX_COMPRESSION_RATIO: u64 = 100;
+
+/// Entries up to this size are read regardless of their compression ratio.
+const RATIO_EXEMPT_BYTES: u64 = 1024 * 1024;
+
+/// Content type handed to extractors, which then match by entry extension
+const ENTRY_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Extracts text from an entry's bytes given its path
+pub(super) type ExtractEntry<'a> =
+    &'a dyn Fn(&[u8], &str) -> Result<Option<String>, (&'static str, String)>;
+
+/// Entries and problems found in one archive attachment
+#[derive(Debug, Default)]
+pub(super) struct ArchiveListing {
+    pub(super) entries: Vec<ArchiveEntry>,
+    /// Issue code (`limit_exceeded` or `extraction_failed`) and message
+    pub(super) issues: Vec<(&'static str, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Zip,
+    Gzip,
+    Tar,
+}
+
+/// Whether an attachment is a ZIP, gzip, or tar archive by content type or extension.
+pub(super) fn is_archive(content_type: &str, filename: Option<&str>) -> bool {
+    matches!(
+        content_type,
+        "application/zip"
+            | "application/x-zip"
+            | "application/x-zip-compressed"
+            | "application/gzip"
+            | "application/x-gzip"
+            | "application/x-tar"
+            | "application/x-gtar"
+            | "application/x-compressed-tar"
+    ) || filename.is_some_and(has_archive_extension)
+}
+
+/// List an archive's entries and extract text from supported ones.
+///
+/// Extracted text across all entries shares `text_budget` characters.
+pub(super) fn list(
+    bytes: &[u8],
+    filename: Option<&str>,
+    text_budget: usize,
+    extract: ExtractEntry<'_>,
+) -> ArchiveListing {
+    let mut walker = Walker {
+        extract,
+        listing: ArchiveListing::default(),
+        decompressed_bytes: 0,
+        text_budget,
+        entries_truncated: false,
+        budget_reported: false,
+    };
+    walker.open(bytes, filename.unwrap_or("archive"), "", 1);
+    walker.listing
+}
+
+struct Walker<'a> {
+    extract: ExtractEntry<'a>,
+    listing: ArchiveListing,
+    decompressed_bytes: u64,
+    text_budget: usize,
+    entries_truncated: bool,
+    budget_reported: bool,
+}
+
+impl Walker<'_> {
+    /// Open archive `bytes` named `name`, listing its entries under `prefix`.
+    fn open(&mut self, bytes: &[u8], name: &str, prefix: &str, depth: usize) {
+        match detect(bytes) {
+            Some(Format::Zip) => self.zip(bytes, prefix, depth),
+            Some(Format::Gzip) => self.gzip(bytes, name, prefix, depth),
+            Some(Format::Tar) => self.tar(bytes, prefix, depth),
+            None => self.issue(
+                "extraction_failed",
+                format!("{name} is not a ZIP, gzip, or tar archive"),
+            ),
+        }
+    }
+
+    fn zip(&mut self, bytes: &[u8], prefix: &str, depth: usize) {
+        let mut archive = match ZipArchive::new(Cursor::new(bytes)) {
+            Ok(archive) => archive,
+            Err(e) => {
+                self.issue("extraction_failed", format!("failed to open ZIP: {e}"));
+                return;
+            }
+        };
+        for index in 0..archive.len() {
+            if !self.has_room() {
+                return;
+            }
+            let metadata = archive.by_index_raw(index).and_then(|file| {
+                Ok((
+                    file.name()?.into_owned(),
+                    file.size(),
+                    file.compressed_size(),
+                    file.encrypted(),
+                    file.is_dir(),
+                ))
+            });
+            let (name, size, compressed, encrypted, is_dir) = match metadata {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    self.issue(
+                        "extraction_failed",
+                        format!("failed to read ZIP entry: {e}"),
+                    );
+                    continue;
+                }
+            };
+            if is_dir {
+                continue;
+            }
+            let path = format!("{prefix}{name}");
+            let entry = self.push(&path, size, Some(compressed), encrypted, depth);
+            if encrypted || !self.wants(&path, depth) {
+                continue;
+            }
+            let Some(limit) = self.read_limit(&path, size, Some(compressed)) else {
+                continue;
+            };
+            let data = match archive.by_index(index) {
+                Ok(file) => self.read(file, limit, &path),
+                Err(e) => {
+                    self.issue("extraction_failed", format!("entry {path}: {e}"));
+                    continue;
+                }
+            };
+            if let Some(data) = data {
+                self.process(entry, &data, &path, depth);
+            }
+        }
+    }
+
+    /// Decompress a gzip stream: a tarball lists its entries, anything else
+    /// is a single entry named after the archive without its `.gz` suffix.
+    fn gzip(&mut self, bytes: &[u8], name: &str, prefix: &str, depth: usize) {
+        let Some(limit) = self.read_limit(name, 0, Some(bytes.len() as u64)) else {
+            return;
+        };
+        let Some(data) = self.read(GzDecoder::new(bytes), limit, name) else {
+            return;
+        };
+        if detect(&data) == Some(Format::Tar) {
+            self.tar(&data, prefix, depth);
+            return;
+        }
+        if !self.has_room() {
+            return;
+        }
+        let base = name.rsplit('/').next().unwrap_or(name);
+        let inner = strip_suffix_ignore_case(base, ".tgz")
+            .map(|stem| format!("{stem}.tar"))
+            .or_else(|| strip_suffix_ignore_case(base, ".gz").map(str::to_owned))
+            .unwrap_or_else(|| base.to_owned());
+        let path = format!("{prefix}{inner}");
+        let entry = self.push(
+            &path,
+            data.len() as u64,
+            Some(bytes.len() as u64),
+            false,
+            depth,
+        );
+        if self.wants(&path, depth) {
+            self.process(entry, &data, &path, depth);
+        }
+    }
+
+    /// List a tar stream held in memory; entry contents are sliced, not copied.
+    fn tar(&mut self, bytes: &[u8], prefix: &str, depth: usize) {
+        let mut archive = tar::Archive::new(bytes);
+        let entries = match archive.entries() {
+            Ok(entries) => entries,
+            Err(e) => {
+                self.issue("extraction_failed", format!("failed to open tar: {e}"));
+                return;
+            }
+        };
+        for entry in entries {
+            if !self.has_room() {
+                return;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.issue(
+                        "extraction_failed",
+                        format!("failed to read tar entry: {e}"),
+                    );
+                    return;
+                }
+            };
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = entry
+                .path()
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let path = format!("{prefix}{name}");
+            let size = entry.size();
+            let index = self.push(&path, size, None, false, depth);
+            if !self.wants(&path, depth) {
+                continue;
+            }
+            let start = usize::try_from(entry.raw_file_position()).unwrap_or(usize::MAX);
+            let data = usize::try_from(size)
+                .ok()
+                .and_then(|size| bytes.get(start..start.checked_add(size)?));
+            match data {
+                Some(data) => self.process(index, data, &path, depth),
+                None => self.issue("extraction_failed", format!("entry {path} is truncated")),
+            }
+        }
+    }
+
+    /// Open a nested archive or extract text from a supported entry.
+    fn process(&mut self, index: usize, data: &[u8], path: &str, depth: usize) {
+        if has_archive_extension(path) {
+            self.open(data, path, &format!("{path}/"), depth + 1);
+            return;
+        }
+        match (self.extract)(data, path) {
+            Ok(Some(text)) => {
+                let text = super::truncate_chars(text, self.text_budget);
+                self.text_budget -= text.chars().count();
+                self.listing.entries[index].extracted_text = Some(text);
+            }
+            Ok(None) => {}
+            Err((code, message)) => self.issue(code, format!("entry {path}: {message}")),
+        }
+    }
+
+    /// Whether an entry should be read: a nested archive within the depth
+    /// limit, or a supported format while text budget remains.
+    fn wants(&mut self, path: &str, depth: usize) -> bool {
+        if has_archive_extension(path) {
+            if depth >= MAX_ARCHIVE_DEPTH {
+                self.issue(
+                    "limit_exceeded",
+                    format!(
+                        "entry {path}: archives nested deeper than {MAX_ARCHIVE_DEPTH} levels are not opened"
+                    ),
+                );
+                return false;
+            }
+            return true;
+        }
+        if extract::find_extractor(ENTRY_CONTENT_TYPE, Some(path)).is_none() {
+            return false;
+        }
+        if self.text_budget == 0 {
+            if !self.budget_reported {
+                self.budget_reported = true;
+                self.issue(
+                    "limit_exceeded",
+                    format!(
+                        "extracted text budget exhausted at entry {path}; later entries were not extracted"
+                    ),
+                );
+            }
+            return false;
+        }
+        true
+    }
+
+    /// Bytes an entry may decompress to, or `None` (with an issue) when its
+    /// declared size or compression ratio is already over a limit.
+    fn read_limit(&mut self, path: &str, size: u64, compressed: Option<u64>) -> Option<u64> {
+        let remaining = MAX_ARCHIVE_TOTAL_BYTES.saturating_sub(self.decompressed_bytes);
+        let ratio_limit = compressed.map_or(u64::MAX, |compressed| {
+            compressed
+                .saturating_mul(MAX_COMPRESSION_RATIO)
+                .max(RATIO_EXEMPT_BYTES)
+        });
+        if size > ratio_limit {
+            self.ratio_issue(path);
+            return None;
+        }
+        if size > remaining {
+            self.total_issue(path);
+            return None;
+        }
+        Some(remaining.min(ratio_limit))
+    }
+
+    /// Decompress up to `limit` bytes, reporting an issue when the entry is larger.
+    fn read(&mut self, reader: impl Read, limit: u64, path: &str) -> Option<Vec<u8>> {
+        let mut data = Vec::new();
+        let result = reader.take(limit.saturating_add(1)).read_to_end(&mut data);
+        self.decompressed_bytes = self.decompressed_bytes.saturating_add(data.len() as u64);
+        if let Err(e) = result {
+            self.issue("extraction_failed", format!("entry {path}: {e}"));
+            return None;
+        }
+        if data.len() as u64 > limit {
+            if self.decompressed_bytes > MAX_ARCHIVE_TOTAL_BYTES {
+                self.total_issue(path);
+            } else {
+                self.ratio_issue(path);
+            }
+            return None;
+        }
+        Some(data)
+    }
+
+    fn push(
+        &mut self,
+        path: &str,
+        size: u64,
+        compressed: Option<u64>,
+        encrypted: bool,
+        depth: usize,
+    ) -> usize {
+        self.listing.entries.push(ArchiveEntry {
+            path: path.to_owned(),
+            size_bytes: size,
+            compressed_size_bytes: compressed,
+            encrypted,
+            depth,
+            extracted_text: None,
+        });
+        self.listing.entries.len() - 1
+    }
+
+    fn has_room(&mut self) -> bool {
+        if self.listing.entries.len() < MAX_ARCHIVE_ENTRIES {
+            return true;
+        }
+        if !self.entries_truncated {
+            self.entries_truncated = true;
+            self.issue(
+                "limit_exceeded",
+                format!("archive has more than {MAX_ARCHIVE_ENTRIES} entries; listing stopped"),
+            );
+        }
+        false
+    }
+
+    fn ratio_issue(&mut self, path: &str) {
+        self.issue(
+            "limit_exceeded",
+            format!(
+                "entry {path}: compression ratio exceeds {MAX_COMPRESSION_RATIO}:1; not decompressed"
+            ),
+        );
+    }
+
+    fn total_issue(&mut self, path: &str) {
+        self.issue(
+            "limit_exceeded",
+            format!(
+                "entry {path}: archive decompresses to more than {MAX_ARCHIVE_TOTAL_BYTES} bytes; not decompressed"
+            ),
+        );
+    }
+
+    fn issue(&mut self, code: &'static str, message: String) {
+        self.listing.issues.push((code, message));
+    }
+}
+
+fn detect(bytes: &[u8]) -> Option<Format> {
+    if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+        Some(Format::Zip)
+    } else if bytes.starts_with(&[0x1f, 0x8b]) {
+        Some(Format::Gzip)
+    } else if bytes.get(257..262) == Some(b"ustar") {
+        Some(Format::Tar)
+    } else {
+        None
+    }
+}
+
+fn has_archive_extension(name: &str) -> bool {
+    [".zip", ".gz", ".tgz", ".tar"]
+        .iter()
+        .any(|extension| strip_suffix_ignore_case(name, extension).is_some())
+}
+
+fn strip_suffix_ignore_case<'a>(name: &'a str, suffix: &str) -> Option<&'a str> {
+    let split = name.len().checked_sub(suffix.len())?;
+    (name.is_char_boundary(split) && name[split..].eq_ignore_ascii_case(suffix))
+        .then(|| &name[..split])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use zip::write::{SimpleFileOptions, ZipWriter};
+
+    use super::{MAX_ARCHIVE_ENTRIES, list};
+
+    fn zip_file(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, body) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .expect("start entry");
+            writer.write_all(body).expect("write entry");
+        }
+        writer.finish().expect("finish zip").into_inner()
+    }
+
+    fn tar_gz(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, body) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(body.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, *body)
+                .expect("append entry");
+        }
+        let tar = builder.into_inner().expect("finish tar");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&tar).expect("compress");
+        encoder.finish().expect("finish gzip")
+    }
+
+    fn plain_text(bytes: &[u8], _path: &str) -> Result<Option<String>, (&'static str, String)> {
+        Ok(Some(String::from_utf8_lossy(bytes).into_owned()))
+    }
+
+    /// Tests listing and extraction across ZIP, nested tar.gz, and gzip entries.
+    #[test]
+    fn lists_nested_archives_and_extracts_text() {
+        let inner = tar_gz(&[("q1/totals.csv", b"region,total\nnorth,12\n")]);
+        let archive = zip_file(&[
+            ("summary.txt", b"All regions grew."),
+            ("logo.png", b"\x89PNG"),
+            ("regions.tar.gz", &inner),
+        ]);
+        let listing = list(&archive, Some("reports.zip"), 1000, &plain_text);
+
+        let paths = listing
+            .entries
+            .iter()
+            .map(|entry| (entry.path.as_str(), entry.depth))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                ("summary.txt", 1),
+                ("logo.png", 1),
+                ("regions.tar.gz", 1),
+                ("regions.tar.gz/q1/totals.csv", 2),
+            ]
+        );
+        assert_eq!(
+            listing.entries[0].extracted_text.as_deref(),
+            Some("All regions grew.")
+        );
+        assert_eq!(listing.entries[1].extracted_text, None);
+        assert_eq!(listing.entries[3].size_bytes, 22);
+        assert_eq!(listing.entries[3].compressed_size_bytes, None);
+        assert!(listing.issues.is_empty(), "{:?}", listing.issues);
+    }
+
+    /// Tests that highly compressed entries and entry floods stop at the limits.
+    #[test]
+    fn stops_at_ratio_and_entry_limits() {
+        let bomb = vec![b'0'; 8 * 1024 * 1024];
+        let archive = zip_file(&[("zeros.txt", &bomb)]);
+        let listing = list(&archive, Some("bomb.zip"), 1000, &plain_text);
+        assert_eq!(listing.entries.len(), 1);
+        assert_eq!(listing.entries[0].extracted_text, None);
+        assert_eq!(listing.issues.len(), 1);
+        assert_eq!(listing.issues[0].0, "limit_exceeded");
+        assert!(listing.issues[0].1.contains("compression ratio"));
+
+        let names = (0..=MAX_ARCHIVE_ENTRIES)
+            .map(|index| format!("f{index}.bin"))
+            .collect::<Vec<_>>();
+        let entries = names
+            .iter()
+            .map(|name| (name.as_str(), b"x".as_slice()))
+            .collect::<Vec<_>>();
+        let listing = list(&zip_file(&entries), Some("many.zip"), 1000, &plain_text);
+        assert_eq!(listing.entries.len(), MAX_ARCHIVE_ENTRIES);
+        assert!(listing.issues[0].1.contains("more than"));
+    }
+
+    /// Tests that nesting beyond the depth limit is listed but not opened.
+    #[test]
+    fn stops_at_nesting_depth() {
+        let mut archive = zip_file(&[("deep.txt", b"deep")]);
+        for level in 0..3 {
+            let name = format!("level{level}.zip");
+            archive = zip_file(&[(name.as_str(), &archive)]);
+        }
+        let listing = list(&archive, Some("outer.zip"), 1000, &plain_text);
+        assert_eq!(
+            listing.entries.last().map(|entry| entry.path.as_str()),
+            Some("level2.zip/level1.zip/level0.zip")
+        );
+        assert!(listing.issues[0].1.contains("nested deeper than 3"));
+    }
+}
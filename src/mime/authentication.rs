@@ -0,0 +1,514 @@
+//! `Authentication-Results` (RFC 8601) and ARC (RFC 8617) header parsing
+//!
+//! Turns the verdicts recorded by receiving servers into a structured block
+//! and checks whether Reply-To points at a different organization than From.
+//! Parsing is lenient: unparseable entries are skipped rather than failing the
+//! message.
+
+use std::collections::BTreeMap;
+
+use crate::models::{AuthenticationResult, DkimSignatureResult, MessageAuthentication};
+
+use super::AddressHeaders;
+
+/// Maximum parsed result entries reported per message.
+const MAX_AUTHENTICATION_RESULTS: usize = 50;
+
+/// Second-level labels that act as public suffixes under two-letter TLDs
+/// (`example.co.uk`). This approximates organizational domains without a
+/// public suffix list.
+const SHARED_SECOND_LEVEL_LABELS: &[&str] = &["ac", "co", "com", "edu", "gov", "net", "org"];
+
+/// Build the authentication block from message headers and parsed addresses.
+pub(super) fn message_authentication(
+    headers: &[(String, String)],
+    addresses: &AddressHeaders,
+) -> MessageAuthentication {
+    let mut results = Vec::new();
+    let mut seals = Vec::new();
+    let mut position = 0;
+    let mut arc_position = 0;
+    for (key, value) in headers {
+        if key.eq_ignore_ascii_case("authentication-results") {
+            results.extend(parse_results_header(value, None, position));
+            position += 1;
+        } else if key.eq_ignore_ascii_case("arc-authentication-results") {
+            let (instance, rest) = split_arc_instance(value);
+            if let Some(instance) = instance {
+                results.extend(parse_results_header(rest, Some(instance), arc_position));
+            }
+            arc_position += 1;
+        } else if key.eq_ignore_ascii_case("arc-seal") {
+            let tags = parse_tag_list(value);
+            if let Some(instance) = tags.get("i").and_then(|i| i.parse::<u32>().ok()) {
+                seals.push((instance, tags.get("cv").cloned().unwrap_or_default()));
+            }
+        }
+    }
+    results.truncate(MAX_AUTHENTICATION_RESULTS);
+
+    let verdict = |method: &str| {
+        combine_verdicts(
+            results
+                .iter()
+                .filter(|entry| entry.arc_instance.is_none() && entry.position == 0)
+                .filter(|entry| entry.method == method)
+                .map(|entry| entry.result.as_str()),
+        )
+    };
+    let receiver_arc = verdict("arc");
+    let arc = if receiver_arc == "none" {
+        arc_chain_verdict(&mut seals).to_owned()
+    } else {
+        receiver_arc.to_owned()
+    };
+
+    let from_domain = addresses
+        .from
+        .as_ref()
+        .and_then(|from| from.first())
+        .and_then(|address| address_domain(&address.email));
+    let mut reply_to_domains = Vec::new();
+    for address in addresses.reply_to.iter().flatten() {
+        if let Some(domain) = address_domain(&address.email)
+            && !reply_to_domains.contains(&domain)
+        {
+            reply_to_domains.push(domain);
+        }
+    }
+    let reply_to_misaligned = from_domain.as_deref().is_some_and(|from| {
+        reply_to_domains
+            .iter()
+            .any(|domain| organizational_domain(domain) != organizational_domain(from))
+    });
+
+    let dkim = aligned_dkim_verdict(
+        results
+            .iter()
+            .filter(|entry| entry.arc_instance.is_none() && entry.position == 0)
+            .filter(|entry| entry.method == "dkim")
+            .map(|entry| {
+                let domain = entry.properties.get("header.d").cloned().or_else(|| {
+                    entry
+                        .properties
+                        .get("header.i")
+                        .and_then(|identity| identity.rsplit('@').next())
+                        .map(str::to_owned)
+                });
+                (
+                    entry.result.as_str(),
+                    is_aligned(domain.as_deref(), from_domain.as_deref()),
+                )
+            }),
+    );
+
+    MessageAuthentication {
+        dkim: dkim.to_owned(),
+        spf: verdict("spf").to_owned(),
+        dmarc: verdict("dmarc").to_owned(),
+        arc,
+        results,
+        dkim_signatures: None,
+        from_domain,
+        reply_to_domains,
+        reply_to_misaligned,
+    }
+}
+
+/// Record local DKIM verification results and let them decide the DKIM verdict.
+///
+/// Each signature is marked aligned when its `d=` domain shares the From
+/// address's organizational domain; only aligned signatures can pass.
+pub fn apply_dkim_verification(
+    authentication: &mut MessageAuthentication,
+    mut signatures: Vec<DkimSignatureResult>,
+) {
+    for signature in &mut signatures {
+        signature.aligned = is_aligned(
+            signature.domain.as_deref(),
+            authentication.from_domain.as_deref(),
+        );
+    }
+    authentication.dkim = if signatures.is_empty() {
+        "none".to_owned()
+    } else {
+        aligned_dkim_verdict(
+            signatures
+                .iter()
+                .map(|signature| (signature.result.as_str(), signature.aligned)),
+        )
+        .to_owned()
+    };
+    authentication.dkim_signatures = Some(signatures);
+}
+
+/// Combine DKIM results where only signatures aligned with From may pass
+///
+/// A valid signature by an unrelated domain says nothing about the sender,
+/// so an unaligned `pass` counts as `fail`.
+fn aligned_dkim_verdict<'a>(signatures: impl Iterator<Item = (&'a str, bool)>) -> &'static str {
+    combine_verdicts(signatures.map(|(result, aligned)| {
+        if !aligned && normalize_result(result) == "pass" {
+            "fail"
+        } else {
+            result
+        }
+    }))
+}
+
+/// Whether a signing domain shares the From domain's organizational domain.
+fn is_aligned(signing_domain: Option<&str>, from_domain: Option<&str>) -> bool {
+    let normalize = |domain: &str| domain.trim().trim_end_matches('.').to_ascii_lowercase();
+    match (signing_domain, from_domain) {
+        (Some(signing), Some(from)) => {
+            organizational_domain(&normalize(signing)) == organizational_domain(&normalize(from))
+        }
+        _ => false,
+    }
+}
+
+/// Collapse result keywords: any pass wins, then fail, then neutral.
+fn combine_verdicts<'a>(results: impl Iterator<Item = &'a str>) -> &'static str {
+    let mut verdict = "none";
+    for result in results {
+        match normalize_result(result) {
+            "pass" => return "pass",
+            "fail" => verdict = "fail",
+            "neutral" if verdict == "none" => verdict = "neutral",
+            _ => {}
+        }
+    }
+    verdict
+}
+
+/// Map RFC 8601 result keywords onto `pass|fail|neutral|none`.
+fn normalize_result(result: &str) -> &'static str {
+    match result.to_ascii_lowercase().as_str() {
+        "pass" => "pass",
+        "fail" | "softfail" | "permerror" | "hardfail" => "fail",
+        "none" => "none",
+        _ => "neutral",
+    }
+}
+
+/// Judge the claimed ARC chain from `ARC-Seal` instance numbers and `cv=` values.
+///
+/// Seals are not verified cryptographically; this only reports whether the
+/// chain is complete and every hop claims validation.
+fn arc_chain_verdict(seals: &mut [(u32, String)]) -> &'static str {
+    if seals.is_empty() {
+        return "none";
+    }
+    seals.sort_by_key(|(instance, _)| *instance);
+    let intact = seals.iter().enumerate().all(|(index, (instance, cv))| {
+        let expected_cv = if *instance == 1 { "none" } else { "pass" };
+        usize::try_from(*instance).is_ok_and(|instance| instance == index + 1)
+            && cv.eq_ignore_ascii_case(expected_cv)
+    });
+    if intact { "pass" } else { "fail" }
+}
+
+/// Split the leading `i=N;` instance tag from an `ARC-Authentication-Results` value.
+fn split_arc_instance(value: &str) -> (Option<u32>, &str) {
+    let Some((first, rest)) = value.split_once(';') else {
+        return (None, value);
+    };
+    let instance = first
+        .trim()
+        .strip_prefix("i=")
+        .and_then(|number| number.trim().parse::<u32>().ok());
+    (instance, rest)
+}
+
+/// Parse one results header into method entries.
+fn parse_results_header(
+    value: &str,
+    arc_instance: Option<u32>,
+    position: usize,
+) -> Vec<AuthenticationResult> {
+    let cleaned = strip_comments(value);
+    let mut segments = split_outside_quotes(&cleaned, ';').into_iter();
+    let Some(authserv_id) = segments
+        .next()
+        .and_then(|segment| segment.split_whitespace().next().map(unquote))
+    else {
+        return Vec::new();
+    };
+
+    let mut entries = Vec::new();
+    for segment in segments {
+        let mut tokens = split_outside_quotes(&segment, ' ')
+            .into_iter()
+            .map(|token| token.trim().to_owned())
+            .filter(|token| !token.is_empty());
+        let Some((method, result)) = tokens.next().and_then(|token| {
+            let (method, result) = token.split_once('=')?;
+            let method = method.split('/').next().unwrap_or(method);
+            Some((method.trim().to_ascii_lowercase(), unquote(result)))
+        }) else {
+            continue;
+        };
+
+        let mut reason = None;
+        let mut properties = BTreeMap::new();
+        for token in tokens {
+            let Some((key, value)) = token.split_once('=') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            if key == "reason" {
+                reason = Some(unquote(value));
+            } else if key.contains('.') {
+                properties.insert(key, unquote(value));
+            }
+        }
+        entries.push(AuthenticationResult {
+            source: if arc_instance.is_some() {
+                "arc-authentication-results".to_owned()
+            } else {
+                "authentication-results".to_owned()
+            },
+            position,
+            arc_instance,
+            authserv_id: authserv_id.clone(),
+            method,
+            result: result.to_ascii_lowercase(),
+            reason,
+            properties,
+        });
+    }
+    entries
+}
+
+/// Remove RFC 5322 comments, which may contain `;`, outside quoted strings.
+fn strip_comments(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut escaped = false;
+    for ch in value.chars() {
+        if escaped {
+            escaped = false;
+            if depth == 0 {
+                out.push(ch);
+            }
+            continue;
+        }
+        match ch {
+            '\\' => {
+                escaped = true;
+                if depth == 0 {
+                    out.push(ch);
+                }
+            }
+            '"' if depth == 0 => {
+                quoted = !quoted;
+                out.push(ch);
+            }
+            '(' if !quoted => depth += 1,
+            ')' if !quoted && depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    out.push(' ');
+                }
+            }
+            _ if depth == 0 => out.push(ch),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Split on `separator` (or any whitespace when it is a space) outside quotes.
+fn split_outside_quotes(value: &str, separator: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for ch in value.chars() {
+        let splits = if separator == ' ' {
+            ch.is_whitespace()
+        } else {
+            ch == separator
+        };
+        if ch == '"' {
+            quoted = !quoted;
+            current.push(ch);
+        } else if splits && !quoted {
+            parts.push(std::mem::take(&mut current));
+        } else {
+            current.push(ch);
+        }
+    }
+    parts.push(current);
+    parts
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|inner| inner.strip_suffix('"'))
+        .map(|inner| inner.replace("\\\"", "\"").replace("\\\\", "\\"))
+        .unwrap_or_else(|| value.to_owned())
+}
+
+/// Parse a DKIM-style `tag=value; ...` list (used by `ARC-Seal`).
+fn parse_tag_list(value: &str) -> BTreeMap<String, String> {
+    value
+        .split(';')
+        .filter_map(|tag| {
+            let (name, value) = tag.split_once('=')?;
+            Some((name.trim().to_ascii_lowercase(), value.trim().to_owned()))
+        })
+        .collect()
+}
+
+fn address_domain(email: &str) -> Option<String> {
+    let (_, domain) = email.rsplit_once('@')?;
+    let domain = domain.trim().trim_end_matches('.').to_ascii_lowercase();
+    (!domain.is_empty()).then_some(domain)
+}
+
+/// Approximate the organizational domain (`mail.example.co.uk` → `example.co.uk`).
+pub(super) fn organizational_domain(domain: &str) -> String {
+    let labels = domain.split('.').collect::<Vec<_>>();
+    let keep = match labels.as_slice() {
+        [.., second, tld]
+            if labels.len() >= 3
+                && tld.len() == 2
+                && SHARED_SECOND_LEVEL_LABELS.contains(second) =>
+        {
+            3
+        }
+        _ => 2,
+    };
+    labels[labels.len().saturating_sub(keep)..].join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_dkim_verification, message_authentication, organizational_domain};
+    use crate::mime::parse_address_headers;
+    use crate::models::DkimSignatureResult;
+
+    fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| ((*key).to_owned(), (*value).to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn uses_topmost_results_header_for_verdicts() {
+        let headers = headers(&[
+            (
+                "Authentication-Results",
+                "mx.example.net; dkim=pass (2048-bit key; secure) header.d=example.com header.s=sel1; spf=softfail smtp.mailfrom=bounce.example.com; dmarc=fail reason=\"p=reject; aligned\" header.from=example.com",
+            ),
+            (
+                "Authentication-Results",
+                "forged.example; dkim=pass; spf=pass; dmarc=pass",
+            ),
+        ]);
+        let addresses = parse_address_headers(
+            b"From: Alice <alice@mail.example.com>\r\nReply-To: payments@example-billing.co.uk\r\n\r\n",
+        );
+
+        let authentication = message_authentication(&headers, &addresses);
+        assert_eq!(authentication.dkim, "pass");
+        assert_eq!(authentication.spf, "fail");
+        assert_eq!(authentication.dmarc, "fail");
+        assert_eq!(authentication.arc, "none");
+        assert_eq!(authentication.results.len(), 6);
+
+        let dkim = &authentication.results[0];
+        assert_eq!(dkim.authserv_id, "mx.example.net");
+        assert_eq!(
+            dkim.properties.get("header.d").map(String::as_str),
+            Some("example.com")
+        );
+        let dmarc = &authentication.results[2];
+        assert_eq!(dmarc.reason.as_deref(), Some("p=reject; aligned"));
+        assert_eq!(authentication.results[3].position, 1);
+
+        assert_eq!(
+            authentication.from_domain.as_deref(),
+            Some("mail.example.com")
+        );
+        assert_eq!(authentication.reply_to_domains, ["example-billing.co.uk"]);
+        assert!(authentication.reply_to_misaligned);
+    }
+
+    #[test]
+    fn reports_arc_chain_and_aligned_reply_to() {
+        let headers = headers(&[
+            (
+                "ARC-Seal",
+                "i=2; a=rsa-sha256; cv=pass; d=list.example; s=arc; b=abc",
+            ),
+            (
+                "ARC-Authentication-Results",
+                "i=2; list.example; dkim=pass header.d=example.com",
+            ),
+            (
+                "ARC-Seal",
+                "i=1; a=rsa-sha256; cv=none; d=mx.example; s=arc; b=abc",
+            ),
+        ]);
+        let addresses = parse_address_headers(
+            b"From: alice@example.com\r\nReply-To: team@lists.example.com\r\n\r\n",
+        );
+
+        let authentication = message_authentication(&headers, &addresses);
+        assert_eq!(authentication.arc, "pass");
+        assert_eq!(authentication.dkim, "none");
+        assert_eq!(authentication.results[0].arc_instance, Some(2));
+        assert!(!authentication.reply_to_misaligned);
+
+        let broken = self::headers(&[("ARC-Seal", "i=2; cv=fail")]);
+        assert_eq!(message_authentication(&broken, &addresses).arc, "fail");
+    }
+
+    #[test]
+    fn ignores_dkim_passes_from_unaligned_domains() {
+        let headers = headers(&[(
+            "Authentication-Results",
+            "mx.example.net; dkim=pass header.d=evil.example; dkim=fail header.i=@bank.example",
+        )]);
+        let addresses = parse_address_headers(b"From: alerts@bank.example\r\n\r\n");
+        let mut authentication = message_authentication(&headers, &addresses);
+        assert_eq!(authentication.dkim, "fail");
+
+        let signature = |domain: &str, result: &str| DkimSignatureResult {
+            domain: Some(domain.to_owned()),
+            selector: Some("sel".to_owned()),
+            algorithm: Some("rsa-sha256".to_owned()),
+            result: result.to_owned(),
+            reason: None,
+            aligned: false,
+        };
+        apply_dkim_verification(&mut authentication, vec![signature("evil.example", "pass")]);
+        assert_eq!(authentication.dkim, "fail");
+        let signatures = authentication.dkim_signatures.as_ref().expect("signatures");
+        assert!(!signatures[0].aligned);
+
+        apply_dkim_verification(
+            &mut authentication,
+            vec![
+                signature("evil.example", "pass"),
+                signature("mail.bank.example", "pass"),
+            ],
+        );
+        assert_eq!(authentication.dkim, "pass");
+        let signatures = authentication.dkim_signatures.as_ref().expect("signatures");
+        assert!(signatures[1].aligned);
+    }
+
+    #[test]
+    fn approximates_organizational_domains() {
+        assert_eq!(organizational_domain("mail.example.com"), "example.com");
+        assert_eq!(organizational_domain("a.b.example.co.uk"), "example.co.uk");
+        assert_eq!(organizational_domain("example.io"), "example.io");
+        assert_eq!(organizational_domain("localhost"), "localhost");
+    }
+}
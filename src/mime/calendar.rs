@@ -0,0 +1,627 @@
+//! iCalendar (RFC 5545) event parsing
+//!
+//! Extracts the fields an agent needs to reason about meeting invites from
+//! `text/calendar` parts. Parsing is lenient: unknown properties are ignored
+//! and malformed values are reported as absent rather than failing the message.
+
+use chrono::{
+    Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+
+use crate::models::{CalendarEvent, CalendarParticipant, CalendarTime};
+
+/// Maximum attendees reported per event.
+const MAX_ATTENDEES: usize = 200;
+
+/// One unfolded content line: `NAME;PARAM=value:VALUE`.
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// One STANDARD or DAYLIGHT rule of a VTIMEZONE.
+struct Observance {
+    start: NaiveDateTime,
+    offset_to_seconds: i64,
+    rrule: Option<String>,
+}
+
+struct TimezoneDefinition {
+    tzid: String,
+    observances: Vec<Observance>,
+}
+
+/// Parse every VEVENT in an iCalendar document.
+pub(super) fn parse_calendar(text: &str) -> Vec<CalendarEvent> {
+    let properties = unfold(text)
+        .lines()
+        .filter_map(parse_property)
+        .collect::<Vec<_>>();
+
+    let mut method = None;
+    let mut timezones = Vec::new();
+    let mut event_props: Vec<Vec<&Property>> = Vec::new();
+    let mut stack: Vec<String> = Vec::new();
+    let mut timezone: Option<TimezoneDefinition> = None;
+    let mut observance: Option<Observance> = None;
+
+    for prop in &properties {
+        match prop.name.as_str() {
+            "BEGIN" => {
+                let component = prop.value.to_ascii_uppercase();
+                match component.as_str() {
+                    "VEVENT" if stack.last().is_some_and(|top| top == "VCALENDAR") => {
+                        event_props.push(Vec::new());
+                    }
+                    "VTIMEZONE" => {
+                        timezone = Some(TimezoneDefinition {
+                            tzid: String::new(),
+                            observances: Vec::new(),
+                        });
+                    }
+                    "STANDARD" | "DAYLIGHT" => {
+                        observance = Some(Observance {
+                            start: NaiveDateTime::MIN,
+                            offset_to_seconds: 0,
+                            rrule: None,
+                        });
+                    }
+                    _ => {}
+                }
+                stack.push(component);
+            }
+            "END" => match stack.pop().as_deref() {
+                Some("VTIMEZONE") => timezones.extend(timezone.take()),
+                Some("STANDARD" | "DAYLIGHT") => {
+                    if let (Some(definition), Some(rule)) = (&mut timezone, observance.take()) {
+                        definition.observances.push(rule);
+                    }
+                }
+                _ => {}
+            },
+            _ => match stack.last().map(String::as_str) {
+                Some("VCALENDAR") if prop.name == "METHOD" => {
+                    method = Some(prop.value.trim().to_ascii_uppercase());
+                }
+                Some("VEVENT") => {
+                    if let Some(current) = event_props.last_mut() {
+                        current.push(prop);
+                    }
+                }
+                Some("VTIMEZONE") if prop.name == "TZID" => {
+                    if let Some(definition) = &mut timezone {
+                        definition.tzid = prop.value.clone();
+                    }
+                }
+                Some("STANDARD" | "DAYLIGHT") => {
+                    if let Some(rule) = &mut observance {
+                        match prop.name.as_str() {
+                            "DTSTART" => {
+                                if let Some(start) = parse_naive_datetime(&prop.value) {
+                                    rule.start = start;
+                                }
+                            }
+                            "TZOFFSETTO" => {
+                                if let Some(offset) = parse_utc_offset(&prop.value) {
+                                    rule.offset_to_seconds = offset;
+                                }
+                            }
+                            "RRULE" => rule.rrule = Some(prop.value.clone()),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+
+    event_props
+        .iter()
+        .map(|props| build_event(props, method.clone(), &timezones))
+        .collect()
+}
+
+fn build_event(
+    props: &[&Property],
+    method: Option<String>,
+    timezones: &[TimezoneDefinition],
+) -> CalendarEvent {
+    let first = |name: &str| props.iter().copied().find(|prop| prop.name == name);
+    let text = |name: &str| {
+        first(name)
+            .map(|prop| unescape_text(&prop.value))
+            .filter(|value| !value.is_empty())
+    };
+
+    let start = first("DTSTART").and_then(|prop| parse_time(prop, timezones));
+    let end = first("DTEND")
+        .and_then(|prop| parse_time(prop, timezones))
+        .or_else(|| {
+            let duration = first("DURATION").and_then(|prop| parse_duration(&prop.value))?;
+            let start_prop = first("DTSTART")?;
+            shift_time(start_prop, duration, timezones)
+        });
+
+    CalendarEvent {
+        method,
+        uid: text("UID"),
+        sequence: first("SEQUENCE").and_then(|prop| prop.value.trim().parse().ok()),
+        status: first("STATUS").map(|prop| prop.value.trim().to_ascii_uppercase()),
+        summary: text("SUMMARY"),
+        location: text("LOCATION"),
+        organizer: first("ORGANIZER").map(participant),
+        attendees: props
+            .iter()
+            .filter(|prop| prop.name == "ATTENDEE")
+            .take(MAX_ATTENDEES)
+            .map(|prop| participant(prop))
+            .collect(),
+        start,
+        end,
+        recurrence_rule: first("RRULE").map(|prop| prop.value.trim().to_owned()),
+    }
+}
+
+fn participant(prop: &Property) -> CalendarParticipant {
+    let address = prop.value.trim();
+    let email = address
+        .get(..7)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("mailto:"))
+        .map(|_| address[7..].to_owned())
+        .filter(|email| !email.is_empty());
+    CalendarParticipant {
+        email,
+        name: prop.param("CN").map(str::to_owned),
+        partstat: prop.param("PARTSTAT").map(str::to_ascii_uppercase),
+        role: prop.param("ROLE").map(str::to_ascii_uppercase),
+    }
+}
+
+/// Join folded continuation lines (RFC 5545 section 3.1).
+fn unfold(text: &str) -> String {
+    text.replace("\r\n ", "")
+        .replace("\r\n\t", "")
+        .replace("\n ", "")
+        .replace("\n\t", "")
+}
+
+fn parse_property(line: &str) -> Option<Property> {
+    let line = line.trim_end_matches('\r');
+    let mut in_quotes = false;
+    let mut value_start = None;
+    for (index, ch) in line.char_indices() {
+        match ch {
+            '"' => in_quotes = !in_quotes,
+            ':' if !in_quotes => {
+                value_start = Some(index);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let value_start = value_start?;
+    let head = &line[..value_start];
+    let value = line[value_start + 1..].to_owned();
+
+    let mut parts = split_unquoted(head, ';').into_iter();
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    if name.is_empty() {
+        return None;
+    }
+    let params = parts
+        .filter_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            Some((
+                key.trim().to_ascii_uppercase(),
+                value.trim().trim_matches('"').to_owned(),
+            ))
+        })
+        .collect();
+    Some(Property {
+        name,
+        params,
+        value,
+    })
+}
+
+fn split_unquoted(input: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (index, ch) in input.char_indices() {
+        if ch == '"' {
+            in_quotes = !in_quotes;
+        } else if ch == separator && !in_quotes {
+            parts.push(&input[start..index]);
+            start = index + ch.len_utf8();
+        }
+    }
+    parts.push(&input[start..]);
+    parts
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out.trim().to_owned()
+}
+
+fn parse_naive_datetime(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value.trim().trim_end_matches('Z'), "%Y%m%dT%H%M%S").ok()
+}
+
+fn parse_time(prop: &Property, timezones: &[TimezoneDefinition]) -> Option<CalendarTime> {
+    let value = prop.value.trim();
+    if prop.param("VALUE") == Some("DATE") || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some(all_day_time(date));
+    }
+    let local = parse_naive_datetime(value)?;
+    let timezone = if value.ends_with('Z') {
+        Some("UTC")
+    } else {
+        prop.param("TZID")
+    };
+    Some(resolve_time(local, timezone, timezones))
+}
+
+/// Apply a duration to a DTSTART value, keeping its timezone.
+fn shift_time(
+    start: &Property,
+    duration: Duration,
+    timezones: &[TimezoneDefinition],
+) -> Option<CalendarTime> {
+    let value = start.value.trim();
+    if start.param("VALUE") == Some("DATE") || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some(all_day_time(date.checked_add_signed(duration)?));
+    }
+    let local = parse_naive_datetime(value)?.checked_add_signed(duration)?;
+    let timezone = if value.ends_with('Z') {
+        Some("UTC")
+    } else {
+        start.param("TZID")
+    };
+    Some(resolve_time(local, timezone, timezones))
+}
+
+fn all_day_time(date: NaiveDate) -> CalendarTime {
+    CalendarTime {
+        local: date.format("%Y-%m-%d").to_string(),
+        timezone: None,
+        utc: None,
+        all_day: true,
+    }
+}
+
+/// Resolve a wall-clock time via UTC, an IANA zone, or the invite's VTIMEZONE.
+fn resolve_time(
+    local: NaiveDateTime,
+    timezone: Option<&str>,
+    timezones: &[TimezoneDefinition],
+) -> CalendarTime {
+    let utc = match timezone {
+        Some("UTC") => Some(local),
+        Some(tzid) => iana_to_utc(local, tzid).or_else(|| {
+            timezones
+                .iter()
+                .find(|definition| definition.tzid == tzid)
+                .and_then(|definition| vtimezone_to_utc(local, definition))
+        }),
+        None => None,
+    };
+    CalendarTime {
+        local: local.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        timezone: timezone.map(str::to_owned),
+        utc: utc.map(|instant| {
+            Utc.from_utc_datetime(&instant)
+                .to_rfc3339_opts(SecondsFormat::Secs, true)
+        }),
+        all_day: false,
+    }
+}
+
+fn iana_to_utc(local: NaiveDateTime, tzid: &str) -> Option<NaiveDateTime> {
+    let tz = tzid.trim_start_matches('/').parse::<Tz>().ok()?;
+    let resolved = tz.from_local_datetime(&local);
+    resolved
+        .earliest()
+        .or_else(|| resolved.latest())
+        .map(|instant| instant.naive_utc())
+}
+
+/// Pick the observance in effect at `local` and apply its UTC offset.
+///
+/// Supports fixed onsets and the common `FREQ=YEARLY;BYMONTH=..;BYDAY=..`
+/// transition rules used by calendar clients.
+fn vtimezone_to_utc(
+    local: NaiveDateTime,
+    definition: &TimezoneDefinition,
+) -> Option<NaiveDateTime> {
+    let mut best: Option<(NaiveDateTime, i64)> = None;
+    for rule in &definition.observances {
+        let mut onsets = vec![rule.start];
+        if let Some(rrule) = &rule.rrule {
+            for year in [local.year() - 1, local.year()] {
+                if year >= rule.start.year()
+                    && let Some(onset) = yearly_onset(rrule, year, rule.start.time())
+                {
+                    onsets.push(onset);
+                }
+            }
+        }
+        for onset in onsets.into_iter().filter(|onset| *onset <= local) {
+            if best.is_none_or(|(current, _)| onset > current) {
+                best = Some((onset, rule.offset_to_seconds));
+            }
+        }
+    }
+    let offset = best.map(|(_, offset)| offset).or_else(|| {
+        definition
+            .observances
+            .first()
+            .map(|rule| rule.offset_to_seconds)
+    })?;
+    local.checked_sub_signed(Duration::seconds(offset))
+}
+
+/// Onset of a yearly `BYMONTH`/`BYDAY` rule such as `BYMONTH=3;BYDAY=2SU`.
+fn yearly_onset(rrule: &str, year: i32, time: NaiveTime) -> Option<NaiveDateTime> {
+    let mut month = None;
+    let mut by_day = None;
+    for part in rrule.split(';') {
+        match part.split_once('=') {
+            Some(("FREQ", freq)) if freq != "YEARLY" => return None,
+            Some(("BYMONTH", value)) => month = value.parse::<u32>().ok(),
+            Some(("BYDAY", value)) => by_day = Some(value),
+            _ => {}
+        }
+    }
+    let month = month?;
+    let by_day = by_day?;
+    let split = by_day.len().checked_sub(2)?;
+    let (ordinal, weekday) = by_day.split_at(split);
+    let ordinal = if ordinal.is_empty() {
+        1
+    } else {
+        ordinal.trim_start_matches('+').parse::<i8>().ok()?
+    };
+    // A month holds at most five of any weekday; this also keeps the
+    // `-ordinal - 1` arithmetic below in range.
+    if ordinal == 0 || !(-5..=5).contains(&ordinal) {
+        return None;
+    }
+    let weekday = match weekday {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+    let date = if ordinal > 0 {
+        NaiveDate::from_weekday_of_month_opt(year, month, weekday, ordinal as u8)?
+    } else {
+        let next_month = if month == 12 {
+            NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+        } else {
+            NaiveDate::from_ymd_opt(year, month + 1, 1)?
+        };
+        let mut day = next_month.pred_opt()?;
+        while day.weekday() != weekday {
+            day = day.pred_opt()?;
+        }
+        day.checked_sub_signed(Duration::weeks(i64::from(-ordinal - 1)))?
+    };
+    Some(date.and_time(time))
+}
+
+/// Parse `+HHMM`, `-HHMM`, or `+HHMMSS` into seconds east of UTC.
+fn parse_utc_offset(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (sign, digits) = match value.split_at_checked(1)? {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => return None,
+    };
+    if !matches!(digits.len(), 4 | 6) || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let hours = digits[0..2].parse::<i64>().ok()?;
+    let minutes = digits[2..4].parse::<i64>().ok()?;
+    let seconds = digits
+        .get(4..6)
+        .map_or(Some(0), |s| s.parse::<i64>().ok())?;
+    Some(sign * (hours * 3_600 + minutes * 60 + seconds))
+}
+
+/// Parse an RFC 5545 duration such as `PT1H30M`, `P1D`, or `P2W`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (negative, rest) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let rest = rest.strip_prefix('P')?;
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for ch in rest.chars() {
+        match ch {
+            '0'..='9' => number.push(ch),
+            'T' => in_time = true,
+            unit => {
+                let amount = number.parse::<i64>().ok()?;
+                number.clear();
+                let part = match (unit, in_time) {
+                    ('W', false) => Duration::try_weeks(amount),
+                    ('D', false) => Duration::try_days(amount),
+                    ('H', true) => Duration::try_hours(amount),
+                    ('M', true) => Duration::try_minutes(amount),
+                    ('S', true) => Duration::try_seconds(amount),
+                    _ => return None,
+                };
+                total = total.checked_add(&part?)?;
+            }
+        }
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(if negative { -total } else { total })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_calendar, parse_duration, yearly_onset};
+    use chrono::NaiveTime;
+
+    #[test]
+    fn parses_request_with_iana_timezone_and_attendees() {
+        let ics = concat!(
+            "BEGIN:VCALENDAR\r\n",
+            "METHOD:REQUEST\r\n",
+            "BEGIN:VEVENT\r\n",
+            "UID:abc-123@example.com\r\n",
+            "SEQUENCE:2\r\n",
+            "SUMMARY:Quarterly planning\\, Q4\r\n",
+            "LOCATION:Room 4\r\n",
+            "DTSTART;TZID=America/New_York:20261020T100000\r\n",
+            "DURATION:PT1H30M\r\n",
+            "RRULE:FREQ=WEEKLY;BYDAY=TU\r\n",
+            "ORGANIZER;CN=\"Lee, Pat\":mailto:pat@example.com\r\n",
+            "ATTENDEE;CN=Sam;PARTSTAT=ACCEPTED;ROLE=REQ-PARTICIPANT:mailto:sam@exa\r\n",
+            " mple.com\r\n",
+            "ATTENDEE;PARTSTAT=needs-action:MAILTO:kim@example.com\r\n",
+            "BEGIN:VALARM\r\n",
+            "SUMMARY:ignored\r\n",
+            "END:VALARM\r\n",
+            "END:VEVENT\r\n",
+            "END:VCALENDAR\r\n",
+        );
+        let events = parse_calendar(ics);
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.method.as_deref(), Some("REQUEST"));
+        assert_eq!(event.uid.as_deref(), Some("abc-123@example.com"));
+        assert_eq!(event.sequence, Some(2));
+        assert_eq!(event.summary.as_deref(), Some("Quarterly planning, Q4"));
+        assert_eq!(
+            event.recurrence_rule.as_deref(),
+            Some("FREQ=WEEKLY;BYDAY=TU")
+        );
+
+        let start = event.start.as_ref().expect("start");
+        assert_eq!(start.local, "2026-10-20T10:00:00");
+        assert_eq!(start.timezone.as_deref(), Some("America/New_York"));
+        assert_eq!(start.utc.as_deref(), Some("2026-10-20T14:00:00Z"));
+        let end = event.end.as_ref().expect("end from duration");
+        assert_eq!(end.utc.as_deref(), Some("2026-10-20T15:30:00Z"));
+
+        let organizer = event.organizer.as_ref().expect("organizer");
+        assert_eq!(organizer.name.as_deref(), Some("Lee, Pat"));
+        assert_eq!(organizer.email.as_deref(), Some("pat@example.com"));
+        assert_eq!(event.attendees.len(), 2);
+        assert_eq!(event.attendees[0].email.as_deref(), Some("sam@example.com"));
+        assert_eq!(event.attendees[0].partstat.as_deref(), Some("ACCEPTED"));
+        assert_eq!(event.attendees[1].partstat.as_deref(), Some("NEEDS-ACTION"));
+    }
+
+    #[test]
+    fn resolves_custom_vtimezone_and_all_day_cancel() {
+        let ics = concat!(
+            "BEGIN:VCALENDAR\r\n",
+            "METHOD:CANCEL\r\n",
+            "BEGIN:VTIMEZONE\r\n",
+            "TZID:Pacific Standard Time\r\n",
+            "BEGIN:STANDARD\r\n",
+            "DTSTART:16010101T020000\r\n",
+            "TZOFFSETTO:-0800\r\n",
+            "RRULE:FREQ=YEARLY;BYDAY=1SU;BYMONTH=11\r\n",
+            "END:STANDARD\r\n",
+            "BEGIN:DAYLIGHT\r\n",
+            "DTSTART:16010101T020000\r\n",
+            "TZOFFSETTO:-0700\r\n",
+            "RRULE:FREQ=YEARLY;BYDAY=2SU;BYMONTH=3\r\n",
+            "END:DAYLIGHT\r\n",
+            "END:VTIMEZONE\r\n",
+            "BEGIN:VEVENT\r\n",
+            "UID:w1\r\n",
+            "STATUS:CANCELLED\r\n",
+            "DTSTART;TZID=Pacific Standard Time:20260715T090000\r\n",
+            "DTEND;TZID=Pacific Standard Time:20261215T090000\r\n",
+            "END:VEVENT\r\n",
+            "BEGIN:VEVENT\r\n",
+            "UID:d1\r\n",
+            "DTSTART;VALUE=DATE:20261224\r\n",
+            "DURATION:P2D\r\n",
+            "END:VEVENT\r\n",
+            "END:VCALENDAR\r\n",
+        );
+        let events = parse_calendar(ics);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].method.as_deref(), Some("CANCEL"));
+        assert_eq!(events[0].status.as_deref(), Some("CANCELLED"));
+        let start = events[0].start.as_ref().expect("start");
+        assert_eq!(start.utc.as_deref(), Some("2026-07-15T16:00:00Z"));
+        let end = events[0].end.as_ref().expect("end");
+        assert_eq!(end.utc.as_deref(), Some("2026-12-15T17:00:00Z"));
+
+        let all_day = events[1].start.as_ref().expect("all-day start");
+        assert!(all_day.all_day);
+        assert_eq!(all_day.local, "2026-12-24");
+        assert_eq!(
+            events[1].end.as_ref().map(|end| end.local.as_str()),
+            Some("2026-12-26")
+        );
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("PT1H30M").map(|d| d.num_minutes()), Some(90));
+        assert_eq!(parse_duration("P1W").map(|d| d.num_days()), Some(7));
+        assert_eq!(parse_duration("-P1DT2H").map(|d| d.num_hours()), Some(-26));
+        assert!(parse_duration("1H").is_none());
+        assert!(parse_duration("PT5").is_none());
+        assert!(parse_duration("P99999999999999999W").is_none());
+        assert!(parse_duration("P9223372036854775807DT9223372036854775807S").is_none());
+    }
+
+    #[test]
+    fn rejects_out_of_range_byday_ordinals() {
+        let time = NaiveTime::from_hms_opt(2, 0, 0).unwrap();
+        assert!(yearly_onset("FREQ=YEARLY;BYMONTH=10;BYDAY=-128SU", 2024, time).is_none());
+        assert!(yearly_onset("FREQ=YEARLY;BYMONTH=10;BYDAY=6SU", 2024, time).is_none());
+        assert!(yearly_onset("FREQ=YEARLY;BYMONTH=10;BYDAY=0SU", 2024, time).is_none());
+        assert_eq!(
+            yearly_onset("FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU", 2024, time)
+                .map(|onset| onset.date().to_string()),
+            Some("2024-10-27".to_owned())
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Charset sniffing and fallback decoding
+//!
+//! Mail frequently declares no charset, declares `us-ascii` for 8-bit text, or
+//! labels UTF-8 as ISO-8859-1. Declared labels are trusted only while the bytes
+//! decode cleanly; otherwise the charset is detected with `chardetng`, which
+//! covers the common European and East Asian legacy encodings including
+//! ISO-2022-JP.
+
+use std::borrow::Cow;
+
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
+use encoding_rs::{
+    BIG5, EUC_JP, EUC_KR, Encoding, GB18030, GBK, ISO_2022_JP, SHIFT_JIS, UTF_8, UTF_16BE, UTF_16LE,
+};
+
+/// Escape byte that starts ISO-2022-JP shift sequences in otherwise 7-bit text
+const ESC: u8 = 0x1b;
+
+/// How text was decoded when the declared charset could not be used as-is,
+/// or when decoding replaced malformed sequences
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct CharsetReport {
+    /// Charset label from the message, if any
+    pub(super) declared: Option<String>,
+    /// Encoding the text was decoded with
+    pub(super) detected: &'static str,
+    /// Characters replaced with U+FFFD
+    pub(super) replaced_chars: usize,
+    /// Characters in the decoded text
+    pub(super) total_chars: usize,
+}
+
+/// Decode text bytes, sniffing the charset when the label is missing or wrong.
+///
+/// Returns a report when the text was decoded with something other than its
+/// declared charset or when malformed sequences were replaced.
+pub(super) fn decode(bytes: &[u8], declared: Option<&str>) -> (String, Option<CharsetReport>) {
+    let label = declared.map(str::trim).filter(|label| !label.is_empty());
+    if label.is_some_and(|label| label.eq_ignore_ascii_case("utf-7")) {
+        return (super::decode_charset(bytes, label), None);
+    }
+    let encoding = label
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .filter(|_| !label.is_some_and(is_ascii_label));
+    if is_plain_ascii(bytes) && encoding.is_none_or(Encoding::is_ascii_compatible) {
+        return (String::from_utf8_lossy(bytes).into_owned(), None);
+    }
+
+    let chosen = match encoding {
+        Some(encoding) if !is_multi_byte(encoding) && std::str::from_utf8(bytes).is_ok() => UTF_8,
+        Some(encoding) if mostly_replaced(encoding, bytes) => {
+            let sniffed = sniff(bytes);
+            if decodes_cleanly(sniffed, bytes) {
+                sniffed
+            } else {
+                encoding
+            }
+        }
+        Some(encoding) => encoding,
+        None => sniff(bytes),
+    };
+    let (text, _) = chosen.decode_without_bom_handling(bytes);
+    let text = text.into_owned();
+    let replaced_chars = text.chars().filter(|ch| *ch == '\u{FFFD}').count();
+    let fell_back = encoding != Some(chosen);
+    let report = (fell_back || replaced_chars > 0).then(|| CharsetReport {
+        declared: label.map(str::to_owned),
+        detected: chosen.name(),
+        replaced_chars,
+        total_chars: text.chars().count(),
+    });
+    (text, report)
+}
+
+/// Re-encode a header block as UTF-8 when it carries raw legacy-charset bytes.
+///
+/// Only the header section of `raw` is returned. Blocks that are already
+/// valid UTF-8 without ISO-2022-JP escapes are borrowed unchanged, so
+/// RFC 2047 encoded words and RFC 6532 UTF-8 headers parse as before.
+pub(super) fn utf8_header_block(raw: &[u8]) -> (Cow<'_, [u8]>, Option<CharsetReport>) {
+    let end = mailparse::parse_headers(raw).map_or(raw.len(), |(_, end)| end);
+    let block = &raw[..end];
+    if std::str::from_utf8(block).is_ok() && !block.contains(&ESC) {
+        return (Cow::Borrowed(block), None);
+    }
+    let (text, report) = decode(block, None);
+    (Cow::Owned(text.into_bytes()), report)
+}
+
+/// Decode a raw header value that is not valid UTF-8, or `None` when it is.
+pub(super) fn decode_raw_header(raw: &[u8]) -> Option<(String, Option<CharsetReport>)> {
+    (std::str::from_utf8(raw).is_err() || raw.contains(&ESC)).then(|| decode(raw, None))
+}
+
+fn sniff(bytes: &[u8]) -> &'static Encoding {
+    if std::str::from_utf8(bytes).is_ok() && !bytes.contains(&ESC) {
+        return UTF_8;
+    }
+    let mut detector = EncodingDetector::new(Iso2022JpDetection::Allow);
+    detector.feed(bytes, true);
+    detector.guess(None, Utf8Detection::Allow)
+}
+
+/// Whether most non-ASCII characters decoded with `encoding` are replacements
+///
+/// A few corrupt bytes in otherwise valid text keep the declared charset; a
+/// wrong label replaces nearly every non-ASCII character.
+fn mostly_replaced(encoding: &'static Encoding, bytes: &[u8]) -> bool {
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    let replaced = text.chars().filter(|ch| *ch == '\u{FFFD}').count();
+    let non_ascii = text.chars().filter(|ch| !ch.is_ascii()).count();
+    replaced * 2 > non_ascii
+}
+
+fn decodes_cleanly(encoding: &'static Encoding, bytes: &[u8]) -> bool {
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .is_some()
+}
+
+fn is_plain_ascii(bytes: &[u8]) -> bool {
+    bytes.is_ascii() && !bytes.contains(&ESC)
+}
+
+/// Labels that only promise 7-bit text, which 8-bit bytes prove wrong
+fn is_ascii_label(label: &str) -> bool {
+    ["us-ascii", "ascii", "ansi_x3.4-1968", "iso646-us"]
+        .iter()
+        .any(|ascii| label.eq_ignore_ascii_case(ascii))
+}
+
+fn is_multi_byte(encoding: &'static Encoding) -> bool {
+    [
+        SHIFT_JIS,
+        EUC_JP,
+        ISO_2022_JP,
+        EUC_KR,
+        GBK,
+        GB18030,
+        BIG5,
+        UTF_16LE,
+        UTF_16BE,
+    ]
+    .contains(&encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, utf8_header_block};
+
+    /// Tests that clean declared charsets decode without a report.
+    #[test]
+    fn trusts_clean_declared_charsets() {
+        let (text, report) = decode(b"caf\xe9", Some("iso-8859-1"));
+        assert_eq!(text, "café");
+        assert_eq!(report, None);
+
+        let (text, report) = decode(b"plain", None);
+        assert_eq!(text, "plain");
+        assert_eq!(report, None);
+    }
+
+    /// Tests that 8-bit text labelled ASCII and UTF-8 labelled Latin-1 are redetected.
+    #[test]
+    fn redetects_mislabelled_text() {
+        let (text, report) = decode(b"5\x80 \x93quoted\x94", Some("us-ascii"));
+        assert_eq!(text, "5€ “quoted”");
+        let report = report.expect("fallback is reported");
+        assert_eq!(report.declared.as_deref(), Some("us-ascii"));
+        assert_eq!(report.detected, "windows-1252");
+
+        let (text, report) = decode("Grüße".as_bytes(), Some("ISO-8859-1"));
+        assert_eq!(text, "Grüße");
+        assert_eq!(report.map(|report| report.detected), Some("UTF-8"));
+
+        let (text, report) = decode(b"caf\xe9 cr\xe8me br\xfbl\xe9e", Some("utf-8"));
+        assert_eq!(text, "café crème brûlée");
+        assert_eq!(report.map(|report| report.detected), Some("windows-1252"));
+    }
+
+    /// Tests that stray corrupt bytes keep the declared charset and are counted.
+    #[test]
+    fn counts_replacement_characters() {
+        let mut bytes = "Grüße aus Köln ".as_bytes().to_vec();
+        bytes.push(0xff);
+        let (text, report) = decode(&bytes, Some("utf-8"));
+        assert_eq!(text, "Grüße aus Köln \u{FFFD}");
+        let report = report.expect("replacements are reported");
+        assert_eq!(report.detected, "UTF-8");
+        assert_eq!(report.replaced_chars, 1);
+        assert_eq!(report.total_chars, 16);
+    }
+
+    /// Tests that raw Shift_JIS header bytes are re-encoded as UTF-8.
+    #[test]
+    fn reencodes_legacy_header_blocks() {
+        let mut raw = b"Subject: ".to_vec();
+        raw.extend_from_slice(b"\x89\xef\x8bc\x8e\x91\x97\xbf\x82\xcc\x82\xb2\x91\x97\x95t");
+        raw.extend_from_slice(b"\r\nFrom: a@example.com\r\n\r\nbody");
+        let (block, report) = utf8_header_block(&raw);
+        let block = std::str::from_utf8(&block).expect("block is UTF-8");
+        assert!(block.starts_with("Subject: 会議資料のご送付\r\n"));
+        assert!(!block.contains("body"));
+        assert_eq!(report.map(|report| report.detected), Some("Shift_JIS"));
+
+        let (block, report) = utf8_header_block(b"Subject: =?utf-8?q?hi?=\r\n\r\n");
+        assert_eq!(&*block, b"Subject: =?utf-8?q?hi?=\r\n\r\n");
+        assert_eq!(report, None);
+    }
+}
@@ -0,0 +1,935 @@
+//! Attachment text extractors
+//!
+//! Each supported format implements [`AttachmentExtractor`]; [`find_extractor`]
+//! picks the first registered extractor that accepts an attachment's content
+//! type or filename extension. Adding a format means implementing the trait and
+//! appending it to `EXTRACTORS`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Cursor, Read};
+
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, XmlVersion};
+use zip::ZipArchive;
+
+use crate::errors::{AppError, AppResult};
+
+/// Maximum decompressed bytes read from a single entry of an Office/ODF package.
+const MAX_PACKAGE_ENTRY_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Maximum decompressed bytes read across all entries of one Office/ODF package.
+const MAX_PACKAGE_TOTAL_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Maximum empty cells materialized for a repeated or skipped spreadsheet column.
+const MAX_FILLED_CELLS: usize = 256;
+
+/// Decoded attachment content handed to an extractor
+pub struct AttachmentContent<'a> {
+    /// Declared charset parameter, if any
+    pub charset: Option<&'a str>,
+    /// Attachment bytes with transfer encoding already removed
+    pub bytes: &'a [u8],
+}
+
+/// Converts one attachment format into plain text
+pub trait AttachmentExtractor: Sync {
+    /// Whether this extractor handles the given content type or extension
+    ///
+    /// `extension` is the lowercase filename extension without the dot.
+    fn supports(&self, content_type: &str, extension: Option<&str>) -> bool;
+
+    /// Extract plain text from the attachment
+    fn extract(&self, content: &AttachmentContent<'_>) -> AppResult<String>;
+}
+
+static EXTRACTORS: &[&dyn AttachmentExtractor] = &[
+    &PdfExtractor,
+    &DocxExtractor,
+    &XlsxExtractor,
+    &PptxExtractor,
+    &OpenDocumentExtractor,
+    &RtfExtractor,
+    &HtmlExtractor,
+    &PlainTextExtractor,
+];
+
+/// Find the extractor for an attachment, if its format is supported.
+pub fn find_extractor(
+    content_type: &str,
+    filename: Option<&str>,
+) -> Option<&'static dyn AttachmentExtractor> {
+    let extension = filename
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_ascii_lowercase());
+    EXTRACTORS
+        .iter()
+        .copied()
+        .find(|extractor| extractor.supports(content_type, extension.as_deref()))
+}
+
+struct PdfExtractor;
+
+impl AttachmentExtractor for PdfExtractor {
+    fn supports(&self, content_type: &str, extension: Option<&str>) -> bool {
+        content_type == "application/pdf" || extension == Some("pdf")
+    }
+
+    fn extract(&self, content: &AttachmentContent<'_>) -> AppResult<String> {
+        pdf_extract::extract_text_from_mem(content.bytes)
+            .map_err(|e| AppError::Internal(format!("failed to extract PDF text: {e}")))
+    }
+}
+
+struct DocxExtractor;
+
+impl AttachmentExtractor for DocxExtractor {
+    fn supports(&self, content_type: &str, extension: Option<&str>) -> bool {
+        content_type == "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+            || extension == Some("docx")
+    }
+
+    fn extract(&self, content: &AttachmentContent<'_>) -> AppResult<String> {
+        let mut package = open_package(content.bytes)?;
+        let xml = read_package_entry(&mut package, "word/document.xml")?;
+        ooxml_paragraph_text(&xml, "p")
+    }
+}
+
+struct PptxExtractor;
+
+impl AttachmentExtractor for PptxExtractor {
+    fn supports(&self, content_type: &str, extension: Option<&str>) -> bool {
+        content_type == "application/vnd.openxmlformats-officedocument.presentationml.presentation"
+            || extension == Some("pptx")
+    }
+
+    fn extract(&self, content: &AttachmentContent<'_>) -> AppResult<String> {
+        let mut package = open_package(content.bytes)?;
+        let mut slides = package
+            .archive
+            .file_names()
+            .filter_map(|name| {
+                let name = name.ok()?;
+                let number = name
+                    .strip_prefix("ppt/slides/slide")?
+                    .strip_suffix(".xml")?
+                    .parse::<u32>()
+                    .ok()?;
+                Some((number, name.into_owned()))
+            })
+            .collect::<Vec<_>>();
+        slides.sort_unstable();
+
+        let mut out = String::new();
+        for (number, name) in slides {
+            let xml = read_package_entry(&mut package, &name)?;
+            let text = ooxml_paragraph_text(&xml, "p")?;
+            push_section(&mut out, &format!("Slide {number}"), &text);
+        }
+        Ok(out)
+    }
+}
+
+struct XlsxExtractor;
+
+impl AttachmentExtractor for XlsxExtractor {
+    fn supports(&self, content_type: &str, extension: Option<&str>) -> bool {
+        content_type == "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+            || extension == Some("xlsx")
+    }
+
+    fn extract(&self, content: &AttachmentContent<'_>) -> AppResult<String> {
+        let mut package = open_package(content.bytes)?;
+        let shared_strings = if package
+            .archive
+            .index_for_name("xl/sharedStrings.xml")
+            .is_some()
+        {
+            xlsx_shared_strings(&read_package_entry(&mut package, "xl/sharedStrings.xml")?)?
+        } else {
+            Vec::new()
+        };
+        let workbook = read_package_entry(&mut package, "xl/workbook.xml")?;
+        let rels = read_package_entry(&mut package, "xl/_rels/workbook.xml.rels")?;
+        let targets = relationship_targets(&rels)?;
+
+        let mut out = String::new();
+        for (sheet_name, rel_id) in xlsx_sheets(&workbook)? {
+            let Some(target) = targets.get(&rel_id) else {
+                continue;
+            };
+            let path = match target.strip_prefix('/') {
+                Some(absolute) => absolute.to_owned(),
+                None => format!("xl/{target}"),
+            };
+            let xml = read_package_entry(&mut package, &path)?;
+            let rows = xlsx_sheet_rows(&xml, &shared_strings)?;
+            push_section(&mut out, &sheet_name, &rows);
+        }
+        Ok(out)
+    }
+}
+
+struct OpenDocumentExtractor;
+
+impl AttachmentExtractor for OpenDocumentExtractor {
+    fn supports(&self, content_type: &str, extension: Option<&str>) -> bool {
+        content_type.starts_with("application/vnd.oasis.opendocument.")
+            || matches!(extension, Some("odt" | "ods" | "odp"))
+    }
+
+    fn extract(&self, content: &AttachmentContent<'_>) -> AppResult<String> {
+        let mut package = open_package(content.bytes)?;
+        let xml = read_package_entry(&mut package, "content.xml")?;
+        opendocument_text(&xml)
+    }
+}
+
+struct RtfExtractor;
+
+impl AttachmentExtractor for RtfExtractor {
+    fn supports(&self, content_type: &str, extension: Option<&str>) -> bool {
+        matches!(content_type, "application/rtf" | "text/rtf") || extension == Some("rtf")
+    }
+
+    fn extract(&self, content: &AttachmentContent<'_>) -> AppResult<String> {
+        rtf_text(content.bytes)
+    }
+}
+
+struct HtmlExtractor;
+
+impl AttachmentExtractor for HtmlExtractor {
+    fn supports(&self, content_type: &str, extension: Option<&str>) -> bool {
+        content_type == "text/html" || matches!(extension, Some("html" | "htm"))
+    }
+
+    fn extract(&self, content: &AttachmentContent<'_>) -> AppResult<String> {
+        let html = super::charset::decode(content.bytes, content.charset).0;
+        Ok(super::html_to_text(&ammonia::clean(&html)).unwrap_or_default())
+    }
+}
+
+/// CSV, JSON, XML and any other `text/*` attachment, decoded as text.
+struct PlainTextExtractor;
+
+impl AttachmentExtractor for PlainTextExtractor {
+    fn supports(&self, content_type: &str, extension: Option<&str>) -> bool {
+        content_type.starts_with("text/")
+            || matches!(
+                content_type,
+                "application/json" | "application/xml" | "application/csv"
+            )
+            || content_type.ends_with("+json")
+            || content_type.ends_with("+xml")
+            || matches!(
+                extension,
+                Some("txt" | "log" | "csv" | "tsv" | "json" | "xml" | "md" | "yaml" | "yml")
+            )
+    }
+
+    fn extract(&self, content: &AttachmentContent<'_>) -> AppResult<String> {
+        Ok(super::charset::decode(content.bytes, content.charset).0)
+    }
+}
+
+/// An opened Office/ODF package with its remaining decompression budget.
+struct Package<'a> {
+    archive: ZipArchive<Cursor<&'a [u8]>>,
+    remaining: u64,
+}
+
+fn open_package(bytes: &[u8]) -> AppResult<Package<'_>> {
+    let archive = ZipArchive::new(Cursor::new(bytes))
+        .map_err(|e| AppError::Internal(format!("failed to open document package: {e}")))?;
+    Ok(Package {
+        archive,
+        remaining: MAX_PACKAGE_TOTAL_BYTES,
+    })
+}
+
+/// Read one entry, charging its decompressed size to the package budget.
+fn read_package_entry(package: &mut Package<'_>, name: &str) -> AppResult<String> {
+    let entry = package
+        .archive
+        .by_name(name)
+        .map_err(|e| AppError::Internal(format!("document package entry {name}: {e}")))?;
+    let limit = MAX_PACKAGE_ENTRY_BYTES.min(package.remaining);
+    let mut buf = Vec::new();
+    entry
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut buf)
+        .map_err(|e| AppError::Internal(format!("failed to read document entry {name}: {e}")))?;
+    let read = buf.len() as u64;
+    if read > limit {
+        package.remaining = 0;
+        return Err(AppError::Internal(format!(
+            "document package entry {name} exceeds the decompression limit \
+             ({MAX_PACKAGE_ENTRY_BYTES} bytes per entry, {MAX_PACKAGE_TOTAL_BYTES} per package)"
+        )));
+    }
+    package.remaining -= read;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Append a titled block, separated from earlier blocks by a blank line.
+fn push_section(out: &mut String, title: &str, body: &str) {
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out.push_str(&format!("[{title}]\n"));
+    out.push_str(body.trim_end());
+    out.push('\n');
+}
+
+/// Simplified XML event used by the format walkers.
+enum XmlNode<'a> {
+    Open(&'a BytesStart<'a>),
+    Close(&'a str),
+    Text(&'a str),
+}
+
+/// Stream an XML document, reporting element boundaries by local name and
+/// text with predefined and character entities resolved.
+fn walk_xml(xml: &str, mut visit: impl FnMut(XmlNode<'_>)) -> AppResult<()> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| AppError::Internal(format!("malformed document XML: {e}")))?;
+        match event {
+            Event::Start(start) => visit(XmlNode::Open(&start)),
+            Event::Empty(start) => {
+                visit(XmlNode::Open(&start));
+                visit(XmlNode::Close(start.local_name().as_ref()));
+            }
+            Event::End(end) => visit(XmlNode::Close(end.local_name().as_ref())),
+            Event::Text(text) => visit(XmlNode::Text(&text.xml10_content())),
+            Event::CData(data) => visit(XmlNode::Text(&data)),
+            Event::GeneralRef(reference) => {
+                let resolved = match reference.resolve_char_ref() {
+                    Ok(Some(ch)) => Some(ch.to_string()),
+                    _ => resolve_predefined_entity(&reference).map(str::to_owned),
+                };
+                if let Some(resolved) = resolved {
+                    visit(XmlNode::Text(&resolved));
+                }
+            }
+            Event::Eof => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+fn local_name<'a>(start: &'a BytesStart<'_>) -> &'a str {
+    let name = start.name().into_inner();
+    name.rsplit_once(':').map_or(name, |(_, local)| local)
+}
+
+fn attribute(start: &BytesStart<'_>, local: &str) -> Option<String> {
+    start.attributes().flatten().find_map(|attr| {
+        (attr.key.local_name().as_ref() == local)
+            .then(|| {
+                attr.normalized_value(XmlVersion::Implicit1_0)
+                    .ok()
+                    .map(|value| value.into_owned())
+            })
+            .flatten()
+    })
+}
+
+/// Collect `<t>` run text from WordprocessingML/DrawingML, one line per
+/// `paragraph` element.
+fn ooxml_paragraph_text(xml: &str, paragraph: &str) -> AppResult<String> {
+    let mut out = String::new();
+    let mut in_text = false;
+    walk_xml(xml, |node| match node {
+        XmlNode::Open(start) => match local_name(start) {
+            "t" => in_text = true,
+            "tab" => out.push('\t'),
+            "br" | "cr" => out.push('\n'),
+            _ => {}
+        },
+        XmlNode::Close(name) => {
+            if name == "t" {
+                in_text = false;
+            } else if name == paragraph {
+                out.push('\n');
+            }
+        }
+        XmlNode::Text(text) if in_text => out.push_str(text),
+        XmlNode::Text(_) => {}
+    })?;
+    Ok(out)
+}
+
+/// Map relationship ids to their targets from a `.rels` part.
+fn relationship_targets(xml: &str) -> AppResult<HashMap<String, String>> {
+    let mut targets = HashMap::new();
+    walk_xml(xml, |node| {
+        if let XmlNode::Open(start) = node
+            && local_name(start) == "Relationship"
+            && let (Some(id), Some(target)) = (attribute(start, "Id"), attribute(start, "Target"))
+        {
+            targets.insert(id, target);
+        }
+    })?;
+    Ok(targets)
+}
+
+/// Sheet names and relationship ids in workbook order.
+fn xlsx_sheets(xml: &str) -> AppResult<Vec<(String, String)>> {
+    let mut sheets = Vec::new();
+    walk_xml(xml, |node| {
+        if let XmlNode::Open(start) = node
+            && local_name(start) == "sheet"
+            && let (Some(name), Some(id)) = (attribute(start, "name"), attribute(start, "id"))
+        {
+            sheets.push((name, id));
+        }
+    })?;
+    Ok(sheets)
+}
+
+fn xlsx_shared_strings(xml: &str) -> AppResult<Vec<String>> {
+    let mut strings = Vec::new();
+    let mut current = String::new();
+    let mut in_text = false;
+    let mut in_phonetic = false;
+    walk_xml(xml, |node| match node {
+        XmlNode::Open(start) => match local_name(start) {
+            "si" => current.clear(),
+            "t" => in_text = true,
+            "rPh" => in_phonetic = true,
+            _ => {}
+        },
+        XmlNode::Close(name) => match name {
+            "si" => strings.push(std::mem::take(&mut current)),
+            "t" => in_text = false,
+            "rPh" => in_phonetic = false,
+            _ => {}
+        },
+        XmlNode::Text(text) if in_text && !in_phonetic => current.push_str(text),
+        XmlNode::Text(_) => {}
+    })?;
+    Ok(strings)
+}
+
+/// Render worksheet cells as CSV-like rows, placing values by column reference.
+fn xlsx_sheet_rows(xml: &str, shared_strings: &[String]) -> AppResult<String> {
+    let mut out = String::new();
+    let mut row: BTreeMap<usize, String> = BTreeMap::new();
+    let mut cell_column = 0usize;
+    let mut cell_type = String::new();
+    let mut value = String::new();
+    let mut capture = false;
+    walk_xml(xml, |node| match node {
+        XmlNode::Open(start) => match local_name(start) {
+            "row" => row.clear(),
+            "c" => {
+                cell_column = attribute(start, "r")
+                    .and_then(|reference| column_index(&reference))
+                    .unwrap_or_else(|| row.keys().next_back().map_or(0, |last| last + 1));
+                cell_type = attribute(start, "t").unwrap_or_default();
+                value.clear();
+            }
+            "v" | "t" => capture = true,
+            _ => {}
+        },
+        XmlNode::Close(name) => match name {
+            "v" | "t" => capture = false,
+            "c" => {
+                let resolved = match cell_type.as_str() {
+                    "s" => value
+                        .trim()
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|index| shared_strings.get(index).cloned())
+                        .unwrap_or_default(),
+                    "b" => if value.trim() == "1" { "TRUE" } else { "FALSE" }.to_owned(),
+                    _ => std::mem::take(&mut value),
+                };
+                if !resolved.is_empty() {
+                    row.insert(cell_column, resolved);
+                }
+            }
+            "row" => push_csv_row(&mut out, &row),
+            _ => {}
+        },
+        XmlNode::Text(text) if capture => value.push_str(text),
+        XmlNode::Text(_) => {}
+    })?;
+    Ok(out)
+}
+
+/// Convert an A1-style cell reference to a zero-based column index.
+fn column_index(reference: &str) -> Option<usize> {
+    let letters = reference
+        .chars()
+        .take_while(char::is_ascii_alphabetic)
+        .collect::<String>();
+    if letters.is_empty() {
+        return None;
+    }
+    let index = letters.chars().fold(0usize, |acc, ch| {
+        acc.saturating_mul(26)
+            .saturating_add((ch.to_ascii_uppercase() as usize) - ('A' as usize) + 1)
+    });
+    Some(index - 1)
+}
+
+/// Append one CSV row; a run of more than `MAX_FILLED_CELLS` empty cells
+/// between values collapses into a single `[N empty cells]` field.
+fn push_csv_row(out: &mut String, row: &BTreeMap<usize, String>) {
+    if row.is_empty() {
+        return;
+    }
+    let mut cells = Vec::new();
+    let mut next = 0usize;
+    for (&column, value) in row {
+        let gap = column - next;
+        if gap > MAX_FILLED_CELLS {
+            cells.push(format!("[{gap} empty cells]"));
+        } else {
+            cells.extend(std::iter::repeat_n(String::new(), gap));
+        }
+        cells.push(csv_field(value));
+        next = column + 1;
+    }
+    out.push_str(&cells.join(","));
+    out.push('\n');
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Extract OpenDocument `content.xml` text; spreadsheet tables become
+/// CSV-like rows under a `[table name]` heading.
+fn opendocument_text(xml: &str) -> AppResult<String> {
+    let mut out = String::new();
+    let mut row: BTreeMap<usize, String> = BTreeMap::new();
+    let mut column = 0usize;
+    let mut cell = String::new();
+    let mut cell_repeat = 1usize;
+    let mut cell_depth = 0usize;
+    walk_xml(xml, |node| match node {
+        XmlNode::Open(start) => match local_name(start) {
+            "table" => {
+                let name = attribute(start, "name").unwrap_or_else(|| "Table".to_owned());
+                if !out.is_empty() && !out.ends_with("\n\n") {
+                    out.push('\n');
+                }
+                out.push_str(&format!("[{name}]\n"));
+            }
+            "table-row" => {
+                row.clear();
+                column = 0;
+            }
+            "table-cell" | "covered-table-cell" => {
+                cell_depth += 1;
+                cell.clear();
+                cell_repeat = attribute(start, "number-columns-repeated")
+                    .and_then(|value| value.parse::<usize>().ok())
+                    .unwrap_or(1)
+                    .clamp(1, MAX_FILLED_CELLS);
+            }
+            "s" => push_text(&mut out, &mut cell, cell_depth, " "),
+            "tab" => push_text(&mut out, &mut cell, cell_depth, "\t"),
+            "line-break" => push_text(&mut out, &mut cell, cell_depth, "\n"),
+            _ => {}
+        },
+        XmlNode::Close(name) => match name {
+            "table-cell" | "covered-table-cell" => {
+                cell_depth = cell_depth.saturating_sub(1);
+                let value = cell.trim().to_owned();
+                for _ in 0..cell_repeat {
+                    if !value.is_empty() {
+                        row.insert(column, value.clone());
+                    }
+                    column += 1;
+                }
+            }
+            "table-row" => push_csv_row(&mut out, &row),
+            "p" | "h" if cell_depth > 0 => cell.push('\n'),
+            "p" | "h" => out.push('\n'),
+            _ => {}
+        },
+        XmlNode::Text(text) => push_text(&mut out, &mut cell, cell_depth, text),
+    })?;
+    Ok(out)
+}
+
+fn push_text(out: &mut String, cell: &mut String, cell_depth: usize, text: &str) {
+    if cell_depth > 0 {
+        cell.push_str(text);
+    } else {
+        out.push_str(text);
+    }
+}
+
+/// Destinations whose content is formatting or metadata rather than text.
+const RTF_SKIPPED_DESTINATIONS: [&str; 10] = [
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "header",
+    "footer",
+    "listtable",
+    "listoverridetable",
+    "object",
+];
+
+/// Strip RTF control words and groups, keeping visible text.
+pub(super) fn rtf_text(bytes: &[u8]) -> AppResult<String> {
+    if !bytes.starts_with(b"{\\rtf") {
+        return Err(AppError::Internal("not an RTF document".to_owned()));
+    }
+    let mut out = String::new();
+    // Per-group flag: whether the group's content is skipped.
+    let mut skip_stack = vec![false];
+    let mut unicode_skip = 0usize;
+    let mut i = 0usize;
+    while i < bytes.len() {
+        let skipping = skip_stack.last().copied().unwrap_or(false);
+        match bytes[i] {
+            b'{' => {
+                skip_stack.push(skipping);
+                i += 1;
+            }
+            b'}' => {
+                skip_stack.pop();
+                if skip_stack.is_empty() {
+                    break;
+                }
+                i += 1;
+            }
+            b'\\' => {
+                i += 1;
+                let Some(&next) = bytes.get(i) else {
+                    break;
+                };
+                if next.is_ascii_alphabetic() {
+                    let word_start = i;
+                    while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+                        i += 1;
+                    }
+                    let word = String::from_utf8_lossy(&bytes[word_start..i]).into_owned();
+                    let param_start = i;
+                    if i < bytes.len() && bytes[i] == b'-' {
+                        i += 1;
+                    }
+                    while i < bytes.len() && bytes[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                    let param = std::str::from_utf8(&bytes[param_start..i])
+                        .ok()
+                        .and_then(|value| value.parse::<i32>().ok());
+                    if i < bytes.len() && bytes[i] == b' ' {
+                        i += 1;
+                    }
+                    if RTF_SKIPPED_DESTINATIONS.contains(&word.as_str()) {
+                        if let Some(top) = skip_stack.last_mut() {
+                            *top = true;
+                        }
+                        continue;
+                    }
+                    if skipping {
+                        continue;
+                    }
+                    match word.as_str() {
+                        "par" | "line" | "row" => out.push('\n'),
+                        "tab" | "cell" => out.push('\t'),
+                        "u" => {
+                            if let Some(ch) = param
+                                .map(|value| if value < 0 { value + 65_536 } else { value })
+                                .and_then(|value| char::from_u32(value as u32))
+                            {
+                                out.push(ch);
+                            }
+                            unicode_skip = 1;
+                        }
+                        _ => {}
+                    }
+                } else {
+                    i += 1;
+                    match next {
+                        b'*' => {
+                            if let Some(top) = skip_stack.last_mut() {
+                                *top = true;
+                            }
+                        }
+                        b'\'' => {
+                            let hex = bytes.get(i..i + 2).and_then(|pair| {
+                                u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()
+                            });
+                            i += 2;
+                            if let Some(byte) = hex
+                                && !skipping
+                            {
+                                if unicode_skip > 0 {
+                                    unicode_skip -= 1;
+                                } else {
+                                    out.push(char::from(byte));
+                                }
+                            }
+                        }
+                        b'~' if !skipping => out.push('\u{00A0}'),
+                        b'\\' | b'{' | b'}' if !skipping => out.push(char::from(next)),
+                        _ => {}
+                    }
+                }
+            }
+            b'\r' | b'\n' => i += 1,
+            byte => {
+                if !skipping {
+                    if unicode_skip > 0 {
+                        unicode_skip -= 1;
+                    } else {
+                        out.push(char::from(byte));
+                    }
+                }
+                i += 1;
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::write::{SimpleFileOptions, ZipWriter};
+
+    use super::{
+        AttachmentContent, MAX_PACKAGE_ENTRY_BYTES, MAX_PACKAGE_TOTAL_BYTES, column_index,
+        find_extractor, rtf_text,
+    };
+
+    fn package(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, body) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .expect("start zip entry");
+            writer.write_all(body.as_bytes()).expect("write zip entry");
+        }
+        writer.finish().expect("finish zip").into_inner()
+    }
+
+    fn extract(content_type: &str, filename: &str, bytes: &[u8]) -> String {
+        find_extractor(content_type, Some(filename))
+            .expect("format should be supported")
+            .extract(&AttachmentContent {
+                charset: None,
+                bytes,
+            })
+            .expect("extraction should succeed")
+    }
+
+    #[test]
+    fn selects_extractors_by_type_or_extension() {
+        assert!(find_extractor("application/octet-stream", Some("report.DOCX")).is_some());
+        assert!(find_extractor("text/csv", None).is_some());
+        assert!(find_extractor("application/json", None).is_some());
+        assert!(find_extractor("image/png", Some("photo.png")).is_none());
+    }
+
+    #[test]
+    fn extracts_docx_paragraphs() {
+        let docx = package(&[(
+            "word/document.xml",
+            r#"<w:document xmlns:w="w"><w:body><w:p><w:r><w:t>Q3 &amp; Q4</w:t></w:r><w:r><w:tab/><w:t xml:space="preserve"> plan</w:t></w:r></w:p><w:p><w:r><w:t>Next</w:t></w:r></w:p></w:body></w:document>"#,
+        )]);
+        assert_eq!(
+            extract("application/octet-stream", "plan.docx", &docx),
+            "Q3 & Q4\t plan\nNext\n"
+        );
+    }
+
+    #[test]
+    fn extracts_xlsx_rows_with_shared_strings() {
+        let xlsx = package(&[
+            (
+                "xl/workbook.xml",
+                r#"<workbook xmlns:r="r"><sheets><sheet name="Totals" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                r#"<Relationships><Relationship Id="rId1" Target="worksheets/sheet1.xml"/></Relationships>"#,
+            ),
+            (
+                "xl/sharedStrings.xml",
+                r#"<sst><si><t>Item</t></si><si><t>Cost, USD</t></si><si><r><t>Wid</t></r><r><t>get</t></r></si></sst>"#,
+            ),
+            (
+                "xl/worksheets/sheet1.xml",
+                r#"<worksheet><sheetData><row r="1"><c r="A1" t="s"><v>0</v></c><c r="B1" t="s"><v>1</v></c></row><row r="2"><c r="A2" t="s"><v>2</v></c><c r="C2"><v>12.5</v></c></row></sheetData></worksheet>"#,
+            ),
+        ]);
+        assert_eq!(
+            extract(
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+                "costs.xlsx",
+                &xlsx
+            ),
+            "[Totals]\nItem,\"Cost, USD\"\nWidget,,12.5\n"
+        );
+    }
+
+    #[test]
+    fn keeps_sparse_xlsx_cells_past_the_filler_limit() {
+        let xlsx = package(&[
+            (
+                "xl/workbook.xml",
+                r#"<workbook xmlns:r="r"><sheets><sheet name="Wide" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                r#"<Relationships><Relationship Id="rId1" Target="worksheets/sheet1.xml"/></Relationships>"#,
+            ),
+            (
+                "xl/worksheets/sheet1.xml",
+                r#"<worksheet><sheetData><row r="1"><c r="A1"><v>1</v></c><c r="C1"><v>2</v></c><c r="XFD1"><v>3</v></c></row></sheetData></worksheet>"#,
+            ),
+        ]);
+        assert_eq!(
+            extract("application/octet-stream", "wide.xlsx", &xlsx),
+            "[Wide]\n1,,2,[16380 empty cells],3\n"
+        );
+    }
+
+    #[test]
+    fn reports_unreadable_xlsx_shared_strings() {
+        let mut writer = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        writer
+            .start_file("xl/sharedStrings.xml", SimpleFileOptions::default())
+            .expect("start zip entry");
+        let filler = vec![b' '; 1024 * 1024];
+        for _ in 0..=MAX_PACKAGE_ENTRY_BYTES / filler.len() as u64 {
+            writer.write_all(&filler).expect("write zip entry");
+        }
+        let xlsx = writer.finish().expect("finish zip").into_inner();
+
+        let error = find_extractor("application/octet-stream", Some("bomb.xlsx"))
+            .expect("xlsx is supported")
+            .extract(&AttachmentContent {
+                charset: None,
+                bytes: &xlsx,
+            })
+            .expect_err("oversized shared strings should fail");
+        assert!(error.to_string().contains("decompression limit"));
+    }
+
+    #[test]
+    fn extracts_ods_tables_and_odt_paragraphs() {
+        let ods = package(&[(
+            "content.xml",
+            r#"<office:document-content xmlns:office="o" xmlns:table="t" xmlns:text="x"><office:body><office:spreadsheet><table:table table:name="Q1"><table:table-row><table:table-cell><text:p>a</text:p></table:table-cell><table:table-cell table:number-columns-repeated="2"/><table:table-cell><text:p>b</text:p></table:table-cell></table:table-row></table:table></office:spreadsheet></office:body></office:document-content>"#,
+        )]);
+        assert_eq!(
+            extract(
+                "application/vnd.oasis.opendocument.spreadsheet",
+                "q.ods",
+                &ods
+            ),
+            "[Q1]\na,,,b\n"
+        );
+
+        let odt = package(&[(
+            "content.xml",
+            r#"<office:document-content xmlns:office="o" xmlns:text="x"><office:body><office:text><text:h>Title</text:h><text:p>one<text:s/>two</text:p></office:text></office:body></office:document-content>"#,
+        )]);
+        assert_eq!(
+            extract("application/vnd.oasis.opendocument.text", "n.odt", &odt),
+            "Title\none two\n"
+        );
+    }
+
+    #[test]
+    fn extracts_pptx_slides_in_order() {
+        let pptx = package(&[
+            (
+                "ppt/slides/slide10.xml",
+                r#"<p:sld xmlns:a="a" xmlns:p="p"><a:p><a:r><a:t>Last</a:t></a:r></a:p></p:sld>"#,
+            ),
+            (
+                "ppt/slides/slide2.xml",
+                r#"<p:sld xmlns:a="a" xmlns:p="p"><a:p><a:r><a:t>First</a:t></a:r></a:p></p:sld>"#,
+            ),
+        ]);
+        assert_eq!(
+            extract("application/octet-stream", "deck.pptx", &pptx),
+            "[Slide 2]\nFirst\n\n[Slide 10]\nLast\n"
+        );
+    }
+
+    #[test]
+    fn stops_reading_a_package_past_its_total_decompressed_budget() {
+        let slides = MAX_PACKAGE_TOTAL_BYTES / MAX_PACKAGE_ENTRY_BYTES + 1;
+        let filler = vec![b' '; 1024 * 1024];
+        let mut writer = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for number in 1..=slides {
+            writer
+                .start_file(
+                    format!("ppt/slides/slide{number}.xml"),
+                    SimpleFileOptions::default(),
+                )
+                .expect("start zip entry");
+            for _ in 0..MAX_PACKAGE_ENTRY_BYTES / filler.len() as u64 {
+                writer.write_all(&filler).expect("write zip entry");
+            }
+        }
+        let pptx = writer.finish().expect("finish zip").into_inner();
+        assert!(
+            pptx.len() < 1024 * 1024,
+            "test package should be a small bomb"
+        );
+
+        let error = find_extractor("application/octet-stream", Some("bomb.pptx"))
+            .expect("pptx is supported")
+            .extract(&AttachmentContent {
+                charset: None,
+                bytes: &pptx,
+            })
+            .expect_err("package over the total budget should fail");
+        assert!(error.to_string().contains("decompression limit"));
+    }
+
+    #[test]
+    fn strips_rtf_markup() {
+        let rtf = br"{\rtf1\ansi{\fonttbl{\f0 Arial;}}{\*\generator Word;}\f0 Caf\'e9 \b bold\b0\par Line\u8364?two}";
+        assert_eq!(
+            rtf_text(rtf).expect("rtf parses"),
+            "Caf\u{e9} bold\nLine\u{20ac}two"
+        );
+        assert!(rtf_text(b"plain").is_err());
+    }
+
+    #[test]
+    fn decodes_text_attachments_with_charset() {
+        let text = find_extractor("text/csv", Some("data.csv"))
+            .expect("csv supported")
+            .extract(&AttachmentContent {
+                charset: Some("iso-8859-1"),
+                bytes: b"name\nJos\xe9\n",
+            })
+            .expect("csv decodes");
+        assert_eq!(text, "name\nJosé\n");
+    }
+
+    #[test]
+    fn converts_column_references() {
+        assert_eq!(column_index("A1"), Some(0));
+        assert_eq!(column_index("AB12"), Some(27));
+        assert_eq!(column_index("12"), None);
+    }
+}
@@ -0,0 +1,318 @@
+//! Link and remote image inventory for HTML bodies
+//!
+//! Lists anchors with their target and visible-text domains and remote images
+//! with likely tracking pixels flagged. The HTML is normalized with `ammonia`
+//! first so the scan only sees well-formed, double-quoted markup.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::models::{MessageLink, RemoteImage};
+
+use super::authentication::organizational_domain;
+use super::truncate_chars;
+
+/// Maximum links listed per message.
+pub const MAX_LINKS: usize = 200;
+
+/// Maximum remote images listed per message.
+pub const MAX_REMOTE_IMAGES: usize = 100;
+
+/// Maximum characters kept from an `href` or `src`.
+const MAX_URL_CHARS: usize = 2048;
+
+/// Hosts that serve open-tracking images, matched as a domain suffix
+const TRACKER_DOMAINS: &[&str] = &[
+    "awstrack.me",
+    "bananatag.com",
+    "convertkit-mail.com",
+    "doubleclick.net",
+    "exct.net",
+    "getnotify.com",
+    "google-analytics.com",
+    "list-manage.com",
+    "mailstat.us",
+    "mailtrack.io",
+    "mandrillapp.com",
+    "r.superhuman.com",
+    "sendgrid.net",
+    "sparkpostmail.com",
+    "t.yesware.com",
+    "track.hubspot.com",
+];
+
+/// Links and remote images found in one HTML body
+#[derive(Debug, Default)]
+pub(super) struct LinkInventory {
+    pub(super) links: Vec<MessageLink>,
+    pub(super) remote_images: Vec<RemoteImage>,
+    /// Whether links or images beyond the limits were dropped
+    pub(super) truncated: bool,
+}
+
+/// Collect anchors and remote images from an HTML body in document order.
+pub(super) fn inventory(html: &str) -> LinkInventory {
+    static ANCHOR: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(?s)<a\b([^>]*)>(.*?)</a>").expect("static regex is valid"));
+    static IMAGE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"<img\b([^>]*)>").expect("static regex is valid"));
+
+    let normalized = ammonia::Builder::default()
+        .link_rel(None)
+        .add_tag_attributes("img", ["style"])
+        .clean(html)
+        .to_string();
+    let mut inventory = LinkInventory::default();
+
+    for captures in ANCHOR.captures_iter(&normalized) {
+        let attrs = captures.get(1).map_or("", |m| m.as_str());
+        let Some(href) = attribute(attrs, "href").filter(|href| !href.is_empty()) else {
+            continue;
+        };
+        if inventory.links.len() >= MAX_LINKS {
+            inventory.truncated = true;
+            break;
+        }
+        let text = captures
+            .get(2)
+            .map(|m| visible_text(m.as_str()))
+            .filter(|text| !text.is_empty());
+        let domain = url_domain(&href);
+        let text_domain = text.as_deref().and_then(text_domain);
+        let domain_mismatch = match (&domain, &text_domain) {
+            (Some(domain), Some(text_domain)) => {
+                organizational_domain(domain) != organizational_domain(text_domain)
+            }
+            _ => false,
+        };
+        inventory.links.push(MessageLink {
+            href: truncate_chars(href, MAX_URL_CHARS),
+            text,
+            domain,
+            text_domain,
+            domain_mismatch,
+        });
+    }
+
+    for captures in IMAGE.captures_iter(&normalized) {
+        let attrs = captures.get(1).map_or("", |m| m.as_str());
+        let Some(src) = attribute(attrs, "src").filter(|src| is_remote(src)) else {
+            continue;
+        };
+        if inventory.remote_images.len() >= MAX_REMOTE_IMAGES {
+            inventory.truncated = true;
+            break;
+        }
+        inventory.remote_images.push(remote_image(src, attrs));
+    }
+    inventory
+}
+
+fn remote_image(src: String, attrs: &str) -> RemoteImage {
+    let style = attribute(attrs, "style")
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+        .replace(char::is_whitespace, "");
+    let width = attribute(attrs, "width")
+        .and_then(|value| leading_pixels(&value))
+        .or_else(|| style_pixels(&style, "width"));
+    let height = attribute(attrs, "height")
+        .and_then(|value| leading_pixels(&value))
+        .or_else(|| style_pixels(&style, "height"));
+    let domain = url_domain(&src);
+
+    let mut tracking_reasons = Vec::new();
+    let tiny = matches!((width, height), (Some(w), Some(h)) if w <= 1 && h <= 1)
+        || width == Some(0)
+        || height == Some(0);
+    if tiny {
+        tracking_reasons.push("tiny".to_owned());
+    }
+    if style.contains("display:none")
+        || style.contains("visibility:hidden")
+        || style.contains("opacity:0;")
+        || style.ends_with("opacity:0")
+    {
+        tracking_reasons.push("hidden".to_owned());
+    }
+    if domain.as_deref().is_some_and(is_tracker_domain) {
+        tracking_reasons.push("known_tracker".to_owned());
+    }
+
+    RemoteImage {
+        src: truncate_chars(src, MAX_URL_CHARS),
+        domain,
+        alt: attribute(attrs, "alt").filter(|alt| !alt.trim().is_empty()),
+        width,
+        height,
+        tracking_pixel: !tracking_reasons.is_empty(),
+        tracking_reasons,
+    }
+}
+
+/// Read a double-quoted attribute from serialized `ammonia` output.
+fn attribute(attrs: &str, name: &str) -> Option<String> {
+    static ATTRIBUTE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r#"([A-Za-z][A-Za-z0-9-]*)="([^"]*)""#).expect("static regex is valid")
+    });
+
+    ATTRIBUTE
+        .captures_iter(attrs)
+        .find(|captures| captures[1].eq_ignore_ascii_case(name))
+        .map(|captures| decode_entities(captures[2].trim()))
+}
+
+/// Strip markup from anchor content and collapse whitespace.
+fn visible_text(inner_html: &str) -> String {
+    static TAG: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"<[^>]*>").expect("static regex is valid"));
+
+    let text = decode_entities(&TAG.replace_all(inner_html, " "));
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Undo the escaping `html5ever` applies when serializing text and attributes.
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+fn is_remote(src: &str) -> bool {
+    let lower = src.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("//")
+}
+
+/// Host of an absolute or protocol-relative URL, or the domain of a `mailto:` address
+///
+/// Userinfo is dropped, so `https://bank.example@evil.example/` yields
+/// `evil.example`.
+fn url_domain(url: &str) -> Option<String> {
+    let lower = url.trim().to_ascii_lowercase();
+    if let Some(address) = lower.strip_prefix("mailto:") {
+        let address = address.split(['?', ',']).next().unwrap_or_default();
+        let (_, domain) = address.rsplit_once('@')?;
+        return normalize_host(domain);
+    }
+    let rest = lower
+        .strip_prefix("http://")
+        .or_else(|| lower.strip_prefix("https://"))
+        .or_else(|| lower.strip_prefix("//"))?;
+    let authority = rest.split(['/', '?', '#', '\\']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    normalize_host(host)
+}
+
+fn normalize_host(host: &str) -> Option<String> {
+    let host = host.trim().trim_end_matches('.');
+    (!host.is_empty()).then(|| host.to_owned())
+}
+
+/// Domain named by link text such as `www.bank.example`, `https://bank.example/login`,
+/// or `help@bank.example`.
+fn text_domain(text: &str) -> Option<String> {
+    static DOMAIN_TEXT: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(
+            r"(?i)^(?:[a-z][a-z0-9+.-]*://)?(?:[^\s/@]+@)?((?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+[a-z]{2,63})\.?(?::\d+)?(?:[/?#]\S*)?$",
+        )
+        .expect("static regex is valid")
+    });
+
+    DOMAIN_TEXT
+        .captures(text.trim())
+        .map(|captures| captures[1].to_ascii_lowercase())
+}
+
+fn is_tracker_domain(domain: &str) -> bool {
+    TRACKER_DOMAINS.iter().any(|tracker| {
+        domain == *tracker
+            || domain
+                .strip_suffix(tracker)
+                .is_some_and(|prefix| prefix.ends_with('.'))
+    })
+}
+
+fn leading_pixels(value: &str) -> Option<u32> {
+    let digits: String = value
+        .trim()
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok()
+}
+
+/// Read `width:Npx` or `height:Npx` from a whitespace-free, lowercase style.
+fn style_pixels(style: &str, property: &str) -> Option<u32> {
+    style.split(';').find_map(|declaration| {
+        let (name, value) = declaration.split_once(':')?;
+        (name == property)
+            .then(|| value.strip_suffix("px").unwrap_or(value))
+            .and_then(leading_pixels)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inventory, url_domain};
+
+    /// Tests that anchor text naming another organization is flagged.
+    #[test]
+    fn flags_mismatched_link_text() {
+        let html = r#"<p>Sign in at <a href="https://login.evil.example/bank?a=1&amp;b=2">www.bank.example</a>
+            or <a href="https://www.bank.example/help">help.bank.example</a>,
+            <a href="mailto:support@bank.example">Contact support</a>,
+            and <a href="/unsubscribe">Unsubscribe</a>.</p>"#;
+        let links = inventory(html).links;
+
+        assert_eq!(links.len(), 4);
+        assert_eq!(links[0].href, "https://login.evil.example/bank?a=1&b=2");
+        assert_eq!(links[0].domain.as_deref(), Some("login.evil.example"));
+        assert_eq!(links[0].text_domain.as_deref(), Some("www.bank.example"));
+        assert!(links[0].domain_mismatch);
+        assert!(!links[1].domain_mismatch);
+        assert_eq!(links[2].domain.as_deref(), Some("bank.example"));
+        assert_eq!(links[2].text_domain, None);
+        assert_eq!(links[3].text.as_deref(), Some("Unsubscribe"));
+        assert_eq!(links[3].domain, None);
+    }
+
+    /// Tests that tiny, hidden, and known-tracker images are flagged.
+    #[test]
+    fn flags_tracking_pixels() {
+        let html = r#"<img src="https://cdn.shop.example/logo.png" width="120" height="40" alt="Shop">
+            <img src="https://t.shop.example/open?id=7" width="1" height="1">
+            <img src="https://img.shop.example/o.gif" style="display: none">
+            <img src="https://shop.us1.list-manage.com/track/open.php?u=1">
+            <img src="cid:logo@shop.example">"#;
+        let images = inventory(html).remote_images;
+
+        assert_eq!(images.len(), 4);
+        assert!(!images[0].tracking_pixel);
+        assert_eq!(images[0].alt.as_deref(), Some("Shop"));
+        assert_eq!(images[1].tracking_reasons, ["tiny"]);
+        assert_eq!(images[2].tracking_reasons, ["hidden"]);
+        assert_eq!(images[3].tracking_reasons, ["known_tracker"]);
+    }
+
+    /// Tests that userinfo and ports do not hide the real host.
+    #[test]
+    fn extracts_host_behind_userinfo() {
+        assert_eq!(
+            url_domain("https://www.bank.example@evil.example:8443/login").as_deref(),
+            Some("evil.example")
+        );
+        assert_eq!(
+            url_domain("//cdn.example/a.png").as_deref(),
+            Some("cdn.example")
+        );
+        assert_eq!(url_domain("tel:+15551234"), None);
+    }
+}
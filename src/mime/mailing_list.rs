@@ -0,0 +1,178 @@
+//! Mailing-list header parsing (RFC 2369, RFC 2919, RFC 8058)
+//!
+//! Extracts list identity, unsubscribe targets, and archive links from
+//! `List-*` headers, and classifies bulk mail from those headers and
+//! `Precedence`.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::models::MailingList;
+
+/// Maximum characters kept from a list URI.
+const MAX_URI_CHARS: usize = 2048;
+
+/// `List-Unsubscribe-Post` value that enables RFC 8058 one-click unsubscribe
+const ONE_CLICK_POST: &str = "List-Unsubscribe=One-Click";
+
+/// Build the mailing-list block, or `None` when no list headers are present.
+pub(super) fn mailing_list(headers: &[(String, String)]) -> Option<MailingList> {
+    let list_id = header(headers, "list-id");
+    let unsubscribe = header(headers, "list-unsubscribe");
+    let unsubscribe_post = header(headers, "list-unsubscribe-post");
+    let archive = header(headers, "list-archive");
+    let precedence = header(headers, "precedence").map(|value| value.trim().to_ascii_lowercase());
+    if list_id.is_none() && unsubscribe.is_none() && archive.is_none() && precedence.is_none() {
+        return None;
+    }
+
+    let (id, name) = list_id.map(parse_list_id).unwrap_or_default();
+    let unsubscribe_uris = unsubscribe.map(angle_uris).unwrap_or_default();
+    let unsubscribe_mailto = unsubscribe_uris
+        .iter()
+        .find(|uri| uri.to_ascii_lowercase().starts_with("mailto:"))
+        .cloned();
+    let unsubscribe_url = unsubscribe_uris.iter().find(|uri| is_web_uri(uri)).cloned();
+    let one_click_unsubscribe = unsubscribe_post
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case(ONE_CLICK_POST))
+        && unsubscribe_url
+            .as_deref()
+            .is_some_and(|url| url.to_ascii_lowercase().starts_with("https://"));
+    let archive_url = archive
+        .map(angle_uris)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|uri| is_web_uri(uri));
+
+    Some(MailingList {
+        is_bulk: id.is_some()
+            || !unsubscribe_uris.is_empty()
+            || precedence.as_deref().is_some_and(is_bulk_precedence),
+        id,
+        name,
+        unsubscribe_mailto,
+        unsubscribe_url,
+        one_click_unsubscribe,
+        archive_url,
+        precedence,
+    })
+}
+
+/// Whether headers mark the message as list or bulk mail
+///
+/// True when `List-Id` or `List-Unsubscribe` is present or `Precedence` is
+/// `bulk`, `list`, or `junk`.
+pub fn is_bulk(headers: &[(String, String)]) -> bool {
+    mailing_list(headers).is_some_and(|list| list.is_bulk)
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+        .filter(|value| !value.trim().is_empty())
+}
+
+/// Split `List-Id: "Weekly News" <news.example.com>` into identifier and name.
+fn parse_list_id(value: &str) -> (Option<String>, Option<String>) {
+    let value = value.trim();
+    match value.rsplit_once('<') {
+        Some((name, rest)) => {
+            let id = rest.split('>').next().unwrap_or_default().trim();
+            let name = name.trim().trim_matches('"').trim();
+            (
+                (!id.is_empty()).then(|| id.to_ascii_lowercase()),
+                (!name.is_empty()).then(|| name.to_owned()),
+            )
+        }
+        None => (
+            (!value.is_empty()).then(|| value.to_ascii_lowercase()),
+            None,
+        ),
+    }
+}
+
+/// URIs enclosed in angle brackets, in header order, with folding whitespace removed.
+fn angle_uris(value: &str) -> Vec<String> {
+    static URI: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"<([^>]*)>").expect("static regex is valid"));
+
+    URI.captures_iter(value)
+        .map(|captures| captures[1].split_whitespace().collect::<String>())
+        .filter(|uri| !uri.is_empty())
+        .map(|uri| uri.chars().take(MAX_URI_CHARS).collect())
+        .collect()
+}
+
+fn is_web_uri(uri: &str) -> bool {
+    let lower = uri.to_ascii_lowercase();
+    lower.starts_with("https://") || lower.starts_with("http://")
+}
+
+fn is_bulk_precedence(precedence: &str) -> bool {
+    matches!(precedence, "bulk" | "list" | "junk")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_bulk, mailing_list};
+
+    fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| ((*key).to_owned(), (*value).to_owned()))
+            .collect()
+    }
+
+    /// Tests a newsletter with mailto and one-click https unsubscribe targets.
+    #[test]
+    fn parses_newsletter_list_headers() {
+        let headers = headers(&[
+            ("List-Id", "\"Weekly News\" <News.Example.com>"),
+            (
+                "List-Unsubscribe",
+                "<mailto:leave@news.example.com?subject=unsubscribe>, <https://news.example.com/u/ab\r\n c>",
+            ),
+            ("List-Unsubscribe-Post", "List-Unsubscribe=One-Click"),
+            ("List-Archive", "<https://news.example.com/archive>"),
+            ("Precedence", "Bulk"),
+        ]);
+        let list = mailing_list(&headers).expect("list headers present");
+
+        assert_eq!(list.id.as_deref(), Some("news.example.com"));
+        assert_eq!(list.name.as_deref(), Some("Weekly News"));
+        assert_eq!(
+            list.unsubscribe_mailto.as_deref(),
+            Some("mailto:leave@news.example.com?subject=unsubscribe")
+        );
+        assert_eq!(
+            list.unsubscribe_url.as_deref(),
+            Some("https://news.example.com/u/abc")
+        );
+        assert!(list.one_click_unsubscribe);
+        assert_eq!(
+            list.archive_url.as_deref(),
+            Some("https://news.example.com/archive")
+        );
+        assert_eq!(list.precedence.as_deref(), Some("bulk"));
+        assert!(list.is_bulk);
+    }
+
+    /// Tests that one-click requires an https target and bulk needs list evidence.
+    #[test]
+    fn requires_https_for_one_click_and_list_evidence_for_bulk() {
+        let http_only = headers(&[
+            ("List-Unsubscribe", "<http://lists.example.org/unsub>"),
+            ("List-Unsubscribe-Post", "List-Unsubscribe=One-Click"),
+        ]);
+        let list = mailing_list(&http_only).expect("list headers present");
+        assert!(!list.one_click_unsubscribe);
+        assert!(list.is_bulk);
+
+        let personal = headers(&[("Precedence", "first-class"), ("Subject", "Lunch?")]);
+        assert!(!is_bulk(&personal));
+        assert!(mailing_list(&headers(&[("Subject", "Lunch?")])).is_none());
+    }
+}
@@ -0,0 +1,429 @@
+//! OpenPGP signature verification and decryption (RFC 3156, RFC 9580)
+//!
+//! Verifies detached `application/pgp-signature` parts against the
+//! account's configured public keys and decrypts `multipart/encrypted`
+//! payloads with its secret keys, verifying any signature inside.
+
+use std::io::{Cursor, Read};
+use std::time::SystemTime;
+
+use pgp::composed::{
+    Deserializable, DetachedSignature, Message, SignedPublicKey, SignedPublicSubKey,
+};
+use pgp::packet::{Signature, SignatureType};
+use pgp::types::{KeyDetails, Password, Timestamp, VerifyingKey};
+use secrecy::ExposeSecret;
+
+use crate::config::MessageKeys;
+use crate::models::{SecurityLayer, SignerIdentity};
+
+const PROTOCOL: &str = "openpgp";
+
+/// Largest decrypted payload read from a message, matching the
+/// per-attachment extraction limit.
+const MAX_DECRYPTED_BYTES: u64 = super::MAX_EXTRACT_ATTACHMENT_BYTES as u64;
+
+fn layer(
+    part_id: &str,
+    kind: &str,
+    status: &str,
+    reason: Option<String>,
+    signer: Option<SignerIdentity>,
+) -> SecurityLayer {
+    SecurityLayer {
+        part_id: part_id.to_owned(),
+        protocol: PROTOCOL.to_owned(),
+        kind: kind.to_owned(),
+        status: status.to_owned(),
+        reason,
+        signer,
+    }
+}
+
+/// Verify an armored detached signature over the signed entity.
+///
+/// `senders` are the message's `From` addresses; the signer key must carry a
+/// user ID for one of them to report `valid`.
+pub(super) fn verify_detached(
+    part_id: &str,
+    content: &[u8],
+    signature: &[u8],
+    keys: &MessageKeys,
+    senders: &[String],
+) -> SecurityLayer {
+    let signature = match DetachedSignature::from_armor_single(signature) {
+        Ok((signature, _)) => signature,
+        Err(error) => {
+            return layer(
+                part_id,
+                "signed",
+                "error",
+                Some(format!("malformed OpenPGP signature: {error}")),
+                None,
+            );
+        }
+    };
+    let mut matched_issuer = None;
+    for key in &keys.openpgp_public_keys {
+        let subkeys = key
+            .public_subkeys
+            .iter()
+            .map(|subkey| (Some(subkey), check_key(&signature, subkey, content)));
+        let primary = (None, check_key(&signature, key, content));
+        for (subkey, check) in std::iter::once(primary).chain(subkeys) {
+            match check {
+                KeyCheck::Valid => return signer_layer(part_id, key, subkey, senders),
+                KeyCheck::IssuerMismatch => matched_issuer = Some(key),
+                KeyCheck::NotIssuer => {}
+            }
+        }
+    }
+
+    match matched_issuer {
+        Some(key) => layer(
+            part_id,
+            "signed",
+            "invalid",
+            Some("signature does not match the signed content".to_owned()),
+            Some(key_identity(key)),
+        ),
+        None => layer(
+            part_id,
+            "signed",
+            "unknown_key",
+            Some("signer key is not among the configured OpenPGP public keys".to_owned()),
+            signature
+                .signature
+                .issuer_fingerprint()
+                .first()
+                .map(|fingerprint| SignerIdentity {
+                    name: None,
+                    email: None,
+                    fingerprint: Some(format!("{fingerprint:X}")),
+                    issuer: None,
+                    trusted: false,
+                }),
+        ),
+    }
+}
+
+/// Decrypt an armored `multipart/encrypted` payload
+///
+/// Returns the encryption layer, a signed layer when the payload carries a
+/// signature, and the decrypted MIME entity. The signer is checked against
+/// `senders` as in [`verify_detached`].
+pub(super) fn decrypt(
+    part_id: &str,
+    armored: &[u8],
+    keys: &MessageKeys,
+    senders: &[String],
+) -> (Vec<SecurityLayer>, Option<Vec<u8>>) {
+    if keys.openpgp_secret_keys.is_empty() {
+        return (
+            vec![layer(
+                part_id,
+                "encrypted",
+                "no_key",
+                Some("no OpenPGP secret key configured".to_owned()),
+                None,
+            )],
+            None,
+        );
+    }
+    let message = match Message::from_armor(Cursor::new(armored)) {
+        Ok((message, _)) => message,
+        Err(error) => {
+            return (
+                vec![layer(
+                    part_id,
+                    "encrypted",
+                    "error",
+                    Some(format!("malformed OpenPGP message: {error}")),
+                    None,
+                )],
+                None,
+            );
+        }
+    };
+
+    let password = keys
+        .openpgp_passphrase
+        .as_ref()
+        .map_or_else(Password::empty, |secret| {
+            Password::from(secret.expose_secret())
+        });
+    let mut message = match message.decrypt_with_keys(
+        vec![&password; keys.openpgp_secret_keys.len()],
+        keys.openpgp_secret_keys.iter().collect(),
+    ) {
+        Ok(message) => message,
+        Err(pgp::errors::Error::MissingKey) => {
+            return (
+                vec![layer(
+                    part_id,
+                    "encrypted",
+                    "no_key",
+                    Some("message is not encrypted to a configured OpenPGP key".to_owned()),
+                    None,
+                )],
+                None,
+            );
+        }
+        Err(error) => {
+            return (
+                vec![layer(
+                    part_id,
+                    "encrypted",
+                    "error",
+                    Some(format!("failed to decrypt OpenPGP message: {error}")),
+                    None,
+                )],
+                None,
+            );
+        }
+    };
+    if message.is_compressed() {
+        message = match message.decompress() {
+            Ok(message) => message,
+            Err(error) => {
+                return (
+                    vec![layer(
+                        part_id,
+                        "encrypted",
+                        "error",
+                        Some(format!("failed to decompress OpenPGP message: {error}")),
+                        None,
+                    )],
+                    None,
+                );
+            }
+        };
+        if message.is_compressed() {
+            return (
+                vec![layer(
+                    part_id,
+                    "encrypted",
+                    "limit_exceeded",
+                    Some("OpenPGP message has nested compression layers".to_owned()),
+                    None,
+                )],
+                None,
+            );
+        }
+    }
+
+    let mut content = Vec::new();
+    if let Err(error) = message
+        .by_ref()
+        .take(MAX_DECRYPTED_BYTES + 1)
+        .read_to_end(&mut content)
+    {
+        return (
+            vec![layer(
+                part_id,
+                "encrypted",
+                "error",
+                Some(format!("failed to read decrypted OpenPGP data: {error}")),
+                None,
+            )],
+            None,
+        );
+    }
+    if content.len() as u64 > MAX_DECRYPTED_BYTES {
+        return (
+            vec![layer(
+                part_id,
+                "encrypted",
+                "limit_exceeded",
+                Some(format!(
+                    "decrypted OpenPGP data exceeds {MAX_DECRYPTED_BYTES} bytes"
+                )),
+                None,
+            )],
+            None,
+        );
+    }
+    let mut layers = vec![layer(part_id, "encrypted", "decrypted", None, None)];
+    if message.is_signed() {
+        let signer = keys.openpgp_public_keys.iter().find_map(|key| {
+            if message.verify(key).is_ok() {
+                return Some((key, None));
+            }
+            key.public_subkeys
+                .iter()
+                .find(|subkey| message.verify(*subkey).is_ok())
+                .map(|subkey| (key, Some(subkey)))
+        });
+        layers.push(match signer {
+            Some((key, subkey)) => signer_layer(part_id, key, subkey, senders),
+            None => layer(
+                part_id,
+                "signed",
+                "unknown_key",
+                Some("signature does not verify with any configured OpenPGP public key".to_owned()),
+                None,
+            ),
+        });
+    }
+    (layers, Some(content))
+}
+
+enum KeyCheck {
+    Valid,
+    /// The signature names this key as its issuer but does not verify
+    IssuerMismatch,
+    NotIssuer,
+}
+
+fn check_key<K: KeyDetails + VerifyingKey>(
+    signature: &DetachedSignature,
+    key: &K,
+    content: &[u8],
+) -> KeyCheck {
+    if signature.verify(key, content).is_ok() {
+        return KeyCheck::Valid;
+    }
+    let packet = &signature.signature;
+    if packet.issuer_key_id().contains(&&key.legacy_key_id())
+        || packet.issuer_fingerprint().contains(&&key.fingerprint())
+    {
+        KeyCheck::IssuerMismatch
+    } else {
+        KeyCheck::NotIssuer
+    }
+}
+
+/// Layer for a signature that verified with a configured key
+///
+/// Like S/MIME, the signature is only `valid` when the key (and the signing
+/// subkey, if any) is neither revoked nor expired and one of its user IDs
+/// names a `From` address.
+fn signer_layer(
+    part_id: &str,
+    key: &SignedPublicKey,
+    subkey: Option<&SignedPublicSubKey>,
+    senders: &[String],
+) -> SecurityLayer {
+    let mut identity = key_identity(key);
+    let (status, reason) = if let Some(reason) = revocation(key, subkey) {
+        identity.trusted = false;
+        ("untrusted", Some(reason))
+    } else if key_expired(key, subkey) {
+        identity.trusted = false;
+        ("expired", Some("signer key has expired".to_owned()))
+    } else if !issued_for_sender(key, senders) {
+        (
+            "untrusted",
+            Some("signer key has no user ID for the From address".to_owned()),
+        )
+    } else {
+        ("valid", None)
+    };
+    layer(part_id, "signed", status, reason, Some(identity))
+}
+
+/// Reason the key or signing subkey is revoked, if a revocation verifies.
+fn revocation(key: &SignedPublicKey, subkey: Option<&SignedPublicSubKey>) -> Option<String> {
+    if key
+        .details
+        .revocation_signatures
+        .iter()
+        .any(|signature| signature.verify_key(&key.primary_key).is_ok())
+    {
+        return Some("signer key is revoked".to_owned());
+    }
+    let subkey = subkey?;
+    subkey
+        .signatures
+        .iter()
+        .filter(|signature| signature.typ() == Some(SignatureType::SubkeyRevocation))
+        .any(|signature| {
+            signature
+                .verify_subkey_binding(&key.primary_key, &subkey.key)
+                .is_ok()
+        })
+        .then(|| "signing subkey is revoked".to_owned())
+}
+
+/// Whether the newest self-signature of the key or signing subkey sets a
+/// key expiration time that has passed.
+fn key_expired(key: &SignedPublicKey, subkey: Option<&SignedPublicSubKey>) -> bool {
+    let self_signatures = key
+        .details
+        .direct_signatures
+        .iter()
+        .chain(key.details.users.iter().flat_map(|user| &user.signatures))
+        .filter(|signature| is_issued_by(signature, key));
+    if expired_since(key.created_at(), self_signatures) {
+        return true;
+    }
+    subkey.is_some_and(|subkey| {
+        let bindings = subkey
+            .signatures
+            .iter()
+            .filter(|signature| signature.typ() == Some(SignatureType::SubkeyBinding));
+        expired_since(subkey.key.created_at(), bindings)
+    })
+}
+
+fn is_issued_by(signature: &Signature, key: &SignedPublicKey) -> bool {
+    signature.issuer_key_id().contains(&&key.legacy_key_id())
+        || signature.issuer_fingerprint().contains(&&key.fingerprint())
+}
+
+fn expired_since<'a>(created: Timestamp, signatures: impl Iterator<Item = &'a Signature>) -> bool {
+    let lifetime = signatures
+        .max_by_key(|signature| signature.created())
+        .and_then(Signature::key_expiration_time)
+        .map(std::time::Duration::from)
+        .filter(|lifetime| !lifetime.is_zero());
+    lifetime.is_some_and(|lifetime| SystemTime::from(created) + lifetime < SystemTime::now())
+}
+
+/// Whether any user ID of the key names one of the `From` addresses.
+fn issued_for_sender(key: &SignedPublicKey, senders: &[String]) -> bool {
+    key.details
+        .users
+        .iter()
+        .filter_map(|user| user.id.as_str())
+        .filter_map(|user_id| split_user_id(user_id).1)
+        .any(|email| {
+            senders
+                .iter()
+                .any(|sender| sender.trim().eq_ignore_ascii_case(&email))
+        })
+}
+
+/// Describe a configured key by its fingerprint and first user ID.
+fn key_identity(key: &SignedPublicKey) -> SignerIdentity {
+    let user_id = key
+        .details
+        .users
+        .first()
+        .and_then(|user| user.id.as_str())
+        .unwrap_or_default();
+    let (name, email) = split_user_id(user_id);
+    SignerIdentity {
+        name,
+        email,
+        fingerprint: Some(format!("{:X}", key.fingerprint())),
+        issuer: None,
+        trusted: true,
+    }
+}
+
+/// Split a `Name <email>` user ID into its name and email parts.
+fn split_user_id(user_id: &str) -> (Option<String>, Option<String>) {
+    match user_id.rsplit_once('<') {
+        Some((name, rest)) => (
+            Some(name.trim().trim_matches('"').to_owned()).filter(|name| !name.is_empty()),
+            rest.split_once('>')
+                .map(|(email, _)| email.trim().to_owned()),
+        ),
+        None if user_id.contains('@') => (None, Some(user_id.trim().to_owned())),
+        None => (
+            Some(user_id.trim().to_owned()).filter(|name| !name.is_empty()),
+            None,
+        ),
+    }
+}
@@ -0,0 +1,956 @@
+//! S/MIME signature verification and decryption (RFC 8551, RFC 5652)
+//!
+//! Verifies detached (`multipart/signed`) and opaque (`signed-data`) CMS
+//! signatures and decrypts `enveloped-data` addressed to the account
+//! certificate. Supports RSA signers with SHA-256/384/512 digests, RSA
+//! PKCS #1 v1.5 key transport, and AES-CBC or 3DES-CBC content encryption.
+
+use std::time::SystemTime;
+
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockDecryptMut, KeyIvInit};
+use cms::cert::{CertificateChoices, IssuerAndSerialNumber};
+use cms::content_info::ContentInfo;
+use cms::enveloped_data::{EnvelopedData, RecipientIdentifier, RecipientInfo};
+use cms::signed_data::{SignedData, SignerIdentifier};
+use rsa::pkcs8::DecodePublicKey;
+use rsa::{Pkcs1v15Encrypt, Pkcs1v15Sign, RsaPublicKey};
+use sha2::{Digest as _, Sha256, Sha384, Sha512};
+use x509_cert::Certificate;
+use x509_cert::der::asn1::{Ia5StringRef, OctetString, PrintableStringRef, Utf8StringRef};
+use x509_cert::der::oid::ObjectIdentifier;
+use x509_cert::der::{Any, Decode, Encode, Tag, Tagged};
+use x509_cert::ext::pkix::{
+    BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAltName, SubjectKeyIdentifier,
+    name::GeneralName,
+};
+use x509_cert::spki::AlgorithmIdentifierOwned;
+
+use crate::config::MessageKeys;
+use crate::models::{SecurityLayer, SignerIdentity};
+
+const PROTOCOL: &str = "smime";
+
+/// Maximum issuer hops followed when checking signer trust.
+const MAX_CHAIN_DEPTH: usize = 5;
+
+/// Maximum BER nesting depth accepted when normalizing to DER.
+const MAX_BER_DEPTH: usize = 64;
+
+const OID_SIGNED_DATA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.7.2");
+const OID_ENVELOPED_DATA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.7.3");
+const OID_AUTH_ENVELOPED_DATA: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.16.1.23");
+const OID_MESSAGE_DIGEST: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.4");
+const OID_RSA_ENCRYPTION: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+const OID_SHA256_WITH_RSA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.11");
+const OID_SHA384_WITH_RSA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.12");
+const OID_SHA512_WITH_RSA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.13");
+const OID_SHA1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.14.3.2.26");
+const OID_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.2.1");
+const OID_SHA384: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.2.2");
+const OID_SHA512: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.2.3");
+const OID_AES128_CBC: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.1.2");
+const OID_AES192_CBC: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.1.22");
+const OID_AES256_CBC: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.1.42");
+const OID_DES_EDE3_CBC: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.3.7");
+const OID_COMMON_NAME: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.4.3");
+const OID_EMAIL_ADDRESS: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.1");
+const OID_SUBJECT_KEY_IDENTIFIER: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.14");
+const OID_SUBJECT_ALT_NAME: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.17");
+const OID_KEY_USAGE: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.15");
+const OID_BASIC_CONSTRAINTS: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.19");
+const OID_EXTENDED_KEY_USAGE: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.37");
+const OID_ANY_EXTENDED_KEY_USAGE: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.37.0");
+const OID_EMAIL_PROTECTION: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.6.1.5.5.7.3.4");
+
+/// Outcome of one signature or decryption attempt.
+struct Outcome {
+    status: &'static str,
+    reason: Option<String>,
+    signer: Option<SignerIdentity>,
+}
+
+impl Outcome {
+    fn failed(status: &'static str, reason: impl Into<String>) -> Self {
+        Self {
+            status,
+            reason: Some(reason.into()),
+            signer: None,
+        }
+    }
+
+    fn into_layer(self, part_id: &str, kind: &str) -> SecurityLayer {
+        SecurityLayer {
+            part_id: part_id.to_owned(),
+            protocol: PROTOCOL.to_owned(),
+            kind: kind.to_owned(),
+            status: self.status.to_owned(),
+            reason: self.reason,
+            signer: self.signer,
+        }
+    }
+}
+
+/// Status and reason of a failed decryption.
+type Failure = (&'static str, String);
+
+fn failure(status: &'static str, reason: impl Into<String>) -> Failure {
+    (status, reason.into())
+}
+
+#[derive(Clone, Copy)]
+enum DigestAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    fn from_digest_oid(oid: &ObjectIdentifier) -> Option<Self> {
+        match *oid {
+            OID_SHA256 => Some(Self::Sha256),
+            OID_SHA384 => Some(Self::Sha384),
+            OID_SHA512 => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    fn from_signature_oid(oid: &ObjectIdentifier) -> Option<Self> {
+        match *oid {
+            OID_SHA256_WITH_RSA => Some(Self::Sha256),
+            OID_SHA384_WITH_RSA => Some(Self::Sha384),
+            OID_SHA512_WITH_RSA => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    fn hash(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha256 => Sha256::digest(data).to_vec(),
+            Self::Sha384 => Sha384::digest(data).to_vec(),
+            Self::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+
+    fn pkcs1(self) -> Pkcs1v15Sign {
+        match self {
+            Self::Sha256 => Pkcs1v15Sign::new::<Sha256>(),
+            Self::Sha384 => Pkcs1v15Sign::new::<Sha384>(),
+            Self::Sha512 => Pkcs1v15Sign::new::<Sha512>(),
+        }
+    }
+}
+
+/// Verify a detached `application/pkcs7-signature` over the signed entity.
+///
+/// `senders` are the message `From` addresses the signer must be issued for.
+pub(super) fn verify_detached(
+    part_id: &str,
+    content: &[u8],
+    signature: &[u8],
+    keys: &MessageKeys,
+    senders: &[String],
+) -> SecurityLayer {
+    let outcome = match decode_content_info(signature) {
+        Ok(info) if info.content_type == OID_SIGNED_DATA => {
+            match info.content.decode_as::<SignedData>() {
+                Ok(signed) => verify_signed_data(&signed, content, keys, senders),
+                Err(error) => Outcome::failed("error", format!("malformed signed-data: {error}")),
+            }
+        }
+        Ok(_) => Outcome::failed("error", "signature part is not CMS signed-data"),
+        Err(reason) => Outcome::failed("error", reason),
+    };
+    outcome.into_layer(part_id, "signed")
+}
+
+/// Open an `application/pkcs7-mime` body
+///
+/// Decrypts `enveloped-data` or unwraps opaque `signed-data`. Returns the
+/// layer and the inner MIME entity when it could be recovered; signed
+/// content is returned even when its signature does not verify.
+pub(super) fn open(
+    part_id: &str,
+    body: &[u8],
+    keys: &MessageKeys,
+    senders: &[String],
+) -> (SecurityLayer, Option<Vec<u8>>) {
+    let info = match decode_content_info(body) {
+        Ok(info) => info,
+        Err(reason) => {
+            return (
+                Outcome::failed("error", reason).into_layer(part_id, "encrypted"),
+                None,
+            );
+        }
+    };
+    match info.content_type {
+        OID_ENVELOPED_DATA => {
+            let result = info
+                .content
+                .decode_as::<EnvelopedData>()
+                .map_err(|error| failure("error", format!("malformed enveloped-data: {error}")))
+                .and_then(|enveloped| decrypt_enveloped(&enveloped, keys));
+            match result {
+                Ok(content) => (
+                    Outcome {
+                        status: "decrypted",
+                        reason: None,
+                        signer: None,
+                    }
+                    .into_layer(part_id, "encrypted"),
+                    Some(content),
+                ),
+                Err((status, reason)) => (
+                    Outcome::failed(status, reason).into_layer(part_id, "encrypted"),
+                    None,
+                ),
+            }
+        }
+        OID_SIGNED_DATA => {
+            let signed = match info.content.decode_as::<SignedData>() {
+                Ok(signed) => signed,
+                Err(error) => {
+                    return (
+                        Outcome::failed("error", format!("malformed signed-data: {error}"))
+                            .into_layer(part_id, "signed"),
+                        None,
+                    );
+                }
+            };
+            let Some(content) = signed
+                .encap_content_info
+                .econtent
+                .as_ref()
+                .and_then(|econtent| econtent.decode_as::<OctetString>().ok())
+                .map(OctetString::into_bytes)
+            else {
+                return (
+                    Outcome::failed("error", "signed-data has no encapsulated content")
+                        .into_layer(part_id, "signed"),
+                    None,
+                );
+            };
+            let layer =
+                verify_signed_data(&signed, &content, keys, senders).into_layer(part_id, "signed");
+            (layer, Some(content))
+        }
+        OID_AUTH_ENVELOPED_DATA => (
+            Outcome::failed(
+                "unsupported",
+                "authenticated enveloped-data (AES-GCM) is not supported",
+            )
+            .into_layer(part_id, "encrypted"),
+            None,
+        ),
+        _ => (
+            Outcome::failed("unsupported", "unknown CMS content type")
+                .into_layer(part_id, "encrypted"),
+            None,
+        ),
+    }
+}
+
+fn decode_content_info(bytes: &[u8]) -> Result<ContentInfo, String> {
+    let der = ber_to_der(bytes).ok_or_else(|| "malformed CMS encoding".to_owned())?;
+    ContentInfo::from_der(&der).map_err(|error| format!("malformed CMS structure: {error}"))
+}
+
+/// Verify the first signer of a CMS `SignedData` over `content`.
+///
+/// A matching signature is `valid` only when the signer certificate may sign
+/// email, is currently valid, chains to a configured trust anchor, and names
+/// one of the `senders`. Otherwise it is `expired` or `untrusted` with a
+/// reason.
+fn verify_signed_data(
+    signed: &SignedData,
+    content: &[u8],
+    keys: &MessageKeys,
+    senders: &[String],
+) -> Outcome {
+    let Some(signer_info) = signed.signer_infos.0.iter().next() else {
+        return Outcome::failed("error", "signed-data has no signer");
+    };
+    let embedded: Vec<&Certificate> = signed
+        .certificates
+        .iter()
+        .flat_map(|set| set.0.iter())
+        .filter_map(|choice| match choice {
+            CertificateChoices::Certificate(cert) => Some(cert),
+            CertificateChoices::Other(_) => None,
+        })
+        .collect();
+    let Some(cert) = embedded
+        .iter()
+        .copied()
+        .chain(keys.smime_trusted_certificates.iter())
+        .find(|cert| match &signer_info.sid {
+            SignerIdentifier::IssuerAndSerialNumber(id) => matches_issuer_serial(cert, id),
+            SignerIdentifier::SubjectKeyIdentifier(id) => matches_subject_key_id(cert, id),
+        })
+    else {
+        return Outcome::failed(
+            "unknown_key",
+            "signer certificate is neither included nor configured",
+        );
+    };
+
+    let mut signer = signer_identity(cert);
+    if let Err((status, reason)) = check_signer_signature(signer_info, cert, content) {
+        return Outcome {
+            status,
+            reason: Some(reason),
+            signer: Some(signer),
+        };
+    }
+
+    let (status, reason) = if !permits_email_signing(cert) {
+        (
+            "untrusted",
+            Some("signer certificate is not permitted to sign email".to_owned()),
+        )
+    } else if !currently_valid(cert) {
+        (
+            "expired",
+            Some("signer certificate is expired or not yet valid".to_owned()),
+        )
+    } else if !chains_to_trust_anchor(cert, &embedded, &keys.smime_trusted_certificates) {
+        (
+            "untrusted",
+            Some("signer certificate does not chain to a configured trust anchor".to_owned()),
+        )
+    } else {
+        signer.trusted = true;
+        if issued_for_sender(cert, senders) {
+            ("valid", None)
+        } else {
+            (
+                "untrusted",
+                Some("signer certificate is not issued for the From address".to_owned()),
+            )
+        }
+    };
+    Outcome {
+        status,
+        reason,
+        signer: Some(signer),
+    }
+}
+
+fn check_signer_signature(
+    signer_info: &cms::signed_data::SignerInfo,
+    cert: &Certificate,
+    content: &[u8],
+) -> Result<(), (&'static str, String)> {
+    let digest_oid = &signer_info.digest_alg.oid;
+    let Some(digest) = DigestAlgorithm::from_digest_oid(digest_oid) else {
+        let reason = if *digest_oid == OID_SHA1 {
+            "SHA-1 signatures are not accepted".to_owned()
+        } else {
+            format!("unsupported digest algorithm {digest_oid}")
+        };
+        return Err(("unsupported", reason));
+    };
+    let signature_oid = signer_info.signature_algorithm.oid;
+    if signature_oid != OID_RSA_ENCRYPTION
+        && DigestAlgorithm::from_signature_oid(&signature_oid).is_none()
+    {
+        return Err((
+            "unsupported",
+            format!("unsupported signature algorithm {signature_oid}"),
+        ));
+    }
+    let key = rsa_public_key(cert).ok_or_else(|| {
+        (
+            "unsupported",
+            "only RSA signer keys are supported".to_owned(),
+        )
+    })?;
+
+    let content_digest = digest.hash(content);
+    let signed_bytes = match &signer_info.signed_attrs {
+        Some(attrs) => {
+            let message_digest = attrs
+                .iter()
+                .find(|attr| attr.oid == OID_MESSAGE_DIGEST)
+                .and_then(|attr| attr.values.iter().next())
+                .and_then(|value| value.decode_as::<OctetString>().ok());
+            if message_digest.as_ref().map(OctetString::as_bytes) != Some(content_digest.as_slice())
+            {
+                return Err((
+                    "invalid",
+                    "message digest does not match the content".to_owned(),
+                ));
+            }
+            attrs.to_der().map_err(|error| {
+                (
+                    "error",
+                    format!("failed to encode signed attributes: {error}"),
+                )
+            })?
+        }
+        None => content.to_vec(),
+    };
+    key.verify(
+        digest.pkcs1(),
+        &digest.hash(&signed_bytes),
+        signer_info.signature.as_bytes(),
+    )
+    .map_err(|_| {
+        (
+            "invalid",
+            "signature does not match the signer key".to_owned(),
+        )
+    })
+}
+
+/// Decrypt `EnvelopedData` with the account certificate and private key.
+fn decrypt_enveloped(enveloped: &EnvelopedData, keys: &MessageKeys) -> Result<Vec<u8>, Failure> {
+    let (Some(cert), Some(private_key)) = (&keys.smime_certificate, &keys.smime_private_key) else {
+        return Err(failure(
+            "no_key",
+            "no S/MIME certificate and key configured",
+        ));
+    };
+    let recipient = enveloped.recip_infos.0.iter().find_map(|info| match info {
+        RecipientInfo::Ktri(ktri) => match &ktri.rid {
+            RecipientIdentifier::IssuerAndSerialNumber(id) if matches_issuer_serial(cert, id) => {
+                Some(ktri)
+            }
+            RecipientIdentifier::SubjectKeyIdentifier(id) if matches_subject_key_id(cert, id) => {
+                Some(ktri)
+            }
+            _ => None,
+        },
+        _ => None,
+    });
+    let Some(recipient) = recipient else {
+        return Err(failure(
+            "no_key",
+            "message is not encrypted to the configured certificate",
+        ));
+    };
+    if recipient.key_enc_alg.oid != OID_RSA_ENCRYPTION {
+        return Err(failure(
+            "unsupported",
+            format!(
+                "unsupported key transport algorithm {}",
+                recipient.key_enc_alg.oid
+            ),
+        ));
+    }
+    let content_key = private_key
+        .decrypt(Pkcs1v15Encrypt, recipient.enc_key.as_bytes())
+        .map_err(|_| failure("error", "failed to decrypt the content key"))?;
+    let encrypted = &enveloped.encrypted_content;
+    let Some(ciphertext) = &encrypted.encrypted_content else {
+        return Err(failure("error", "enveloped-data has no encrypted content"));
+    };
+    decrypt_content(
+        &encrypted.content_enc_alg,
+        &content_key,
+        ciphertext.as_bytes(),
+    )
+}
+
+fn decrypt_content(
+    algorithm: &AlgorithmIdentifierOwned,
+    key: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, Failure> {
+    let iv = algorithm
+        .parameters
+        .as_ref()
+        .and_then(|parameters| parameters.decode_as::<OctetString>().ok())
+        .ok_or_else(|| failure("error", "content encryption IV is missing"))?;
+    let iv = iv.as_bytes();
+    let result = match algorithm.oid {
+        OID_AES128_CBC => cbc_decrypt::<aes::Aes128>(key, iv, ciphertext),
+        OID_AES192_CBC => cbc_decrypt::<aes::Aes192>(key, iv, ciphertext),
+        OID_AES256_CBC => cbc_decrypt::<aes::Aes256>(key, iv, ciphertext),
+        OID_DES_EDE3_CBC => cbc_decrypt::<des::TdesEde3>(key, iv, ciphertext),
+        oid => {
+            return Err(failure(
+                "unsupported",
+                format!("unsupported content encryption algorithm {oid}"),
+            ));
+        }
+    };
+    result.ok_or_else(|| failure("error", "failed to decrypt the message content"))
+}
+
+fn cbc_decrypt<C>(key: &[u8], iv: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>>
+where
+    C: cbc::cipher::BlockDecryptMut + cbc::cipher::BlockCipher + cbc::cipher::KeyInit,
+{
+    cbc::Decryptor::<C>::new_from_slices(key, iv)
+        .ok()?
+        .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+        .ok()
+}
+
+fn matches_issuer_serial(cert: &Certificate, id: &IssuerAndSerialNumber) -> bool {
+    cert.tbs_certificate.issuer == id.issuer
+        && cert.tbs_certificate.serial_number == id.serial_number
+}
+
+fn matches_subject_key_id(cert: &Certificate, id: &SubjectKeyIdentifier) -> bool {
+    cert.tbs_certificate
+        .extensions
+        .iter()
+        .flatten()
+        .filter(|ext| ext.extn_id == OID_SUBJECT_KEY_IDENTIFIER)
+        .filter_map(|ext| SubjectKeyIdentifier::from_der(ext.extn_value.as_bytes()).ok())
+        .any(|key_id| key_id == *id)
+}
+
+fn rsa_public_key(cert: &Certificate) -> Option<RsaPublicKey> {
+    let spki = cert.tbs_certificate.subject_public_key_info.to_der().ok()?;
+    RsaPublicKey::from_public_key_der(&spki).ok()
+}
+
+/// Whether a signer certificate may sign email (RFC 8550 section 4.4)
+///
+/// Key usage, when present, must include `digitalSignature`; extended key
+/// usage, when present, must include `emailProtection` or
+/// `anyExtendedKeyUsage`.
+fn permits_email_signing(cert: &Certificate) -> bool {
+    cert.tbs_certificate
+        .extensions
+        .iter()
+        .flatten()
+        .all(|ext| match ext.extn_id {
+            OID_KEY_USAGE => KeyUsage::from_der(ext.extn_value.as_bytes())
+                .is_ok_and(|usage| usage.digital_signature()),
+            OID_EXTENDED_KEY_USAGE => ExtendedKeyUsage::from_der(ext.extn_value.as_bytes())
+                .is_ok_and(|usage| {
+                    usage.0.iter().any(|oid| {
+                        *oid == OID_EMAIL_PROTECTION || *oid == OID_ANY_EXTENDED_KEY_USAGE
+                    })
+                }),
+            _ => true,
+        })
+}
+
+/// Whether the certificate names one of the `From` addresses
+///
+/// Compares subjectAltName `rfc822Name` entries, or the subject
+/// `emailAddress` when the certificate has none, case-insensitively.
+fn issued_for_sender(cert: &Certificate, senders: &[String]) -> bool {
+    let mut emails = certificate_emails(cert);
+    if emails.is_empty() {
+        emails.extend(subject_value(cert, OID_EMAIL_ADDRESS));
+    }
+    emails.iter().any(|email| {
+        senders
+            .iter()
+            .any(|sender| sender.trim().eq_ignore_ascii_case(email.trim()))
+    })
+}
+
+fn currently_valid(cert: &Certificate) -> bool {
+    let validity = &cert.tbs_certificate.validity;
+    let now = SystemTime::now();
+    validity.not_before.to_system_time() <= now && now <= validity.not_after.to_system_time()
+}
+
+/// Follow issuers through embedded intermediates until a trust anchor.
+///
+/// The signer is trusted when it is itself an anchor or a chain of valid
+/// RSA signatures of at most `MAX_CHAIN_DEPTH` hops leads to one. Every
+/// issuer on the chain, anchors included, must be allowed to sign
+/// certificates at its depth (see [`can_issue`]).
+fn chains_to_trust_anchor(
+    cert: &Certificate,
+    intermediates: &[&Certificate],
+    anchors: &[Certificate],
+) -> bool {
+    if anchors.iter().any(|anchor| anchor == cert) {
+        return true;
+    }
+    let mut current = cert;
+    for ca_certs_below in 0..MAX_CHAIN_DEPTH {
+        let signs_current =
+            |issuer: &Certificate| can_issue(issuer, ca_certs_below) && issued_by(current, issuer);
+        if anchors.iter().any(signs_current) {
+            return true;
+        }
+        match intermediates
+            .iter()
+            .find(|candidate| **candidate != current && signs_current(candidate))
+        {
+            Some(issuer) if currently_valid(issuer) => current = issuer,
+            _ => return false,
+        }
+    }
+    false
+}
+
+/// Whether `issuer` may sign certificates with `ca_certs_below` CA
+/// certificates between it and the signer (RFC 5280 section 4.2.1.9).
+///
+/// Requires basicConstraints `cA=TRUE`, a satisfied `pathLenConstraint`,
+/// and `keyCertSign` when a key usage extension is present.
+fn can_issue(issuer: &Certificate, ca_certs_below: usize) -> bool {
+    let mut constraints = None;
+    let mut key_cert_sign = true;
+    for ext in issuer.tbs_certificate.extensions.iter().flatten() {
+        if ext.extn_id == OID_BASIC_CONSTRAINTS {
+            constraints = BasicConstraints::from_der(ext.extn_value.as_bytes()).ok();
+        } else if ext.extn_id == OID_KEY_USAGE {
+            key_cert_sign = KeyUsage::from_der(ext.extn_value.as_bytes())
+                .is_ok_and(|usage| usage.key_cert_sign());
+        }
+    }
+    key_cert_sign
+        && constraints.is_some_and(|constraints| {
+            constraints.ca
+                && constraints
+                    .path_len_constraint
+                    .is_none_or(|max| ca_certs_below <= usize::from(max))
+        })
+}
+
+fn issued_by(cert: &Certificate, issuer: &Certificate) -> bool {
+    if cert.tbs_certificate.issuer != issuer.tbs_certificate.subject {
+        return false;
+    }
+    let (Some(digest), Some(key), Ok(tbs)) = (
+        DigestAlgorithm::from_signature_oid(&cert.signature_algorithm.oid),
+        rsa_public_key(issuer),
+        cert.tbs_certificate.to_der(),
+    ) else {
+        return false;
+    };
+    cert.signature.as_bytes().is_some_and(|signature| {
+        key.verify(digest.pkcs1(), &digest.hash(&tbs), signature)
+            .is_ok()
+    })
+}
+
+/// Describe the signer from its certificate subject and alternative names.
+fn signer_identity(cert: &Certificate) -> SignerIdentity {
+    let tbs = &cert.tbs_certificate;
+    SignerIdentity {
+        name: subject_value(cert, OID_COMMON_NAME),
+        email: certificate_emails(cert)
+            .into_iter()
+            .next()
+            .or_else(|| subject_value(cert, OID_EMAIL_ADDRESS)),
+        fingerprint: cert
+            .to_der()
+            .ok()
+            .map(|der| hex_upper(&Sha256::digest(der))),
+        issuer: Some(tbs.issuer.to_string()),
+        trusted: false,
+    }
+}
+
+fn subject_value(cert: &Certificate, oid: ObjectIdentifier) -> Option<String> {
+    cert.tbs_certificate
+        .subject
+        .0
+        .iter()
+        .flat_map(|rdn| rdn.0.iter())
+        .find(|attr| attr.oid == oid)
+        .and_then(|attr| directory_string(&attr.value))
+}
+
+/// The subjectAltName `rfc822Name` entries of a certificate.
+fn certificate_emails(cert: &Certificate) -> Vec<String> {
+    cert.tbs_certificate
+        .extensions
+        .iter()
+        .flatten()
+        .filter(|ext| ext.extn_id == OID_SUBJECT_ALT_NAME)
+        .filter_map(|ext| SubjectAltName::from_der(ext.extn_value.as_bytes()).ok())
+        .flat_map(|names| names.0)
+        .filter_map(|name| match name {
+            GeneralName::Rfc822Name(email) => Some(email.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn directory_string(value: &Any) -> Option<String> {
+    match value.tag() {
+        Tag::Utf8String => value
+            .decode_as::<Utf8StringRef<'_>>()
+            .ok()
+            .map(|s| s.to_string()),
+        Tag::PrintableString => value
+            .decode_as::<PrintableStringRef<'_>>()
+            .ok()
+            .map(|s| s.to_string()),
+        Tag::Ia5String => value
+            .decode_as::<Ia5StringRef<'_>>()
+            .ok()
+            .map(|s| s.to_string()),
+        _ => None,
+    }
+}
+
+fn hex_upper(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+/// BER element parsed for re-encoding.
+enum BerElement {
+    Primitive { tag: u8, content: Vec<u8> },
+    Constructed { tag: u8, children: Vec<BerElement> },
+}
+
+/// Re-encode BER as DER
+///
+/// Streaming S/MIME encoders emit indefinite lengths and chunked OCTET
+/// STRINGs, which the DER decoder rejects. Lengths become definite and
+/// chunked OCTET STRINGs are joined. A constructed `[0]` that follows an OID
+/// and an AlgorithmIdentifier is the implicitly tagged content of
+/// `EncryptedContentInfo`, so its chunks are joined into a primitive `[0]`.
+/// Returns `None` for malformed input or multi-byte tags.
+fn ber_to_der(input: &[u8]) -> Option<Vec<u8>> {
+    let (element, _) = read_ber_element(input, 0)?;
+    let mut out = Vec::with_capacity(input.len());
+    write_der_element(&element, &mut out);
+    Some(out)
+}
+
+fn read_ber_element(input: &[u8], depth: usize) -> Option<(BerElement, &[u8])> {
+    if depth > MAX_BER_DEPTH {
+        return None;
+    }
+    let (&tag, rest) = input.split_first()?;
+    if tag & 0x1f == 0x1f {
+        return None;
+    }
+    let (&first, mut rest) = rest.split_first()?;
+    let length = match first {
+        0x80 => None,
+        len if len < 0x80 => Some(usize::from(len)),
+        len => {
+            let count = usize::from(len & 0x7f);
+            if count > 4 || rest.len() < count {
+                return None;
+            }
+            let (bytes, tail) = rest.split_at(count);
+            rest = tail;
+            Some(
+                bytes
+                    .iter()
+                    .fold(0usize, |acc, byte| (acc << 8) | usize::from(*byte)),
+            )
+        }
+    };
+    let constructed = tag & 0x20 != 0;
+
+    if !constructed {
+        let length = length?;
+        if rest.len() < length {
+            return None;
+        }
+        let (content, tail) = rest.split_at(length);
+        return Some((
+            BerElement::Primitive {
+                tag,
+                content: content.to_vec(),
+            },
+            tail,
+        ));
+    }
+
+    let mut children = Vec::new();
+    let tail = match length {
+        Some(length) => {
+            if rest.len() < length {
+                return None;
+            }
+            let (mut content, tail) = rest.split_at(length);
+            while !content.is_empty() {
+                let (child, next) = read_ber_element(content, depth + 1)?;
+                children.push(child);
+                content = next;
+            }
+            tail
+        }
+        None => loop {
+            if rest.starts_with(&[0, 0]) {
+                break &rest[2..];
+            }
+            let (child, next) = read_ber_element(rest, depth + 1)?;
+            children.push(child);
+            rest = next;
+        },
+    };
+    Some((normalize_constructed(tag, children), tail))
+}
+
+/// Join chunked OCTET STRINGs and implicitly tagged encrypted content.
+fn normalize_constructed(tag: u8, children: Vec<BerElement>) -> BerElement {
+    if tag == 0x24 {
+        return BerElement::Primitive {
+            tag: 0x04,
+            content: joined_octets(&children),
+        };
+    }
+    let tags: Vec<u8> = children.iter().map(BerElement::tag).collect();
+    let children = children
+        .into_iter()
+        .enumerate()
+        .map(|(index, child)| match child {
+            BerElement::Constructed {
+                tag: 0xa0,
+                children,
+            } if index >= 2
+                && tags[index - 2] == 0x06
+                && tags[index - 1] == 0x30
+                && children.iter().all(|chunk| chunk.tag() == 0x04) =>
+            {
+                BerElement::Primitive {
+                    tag: 0x80,
+                    content: joined_octets(&children),
+                }
+            }
+            other => other,
+        })
+        .collect();
+    BerElement::Constructed { tag, children }
+}
+
+fn joined_octets(children: &[BerElement]) -> Vec<u8> {
+    children
+        .iter()
+        .flat_map(|child| match child {
+            BerElement::Primitive { content, .. } => content.clone(),
+            BerElement::Constructed { children, .. } => joined_octets(children),
+        })
+        .collect()
+}
+
+impl BerElement {
+    fn tag(&self) -> u8 {
+        match self {
+            Self::Primitive { tag, .. } | Self::Constructed { tag, .. } => *tag,
+        }
+    }
+}
+
+fn write_der_element(element: &BerElement, out: &mut Vec<u8>) {
+    let (tag, content) = match element {
+        BerElement::Primitive { tag, content } => (*tag, content.clone()),
+        BerElement::Constructed { tag, children } => {
+            let mut content = Vec::new();
+            for child in children {
+                write_der_element(child, &mut content);
+            }
+            (*tag, content)
+        }
+    };
+    out.push(tag);
+    let length = content.len();
+    if length < 0x80 {
+        out.push(length as u8);
+    } else {
+        let bytes = length.to_be_bytes();
+        let skip = bytes.iter().take_while(|byte| **byte == 0).count();
+        out.push(0x80 | (bytes.len() - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+    out.extend_from_slice(&content);
+}
+
+#[cfg(test)]
+mod tests {
+    use x509_cert::Certificate;
+    use x509_cert::der::DecodePem;
+
+    use super::{ber_to_der, chains_to_trust_anchor, issued_for_sender, permits_email_signing};
+
+    fn fixture_cert(name: &str) -> Certificate {
+        let path = format!(
+            "{}/tests/fixtures/secure/{name}",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let pem = std::fs::read(&path).unwrap_or_else(|e| panic!("read {path}: {e}"));
+        Certificate::from_pem(pem).expect("fixture certificate parses")
+    }
+
+    /// Tests that a chain through a valid CA reaches its anchor.
+    #[test]
+    fn trusts_certificates_issued_by_an_anchor() {
+        let anchors = [fixture_cert("ca.pem")];
+        assert!(chains_to_trust_anchor(
+            &fixture_cert("alice-cert.pem"),
+            &[],
+            &anchors
+        ));
+
+        let intermediate = fixture_cert("path-intermediate.pem");
+        assert!(chains_to_trust_anchor(
+            &fixture_cert("path-direct-leaf.pem"),
+            &[&intermediate],
+            &[fixture_cert("path-root.pem")],
+        ));
+    }
+
+    /// Tests that an end-entity certificate cannot act as an issuer.
+    #[test]
+    fn rejects_end_entity_certificates_as_issuers() {
+        let alice = fixture_cert("alice-cert.pem");
+        assert!(!chains_to_trust_anchor(
+            &fixture_cert("mallory-cert.pem"),
+            &[&alice],
+            &[fixture_cert("ca.pem")],
+        ));
+    }
+
+    /// Tests that a CA's pathLenConstraint bounds the intermediates below it.
+    #[test]
+    fn enforces_path_length_constraints() {
+        let intermediate = fixture_cert("path-intermediate.pem");
+        let sub = fixture_cert("path-sub.pem");
+        assert!(!chains_to_trust_anchor(
+            &fixture_cert("path-leaf.pem"),
+            &[&intermediate, &sub],
+            &[fixture_cert("path-root.pem")],
+        ));
+    }
+
+    /// Tests that only certificates allowed to sign email qualify as signers.
+    #[test]
+    fn requires_email_signing_usage() {
+        assert!(permits_email_signing(&fixture_cert("alice-cert.pem")));
+        assert!(permits_email_signing(&fixture_cert("path-leaf.pem")));
+        assert!(!permits_email_signing(&fixture_cert("path-root.pem")));
+    }
+
+    /// Tests that the signer must be issued for a From address.
+    #[test]
+    fn matches_signer_to_from_address() {
+        let alice = fixture_cert("alice-cert.pem");
+        assert!(issued_for_sender(&alice, &["Alice@Example.com".to_owned()]));
+        assert!(!issued_for_sender(
+            &alice,
+            &["ceo@victim.example".to_owned()]
+        ));
+        assert!(!issued_for_sender(&alice, &[]));
+    }
+
+    /// Tests that indefinite lengths and chunked octet strings become DER.
+    #[test]
+    fn normalizes_streamed_ber() {
+        let ber = [
+            0x24, 0x80, 0x04, 0x02, b'a', b'b', 0x04, 0x01, b'c', 0x00, 0x00,
+        ];
+        assert_eq!(
+            ber_to_der(&ber).expect("valid BER"),
+            [0x04, 0x03, b'a', b'b', b'c']
+        );
+    }
+
+    /// Tests that truncated input is rejected rather than misread.
+    #[test]
+    fn rejects_truncated_ber() {
+        assert!(ber_to_der(&[0x30, 0x80, 0x04, 0x02, b'a']).is_none());
+    }
+}
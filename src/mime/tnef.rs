@@ -0,0 +1,427 @@
+//! TNEF (`application/ms-tnef`, `winmail.dat`) decoding
+//!
+//! Outlook wraps the rich-text body and the real attachments of some messages
+//! in a TNEF stream (MS-OXTNEF). This module reads the attribute stream and the
+//! MAPI property lists inside it, decompresses the RTF body (MS-OXRTFCP), and
+//! returns the body and attachments for the MIME walker. Embedded Outlook
+//! messages and OLE objects are skipped.
+
+use encoding_rs::UTF_16LE;
+
+use super::{charset, extract};
+
+/// Maximum decompressed RTF body bytes.
+const MAX_RTF_BYTES: usize = 5_000_000;
+
+const SIGNATURE: u32 = 0x223E_9F78;
+
+const LEVEL_MESSAGE: u8 = 1;
+const LEVEL_ATTACHMENT: u8 = 2;
+
+// Attribute ids (low 16 bits of the attribute tag)
+const ATT_BODY: u16 = 0x800C;
+const ATT_ATTACH_DATA: u16 = 0x800F;
+const ATT_ATTACH_TITLE: u16 = 0x8010;
+const ATT_ATTACH_REND_DATA: u16 = 0x9002;
+const ATT_MSG_PROPS: u16 = 0x9003;
+const ATT_ATTACHMENT: u16 = 0x9005;
+const ATT_OEM_CODEPAGE: u16 = 0x9007;
+
+// MAPI property ids
+const PR_BODY: u16 = 0x1000;
+const PR_RTF_COMPRESSED: u16 = 0x1009;
+const PR_HTML: u16 = 0x1013;
+const PR_ATTACH_DATA_BIN: u16 = 0x3701;
+const PR_ATTACH_FILENAME: u16 = 0x3704;
+const PR_ATTACH_LONG_FILENAME: u16 = 0x3707;
+const PR_ATTACH_MIME_TAG: u16 = 0x370E;
+
+// MAPI property types
+const PT_STRING8: u16 = 0x001E;
+const PT_UNICODE: u16 = 0x001F;
+const PT_BINARY: u16 = 0x0102;
+const PT_OBJECT: u16 = 0x000D;
+
+/// Dictionary preload shared by every compressed RTF stream
+const RTF_PREBUF: &[u8] = b"{\\rtf1\\ansi\\mac\\deff0\\deftab720{\\fonttbl;}{\\f0\\fnil \\froman \\fswiss \\fmodern \\fscript \\fdecor MS Sans SerifSymbolArialTimes New RomanCourier{\\colortbl\\red0\\green0\\blue0\r\n\\par \\pard\\plain\\f0\\fs20\\b\\i\\u\\tab\\tx";
+
+/// Body and attachments decoded from a TNEF stream
+#[derive(Debug, Default)]
+pub(super) struct TnefContent {
+    /// Text of the RTF body, or the plain-text body when there is no RTF
+    pub(super) body_text: Option<String>,
+    /// HTML body, when Outlook stored one
+    pub(super) body_html: Option<String>,
+    pub(super) attachments: Vec<TnefAttachment>,
+}
+
+/// File attachment carried inside a TNEF stream
+#[derive(Debug, Default)]
+pub(super) struct TnefAttachment {
+    pub(super) filename: Option<String>,
+    /// Lowercase MIME type from `PR_ATTACH_MIME_TAG`, if present
+    pub(super) content_type: Option<String>,
+    pub(super) data: Vec<u8>,
+}
+
+/// Text property value before the OEM codepage is known
+enum Text<'a> {
+    Legacy(&'a [u8]),
+    Unicode(&'a [u8]),
+}
+
+#[derive(Default)]
+struct RawAttachment<'a> {
+    title: Option<Text<'a>>,
+    long_filename: Option<Text<'a>>,
+    filename: Option<Text<'a>>,
+    mime_tag: Option<Text<'a>>,
+    data: Option<&'a [u8]>,
+}
+
+/// Whether a part is a TNEF container, by content type or `winmail.dat` name.
+pub(super) fn is_tnef(content_type: &str, filename: Option<&str>) -> bool {
+    matches!(
+        content_type,
+        "application/ms-tnef" | "application/vnd.ms-tnef"
+    ) || filename.is_some_and(|name| name.eq_ignore_ascii_case("winmail.dat"))
+}
+
+/// Decode a TNEF stream into its body and file attachments.
+///
+/// Attributes after a truncated or malformed one are ignored; a stream
+/// without the TNEF signature is an error.
+pub(super) fn decode(bytes: &[u8]) -> Result<TnefContent, String> {
+    let mut reader = Reader::new(bytes);
+    if reader.u32() != Some(SIGNATURE) {
+        return Err("not a TNEF stream".to_owned());
+    }
+    reader.take(2).ok_or("truncated TNEF header")?;
+
+    let mut codepage = None;
+    let mut plain_body = None;
+    let mut rtf_body = None;
+    let mut html_body = None;
+    let mut attachments: Vec<RawAttachment<'_>> = Vec::new();
+    while let Some((level, id, data)) = reader.attribute() {
+        match (level, id) {
+            (_, ATT_OEM_CODEPAGE) => codepage = Reader::new(data).u32(),
+            (LEVEL_MESSAGE, ATT_BODY) => plain_body = Some(Text::Legacy(data)),
+            (LEVEL_MESSAGE, ATT_MSG_PROPS) => {
+                for (prop_id, value) in properties(data) {
+                    match (prop_id, value) {
+                        (PR_BODY, Value::Text(text)) => plain_body = Some(text),
+                        (PR_RTF_COMPRESSED, Value::Binary(data)) => rtf_body = Some(data),
+                        (PR_HTML, Value::Binary(data)) => html_body = Some(Text::Legacy(data)),
+                        (PR_HTML, Value::Text(text)) => html_body = Some(text),
+                        _ => {}
+                    }
+                }
+            }
+            (LEVEL_ATTACHMENT, ATT_ATTACH_REND_DATA) => attachments.push(RawAttachment::default()),
+            (LEVEL_ATTACHMENT, _) => {
+                let Some(attachment) = attachments.last_mut() else {
+                    continue;
+                };
+                match id {
+                    ATT_ATTACH_TITLE => attachment.title = Some(Text::Legacy(data)),
+                    ATT_ATTACH_DATA => attachment.data = Some(data),
+                    ATT_ATTACHMENT => {
+                        for (prop_id, value) in properties(data) {
+                            match (prop_id, value) {
+                                (PR_ATTACH_LONG_FILENAME, Value::Text(text)) => {
+                                    attachment.long_filename = Some(text);
+                                }
+                                (PR_ATTACH_FILENAME, Value::Text(text)) => {
+                                    attachment.filename = Some(text);
+                                }
+                                (PR_ATTACH_MIME_TAG, Value::Text(text)) => {
+                                    attachment.mime_tag = Some(text);
+                                }
+                                (PR_ATTACH_DATA_BIN, Value::Binary(data)) => {
+                                    attachment.data.get_or_insert(data);
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let label = codepage.and_then(codepage_label);
+    let decode_text = |text: Text<'_>| -> Option<String> {
+        let decoded = match text {
+            Text::Legacy(bytes) => charset::decode(bytes, label).0,
+            Text::Unicode(bytes) => UTF_16LE.decode_without_bom_handling(bytes).0.into_owned(),
+        };
+        let decoded = decoded.trim_end_matches('\0').trim_end();
+        (!decoded.is_empty()).then(|| decoded.to_owned())
+    };
+
+    let rtf_text = rtf_body
+        .and_then(|compressed| decompress_rtf(compressed).ok())
+        .and_then(|rtf| extract::rtf_text(&rtf).ok())
+        .map(|text| text.trim().to_owned())
+        .filter(|text| !text.is_empty());
+    Ok(TnefContent {
+        body_text: rtf_text.or_else(|| plain_body.and_then(decode_text)),
+        body_html: html_body.and_then(decode_text),
+        attachments: attachments
+            .into_iter()
+            .filter_map(|attachment| {
+                Some(TnefAttachment {
+                    filename: attachment
+                        .long_filename
+                        .and_then(decode_text)
+                        .or_else(|| attachment.title.and_then(decode_text))
+                        .or_else(|| attachment.filename.and_then(decode_text)),
+                    content_type: attachment
+                        .mime_tag
+                        .and_then(decode_text)
+                        .map(|tag| tag.to_ascii_lowercase()),
+                    data: attachment.data?.to_vec(),
+                })
+            })
+            .collect(),
+    })
+}
+
+/// Single-valued MAPI property value the decoder uses
+enum Value<'a> {
+    Text(Text<'a>),
+    Binary(&'a [u8]),
+    Other,
+}
+
+/// Parse a MAPI property list (`attMsgProps`, `attAttachment`).
+///
+/// Stops at the first truncated or unknown property type, keeping what was
+/// read before it.
+fn properties(data: &[u8]) -> Vec<(u16, Value<'_>)> {
+    let mut reader = Reader::new(data);
+    let mut props = Vec::new();
+    let Some(count) = reader.u32() else {
+        return props;
+    };
+    for _ in 0..count {
+        let (Some(kind), Some(id)) = (reader.u16(), reader.u16()) else {
+            break;
+        };
+        if id >= 0x8000 && reader.skip_property_name().is_none() {
+            break;
+        }
+        let multi_valued = kind & 0x1000 != 0;
+        let base = kind & 0x0FFF;
+        let variable = matches!(base, PT_STRING8 | PT_UNICODE | PT_BINARY | PT_OBJECT);
+        let values = if multi_valued || variable {
+            reader.u32()
+        } else {
+            Some(1)
+        };
+        let Some(values) = values else {
+            break;
+        };
+        let mut value = Value::Other;
+        for _ in 0..values {
+            let raw = if variable {
+                reader.padded_value()
+            } else {
+                fixed_size(base).and_then(|size| reader.take(size))
+            };
+            let Some(raw) = raw else {
+                return props;
+            };
+            if !multi_valued {
+                value = match base {
+                    PT_STRING8 => Value::Text(Text::Legacy(raw)),
+                    PT_UNICODE => Value::Text(Text::Unicode(raw)),
+                    PT_BINARY => Value::Binary(raw),
+                    _ => Value::Other,
+                };
+            }
+        }
+        props.push((id, value));
+    }
+    props
+}
+
+/// Encoded size of a fixed-width property type, padded to 4 bytes.
+fn fixed_size(base: u16) -> Option<usize> {
+    match base {
+        0x0001..=0x0004 | 0x000A | 0x000B => Some(4),
+        0x0005..=0x0007 | 0x0014 | 0x0040 => Some(8),
+        0x0048 => Some(16),
+        _ => None,
+    }
+}
+
+/// Charset label for a Windows OEM/ANSI codepage number.
+fn codepage_label(codepage: u32) -> Option<&'static str> {
+    Some(match codepage {
+        874 => "windows-874",
+        932 => "shift_jis",
+        936 => "gbk",
+        949 => "euc-kr",
+        950 => "big5",
+        1250 => "windows-1250",
+        1251 => "windows-1251",
+        1252 => "windows-1252",
+        1253 => "windows-1253",
+        1254 => "windows-1254",
+        1255 => "windows-1255",
+        1256 => "windows-1256",
+        1257 => "windows-1257",
+        1258 => "windows-1258",
+        65001 => "utf-8",
+        _ => return None,
+    })
+}
+
+/// Decompress an RTF body stored as `PR_RTF_COMPRESSED` (MS-OXRTFCP).
+fn decompress_rtf(data: &[u8]) -> Result<Vec<u8>, String> {
+    const COMPRESSED: u32 = 0x7546_5A4C; // "LZFu"
+    const UNCOMPRESSED: u32 = 0x414C_454D; // "MELA"
+
+    let mut header = Reader::new(data);
+    let (Some(compressed_size), Some(raw_size), Some(kind), Some(_crc)) =
+        (header.u32(), header.u32(), header.u32(), header.u32())
+    else {
+        return Err("truncated compressed RTF header".to_owned());
+    };
+    let raw_size = (raw_size as usize).min(MAX_RTF_BYTES);
+    let end = (compressed_size as usize).saturating_add(4).min(data.len());
+    let body = data.get(16..end).unwrap_or_default();
+    match kind {
+        UNCOMPRESSED => Ok(body[..raw_size.min(body.len())].to_vec()),
+        COMPRESSED => Ok(lzfu(body, raw_size)),
+        _ => Err(format!("unknown compressed RTF type {kind:#x}")),
+    }
+}
+
+fn lzfu(body: &[u8], raw_size: usize) -> Vec<u8> {
+    let mut dictionary = [0u8; 4096];
+    dictionary[..RTF_PREBUF.len()].copy_from_slice(RTF_PREBUF);
+    let mut out = Vec::with_capacity(raw_size);
+    // The dictionary write position follows the output length.
+    let write_position = |len: usize| (RTF_PREBUF.len() + len) % 4096;
+
+    let mut pos = 0;
+    while pos < body.len() && out.len() < raw_size {
+        let control = body[pos];
+        pos += 1;
+        for bit in 0..8 {
+            if pos >= body.len() || out.len() >= raw_size {
+                break;
+            }
+            if control & (1 << bit) == 0 {
+                dictionary[write_position(out.len())] = body[pos];
+                out.push(body[pos]);
+                pos += 1;
+                continue;
+            }
+            let Some(reference) = body.get(pos..pos + 2) else {
+                return out;
+            };
+            pos += 2;
+            let word = u16::from_be_bytes([reference[0], reference[1]]);
+            let offset = usize::from(word >> 4);
+            if offset == write_position(out.len()) {
+                return out;
+            }
+            for i in 0..(usize::from(word & 0xF) + 2) {
+                let byte = dictionary[(offset + i) % 4096];
+                dictionary[write_position(out.len())] = byte;
+                out.push(byte);
+            }
+        }
+    }
+    out.truncate(raw_size);
+    out
+}
+
+/// Little-endian cursor over a TNEF or MAPI property stream
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(slice)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Read one attribute as level, attribute id, and data, skipping its checksum.
+    fn attribute(&mut self) -> Option<(u8, u16, &'a [u8])> {
+        let level = self.take(1)?[0];
+        let tag = self.u32()?;
+        let len = self.u32()? as usize;
+        let data = self.take(len)?;
+        self.take(2)?;
+        Some((level, (tag & 0xFFFF) as u16, data))
+    }
+
+    /// Read a length-prefixed value padded to 4 bytes.
+    fn padded_value(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()? as usize;
+        let value = self.take(len)?;
+        self.take((4 - len % 4) % 4)?;
+        Some(value)
+    }
+
+    /// Skip the GUID and name or id of a named property.
+    fn skip_property_name(&mut self) -> Option<()> {
+        self.take(16)?;
+        match self.u32()? {
+            0 => self.take(4).map(drop),
+            _ => self.padded_value().map(drop),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, decompress_rtf};
+
+    /// Tests decompression of the MS-OXRTFCP example stream.
+    #[test]
+    fn decompresses_rtf_example() {
+        let compressed = [
+            0x2d, 0x00, 0x00, 0x00, 0x2b, 0x00, 0x00, 0x00, 0x4c, 0x5a, 0x46, 0x75, 0xf1, 0xc5,
+            0xc7, 0xa7, 0x03, 0x00, 0x0a, 0x00, 0x72, 0x63, 0x70, 0x67, 0x31, 0x32, 0x35, 0x42,
+            0x32, 0x0a, 0xf3, 0x20, 0x68, 0x65, 0x6c, 0x09, 0x00, 0x20, 0x62, 0x77, 0x05, 0xb0,
+            0x6c, 0x64, 0x7d, 0x0a, 0x80, 0x0f, 0xa0,
+        ];
+        assert_eq!(
+            decompress_rtf(&compressed).expect("stream decompresses"),
+            b"{\\rtf1\\ansi\\ansicpg1252\\pard hello world}\r\n"
+        );
+    }
+
+    /// Tests that non-TNEF and truncated streams fail or stop cleanly.
+    #[test]
+    fn rejects_non_tnef_and_tolerates_truncation() {
+        assert!(decode(b"PK\x03\x04").is_err());
+        let truncated = [0x78, 0x9f, 0x3e, 0x22, 0x01, 0x00, 0x02, 0x02, 0x90, 0x06];
+        let content = decode(&truncated).expect("signature is valid");
+        assert!(content.attachments.is_empty() && content.body_text.is_none());
+    }
+}
@@ -3,6 +3,8 @@
 //! Defines all data structures used in MCP tool contracts. Each type is
 //! annotated with `JsonSchema` for automatic schema generation.
 
+use std::collections::BTreeMap;
+
 use chrono::{SecondsFormat, Utc};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -103,20 +105,34 @@ pub struct MessageSummary {
     pub uid: u32,
     /// Parsed Date header
     pub date: Option<String>,
-    /// Parsed From header
-    pub from: Option<String>,
+    /// Parsed From header addresses
+    pub from: Option<Vec<EmailAddress>>,
     /// Parsed Subject header
     pub subject: Option<String>,
     /// IMAP flags (e.g., `\Seen`, `\Flagged`)
     pub flags: Option<Vec<String>>,
     /// Optional subject snippet (present when `snippet_max_chars` was requested)
     pub snippet: Option<String>,
+    /// Whether `List-Id`, `List-Unsubscribe`, or `Precedence` mark this as list or bulk mail
+    pub is_bulk: bool,
+}
+
+/// Mailbox address parsed from an address-list header
+///
+/// Display names are decoded from RFC 2047 encoded words. Group syntax is
+/// flattened into its member addresses.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct EmailAddress {
+    /// Decoded display name, if present
+    pub name: Option<String>,
+    /// Address (`local@domain`)
+    pub email: String,
 }
 
 /// Attachment metadata
 ///
-/// Returned in message details. Includes optional extracted text for PDFs
-/// when `extract_attachment_text=true`.
+/// Returned in message details. Includes optional extracted text for
+/// supported document formats when `attachment_mode=extract_text`.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AttachmentInfo {
     /// Filename if present in Content-Disposition or Content-Type
@@ -127,7 +143,29 @@ pub struct AttachmentInfo {
     pub size_bytes: usize,
     /// Part ID for MIME structure (e.g., `1`, `2`, `3.1`)
     pub part_id: String,
-    /// Extracted text from PDF (if enabled and extraction succeeded)
+    /// Extracted attachment text (if enabled, supported, and extraction succeeded)
+    pub extracted_text: Option<String>,
+    /// Entries of a ZIP, gzip, or tar attachment (only when `attachment_mode=extract_text`)
+    pub archive_entries: Option<Vec<ArchiveEntry>>,
+}
+
+/// File inside an archive attachment
+///
+/// Entries of nested archives follow the nested archive, with its path as a
+/// prefix (`reports.zip/q1.csv`).
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ArchiveEntry {
+    /// Path inside the archive
+    pub path: String,
+    /// Uncompressed size in bytes as recorded by the archive
+    pub size_bytes: u64,
+    /// Compressed size in bytes, when the format records one
+    pub compressed_size_bytes: Option<u64>,
+    /// Whether the entry is encrypted; encrypted entries are never read
+    pub encrypted: bool,
+    /// Archive nesting level, 1 for entries of the attachment itself
+    pub depth: usize,
+    /// Extracted text for supported formats
     pub extracted_text: Option<String>,
 }
 
@@ -138,6 +176,7 @@ pub enum BodyMode {
     Text,
     Html,
     Both,
+    Markdown,
 }
 
 /// Requested attachment handling mode for `imap_get_message`.
@@ -169,12 +208,18 @@ pub struct MessageDetail {
     pub uid: u32,
     /// Parsed Date header
     pub date: Option<String>,
-    /// Parsed From header
-    pub from: Option<String>,
-    /// Parsed To header
-    pub to: Option<String>,
-    /// Parsed Cc header
-    pub cc: Option<String>,
+    /// Parsed From header addresses
+    pub from: Option<Vec<EmailAddress>>,
+    /// Parsed To header addresses
+    pub to: Option<Vec<EmailAddress>>,
+    /// Parsed Cc header addresses
+    pub cc: Option<Vec<EmailAddress>>,
+    /// Parsed Bcc header addresses (usually only present on sent mail and drafts)
+    pub bcc: Option<Vec<EmailAddress>>,
+    /// Parsed Reply-To header addresses
+    pub reply_to: Option<Vec<EmailAddress>>,
+    /// Parsed Sender header addresses
+    pub sender: Option<Vec<EmailAddress>>,
     /// Parsed Subject header
     pub subject: Option<String>,
     /// IMAP flags (e.g., `\Seen`, `\Flagged`)
@@ -185,8 +230,339 @@ pub struct MessageDetail {
     pub body_text: Option<String>,
     /// Sanitized HTML body (if `include_html=true`, truncated)
     pub body_html: Option<String>,
+    /// Markdown body (only for `body_mode=markdown`, truncated)
+    pub body_markdown: Option<String>,
+    /// Characters removed by quote and signature stripping (only with `new_content_only`)
+    pub stripped_content: Option<StrippedContent>,
     /// Attachment metadata (up to `MAX_ATTACHMENTS`)
     pub attachments: Option<Vec<AttachmentInfo>>,
+    /// Events parsed from `text/calendar` parts (omitted when there are none)
+    pub calendar_events: Option<Vec<CalendarEvent>>,
+    /// Messages embedded as `message/rfc822` parts (omitted when there are none)
+    pub embedded_messages: Option<Vec<EmbeddedMessage>>,
+    /// DKIM, SPF, DMARC, and ARC verdicts plus sender alignment
+    pub authentication: Option<MessageAuthentication>,
+    /// S/MIME and OpenPGP signature and encryption status
+    pub security: Option<MessageSecurity>,
+    /// Mailing-list metadata (omitted when the message has no list headers)
+    pub mailing_list: Option<MailingList>,
+    /// Links in the HTML body (only when `include_links=true`)
+    pub links: Option<Vec<MessageLink>>,
+    /// Remote images in the HTML body (only when `include_links=true`)
+    pub remote_images: Option<Vec<RemoteImage>>,
+}
+
+/// Email authentication summary for a message
+///
+/// Verdicts come from the topmost `Authentication-Results` header, which is
+/// the one added by the receiving server; lower headers may be forged by the
+/// sender. Each verdict is `pass`, `fail`, `neutral`, or `none` (not evaluated).
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MessageAuthentication {
+    /// DKIM verdict (from local verification when `verify_dkim=true`)
+    pub dkim: String,
+    /// SPF verdict
+    pub spf: String,
+    /// DMARC verdict
+    pub dmarc: String,
+    /// ARC verdict (receiver result, otherwise the claimed `ARC-Seal` chain state)
+    pub arc: String,
+    /// Parsed `Authentication-Results` and `ARC-Authentication-Results` entries
+    pub results: Vec<AuthenticationResult>,
+    /// Local DKIM verification results (only when `verify_dkim=true`)
+    pub dkim_signatures: Option<Vec<DkimSignatureResult>>,
+    /// Domain of the first From address
+    pub from_domain: Option<String>,
+    /// Domains of the Reply-To addresses
+    pub reply_to_domains: Vec<String>,
+    /// Whether any Reply-To domain belongs to a different organization than From
+    pub reply_to_misaligned: bool,
+}
+
+/// One method result from an authentication results header
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AuthenticationResult {
+    /// `authentication-results` or `arc-authentication-results`
+    pub source: String,
+    /// Header position among headers of the same source (0 = topmost)
+    pub position: usize,
+    /// ARC instance (`i=`) for `arc-authentication-results`
+    pub arc_instance: Option<u32>,
+    /// Identifier of the server that produced the result
+    pub authserv_id: String,
+    /// Method (e.g., `dkim`, `spf`, `dmarc`, `arc`)
+    pub method: String,
+    /// Result keyword as reported (e.g., `pass`, `fail`, `softfail`)
+    pub result: String,
+    /// Reason text, if given
+    pub reason: Option<String>,
+    /// Properties such as `header.d`, `smtp.mailfrom`, `header.from`
+    pub properties: BTreeMap<String, String>,
+}
+
+/// Outcome of verifying one `DKIM-Signature` header locally
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DkimSignatureResult {
+    /// Signing domain (`d=`)
+    pub domain: Option<String>,
+    /// Key selector (`s=`)
+    pub selector: Option<String>,
+    /// Signature algorithm (`a=`)
+    pub algorithm: Option<String>,
+    /// `pass`, `fail`, or `neutral` (key unavailable, lookup failed, or
+    /// body content appended after the `l=` length)
+    pub result: String,
+    /// Why the signature did not pass
+    pub reason: Option<String>,
+    /// Whether `domain` has the same organizational domain as the From address
+    pub aligned: bool,
+}
+
+/// Signature and encryption summary for a message
+///
+/// `layers` lists each S/MIME or OpenPGP signed or encrypted part in document
+/// order, outermost first.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MessageSecurity {
+    /// Whether any part of the message is encrypted
+    pub encrypted: bool,
+    /// Whether every encrypted layer was decrypted
+    pub decrypted: bool,
+    /// Whether any part of the message is signed
+    pub signed: bool,
+    /// Whether every signature verified
+    pub verified: bool,
+    /// Signed and encrypted layers
+    pub layers: Vec<SecurityLayer>,
+}
+
+/// One signed or encrypted MIME layer
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SecurityLayer {
+    /// Part carrying the protection (`multipart/signed`, `multipart/encrypted`, or `application/pkcs7-mime`)
+    pub part_id: String,
+    /// `smime` or `openpgp`
+    pub protocol: String,
+    /// `signed` or `encrypted`
+    pub kind: String,
+    /// Signed: `valid|untrusted|expired|invalid|unknown_key|unsupported|error`; encrypted: `decrypted|no_key|unsupported|limit_exceeded|error`
+    pub status: String,
+    /// Why the layer did not verify or decrypt, or why a valid signer is not trusted
+    pub reason: Option<String>,
+    /// Signer identity (signed layers only, when known)
+    pub signer: Option<SignerIdentity>,
+}
+
+/// Identity of a message signer
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SignerIdentity {
+    /// Display name from the certificate subject or OpenPGP user ID
+    pub name: Option<String>,
+    /// Email address from the certificate or OpenPGP user ID
+    pub email: Option<String>,
+    /// SHA-256 certificate fingerprint or OpenPGP key fingerprint (uppercase hex)
+    pub fingerprint: Option<String>,
+    /// Certificate issuer distinguished name (S/MIME only)
+    pub issuer: Option<String>,
+    /// Whether the signer chains to a configured trust anchor or is a configured OpenPGP key
+    pub trusted: bool,
+}
+
+/// Mailing-list metadata from `List-*` and `Precedence` headers
+///
+/// Unsubscribe targets come from `List-Unsubscribe`; `one_click_unsubscribe`
+/// means an HTTPS POST to `unsubscribe_url` unsubscribes without a browser
+/// (RFC 8058).
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MailingList {
+    /// List identifier from `List-Id` (lowercase, without angle brackets)
+    pub id: Option<String>,
+    /// Descriptive list name from `List-Id`
+    pub name: Option<String>,
+    /// First `mailto:` unsubscribe target
+    pub unsubscribe_mailto: Option<String>,
+    /// First `https:` or `http:` unsubscribe target
+    pub unsubscribe_url: Option<String>,
+    /// Whether `List-Unsubscribe-Post: List-Unsubscribe=One-Click` accompanies an HTTPS target
+    pub one_click_unsubscribe: bool,
+    /// First web URL from `List-Archive`
+    pub archive_url: Option<String>,
+    /// Lowercase `Precedence` header value
+    pub precedence: Option<String>,
+    /// Whether the headers mark this as list or bulk mail
+    pub is_bulk: bool,
+}
+
+/// Link found in an HTML body
+///
+/// `domain_mismatch` flags anchors whose visible text names a different
+/// organization than the link target, a common phishing pattern.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MessageLink {
+    /// Link target as written in the message (truncated to 2048 characters)
+    pub href: String,
+    /// Visible anchor text with markup removed
+    pub text: Option<String>,
+    /// Host of the target (the address domain for `mailto:` links)
+    pub domain: Option<String>,
+    /// Domain named by the visible text, when the text looks like a URL, host, or address
+    pub text_domain: Option<String>,
+    /// Whether `text_domain` belongs to a different organization than `domain`
+    pub domain_mismatch: bool,
+}
+
+/// Remote image referenced by an HTML body
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RemoteImage {
+    /// Image URL (truncated to 2048 characters)
+    pub src: String,
+    /// Host serving the image
+    pub domain: Option<String>,
+    /// Alternative text
+    pub alt: Option<String>,
+    /// Declared width in pixels, from the attribute or inline style
+    pub width: Option<u32>,
+    /// Declared height in pixels, from the attribute or inline style
+    pub height: Option<u32>,
+    /// Whether the image is likely a tracking pixel
+    pub tracking_pixel: bool,
+    /// Why the image was flagged: `tiny`, `hidden`, or `known_tracker`
+    pub tracking_reasons: Vec<String>,
+}
+
+/// Amount of quoted history and signature text removed from the bodies
+///
+/// Counts are measured before `body_max_chars` truncation. A non-zero count
+/// means refetching without `new_content_only` returns more content.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StrippedContent {
+    /// Characters removed from `body_text` (omitted when no text body was requested)
+    pub body_text_chars: Option<usize>,
+    /// Characters removed from `body_html` (omitted when no HTML body was requested)
+    pub body_html_chars: Option<usize>,
+    /// Characters removed from `body_markdown` (omitted when no Markdown body was requested)
+    pub body_markdown_chars: Option<usize>,
+}
+
+/// Message embedded in another message as a `message/rfc822` part
+///
+/// Forwarded-as-attachment emails and bounce reports carry whole messages.
+/// Nested embeddings are flattened into one list in document order; use
+/// `parent_part_id` and `depth` to reconstruct the tree.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EmbeddedMessage {
+    /// Part ID of the `message/rfc822` part (downloadable via `imap_get_attachment`)
+    pub part_id: String,
+    /// Part ID of the enclosing embedded message, if nested
+    pub parent_part_id: Option<String>,
+    /// Nesting depth (1 = embedded directly in the fetched message)
+    pub depth: usize,
+    /// Date header
+    pub date: Option<String>,
+    /// From header addresses
+    pub from: Option<Vec<EmailAddress>>,
+    /// To header addresses
+    pub to: Option<Vec<EmailAddress>>,
+    /// Cc header addresses
+    pub cc: Option<Vec<EmailAddress>>,
+    /// Subject header
+    pub subject: Option<String>,
+    /// Message-ID header
+    pub internet_message_id: Option<String>,
+    /// Plain text body (truncated to `body_max_chars` and a shared budget)
+    pub body_text: Option<String>,
+    /// Attachment metadata, with part ids addressing parts inside this message
+    pub attachments: Vec<AttachmentInfo>,
+}
+
+/// Calendar event parsed from an iCalendar (`text/calendar`) part
+///
+/// Returned in message details for meeting invites, cancellations, and
+/// replies, whether the calendar part is inline or attached.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CalendarEvent {
+    /// iTIP method of the calendar object (e.g., `REQUEST`, `CANCEL`, `REPLY`)
+    pub method: Option<String>,
+    /// Event UID shared by all updates of the same meeting
+    pub uid: Option<String>,
+    /// Event SEQUENCE; higher values supersede earlier updates
+    pub sequence: Option<u32>,
+    /// Event STATUS (e.g., `CONFIRMED`, `CANCELLED`)
+    pub status: Option<String>,
+    /// Event title
+    pub summary: Option<String>,
+    /// Event location
+    pub location: Option<String>,
+    /// Event organizer
+    pub organizer: Option<CalendarParticipant>,
+    /// Invited attendees
+    pub attendees: Vec<CalendarParticipant>,
+    /// Event start
+    pub start: Option<CalendarTime>,
+    /// Event end (derived from DURATION when DTEND is absent)
+    pub end: Option<CalendarTime>,
+    /// Recurrence rule as written in the invite (e.g., `FREQ=WEEKLY;BYDAY=MO`)
+    pub recurrence_rule: Option<String>,
+}
+
+/// Organizer or attendee of a calendar event
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CalendarParticipant {
+    /// Email address from the `mailto:` calendar address
+    pub email: Option<String>,
+    /// Display name (CN parameter)
+    pub name: Option<String>,
+    /// Participation status (e.g., `NEEDS-ACTION`, `ACCEPTED`, `DECLINED`, `TENTATIVE`)
+    pub partstat: Option<String>,
+    /// Participation role (e.g., `REQ-PARTICIPANT`, `OPT-PARTICIPANT`, `CHAIR`)
+    pub role: Option<String>,
+}
+
+/// Calendar date or date-time with timezone resolution
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CalendarTime {
+    /// Wall-clock value as written (`YYYY-MM-DD` or `YYYY-MM-DDTHH:MM:SS`)
+    pub local: String,
+    /// Timezone identifier from TZID, `UTC`, or absent for floating times
+    pub timezone: Option<String>,
+    /// Instant in UTC (RFC 3339) when the timezone could be resolved
+    pub utc: Option<String>,
+    /// Whether this is an all-day date without a time
+    pub all_day: bool,
+}
+
+/// One node of a message's MIME tree, derived from `BODYSTRUCTURE`
+///
+/// Multipart containers list their children in `parts`; a `message/rfc822`
+/// part has its body as the single child.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MimePart {
+    /// Part id accepted by `imap_get_attachment` (absent for multipart containers)
+    pub part_id: Option<String>,
+    /// IMAP section (`TEXT` or `<section>.TEXT` for a message's multipart body)
+    pub section: String,
+    /// Lowercase MIME content type (e.g., `multipart/mixed`, `image/png`)
+    pub content_type: String,
+    /// Content-Type parameters with lowercase names
+    pub parameters: BTreeMap<String, String>,
+    /// Lowercase Content-Disposition type (e.g., `inline`, `attachment`)
+    pub disposition: Option<String>,
+    /// Content-Disposition parameters with lowercase names
+    pub disposition_parameters: BTreeMap<String, String>,
+    /// Filename from the disposition or the `name` type parameter
+    pub filename: Option<String>,
+    /// Lowercase Content-Transfer-Encoding (absent for multipart containers)
+    pub encoding: Option<String>,
+    /// Encoded body size in octets (absent for multipart containers)
+    pub size_bytes: Option<u32>,
+    /// Body line count (text and `message/rfc822` parts only)
+    pub lines: Option<u32>,
+    /// Content-ID
+    pub content_id: Option<String>,
+    /// Content-Language tags
+    pub language: Option<Vec<String>>,
+    /// Child parts
+    pub parts: Vec<MimePart>,
 }
 
 /// Input: account_id only
@@ -227,6 +603,9 @@ pub struct SearchMessagesInput {
     /// Filter by Subject header
     #[schemars(length(min = 1, max = 256))]
     pub subject: Option<String>,
+    /// Filter by `List-Id` header (substring match, e.g. `news.example.com`)
+    #[schemars(length(min = 1, max = 256))]
+    pub list_id: Option<String>,
     /// Filter to unread messages only
     pub unread_only: Option<bool>,
     /// Filter to messages from last N days
@@ -247,6 +626,51 @@ pub struct SearchMessagesInput {
     pub snippet_max_chars: Option<usize>,
 }
 
+/// Input: aggregate statistics over a mailbox search
+///
+/// Used by `imap_mailbox_stats`. Accepts the same criteria as
+/// `imap_search_messages` and returns grouped counts instead of messages.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct MailboxStatsInput {
+    /// Account identifier (defaults to `"default"`)
+    #[serde(default = "default_account_id")]
+    #[schemars(length(min = 1, max = 64), pattern(r"^[A-Za-z0-9_-]+$"))]
+    pub account_id: String,
+    /// Mailbox to aggregate (e.g., `INBOX`, `Sent`, `Archive`)
+    #[schemars(length(min = 1, max = 256))]
+    pub mailbox: String,
+    /// Full-text search query
+    #[schemars(length(min = 1, max = 256))]
+    pub query: Option<String>,
+    /// Filter by From header
+    #[schemars(length(min = 1, max = 256))]
+    pub from: Option<String>,
+    /// Filter by To header
+    #[schemars(length(min = 1, max = 256))]
+    pub to: Option<String>,
+    /// Filter by Subject header
+    #[schemars(length(min = 1, max = 256))]
+    pub subject: Option<String>,
+    /// Filter by `List-Id` header (substring match, e.g. `news.example.com`)
+    #[schemars(length(min = 1, max = 256))]
+    pub list_id: Option<String>,
+    /// Filter to unread messages only
+    pub unread_only: Option<bool>,
+    /// Filter to messages from last N days
+    #[schemars(range(min = 1, max = 365), transform = remove_format)]
+    pub last_days: Option<u16>,
+    /// Filter to messages on or after this date (YYYY-MM-DD)
+    #[schemars(pattern(r"^\d{4}-\d{2}-\d{2}$"))]
+    pub start_date: Option<String>,
+    /// Filter to messages before this date (YYYY-MM-DD)
+    #[schemars(pattern(r"^\d{4}-\d{2}-\d{2}$"))]
+    pub end_date: Option<String>,
+    /// Maximum rows returned for sender and sender-domain tables (1..50, default 10)
+    #[serde(default = "default_top_n")]
+    #[schemars(range(min = 1, max = 50), transform = remove_format)]
+    pub top_n: usize,
+}
+
 /// Input: get parsed message details
 ///
 /// Used by `imap_get_message`. Supports bounded enrichment (char limits,
@@ -274,6 +698,15 @@ pub struct GetMessageInput {
     /// Maximum attachment text length (1..64000, requires `attachment_mode=extract_text`)
     #[schemars(range(min = 1, max = 64_000), transform = remove_format)]
     pub attachment_text_max_chars: Option<usize>,
+    /// Strip quoted replies, reply headers, and signatures from the body (default false)
+    #[serde(default)]
+    pub new_content_only: bool,
+    /// Verify DKIM signatures locally using the configured key file or DNS resolver (default false)
+    #[serde(default)]
+    pub verify_dkim: bool,
+    /// Return `links` and `remote_images` from the HTML body (default false)
+    #[serde(default)]
+    pub include_links: bool,
 }
 
 /// Input: get raw RFC822 message source
@@ -293,6 +726,36 @@ pub struct GetMessageRawInput {
     pub offset_bytes: usize,
 }
 
+/// Input: get decoded attachment content
+///
+/// Used by `imap_get_attachment`. Returns a bounded range of one body part's
+/// decoded bytes.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct GetAttachmentInput {
+    /// Stable message identifier
+    pub message_id: String,
+    /// Attachment part id as reported by `imap_get_message` (e.g. `1.2`)
+    #[schemars(length(min = 1, max = 64))]
+    pub part_id: String,
+    /// Maximum decoded bytes to return (1..64000, default 16000)
+    #[serde(default = "default_raw_max_bytes")]
+    #[schemars(range(min = 1, max = 64_000), transform = remove_format)]
+    pub max_bytes: usize,
+    /// Starting offset in the decoded attachment content
+    #[serde(default)]
+    #[schemars(transform = remove_format)]
+    pub offset_bytes: usize,
+}
+
+/// Input: get the MIME structure of a message
+///
+/// Used by `imap_get_message_structure`. No body content is downloaded.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct GetMessageStructureInput {
+    /// Stable message identifier
+    pub message_id: String,
+}
+
 /// Input: apply a bulk action to explicit messages.
 ///
 /// Used by `imap_apply_to_messages`. Message discovery happens via read tools;
@@ -302,7 +765,8 @@ pub struct ApplyToMessagesInput {
     /// Stable message identifiers to mutate
     #[schemars(length(min = 1, max = 250))]
     pub message_ids: Vec<String>,
-    /// Action to apply to the provided messages
+    /// Action to apply; `delete` moves to Trash (expunging only messages
+    /// already there), `permanent_delete` expunges immediately
     #[schemars(schema_with = "message_action_schema")]
     pub action: String,
     /// Destination mailbox for `move` and `copy`
@@ -342,6 +806,196 @@ pub struct ManageMailboxInput {
     pub destination_mailbox: Option<String>,
 }
 
+/// Input: export messages to the local export directory.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct ExportMessagesInput {
+    /// Stable message identifiers to export (exclusive with `cursor`)
+    #[schemars(length(min = 1, max = 250))]
+    pub message_ids: Option<Vec<String>>,
+    /// Search cursor whose remaining matches are exported (exclusive with `message_ids`)
+    pub cursor: Option<String>,
+    /// On-disk layout of the export
+    #[schemars(schema_with = "store_format_schema")]
+    pub format: String,
+    /// Path relative to the export directory: a directory for `eml` and
+    /// `maildir`, a new file for `mbox`
+    #[schemars(length(min = 1, max = 256))]
+    pub destination: String,
+}
+
+/// Input: import messages from the local import directory.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct ImportMessagesInput {
+    /// Account identifier (defaults to `"default"`)
+    #[serde(default = "default_account_id")]
+    #[schemars(length(min = 1, max = 64), pattern(r"^[A-Za-z0-9_-]+$"))]
+    pub account_id: String,
+    /// Existing mailbox the messages are appended to
+    #[schemars(length(min = 1, max = 256))]
+    pub mailbox: String,
+    /// On-disk layout of the source
+    #[schemars(schema_with = "store_format_schema")]
+    pub format: String,
+    /// Path relative to the import directory: an `.eml` file or directory,
+    /// an mbox file, or a Maildir directory
+    #[schemars(length(min = 1, max = 256))]
+    pub source: String,
+}
+
+/// Input: compose a message and save it to the Drafts mailbox.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct SaveDraftInput {
+    /// Account identifier (defaults to `"default"`)
+    #[serde(default = "default_account_id")]
+    #[schemars(length(min = 1, max = 64), pattern(r"^[A-Za-z0-9_-]+$"))]
+    pub account_id: String,
+    /// Sender such as `Jane <jane@example.com>` (defaults to the account's first identity)
+    #[schemars(length(min = 3, max = 320))]
+    pub from: Option<String>,
+    /// Primary recipient addresses
+    #[serde(default)]
+    #[schemars(length(max = 100))]
+    pub to: Vec<String>,
+    /// Carbon-copy recipient addresses
+    #[serde(default)]
+    #[schemars(length(max = 100))]
+    pub cc: Vec<String>,
+    /// Blind-copy recipient addresses
+    #[serde(default)]
+    #[schemars(length(max = 100))]
+    pub bcc: Vec<String>,
+    /// Subject line
+    #[serde(default)]
+    #[schemars(length(max = 998))]
+    pub subject: String,
+    /// Plain text body
+    #[serde(default)]
+    #[schemars(length(max = 1_000_000))]
+    pub body_text: String,
+    /// Optional HTML alternative to `body_text`
+    #[schemars(length(max = 1_000_000))]
+    pub body_html: Option<String>,
+    /// Files to attach
+    #[serde(default)]
+    #[schemars(length(max = 20))]
+    pub attachments: Vec<DraftAttachmentInput>,
+}
+
+/// Which kind of response `imap_create_reply_draft` composes.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplyMode {
+    Reply,
+    ReplyAll,
+    Forward,
+}
+
+/// How `imap_create_reply_draft` carries the original into a forward.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ForwardStyle {
+    Inline,
+    Attachment,
+}
+
+/// Input: compose a reply, reply-all, or forward draft for a message.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct ReplyDraftInput {
+    /// Stable message identifier of the message being answered
+    pub message_id: String,
+    /// `reply`, `reply_all`, or `forward` (defaults to `reply`)
+    #[serde(default = "default_reply_mode")]
+    pub mode: ReplyMode,
+    /// Text written above the quoted or forwarded original
+    #[serde(default)]
+    #[schemars(length(max = 1_000_000))]
+    pub body: String,
+    /// Quote the original text in replies (defaults to `true`; forwards always include it)
+    #[serde(default = "default_true")]
+    pub quote_original: bool,
+    /// Forward `inline` with the original's attachments, or as a `message/rfc822` `attachment`
+    #[serde(default = "default_forward_style")]
+    pub forward_as: ForwardStyle,
+    /// Additional primary recipients; required for forwards
+    #[serde(default)]
+    #[schemars(length(max = 100))]
+    pub to: Vec<String>,
+    /// Additional carbon-copy recipients
+    #[serde(default)]
+    #[schemars(length(max = 100))]
+    pub cc: Vec<String>,
+    /// Sender (defaults to the identity the original was addressed to)
+    #[schemars(length(min = 3, max = 320))]
+    pub from: Option<String>,
+}
+
+/// Input: send a composed message or an existing draft over SMTP.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct SendMessageInput {
+    /// Account identifier (defaults to `"default"`)
+    #[serde(default = "default_account_id")]
+    #[schemars(length(min = 1, max = 64), pattern(r"^[A-Za-z0-9_-]+$"))]
+    pub account_id: String,
+    /// Existing draft to send as-is (exclusive with the compose fields)
+    pub draft_message_id: Option<String>,
+    /// Sender such as `Jane <jane@example.com>` (defaults to the account's first identity)
+    #[schemars(length(min = 3, max = 320))]
+    pub from: Option<String>,
+    /// Primary recipient addresses
+    #[serde(default)]
+    #[schemars(length(max = 100))]
+    pub to: Vec<String>,
+    /// Carbon-copy recipient addresses
+    #[serde(default)]
+    #[schemars(length(max = 100))]
+    pub cc: Vec<String>,
+    /// Blind-copy recipient addresses, never shown to other recipients
+    #[serde(default)]
+    #[schemars(length(max = 100))]
+    pub bcc: Vec<String>,
+    /// Subject line
+    #[serde(default)]
+    #[schemars(length(max = 998))]
+    pub subject: String,
+    /// Plain text body
+    #[serde(default)]
+    #[schemars(length(max = 1_000_000))]
+    pub body_text: String,
+    /// Optional HTML alternative to `body_text`
+    #[schemars(length(max = 1_000_000))]
+    pub body_html: Option<String>,
+    /// Files to attach
+    #[serde(default)]
+    #[schemars(length(max = 20))]
+    pub attachments: Vec<DraftAttachmentInput>,
+    /// Append a copy to the Sent mailbox (defaults to `true` when write tools
+    /// are enabled, otherwise `false`; `true` requires write tools)
+    pub save_to_sent: Option<bool>,
+    /// Move the sent draft to Trash like `delete` (defaults to `true` when
+    /// write tools are enabled, otherwise `false`; `true` requires write tools)
+    pub delete_draft: Option<bool>,
+}
+
+/// One draft attachment: a file under the attachment directory or a part of
+/// an existing message.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct DraftAttachmentInput {
+    /// Path relative to the attachment directory (exclusive with `message_id`)
+    #[schemars(length(min = 1, max = 256))]
+    pub path: Option<String>,
+    /// Stable message identifier holding the part (requires `part_id`)
+    pub message_id: Option<String>,
+    /// Attachment part id as reported by `imap_get_message`
+    #[schemars(length(min = 1, max = 64))]
+    pub part_id: Option<String>,
+    /// Filename override (defaults to the file or part name)
+    #[schemars(length(min = 1, max = 255))]
+    pub filename: Option<String>,
+    /// MIME type override (defaults to the part type or `application/octet-stream`)
+    #[schemars(length(min = 3, max = 127))]
+    pub content_type: Option<String>,
+}
+
 /// Input: cancel a previously started write operation.
 #[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct OperationIdInput {
@@ -379,6 +1033,11 @@ fn default_limit() -> usize {
     10
 }
 
+/// Default value for `top_n` in mailbox_stats
+fn default_top_n() -> usize {
+    10
+}
+
 /// Default value for `body_max_chars` in get_message
 ///
 /// Provides enough context for most use cases without overwhelming output.
@@ -387,6 +1046,14 @@ fn default_body_max_chars() -> usize {
     2_000
 }
 
+fn default_reply_mode() -> ReplyMode {
+    ReplyMode::Reply
+}
+
+fn default_forward_style() -> ForwardStyle {
+    ForwardStyle::Inline
+}
+
 fn default_body_mode() -> BodyMode {
     BodyMode::Text
 }
@@ -395,7 +1062,7 @@ fn default_attachment_mode() -> AttachmentMode {
     AttachmentMode::Metadata
 }
 
-/// Default value for `max_bytes` in get_message_raw
+/// Default value for `max_bytes` in get_message_raw and get_attachment
 ///
 /// Large enough to capture common diagnostic slices without overwhelming
 /// context. 16KB is a practical default for raw message inspection.
@@ -406,7 +1073,7 @@ fn default_raw_max_bytes() -> usize {
 fn message_action_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
     schemars::json_schema!({
         "type": "string",
-        "enum": ["move", "copy", "delete"]
+        "enum": ["move", "copy", "delete", "permanent_delete"]
     })
 }
 
@@ -417,6 +1084,13 @@ fn mailbox_action_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema
     })
 }
 
+fn store_format_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+    schemars::json_schema!({
+        "type": "string",
+        "enum": ["eml", "mbox", "maildir"]
+    })
+}
+
 fn flag_operation_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
     schemars::json_schema!({
         "type": "string",
@@ -501,8 +1175,10 @@ mod tests {
     use serde_json::{Map, Value};
 
     use super::{
-        AccountOnlyInput, ApplyToMessagesInput, GetMessageInput, GetMessageRawInput,
-        GetOperationInput, ManageMailboxInput, OperationIdInput, SearchMessagesInput,
+        AccountOnlyInput, ApplyToMessagesInput, ExportMessagesInput, GetAttachmentInput,
+        GetMessageInput, GetMessageRawInput, GetMessageStructureInput, GetOperationInput,
+        ImportMessagesInput, MailboxStatsInput, ManageMailboxInput, OperationIdInput,
+        ReplyDraftInput, SaveDraftInput, SearchMessagesInput, SendMessageInput,
         UpdateMessageFlagsInput, validate_client_safe_input_schema,
     };
 
@@ -511,11 +1187,19 @@ mod tests {
         for schema in [
             schema_for_type::<AccountOnlyInput>(),
             schema_for_type::<SearchMessagesInput>(),
+            schema_for_type::<MailboxStatsInput>(),
             schema_for_type::<GetMessageInput>(),
             schema_for_type::<GetMessageRawInput>(),
+            schema_for_type::<GetAttachmentInput>(),
+            schema_for_type::<GetMessageStructureInput>(),
             schema_for_type::<ApplyToMessagesInput>(),
             schema_for_type::<UpdateMessageFlagsInput>(),
             schema_for_type::<ManageMailboxInput>(),
+            schema_for_type::<ExportMessagesInput>(),
+            schema_for_type::<ImportMessagesInput>(),
+            schema_for_type::<SaveDraftInput>(),
+            schema_for_type::<ReplyDraftInput>(),
+            schema_for_type::<SendMessageInput>(),
             schema_for_type::<GetOperationInput>(),
             schema_for_type::<OperationIdInput>(),
         ] {
@@ -523,6 +1207,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn mailbox_stats_schema_bounds_top_n() {
+        let schema = schema_for_type::<MailboxStatsInput>();
+        let properties = schema["properties"]
+            .as_object()
+            .expect("stats schema must expose properties");
+
+        assert_eq!(
+            schema_numeric_property(properties, "top_n", "minimum"),
+            Some(1)
+        );
+        assert_eq!(
+            schema_numeric_property(properties, "top_n", "maximum"),
+            Some(50)
+        );
+        assert!(!properties.contains_key("cursor"));
+    }
+
     #[test]
     fn search_messages_schema_matches_runtime_bounds() {
         let schema = schema_for_type::<SearchMessagesInput>();
@@ -659,6 +1361,11 @@ mod tests {
             schema_for_type::<ApplyToMessagesInput>(),
             schema_for_type::<UpdateMessageFlagsInput>(),
             schema_for_type::<ManageMailboxInput>(),
+            schema_for_type::<ExportMessagesInput>(),
+            schema_for_type::<ImportMessagesInput>(),
+            schema_for_type::<SaveDraftInput>(),
+            schema_for_type::<ReplyDraftInput>(),
+            schema_for_type::<SendMessageInput>(),
             schema_for_type::<GetOperationInput>(),
             schema_for_type::<OperationIdInput>(),
         ] {
@@ -3,13 +3,29 @@
 //! Manages search cursors with TTL and LRU-ish eviction. Cursors encode
 //! search state (UIDs, offset, filters) for efficient pagination
 //! across large result sets.
+//!
+//! When a signing key is configured, cursors are instead stateless: the
+//! search state is serialized into the cursor itself and protected by an
+//! HMAC-SHA256 tag, so any server instance holding the key can resume it.
+//! The payload is only signed, so the search criteria are readable by anyone
+//! holding the cursor.
 
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use uuid::Uuid;
 
+use crate::errors::{AppError, AppResult};
+
+/// Version prefix for stateless signed cursors
+const SIGNED_CURSOR_PREFIX: &str = "s1.";
+
 #[derive(Debug)]
 enum CursorClock {
     System,
@@ -182,13 +198,102 @@ impl CursorStore {
     }
 }
 
+/// Stateless signed cursor payload
+///
+/// Carries everything needed to resume a search without server-side state.
+/// Resuming re-runs the stored IMAP criteria bounded to UIDs below
+/// `last_uid`, so new arrivals never shift the remaining pages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedCursor {
+    /// Account identifier
+    #[serde(rename = "a")]
+    pub account_id: String,
+    /// Mailbox name
+    #[serde(rename = "m")]
+    pub mailbox: String,
+    /// Mailbox UIDVALIDITY at time of search
+    #[serde(rename = "v")]
+    pub uidvalidity: u32,
+    /// IMAP `UID SEARCH` criteria from the original request
+    #[serde(rename = "q")]
+    pub criteria: String,
+    /// Lowest UID already returned (next page starts below it)
+    #[serde(rename = "u")]
+    pub last_uid: u32,
+    /// Number of messages returned by previous pages
+    #[serde(rename = "o")]
+    pub offset: usize,
+    /// Snippet character limit from original search. `None` means snippets are disabled.
+    #[serde(rename = "s", default, skip_serializing_if = "Option::is_none")]
+    pub snippet_max_chars: Option<usize>,
+    /// Expiration as Unix timestamp in seconds
+    #[serde(rename = "e")]
+    pub expires_at_unix: i64,
+}
+
+/// Encode and sign a stateless cursor
+///
+/// Produces `s1.<payload>.<tag>` where both segments are URL-safe base64
+/// without padding.
+///
+/// # Errors
+///
+/// Returns `Internal` if the payload cannot be serialized or the key is
+/// rejected by the MAC implementation.
+pub fn encode_signed_cursor(key: &[u8], cursor: &SignedCursor) -> AppResult<String> {
+    let payload = serde_json::to_vec(cursor)
+        .map_err(|e| AppError::Internal(format!("failed to serialize cursor: {e}")))?;
+    let payload = URL_SAFE_NO_PAD.encode(payload);
+    let mut mac = cursor_mac(key)
+        .map_err(|e| AppError::Internal(format!("invalid cursor signing key: {e}")))?;
+    mac.update(payload.as_bytes());
+    let tag = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+    Ok(format!("{SIGNED_CURSOR_PREFIX}{payload}.{tag}"))
+}
+
+/// Verify and decode a stateless cursor
+///
+/// Returns `None` if the cursor is malformed, the signature does not verify,
+/// or the cursor expired before `now_unix`.
+pub fn decode_signed_cursor(key: &[u8], token: &str, now_unix: i64) -> Option<SignedCursor> {
+    let (payload, tag) = token.strip_prefix(SIGNED_CURSOR_PREFIX)?.split_once('.')?;
+    let tag = URL_SAFE_NO_PAD.decode(tag).ok()?;
+    let mut mac = cursor_mac(key).ok()?;
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&tag).ok()?;
+    let payload = URL_SAFE_NO_PAD.decode(payload).ok()?;
+    let cursor: SignedCursor = serde_json::from_slice(&payload).ok()?;
+    (cursor.expires_at_unix > now_unix).then_some(cursor)
+}
+
+fn cursor_mac(key: &[u8]) -> Result<Hmac<Sha256>, hmac::digest::InvalidLength> {
+    Hmac::<Sha256>::new_from_slice(key)
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
     use std::sync::atomic::{AtomicU64, Ordering};
     use std::time::Instant;
 
-    use super::{CursorEntry, CursorStore};
+    use super::{
+        CursorEntry, CursorStore, SignedCursor, decode_signed_cursor, encode_signed_cursor,
+    };
+
+    const KEY: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    fn signed_cursor() -> SignedCursor {
+        SignedCursor {
+            account_id: "default".to_owned(),
+            mailbox: "INBOX".to_owned(),
+            uidvalidity: 42,
+            criteria: "FROM \"alice\" UNSEEN".to_owned(),
+            last_uid: 120,
+            offset: 20,
+            snippet_max_chars: Some(200),
+            expires_at_unix: 2_000,
+        }
+    }
 
     /// Creates a test cursor entry with the given expiration time.
     ///
@@ -269,6 +374,34 @@ mod tests {
         assert_eq!(remaining, 2);
     }
 
+    /// Tests that a signed cursor round-trips before its expiry.
+    #[test]
+    fn signed_cursor_round_trips() {
+        let token = encode_signed_cursor(KEY, &signed_cursor()).expect("cursor encodes");
+        assert!(token.starts_with("s1."));
+        let decoded = decode_signed_cursor(KEY, &token, 1_000).expect("cursor must verify");
+        assert_eq!(decoded, signed_cursor());
+    }
+
+    /// Tests that tampered payloads, wrong keys, and expired cursors are rejected.
+    #[test]
+    fn signed_cursor_rejects_tampering_wrong_key_and_expiry() {
+        let token = encode_signed_cursor(KEY, &signed_cursor()).expect("cursor encodes");
+
+        let mut forged = signed_cursor();
+        forged.account_id = "work".to_owned();
+        let forged_token = encode_signed_cursor(b"another-key-another-key-another!", &forged)
+            .expect("cursor encodes");
+        let (_, forged_tag) = forged_token.rsplit_once('.').expect("tag present");
+        let (original_payload, _) = token.rsplit_once('.').expect("tag present");
+        let spliced = format!("{original_payload}.{forged_tag}");
+
+        assert!(decode_signed_cursor(KEY, &spliced, 1_000).is_none());
+        assert!(decode_signed_cursor(b"another-key-another-key-another!", &token, 1_000).is_none());
+        assert!(decode_signed_cursor(KEY, &token, 2_000).is_none());
+        assert!(decode_signed_cursor(KEY, "550e8400-e29b-41d4-a716-446655440000", 0).is_none());
+    }
+
     fn advance_ms(offset_ms: &Arc<AtomicU64>, amount: u64) {
         offset_ms.fetch_add(amount, Ordering::Relaxed);
     }
@@ -1,7 +1,12 @@
 //! MCP server implementation with tool handlers.
 
+mod attachment;
+mod draft;
 mod read;
+mod send;
 mod session_cache;
+mod stats;
+mod structure;
 mod types;
 mod validation;
 mod write_ops;
@@ -18,26 +23,49 @@ use tokio::sync::Mutex;
 
 use crate::config::ServerConfig;
 use crate::models::{
-    AccountInfo, AccountOnlyInput, ApplyToMessagesInput, GetMessageInput, GetMessageRawInput,
-    GetOperationInput, ManageMailboxInput, OperationIdInput, SearchMessagesInput,
-    UpdateMessageFlagsInput,
+    AccountInfo, AccountOnlyInput, ApplyToMessagesInput, ExportMessagesInput, GetAttachmentInput,
+    GetMessageInput, GetMessageRawInput, GetMessageStructureInput, GetOperationInput,
+    ImportMessagesInput, MailboxStatsInput, ManageMailboxInput, OperationIdInput, ReplyDraftInput,
+    SaveDraftInput, SearchMessagesInput, SendMessageInput, UpdateMessageFlagsInput,
 };
 use crate::pagination::CursorStore;
 
 use self::session_cache::{IdleSessionCache, ReadSessionCache, ReadSessionLease};
 use self::types::{
-    GetMessageData, GetMessageRawData, ListAccountsData, ListMailboxesData, OperationStatusData,
-    SearchResultData, StoredOperation, finalize_tool, operation_summary,
+    GetAttachmentData, GetMessageData, GetMessageRawData, GetMessageStructureData,
+    ListAccountsData, ListMailboxesData, MailboxStatsData, OperationStatusData, SaveDraftData,
+    SearchResultData, SendMessageData, StoredOperation, draft_summary, finalize_tool,
+    operation_summary, send_summary,
 };
 
 /// Maximum messages per search result page.
 const MAX_SEARCH_LIMIT: usize = 100;
 /// Maximum UID search results stored in a cursor snapshot.
 const MAX_CURSOR_UIDS_STORED: usize = 1_000;
+/// Maximum search matches scanned by `imap_mailbox_stats` (newest first).
+const MAX_STATS_MESSAGES: usize = 5_000;
+/// Maximum encoded body part size fetched by `imap_get_attachment`.
+const MAX_ATTACHMENT_FETCH_BYTES: usize = 25 * 1024 * 1024;
+/// Bytes fetched from a text/plain part when building search snippets.
+const SNIPPET_PLAIN_FETCH_BYTES: usize = 4_096;
+/// Bytes fetched from a text/html part when building search snippets.
+const SNIPPET_HTML_FETCH_BYTES: usize = 8_192;
+/// UIDs fetched per header/flag batch when computing mailbox stats.
+const STATS_FETCH_BATCH: usize = 250;
+/// Maximum per-day rows returned by `imap_mailbox_stats` (most recent first).
+const MAX_STATS_DAYS: usize = 90;
 /// Maximum number of explicit message ids accepted by bulk write tools.
 const MAX_BULK_MESSAGE_IDS: usize = 250;
 /// Valid built-in IMAP system flags.
 const VALID_SYSTEM_FLAGS: [&str; 5] = ["\\Seen", "\\Answered", "\\Flagged", "\\Deleted", "\\Draft"];
+/// Maximum number of messages exported by one `imap_export_messages` call.
+const MAX_EXPORT_MESSAGES: usize = 1_000;
+/// Maximum number of messages read by one `imap_import_messages` call.
+const MAX_IMPORT_MESSAGES: usize = 1_000;
+/// Maximum recipients across To, Cc, and Bcc of one draft.
+const MAX_DRAFT_RECIPIENTS: usize = 100;
+/// Maximum decoded bytes across all attachments of one draft.
+const MAX_DRAFT_ATTACHMENT_BYTES: usize = 25 * 1024 * 1024;
 /// Maximum wall-clock budget for inline write execution before switching to background mode.
 const WRITE_INLINE_BUDGET_MS: u64 = 1_500;
 
@@ -143,6 +171,24 @@ impl MailImapServer {
         finalize_tool(started, "imap_search_messages", result)
     }
 
+    #[tool(
+        name = "imap_mailbox_stats",
+        description = "Aggregate search matches by sender, domain, day, flag, and size"
+    )]
+    async fn mailbox_stats(
+        &self,
+        Parameters(input): Parameters<MailboxStatsInput>,
+    ) -> Result<Json<crate::models::ToolEnvelope<MailboxStatsData>>, ErrorData> {
+        let started = Instant::now();
+        finalize_tool(
+            started,
+            "imap_mailbox_stats",
+            self.mailbox_stats_impl(input)
+                .await
+                .map(|data| (format!("{} message(s) aggregated", data.scanned), data)),
+        )
+    }
+
     #[tool(name = "imap_get_message", description = "Get parsed message details")]
     async fn get_message(
         &self,
@@ -176,6 +222,42 @@ impl MailImapServer {
         )
     }
 
+    #[tool(
+        name = "imap_get_message_structure",
+        description = "Get the MIME part tree without downloading bodies"
+    )]
+    async fn get_message_structure(
+        &self,
+        Parameters(input): Parameters<GetMessageStructureInput>,
+    ) -> Result<Json<crate::models::ToolEnvelope<GetMessageStructureData>>, ErrorData> {
+        let started = Instant::now();
+        finalize_tool(
+            started,
+            "imap_get_message_structure",
+            self.get_message_structure_impl(input)
+                .await
+                .map(|data| ("Message structure retrieved".to_owned(), data)),
+        )
+    }
+
+    #[tool(
+        name = "imap_get_attachment",
+        description = "Get bounded decoded attachment content"
+    )]
+    async fn get_attachment(
+        &self,
+        Parameters(input): Parameters<GetAttachmentInput>,
+    ) -> Result<Json<crate::models::ToolEnvelope<GetAttachmentData>>, ErrorData> {
+        let started = Instant::now();
+        finalize_tool(
+            started,
+            "imap_get_attachment",
+            self.get_attachment_impl(input)
+                .await
+                .map(|data| ("Attachment retrieved".to_owned(), data)),
+        )
+    }
+
     #[tool(
         name = "imap_apply_to_messages",
         description = "Apply one mutation action to explicit messages"
@@ -230,6 +312,96 @@ impl MailImapServer {
         )
     }
 
+    #[tool(
+        name = "imap_export_messages",
+        description = "Export messages to eml, mbox, or Maildir in the export directory"
+    )]
+    async fn export_messages(
+        &self,
+        Parameters(input): Parameters<ExportMessagesInput>,
+    ) -> Result<Json<crate::models::ToolEnvelope<OperationStatusData>>, ErrorData> {
+        let started = Instant::now();
+        finalize_tool(
+            started,
+            "imap_export_messages",
+            self.export_messages_impl(input)
+                .await
+                .map(|data| (operation_summary(&data.status, &data.operation.kind), data)),
+        )
+    }
+
+    #[tool(
+        name = "imap_import_messages",
+        description = "Import eml, mbox, or Maildir messages from the import directory into a mailbox"
+    )]
+    async fn import_messages(
+        &self,
+        Parameters(input): Parameters<ImportMessagesInput>,
+    ) -> Result<Json<crate::models::ToolEnvelope<OperationStatusData>>, ErrorData> {
+        let started = Instant::now();
+        finalize_tool(
+            started,
+            "imap_import_messages",
+            self.import_messages_impl(input)
+                .await
+                .map(|data| (operation_summary(&data.status, &data.operation.kind), data)),
+        )
+    }
+
+    #[tool(
+        name = "imap_save_draft",
+        description = "Compose a message and save it to the Drafts mailbox"
+    )]
+    async fn save_draft(
+        &self,
+        Parameters(input): Parameters<SaveDraftInput>,
+    ) -> Result<Json<crate::models::ToolEnvelope<SaveDraftData>>, ErrorData> {
+        let started = Instant::now();
+        finalize_tool(
+            started,
+            "imap_save_draft",
+            self.save_draft_impl(input)
+                .await
+                .map(|data| (draft_summary(&data), data)),
+        )
+    }
+
+    #[tool(
+        name = "imap_create_reply_draft",
+        description = "Compose a reply, reply-all, or forward of a message and save it to Drafts"
+    )]
+    async fn create_reply_draft(
+        &self,
+        Parameters(input): Parameters<ReplyDraftInput>,
+    ) -> Result<Json<crate::models::ToolEnvelope<SaveDraftData>>, ErrorData> {
+        let started = Instant::now();
+        finalize_tool(
+            started,
+            "imap_create_reply_draft",
+            self.create_reply_draft_impl(input)
+                .await
+                .map(|data| (draft_summary(&data), data)),
+        )
+    }
+
+    #[tool(
+        name = "imap_send_message",
+        description = "Send a composed message or an existing draft over SMTP"
+    )]
+    async fn send_message(
+        &self,
+        Parameters(input): Parameters<SendMessageInput>,
+    ) -> Result<Json<crate::models::ToolEnvelope<SendMessageData>>, ErrorData> {
+        let started = Instant::now();
+        finalize_tool(
+            started,
+            "imap_send_message",
+            self.send_message_impl(input)
+                .await
+                .map(|data| (send_summary(&data), data)),
+        )
+    }
+
     #[tool(
         name = "imap_get_operation",
         description = "Get the status of a background IMAP write operation"
@@ -313,8 +485,9 @@ mod tests {
         GetOperationInput, OperationIdInput, ToolEnvelope, validate_client_safe_input_schema,
     };
     use crate::server::types::{
-        GetMessageData, GetMessageRawData, ListAccountsData, ListMailboxesData, MailboxAction,
-        MailboxManagementResult, ManageMailboxOperation, OperationResultData, OperationState,
+        GetAttachmentData, GetMessageData, GetMessageRawData, GetMessageStructureData,
+        ListAccountsData, ListMailboxesData, MailboxAction, MailboxManagementResult,
+        MailboxStatsData, ManageMailboxOperation, OperationResultData, OperationState,
         OperationStatusData, SearchResultData, StoredOperation, StoredOperationSpec,
     };
 
@@ -390,6 +563,10 @@ mod tests {
                 "imap_search_messages",
                 schema_for_output::<ToolEnvelope<SearchResultData>>().expect("valid schema"),
             ),
+            (
+                "imap_mailbox_stats",
+                schema_for_output::<ToolEnvelope<MailboxStatsData>>().expect("valid schema"),
+            ),
             (
                 "imap_get_message",
                 schema_for_output::<ToolEnvelope<GetMessageData>>().expect("valid schema"),
@@ -398,6 +575,14 @@ mod tests {
                 "imap_get_message_raw",
                 schema_for_output::<ToolEnvelope<GetMessageRawData>>().expect("valid schema"),
             ),
+            (
+                "imap_get_message_structure",
+                schema_for_output::<ToolEnvelope<GetMessageStructureData>>().expect("valid schema"),
+            ),
+            (
+                "imap_get_attachment",
+                schema_for_output::<ToolEnvelope<GetAttachmentData>>().expect("valid schema"),
+            ),
             (
                 "imap_apply_to_messages",
                 schema_for_output::<ToolEnvelope<OperationStatusData>>().expect("valid schema"),
@@ -410,6 +595,26 @@ mod tests {
                 "imap_manage_mailbox",
                 schema_for_output::<ToolEnvelope<OperationStatusData>>().expect("valid schema"),
             ),
+            (
+                "imap_export_messages",
+                schema_for_output::<ToolEnvelope<OperationStatusData>>().expect("valid schema"),
+            ),
+            (
+                "imap_import_messages",
+                schema_for_output::<ToolEnvelope<OperationStatusData>>().expect("valid schema"),
+            ),
+            (
+                "imap_save_draft",
+                schema_for_output::<ToolEnvelope<SaveDraftData>>().expect("valid schema"),
+            ),
+            (
+                "imap_create_reply_draft",
+                schema_for_output::<ToolEnvelope<SaveDraftData>>().expect("valid schema"),
+            ),
+            (
+                "imap_send_message",
+                schema_for_output::<ToolEnvelope<SendMessageData>>().expect("valid schema"),
+            ),
             (
                 "imap_get_operation",
                 schema_for_output::<ToolEnvelope<OperationStatusData>>().expect("valid schema"),
@@ -443,14 +648,22 @@ mod tests {
             accounts: BTreeMap::new(),
             trusted_ca_certs: Vec::new(),
             write_enabled: false,
+            send_enabled: false,
+            permanent_delete_enabled: false,
             connect_timeout_ms: 30_000,
             greeting_timeout_ms: 15_000,
             socket_timeout_ms: 300_000,
             cursor_ttl_seconds: 600,
             cursor_max_entries: 512,
+            cursor_signing_key: None,
             read_session_cache_ttl_seconds: 120,
             read_session_cache_max_per_account: 4,
             operation_max_entries: 256,
+            dkim_keys: BTreeMap::new(),
+            dkim_dns_server: None,
+            export_dir: None,
+            import_dir: None,
+            attachment_dir: None,
         }
     }
 
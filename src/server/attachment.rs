@@ -0,0 +1,540 @@
+use base64::Engine;
+
+use crate::config::ServerConfig;
+use crate::errors::{AppError, AppResult};
+use crate::imap;
+use crate::message_id::MessageId;
+use crate::mime;
+use crate::models::GetAttachmentInput;
+
+use super::read::{ensure_uidvalidity_matches_readonly, release_read_session};
+use super::types::{GetAttachmentData, ToolIssue, build_message_uri, log_runtime_issues};
+use super::validation::{attachment_section, parse_and_validate_message_id, validate_chars};
+use super::{MAX_ATTACHMENT_FETCH_BYTES, MailImapServer};
+
+/// Encoded bytes read from each end of a base64 part to learn its line layout
+const BASE64_PROBE_BYTES: usize = 1024;
+
+/// Decoded bytes of one requested range and the decoded size of the part
+struct DecodedChunk {
+    bytes: Vec<u8>,
+    total_size_bytes: usize,
+}
+
+/// Failure stage and error of a chunk read
+type ChunkFailure = (&'static str, AppError);
+
+impl MailImapServer {
+    pub(super) async fn get_attachment_impl(
+        &self,
+        input: GetAttachmentInput,
+    ) -> AppResult<GetAttachmentData> {
+        validate_chars(input.max_bytes, 1, 64_000, "max_bytes")?;
+
+        let message_id = parse_and_validate_message_id(&input.message_id)?;
+        let section = attachment_section(&input.part_id)?;
+
+        let mut session = match self.checkout_read_session(&message_id.account_id).await {
+            Ok(session) => session,
+            Err(error) => {
+                let issue = ToolIssue::from_error("connect_authenticated", &error);
+                return Ok(failed_attachment(&message_id, &input, issue));
+            }
+        };
+        if let Err(error) =
+            ensure_uidvalidity_matches_readonly(&self.config, session.session(), &message_id).await
+        {
+            let _ = release_read_session(self, session, false).await;
+            return Err(error);
+        }
+
+        let part = match imap::fetch_body_part_ref(
+            &self.config,
+            session.session(),
+            message_id.uid,
+            &section,
+            mime::is_root_part_id(&input.part_id),
+        )
+        .await
+        {
+            Ok(Some(part)) => part,
+            Ok(None) => {
+                let _ = release_read_session(self, session, true).await;
+                return Err(AppError::NotFound(format!(
+                    "part_id {} not found in message",
+                    input.part_id
+                )));
+            }
+            Err(error) => {
+                let _ = release_read_session(self, session, false).await;
+                let issue = ToolIssue::from_error("fetch_body_structure", &error);
+                return Ok(failed_attachment(&message_id, &input, issue));
+            }
+        };
+        if part.encoded_size_bytes > MAX_ATTACHMENT_FETCH_BYTES {
+            let _ = release_read_session(self, session, true).await;
+            return Err(AppError::InvalidInput(format!(
+                "attachment is {} encoded bytes; the maximum fetchable size is {MAX_ATTACHMENT_FETCH_BYTES}",
+                part.encoded_size_bytes
+            )));
+        }
+
+        let chunk = read_chunk(
+            self,
+            session.session(),
+            message_id.uid,
+            &section,
+            &part,
+            input.offset_bytes,
+            input.max_bytes,
+        )
+        .await;
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err((stage, error)) => {
+                let _ = release_read_session(self, session, stage != "fetch_body_section").await;
+                let issue = ToolIssue::from_error(stage, &error);
+                let mut data = failed_attachment(&message_id, &input, issue);
+                if stage == "decode_transfer_encoding" {
+                    data.filename = part.filename;
+                    data.content_type = Some(part.content_type);
+                }
+                return Ok(data);
+            }
+        };
+        let _ = release_read_session(self, session, true).await;
+
+        let total_size_bytes = chunk.total_size_bytes;
+        if input.offset_bytes > total_size_bytes {
+            return Err(AppError::InvalidInput(
+                "offset_bytes must not exceed total attachment size".to_owned(),
+            ));
+        }
+        let chunk = chunk.bytes;
+        let end = input.offset_bytes + chunk.len();
+
+        Ok(GetAttachmentData {
+            status: "ok".to_owned(),
+            issues: Vec::new(),
+            account_id: message_id.account_id.clone(),
+            message_id: message_id.encode(),
+            message_uri: build_message_uri(
+                &message_id.account_id,
+                &message_id.mailbox,
+                message_id.uidvalidity,
+                message_id.uid,
+            ),
+            part_id: input.part_id,
+            filename: part.filename,
+            content_type: Some(part.content_type),
+            total_size_bytes,
+            returned_bytes: chunk.len(),
+            offset_bytes: input.offset_bytes,
+            truncated: end < total_size_bytes,
+            content_base64: Some(base64::engine::general_purpose::STANDARD.encode(chunk)),
+        })
+    }
+}
+
+/// Read `max_bytes` of decoded content at `offset` from one part
+///
+/// Identity encodings fetch exactly the range with a partial `BODY.PEEK`.
+/// Base64 parts with uniform line lengths fetch the aligned encoded window
+/// covering the range, after probing both ends of the part for its layout and
+/// padding. Quoted-printable and irregular base64 parts are fetched whole,
+/// since their encoded offsets cannot be computed. `BINARY.PEEK` (RFC 3516)
+/// is not used because the IMAP client cannot parse `BINARY[]` responses.
+async fn read_chunk(
+    server: &MailImapServer,
+    session: &mut imap::ImapSession,
+    uid: u32,
+    section: &str,
+    part: &imap::BodyPartRef,
+    offset: usize,
+    max_bytes: usize,
+) -> Result<DecodedChunk, ChunkFailure> {
+    let config = &server.config;
+    match part.transfer_encoding.as_str() {
+        "base64" if part.encoded_size_bytes > 2 * BASE64_PROBE_BYTES => {
+            match read_base64_window(config, session, uid, section, part, offset, max_bytes).await?
+            {
+                Some(chunk) => Ok(chunk),
+                None => {
+                    read_whole_chunk(server, session, uid, section, part, offset, max_bytes).await
+                }
+            }
+        }
+        "base64" | "quoted-printable" => {
+            read_whole_chunk(server, session, uid, section, part, offset, max_bytes).await
+        }
+        _ => {
+            let total_size_bytes = part.encoded_size_bytes;
+            let bytes = if offset < total_size_bytes {
+                fetch_range(config, session, uid, section, offset, max_bytes).await?
+            } else {
+                Vec::new()
+            };
+            Ok(DecodedChunk {
+                bytes,
+                total_size_bytes,
+            })
+        }
+    }
+}
+
+/// Read a range of a base64 part through its aligned encoded window
+///
+/// Returns `Ok(None)` when the part's lines are not uniform.
+async fn read_base64_window(
+    config: &ServerConfig,
+    session: &mut imap::ImapSession,
+    uid: u32,
+    section: &str,
+    part: &imap::BodyPartRef,
+    offset: usize,
+    max_bytes: usize,
+) -> Result<Option<DecodedChunk>, ChunkFailure> {
+    let head = fetch_range(config, session, uid, section, 0, BASE64_PROBE_BYTES).await?;
+    let Some(layout) = Base64Layout::from_head(&head) else {
+        return Ok(None);
+    };
+    let tail_start = part.encoded_size_bytes - BASE64_PROBE_BYTES;
+    let tail = fetch_range(
+        config,
+        session,
+        uid,
+        section,
+        tail_start,
+        BASE64_PROBE_BYTES,
+    )
+    .await?;
+    let Some(total) = layout.total(tail_start, &tail) else {
+        return Ok(None);
+    };
+    let window = layout.window(offset, max_bytes, total);
+    let bytes = if window.char_len == 0 {
+        Vec::new()
+    } else {
+        let encoded = fetch_range(
+            config,
+            session,
+            uid,
+            section,
+            window.encoded_offset,
+            window.encoded_len,
+        )
+        .await?;
+        let Some(bytes) = layout.decode_window(&window, &encoded, max_bytes) else {
+            return Ok(None);
+        };
+        bytes
+    };
+    Ok(Some(DecodedChunk {
+        bytes,
+        total_size_bytes: total.decoded_bytes(),
+    }))
+}
+
+async fn fetch_range(
+    config: &ServerConfig,
+    session: &mut imap::ImapSession,
+    uid: u32,
+    section: &str,
+    offset: usize,
+    len: usize,
+) -> Result<Vec<u8>, ChunkFailure> {
+    imap::fetch_body_section_range(config, session, uid, section, offset, len)
+        .await
+        .map_err(|error| ("fetch_body_section", error))
+}
+
+/// Fetch and decode a whole part, then cut the requested range.
+async fn read_whole_chunk(
+    server: &MailImapServer,
+    session: &mut imap::ImapSession,
+    uid: u32,
+    section: &str,
+    part: &imap::BodyPartRef,
+    offset: usize,
+    max_bytes: usize,
+) -> Result<DecodedChunk, ChunkFailure> {
+    let encoded = imap::fetch_body_section(&server.config, session, uid, section)
+        .await
+        .map_err(|error| ("fetch_body_section", error))?;
+    let decoded = mime::decode_transfer_encoding(&encoded, &part.transfer_encoding)
+        .map_err(|error| ("decode_transfer_encoding", error))?;
+    let total_size_bytes = decoded.len();
+    let start = offset.min(total_size_bytes);
+    let end = offset.saturating_add(max_bytes).min(total_size_bytes);
+    Ok(DecodedChunk {
+        bytes: decoded[start..end].to_vec(),
+        total_size_bytes,
+    })
+}
+
+/// Line layout of a base64 body whose lines all have the same length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Base64Layout {
+    /// Base64 characters per full line, a multiple of 4
+    line_chars: usize,
+    /// Line terminator length: 2 for CRLF, 1 for a bare LF
+    terminator: usize,
+}
+
+/// Size of a base64 body in characters, padding included
+#[derive(Debug, Clone, Copy)]
+struct Base64Total {
+    chars: usize,
+    padding: usize,
+}
+
+impl Base64Total {
+    fn decoded_bytes(self) -> usize {
+        self.chars / 4 * 3 - self.padding
+    }
+}
+
+/// Encoded byte range covering a decoded range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Base64Window {
+    encoded_offset: usize,
+    encoded_len: usize,
+    /// Base64 characters in the window, a multiple of 4
+    char_len: usize,
+    /// Decoded bytes before the requested offset in the first quantum
+    skip: usize,
+}
+
+impl Base64Layout {
+    /// Learn the layout from the first line of the body.
+    fn from_head(head: &[u8]) -> Option<Self> {
+        let newline = head.iter().position(|byte| *byte == b'\n')?;
+        let (line_chars, terminator) = match newline.checked_sub(1) {
+            Some(cr) if head[cr] == b'\r' => (cr, 2),
+            _ => (newline, 1),
+        };
+        (line_chars > 0 && line_chars.is_multiple_of(4)).then_some(Self {
+            line_chars,
+            terminator,
+        })
+    }
+
+    fn line_len(self) -> usize {
+        self.line_chars + self.terminator
+    }
+
+    /// Encoded offset of the base64 character at index `chars`.
+    fn encoded_offset(self, chars: usize) -> usize {
+        chars / self.line_chars * self.line_len() + chars % self.line_chars
+    }
+
+    /// Base64 characters before encoded offset `encoded`.
+    fn chars_before(self, encoded: usize) -> usize {
+        encoded / self.line_len() * self.line_chars
+            + (encoded % self.line_len()).min(self.line_chars)
+    }
+
+    /// Whether `bytes` read at encoded offset `start` follow this layout
+    ///
+    /// The last line may be short; only whitespace may follow it.
+    fn conforms(self, start: usize, bytes: &[u8]) -> bool {
+        let mut ended = false;
+        bytes.iter().enumerate().all(|(index, byte)| {
+            if ended {
+                return byte.is_ascii_whitespace();
+            }
+            let column = (start + index) % self.line_len();
+            let expected_terminator = match column.checked_sub(self.line_chars) {
+                None => None,
+                Some(0) if self.terminator == 2 => Some(b'\r'),
+                Some(_) => Some(b'\n'),
+            };
+            match expected_terminator {
+                Some(expected) => *byte == expected,
+                None if is_base64_char(*byte) => true,
+                None => {
+                    ended = byte.is_ascii_whitespace();
+                    ended
+                }
+            }
+        })
+    }
+
+    /// Size of the body from the `tail` bytes read at encoded offset `tail_start`.
+    fn total(self, tail_start: usize, tail: &[u8]) -> Option<Base64Total> {
+        if !self.conforms(tail_start, tail) {
+            return None;
+        }
+        let tail_chars = tail.iter().filter(|byte| is_base64_char(**byte)).count();
+        let chars = self.chars_before(tail_start) + tail_chars;
+        let padding = tail.iter().filter(|byte| **byte == b'=').count();
+        (chars.is_multiple_of(4) && padding <= 2 && chars > 0)
+            .then_some(Base64Total { chars, padding })
+    }
+
+    /// Encoded window covering `max_bytes` decoded bytes at `offset`.
+    fn window(self, offset: usize, max_bytes: usize, total: Base64Total) -> Base64Window {
+        let first_char = (offset / 3 * 4).min(total.chars);
+        let skip = offset % 3;
+        let wanted_chars = (skip + max_bytes).div_ceil(3) * 4;
+        let end_char = first_char.saturating_add(wanted_chars).min(total.chars);
+        let encoded_offset = self.encoded_offset(first_char);
+        Base64Window {
+            encoded_offset,
+            encoded_len: self.encoded_offset(end_char) - encoded_offset,
+            char_len: end_char - first_char,
+            skip,
+        }
+    }
+
+    /// Decode a fetched window and cut it to the requested range.
+    fn decode_window(
+        self,
+        window: &Base64Window,
+        encoded: &[u8],
+        max_bytes: usize,
+    ) -> Option<Vec<u8>> {
+        if !self.conforms(window.encoded_offset, encoded) {
+            return None;
+        }
+        let chars: Vec<u8> = encoded
+            .iter()
+            .copied()
+            .filter(|byte| is_base64_char(*byte))
+            .collect();
+        if chars.len() != window.char_len {
+            return None;
+        }
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(chars)
+            .ok()?;
+        let start = window.skip.min(decoded.len());
+        let end = window.skip.saturating_add(max_bytes).min(decoded.len());
+        Some(decoded[start..end].to_vec())
+    }
+}
+
+fn is_base64_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'+' | b'/' | b'=')
+}
+
+/// Build a `failed` response after a runtime error, logging the issue.
+fn failed_attachment(
+    message_id: &MessageId,
+    input: &GetAttachmentInput,
+    issue: ToolIssue,
+) -> GetAttachmentData {
+    let encoded_message_id = message_id.encode();
+    let issues = vec![
+        issue
+            .with_uid(message_id.uid)
+            .with_message_id(&encoded_message_id),
+    ];
+    log_runtime_issues(
+        "imap_get_attachment",
+        "failed",
+        &message_id.account_id,
+        Some(&message_id.mailbox),
+        &issues,
+    );
+    GetAttachmentData {
+        status: "failed".to_owned(),
+        issues,
+        account_id: message_id.account_id.clone(),
+        message_id: encoded_message_id,
+        message_uri: build_message_uri(
+            &message_id.account_id,
+            &message_id.mailbox,
+            message_id.uidvalidity,
+            message_id.uid,
+        ),
+        part_id: input.part_id.clone(),
+        filename: None,
+        content_type: None,
+        total_size_bytes: 0,
+        returned_bytes: 0,
+        offset_bytes: input.offset_bytes,
+        truncated: false,
+        content_base64: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+
+    use super::{Base64Layout, Base64Window};
+    use crate::imap::section_range_query;
+
+    /// Encode with MIME-style 76-character CRLF lines.
+    fn mime_base64(data: &[u8]) -> Vec<u8> {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+        let mut out = Vec::new();
+        for line in encoded.as_bytes().chunks(76) {
+            out.extend_from_slice(line);
+            out.extend_from_slice(b"\r\n");
+        }
+        out
+    }
+
+    /// Tests that a base64 range fetches only its aligned encoded window.
+    #[test]
+    fn fetches_aligned_base64_window_for_a_range() {
+        let data: Vec<u8> = (0..200_000u32).map(|n| (n * 7 % 251) as u8).collect();
+        let encoded = mime_base64(&data);
+        let layout = Base64Layout::from_head(&encoded[..1024]).expect("uniform layout");
+        assert_eq!(
+            layout,
+            Base64Layout {
+                line_chars: 76,
+                terminator: 2
+            }
+        );
+        let tail_start = encoded.len() - 1024;
+        let total = layout
+            .total(tail_start, &encoded[tail_start..])
+            .expect("tail follows the layout");
+        assert_eq!(total.decoded_bytes(), data.len());
+
+        let window = layout.window(64_000, 16_000, total);
+        assert_eq!(
+            window,
+            Base64Window {
+                encoded_offset: 87_576,
+                encoded_len: 21_898,
+                char_len: 21_336,
+                skip: 1,
+            }
+        );
+        assert_eq!(
+            section_range_query("2", window.encoded_offset, window.encoded_len),
+            "BODY.PEEK[2]<87576.21898>"
+        );
+        let fetched = &encoded[window.encoded_offset..window.encoded_offset + window.encoded_len];
+        let chunk = layout
+            .decode_window(&window, fetched, 16_000)
+            .expect("window decodes");
+        assert_eq!(chunk, &data[64_000..80_000]);
+
+        let last = layout.window(data.len() - 10, 16_000, total);
+        let fetched = &encoded[last.encoded_offset..last.encoded_offset + last.encoded_len];
+        let chunk = layout
+            .decode_window(&last, fetched, 16_000)
+            .expect("final window decodes");
+        assert_eq!(chunk, &data[data.len() - 10..]);
+    }
+
+    /// Tests that irregular line lengths are detected so the part is fetched whole.
+    #[test]
+    fn rejects_base64_with_irregular_lines() {
+        let layout = Base64Layout {
+            line_chars: 76,
+            terminator: 2,
+        };
+        let mut encoded = mime_base64(&[1u8; 600]);
+        encoded.splice(100..100, b"\r\n".iter().copied());
+        assert!(!layout.conforms(0, &encoded));
+        assert!(Base64Layout::from_head(b"QUJD\r\n").is_some());
+        assert!(Base64Layout::from_head(b"QUJDRA\r\n").is_none());
+    }
+}
@@ -0,0 +1,1053 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::Local;
+
+use crate::compose::{self, Attachment, Draft, Mailbox};
+use crate::config::{AccountConfig, ServerConfig};
+use crate::errors::{AppError, AppResult};
+use crate::imap::{self, FetchedMessage, ImapSession, SpecialUse};
+use crate::mailstore;
+use crate::message_id::MessageId;
+use crate::mime;
+use crate::mime::ParsedMessage;
+use crate::models::{
+    AttachmentMode, BodyMode, DraftAttachmentInput, EmailAddress, ForwardStyle, ReplyDraftInput,
+    ReplyMode, SaveDraftInput,
+};
+
+use super::read::ensure_uidvalidity_matches_readonly;
+use super::types::{SaveDraftData, ToolIssue, build_message_uri, log_runtime_issues};
+use super::validation::{
+    attachment_section, escape_imap_quoted, header_value, parse_and_validate_message_id,
+    require_write_enabled, validate_account_id, validate_chars,
+};
+use super::{MAX_DRAFT_ATTACHMENT_BYTES, MAX_DRAFT_RECIPIENTS, MailImapServer};
+
+const SAVE_DRAFT_TOOL: &str = "imap_save_draft";
+const REPLY_DRAFT_TOOL: &str = "imap_create_reply_draft";
+
+/// Flags set on every saved draft
+const DRAFT_FLAGS: [&str; 2] = ["\\Draft", "\\Seen"];
+/// Characters of original text parsed for quoting or forwarding
+const MAX_QUOTED_CHARS: usize = 200_000;
+
+/// Attachment resolved from input, before message parts are downloaded
+pub(super) enum PendingAttachment {
+    Ready(Attachment),
+    Part {
+        message_id: MessageId,
+        part_id: String,
+        section: String,
+        filename: Option<String>,
+        content_type: Option<String>,
+    },
+}
+
+/// Where an appended message landed
+pub(super) struct SavedDraft {
+    pub(super) mailbox: String,
+    pub(super) uid: Option<(u32, u32)>,
+}
+
+/// Message being replied to or forwarded
+struct Original {
+    raw: Vec<u8>,
+    parsed: ParsedMessage,
+}
+
+impl MailImapServer {
+    pub(super) async fn save_draft_impl(&self, input: SaveDraftInput) -> AppResult<SaveDraftData> {
+        require_write_enabled(&self.config)?;
+        validate_account_id(&input.account_id)?;
+        let account = self.config.get_account(&input.account_id)?;
+        let (from, mut draft, pending) = compose_request(&self.config, account, &input)?;
+
+        let account_lock = self.account_write_lock(&input.account_id).await;
+        let _account_guard = account_lock.lock().await;
+        let mut session = match imap::connect_authenticated(&self.config, account).await {
+            Ok(session) => session,
+            Err(error) => {
+                let issue = ToolIssue::from_error("connect_authenticated", &error);
+                return Ok(failed_draft(SAVE_DRAFT_TOOL, &input.account_id, issue));
+            }
+        };
+
+        draft.attachments = match self.load_attachments(&mut session, pending).await {
+            Ok(attachments) => attachments,
+            Err((stage, error)) => {
+                let _ = imap::logout_session_best_effort(&self.config, session).await;
+                return draft_error(SAVE_DRAFT_TOOL, &input.account_id, stage, error);
+            }
+        };
+        let message_id_header = compose::new_message_id(&from);
+        let raw = compose::build_message(&draft, Local::now().fixed_offset(), &message_id_header);
+
+        let saved = self
+            .store_draft(&mut session, &raw, &message_id_header)
+            .await;
+        let _ = imap::logout_session_best_effort(&self.config, session).await;
+        match saved {
+            Ok(saved) => Ok(saved_draft_data(
+                &input.account_id,
+                saved,
+                &draft,
+                message_id_header,
+                raw.len(),
+            )),
+            Err((stage, error)) => draft_error(SAVE_DRAFT_TOOL, &input.account_id, stage, error),
+        }
+    }
+
+    pub(super) async fn create_reply_draft_impl(
+        &self,
+        input: ReplyDraftInput,
+    ) -> AppResult<SaveDraftData> {
+        require_write_enabled(&self.config)?;
+        let message_id = parse_and_validate_message_id(&input.message_id)?;
+        let account = self.config.get_account(&message_id.account_id)?;
+        let from = input
+            .from
+            .as_deref()
+            .map(|from| account_identity(account, from))
+            .transpose()?;
+        let to = parse_recipients(&input.to, "to")?;
+        let cc = parse_recipients(&input.cc, "cc")?;
+        if input.mode == ReplyMode::Forward && to.is_empty() && cc.is_empty() {
+            return Err(AppError::InvalidInput(
+                "forwards need at least one to or cc recipient".to_owned(),
+            ));
+        }
+        let account_id = message_id.account_id.clone();
+
+        let account_lock = self.account_write_lock(&account_id).await;
+        let _account_guard = account_lock.lock().await;
+        let mut session = match imap::connect_authenticated(&self.config, account).await {
+            Ok(session) => session,
+            Err(error) => {
+                let issue = ToolIssue::from_error("connect_authenticated", &error);
+                return Ok(failed_draft(REPLY_DRAFT_TOOL, &account_id, issue));
+            }
+        };
+        let original = match self.fetch_original(&mut session, &message_id).await {
+            Ok(original) => original,
+            Err((stage, error)) => {
+                let _ = imap::logout_session_best_effort(&self.config, session).await;
+                return draft_error(REPLY_DRAFT_TOOL, &account_id, stage, error);
+            }
+        };
+        let from = match from {
+            Some(from) => from,
+            None => {
+                let addresses = &original.parsed.addresses;
+                let recipients = [&addresses.to, &addresses.cc, &addresses.bcc]
+                    .into_iter()
+                    .flat_map(|list| mailboxes(list.as_deref()))
+                    .collect::<Vec<_>>();
+                match default_identity(account, &recipients) {
+                    Ok(from) => from,
+                    Err(error) => {
+                        let _ = imap::logout_session_best_effort(&self.config, session).await;
+                        return Err(error);
+                    }
+                }
+            }
+        };
+        let draft = match reply_draft(&input, from.clone(), to, cc, &account.identities, &original)
+        {
+            Ok(draft) => draft,
+            Err(error) => {
+                let _ = imap::logout_session_best_effort(&self.config, session).await;
+                return Err(error);
+            }
+        };
+        let message_id_header = compose::new_message_id(&from);
+        let raw = compose::build_message(&draft, Local::now().fixed_offset(), &message_id_header);
+
+        let saved = self
+            .store_draft(&mut session, &raw, &message_id_header)
+            .await;
+        let _ = imap::logout_session_best_effort(&self.config, session).await;
+        match saved {
+            Ok(saved) => Ok(saved_draft_data(
+                &account_id,
+                saved,
+                &draft,
+                message_id_header,
+                raw.len(),
+            )),
+            Err((stage, error)) => draft_error(REPLY_DRAFT_TOOL, &account_id, stage, error),
+        }
+    }
+
+    /// Fetch and parse the message a reply or forward is based on
+    async fn fetch_original(
+        &self,
+        session: &mut ImapSession,
+        message_id: &MessageId,
+    ) -> Result<Original, (&'static str, AppError)> {
+        ensure_uidvalidity_matches_readonly(&self.config, session, message_id)
+            .await
+            .map_err(|e| ("select_mailbox_readonly", e))?;
+        let size = imap::fetch_message_size(&self.config, session, message_id.uid)
+            .await
+            .map_err(|e| ("fetch_message_size", e))?;
+        if size > MAX_DRAFT_ATTACHMENT_BYTES {
+            return Err((
+                "fetch_message_size",
+                AppError::InvalidInput(format!(
+                    "original message is {size} bytes; the maximum is {MAX_DRAFT_ATTACHMENT_BYTES}"
+                )),
+            ));
+        }
+        let raw = imap::fetch_raw_message(&self.config, session, message_id.uid)
+            .await
+            .map_err(|e| ("fetch_raw_message", e))?;
+        let account = self
+            .config
+            .get_account(&message_id.account_id)
+            .map_err(|e| ("parse_message", e))?;
+        let parsed = mime::parse_message(
+            &FetchedMessage {
+                raw: raw.clone(),
+                omitted_sizes: HashMap::new(),
+            },
+            MAX_QUOTED_CHARS,
+            BodyMode::Text,
+            AttachmentMode::None,
+            0,
+            false,
+            false,
+            &account.message_keys,
+        )
+        .map_err(|e| ("parse_message", e))?;
+        Ok(Original { raw, parsed })
+    }
+
+    /// Download message-part attachments in input order
+    pub(super) async fn load_attachments(
+        &self,
+        session: &mut ImapSession,
+        pending: Vec<PendingAttachment>,
+    ) -> Result<Vec<Attachment>, (&'static str, AppError)> {
+        let mut attachments = Vec::with_capacity(pending.len());
+        let mut total_bytes = 0;
+        for attachment in pending {
+            let attachment = match attachment {
+                PendingAttachment::Ready(attachment) => attachment,
+                PendingAttachment::Part {
+                    message_id,
+                    part_id,
+                    section,
+                    filename,
+                    content_type,
+                } => {
+                    ensure_uidvalidity_matches_readonly(&self.config, session, &message_id)
+                        .await
+                        .map_err(|e| ("select_mailbox_readonly", e))?;
+                    let part = imap::fetch_body_part_ref(
+                        &self.config,
+                        session,
+                        message_id.uid,
+                        &section,
+                        mime::is_root_part_id(&part_id),
+                    )
+                    .await
+                    .map_err(|e| ("fetch_body_structure", e))?
+                    .ok_or_else(|| {
+                        (
+                            "fetch_body_structure",
+                            AppError::NotFound(format!("part_id {part_id} not found in message")),
+                        )
+                    })?;
+                    if part.decoded_size_bytes() > MAX_DRAFT_ATTACHMENT_BYTES {
+                        return Err((
+                            "fetch_body_section",
+                            attachment_too_large(part.decoded_size_bytes()),
+                        ));
+                    }
+                    let encoded =
+                        imap::fetch_body_section(&self.config, session, message_id.uid, &section)
+                            .await
+                            .map_err(|e| ("fetch_body_section", e))?;
+                    let data = mime::decode_transfer_encoding(&encoded, &part.transfer_encoding)
+                        .map_err(|e| ("decode_transfer_encoding", e))?;
+                    Attachment {
+                        filename: filename
+                            .or(part.filename)
+                            .unwrap_or_else(|| format!("part-{part_id}")),
+                        content_type: content_type.unwrap_or(part.content_type),
+                        data,
+                    }
+                }
+            };
+            total_bytes += attachment.data.len();
+            if total_bytes > MAX_DRAFT_ATTACHMENT_BYTES {
+                return Err(("load_attachments", attachment_too_large(total_bytes)));
+            }
+            attachments.push(attachment);
+        }
+        Ok(attachments)
+    }
+
+    /// Append a composed draft to the account's Drafts mailbox
+    async fn store_draft(
+        &self,
+        session: &mut ImapSession,
+        raw: &[u8],
+        message_id_header: &str,
+    ) -> Result<SavedDraft, (&'static str, AppError)> {
+        let flags = DRAFT_FLAGS.map(str::to_owned);
+        self.append_to_special_use(
+            session,
+            SpecialUse::Drafts,
+            &flags,
+            raw,
+            Some(message_id_header),
+        )
+        .await
+    }
+
+    /// Append a message to the mailbox serving a special-use role
+    ///
+    /// Reads the new UID from `APPENDUID`, or searches for `message_id_header`
+    /// when the server lacks `UIDPLUS`.
+    pub(super) async fn append_to_special_use(
+        &self,
+        session: &mut ImapSession,
+        role: SpecialUse,
+        flags: &[String],
+        raw: &[u8],
+        message_id_header: Option<&str>,
+    ) -> Result<SavedDraft, (&'static str, AppError)> {
+        let find_stage = match role {
+            SpecialUse::Drafts => "find_drafts_mailbox",
+            SpecialUse::Sent => "find_sent_mailbox",
+            SpecialUse::Trash => "find_trash_mailbox",
+        };
+        let mailbox = imap::find_special_use_mailbox(&self.config, session, role)
+            .await
+            .map_err(|e| (find_stage, e))?
+            .ok_or_else(|| {
+                (
+                    find_stage,
+                    AppError::NotFound(format!(
+                        "no {name} mailbox found; create one or mark it with \\{name}",
+                        name = role.name()
+                    )),
+                )
+            })?;
+        let uid = imap::append_with_uid(&self.config, session, &mailbox, flags, raw)
+            .await
+            .map_err(|e| ("append", e))?;
+        let Some(message_id_header) = message_id_header.filter(|_| uid.is_none()) else {
+            return Ok(SavedDraft { mailbox, uid });
+        };
+
+        let uidvalidity = imap::select_mailbox_readonly(&self.config, session, &mailbox)
+            .await
+            .map_err(|e| ("select_mailbox_readonly", e))?;
+        let query = format!(
+            "HEADER Message-ID \"{}\"",
+            escape_imap_quoted(message_id_header).map_err(|e| ("search_appended", e))?
+        );
+        let uids = imap::uid_search(&self.config, session, &query)
+            .await
+            .map_err(|e| ("search_appended", e))?;
+        Ok(SavedDraft {
+            mailbox,
+            uid: uids.first().map(|uid| (uidvalidity, *uid)),
+        })
+    }
+}
+
+/// Validate compose fields into a draft without attachments, plus the
+/// attachments still to load and the resolved sender
+pub(super) fn compose_request(
+    config: &ServerConfig,
+    account: &AccountConfig,
+    input: &SaveDraftInput,
+) -> AppResult<(Mailbox, Draft, Vec<PendingAttachment>)> {
+    validate_chars(input.subject.chars().count(), 0, 998, "subject")?;
+    validate_chars(input.attachments.len(), 0, 20, "attachments")?;
+    let from = match &input.from {
+        Some(from) => account_identity(account, from)?,
+        None => default_identity(account, &[])?,
+    };
+    let to = parse_recipients(&input.to, "to")?;
+    let cc = parse_recipients(&input.cc, "cc")?;
+    let bcc = parse_recipients(&input.bcc, "bcc")?;
+    if to.len() + cc.len() + bcc.len() > MAX_DRAFT_RECIPIENTS {
+        return Err(AppError::InvalidInput(format!(
+            "drafts accept at most {MAX_DRAFT_RECIPIENTS} recipients"
+        )));
+    }
+    let pending = input
+        .attachments
+        .iter()
+        .map(|attachment| {
+            pending_attachment(
+                config.attachment_dir.as_deref(),
+                &input.account_id,
+                attachment,
+            )
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+    let draft = Draft {
+        from: Some(from.clone()),
+        to,
+        cc,
+        bcc,
+        subject: input.subject.clone(),
+        text: input.body_text.clone(),
+        html: input.body_html.clone(),
+        ..Draft::default()
+    };
+    Ok((from, draft, pending))
+}
+
+fn parse_recipients(addresses: &[String], field: &str) -> AppResult<Vec<Mailbox>> {
+    addresses
+        .iter()
+        .map(|address| {
+            Mailbox::parse(address)
+                .map_err(|e| AppError::InvalidInput(format!("{field}: {}", error_detail(&e))))
+        })
+        .collect()
+}
+
+/// Parse a requested sender and require it to be one of the account's identities
+pub(super) fn account_identity(account: &AccountConfig, from: &str) -> AppResult<Mailbox> {
+    let from = Mailbox::parse(from)?;
+    require_identity(account, &from)?;
+    Ok(from)
+}
+
+/// Reject a sender address that is not one of the account's identities
+pub(super) fn require_identity(account: &AccountConfig, from: &Mailbox) -> AppResult<()> {
+    if account
+        .identities
+        .iter()
+        .any(|identity| identity.same_address(from))
+    {
+        return Ok(());
+    }
+    Err(AppError::InvalidInput(format!(
+        "from {} is not an identity of account '{}'; add it to MAIL_IMAP_<ID>_IDENTITIES",
+        from.address, account.account_id
+    )))
+}
+
+/// Sender for a new draft: the first identity among `recipients`, else the
+/// account's first identity
+fn default_identity(account: &AccountConfig, recipients: &[Mailbox]) -> AppResult<Mailbox> {
+    account
+        .identities
+        .iter()
+        .find(|identity| recipients.iter().any(|r| identity.same_address(r)))
+        .or_else(|| account.identities.first())
+        .cloned()
+        .ok_or_else(|| {
+            AppError::InvalidInput(format!(
+                "from is required; account '{}' has no MAIL_IMAP_<ID>_IDENTITIES and its login is not an email address",
+                account.account_id
+            ))
+        })
+}
+
+/// Parsed header addresses as mailboxes, skipping ones that cannot be reused
+fn mailboxes(addresses: Option<&[EmailAddress]>) -> Vec<Mailbox> {
+    addresses
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|address| Mailbox::new(address.name.clone(), &address.email).ok())
+        .collect()
+}
+
+/// Compose the reply or forward of `original`
+///
+/// Reply recipients come from `Reply-To`, else `From`; replies to one's own
+/// message go to its original `To`. Reply-all adds the other `To` and `Cc`
+/// recipients. The sender and the account's identities are never added, and
+/// `extra_to` and `extra_cc` are appended in every mode.
+fn reply_draft(
+    input: &ReplyDraftInput,
+    from: Mailbox,
+    extra_to: Vec<Mailbox>,
+    extra_cc: Vec<Mailbox>,
+    identities: &[Mailbox],
+    original: &Original,
+) -> AppResult<Draft> {
+    let parsed = &original.parsed;
+    let addresses = &parsed.addresses;
+    let is_own = |mailbox: &Mailbox| {
+        from.same_address(mailbox) || identities.iter().any(|i| i.same_address(mailbox))
+    };
+    let original_from = mailboxes(addresses.from.as_deref());
+    let original_to = mailboxes(addresses.to.as_deref());
+    let original_cc = mailboxes(addresses.cc.as_deref());
+
+    let (mut to, mut cc) = match input.mode {
+        ReplyMode::Forward => (Vec::new(), Vec::new()),
+        ReplyMode::Reply | ReplyMode::ReplyAll => {
+            let mut to = mailboxes(addresses.reply_to.as_deref());
+            if to.is_empty() {
+                to.clone_from(&original_from);
+            }
+            if to.iter().all(&is_own) {
+                to.clone_from(&original_to);
+            }
+            let mut cc = Vec::new();
+            if input.mode == ReplyMode::ReplyAll {
+                to.extend(original_to.iter().cloned());
+                cc.clone_from(&original_cc);
+            }
+            (to, cc)
+        }
+    };
+    to.retain(|mailbox| !is_own(mailbox));
+    cc.retain(|mailbox| !is_own(mailbox));
+    to.extend(extra_to);
+    cc.extend(extra_cc);
+    let mut seen = Vec::<Mailbox>::new();
+    for list in [&mut to, &mut cc] {
+        list.retain(|mailbox| {
+            let fresh = !seen.iter().any(|s| s.same_address(mailbox));
+            if fresh {
+                seen.push(mailbox.clone());
+            }
+            fresh
+        });
+    }
+    if to.is_empty() && cc.is_empty() {
+        return Err(AppError::InvalidInput(
+            "could not determine reply recipients; pass to".to_owned(),
+        ));
+    }
+    if to.len() + cc.len() > MAX_DRAFT_RECIPIENTS {
+        return Err(AppError::InvalidInput(format!(
+            "drafts accept at most {MAX_DRAFT_RECIPIENTS} recipients"
+        )));
+    }
+
+    let original_subject = parsed.subject.as_deref().unwrap_or_default();
+    let original_text = parsed.body_text.as_deref().unwrap_or_default();
+    let date = parsed.date.as_deref().unwrap_or("an unknown date");
+    let mut text = input.body.trim_end().to_owned();
+    let mut draft = Draft {
+        from: Some(from),
+        to,
+        cc,
+        ..Draft::default()
+    };
+    match input.mode {
+        ReplyMode::Reply | ReplyMode::ReplyAll => {
+            draft.subject = compose::reply_subject(original_subject);
+            let parent = header_value(&parsed.headers_all, "Message-ID")
+                .and_then(|value| compose::message_ids(&value).into_iter().next());
+            if let Some(parent) = parent {
+                draft.references = compose::reply_references(
+                    header_value(&parsed.headers_all, "References").as_deref(),
+                    header_value(&parsed.headers_all, "In-Reply-To").as_deref(),
+                    &parent,
+                );
+                draft.in_reply_to = Some(parent);
+            }
+            if input.quote_original {
+                let author = original_from
+                    .first()
+                    .map_or_else(|| "an unknown sender".to_owned(), display_mailbox);
+                push_section(
+                    &mut text,
+                    &format!(
+                        "On {date}, {author} wrote:\n{}",
+                        compose::quote_text(original_text)
+                    ),
+                );
+            }
+        }
+        ReplyMode::Forward => {
+            draft.subject = compose::forward_subject(original_subject);
+            match input.forward_as {
+                ForwardStyle::Inline => {
+                    let mut block = "---------- Forwarded message ---------".to_owned();
+                    for (label, list) in [
+                        ("From", &original_from),
+                        ("To", &original_to),
+                        ("Cc", &original_cc),
+                    ] {
+                        if !list.is_empty() {
+                            let names = list.iter().map(display_mailbox).collect::<Vec<_>>();
+                            block.push_str(&format!("\n{label}: {}", names.join(", ")));
+                        }
+                    }
+                    block.push_str(&format!(
+                        "\nDate: {date}\nSubject: {original_subject}\n\n{}",
+                        original_text.trim_end()
+                    ));
+                    push_section(&mut text, &block);
+                    draft.attachments = compose::message_attachments(&original.raw)?;
+                }
+                ForwardStyle::Attachment => {
+                    draft.attachments = vec![Attachment {
+                        filename: "forwarded-message.eml".to_owned(),
+                        content_type: "message/rfc822".to_owned(),
+                        data: original.raw.clone(),
+                    }];
+                }
+            }
+        }
+    }
+    text.push('\n');
+    draft.text = text;
+    Ok(draft)
+}
+
+/// Append a block to a draft body, separated from existing text by a blank line
+fn push_section(text: &mut String, section: &str) {
+    if !text.is_empty() {
+        text.push_str("\n\n");
+    }
+    text.push_str(section);
+}
+
+fn display_mailbox(mailbox: &Mailbox) -> String {
+    match &mailbox.name {
+        Some(name) => format!("{name} <{}>", mailbox.address),
+        None => mailbox.address.clone(),
+    }
+}
+
+/// Validate one attachment input, reading local files immediately
+fn pending_attachment(
+    attachment_dir: Option<&Path>,
+    account_id: &str,
+    input: &DraftAttachmentInput,
+) -> AppResult<PendingAttachment> {
+    if let Some(filename) = &input.filename
+        && (filename.is_empty() || filename.chars().any(char::is_control))
+    {
+        return Err(AppError::InvalidInput(
+            "attachment filename must be non-empty without control characters".to_owned(),
+        ));
+    }
+    let content_type = input
+        .content_type
+        .as_deref()
+        .map(compose::normalize_content_type)
+        .transpose()?;
+    match (&input.path, &input.message_id, &input.part_id) {
+        (Some(path), None, None) => {
+            let root = attachment_dir.ok_or_else(|| {
+                AppError::InvalidInput(
+                    "file attachments are disabled; set MAIL_IMAP_ATTACHMENT_DIR".to_owned(),
+                )
+            })?;
+            let path = mailstore::resolve_sandbox_path(root, path)?;
+            let data = read_attachment_file(&path)?;
+            Ok(PendingAttachment::Ready(Attachment {
+                filename: input.filename.clone().unwrap_or_else(|| {
+                    path.file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| "attachment".to_owned())
+                }),
+                content_type: content_type.unwrap_or_else(|| "application/octet-stream".to_owned()),
+                data,
+            }))
+        }
+        (None, Some(message_id), Some(part_id)) => {
+            let message_id = parse_and_validate_message_id(message_id)?;
+            if message_id.account_id != account_id {
+                return Err(AppError::InvalidInput(
+                    "attachment message_id must belong to the draft's account".to_owned(),
+                ));
+            }
+            let section = attachment_section(part_id)?;
+            Ok(PendingAttachment::Part {
+                message_id,
+                part_id: part_id.clone(),
+                section,
+                filename: input.filename.clone(),
+                content_type,
+            })
+        }
+        _ => Err(AppError::InvalidInput(
+            "each attachment needs either path, or message_id with part_id".to_owned(),
+        )),
+    }
+}
+
+fn read_attachment_file(path: &Path) -> AppResult<Vec<u8>> {
+    let metadata = std::fs::metadata(path).map_err(|e| {
+        AppError::NotFound(format!(
+            "attachment {} is not readable: {e}",
+            path.display()
+        ))
+    })?;
+    if !metadata.is_file() {
+        return Err(AppError::InvalidInput(format!(
+            "attachment {} is not a file",
+            path.display()
+        )));
+    }
+    if metadata.len() > MAX_DRAFT_ATTACHMENT_BYTES as u64 {
+        return Err(attachment_too_large(metadata.len() as usize));
+    }
+    std::fs::read(path)
+        .map_err(|e| AppError::Internal(format!("failed to read {}: {e}", path.display())))
+}
+
+fn attachment_too_large(bytes: usize) -> AppError {
+    AppError::InvalidInput(format!(
+        "attachments total {bytes} bytes; the maximum is {MAX_DRAFT_ATTACHMENT_BYTES}"
+    ))
+}
+
+/// Message of an `InvalidInput` error without its display prefix
+fn error_detail(error: &AppError) -> String {
+    match error {
+        AppError::InvalidInput(message) => message.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Return caller mistakes as errors and runtime failures as a `failed` result
+fn draft_error(
+    tool: &str,
+    account_id: &str,
+    stage: &str,
+    error: AppError,
+) -> AppResult<SaveDraftData> {
+    match error {
+        AppError::InvalidInput(_) | AppError::NotFound(_) | AppError::Conflict(_) => Err(error),
+        error => Ok(failed_draft(
+            tool,
+            account_id,
+            ToolIssue::from_error(stage, &error),
+        )),
+    }
+}
+
+fn saved_draft_data(
+    account_id: &str,
+    saved: SavedDraft,
+    draft: &Draft,
+    message_id_header: String,
+    size_bytes: usize,
+) -> SaveDraftData {
+    let mut issues = Vec::new();
+    let (message_id, message_uri) = match saved.uid {
+        Some((uidvalidity, uid)) => (
+            Some(
+                MessageId {
+                    account_id: account_id.to_owned(),
+                    mailbox: saved.mailbox.clone(),
+                    uidvalidity,
+                    uid,
+                }
+                .encode(),
+            ),
+            Some(build_message_uri(
+                account_id,
+                &saved.mailbox,
+                uidvalidity,
+                uid,
+            )),
+        ),
+        None => {
+            issues.push(ToolIssue {
+                code: "uid_unavailable".to_owned(),
+                stage: "search_appended".to_owned(),
+                message: "draft was saved but its UID could not be determined".to_owned(),
+                retryable: false,
+                uid: None,
+                message_id: None,
+            });
+            (None, None)
+        }
+    };
+    SaveDraftData {
+        status: if issues.is_empty() { "ok" } else { "partial" }.to_owned(),
+        issues,
+        account_id: account_id.to_owned(),
+        mailbox: Some(saved.mailbox),
+        message_id,
+        message_uri,
+        message_id_header: Some(message_id_header),
+        subject: Some(draft.subject.clone()),
+        to: addresses(&draft.to),
+        cc: addresses(&draft.cc),
+        size_bytes,
+    }
+}
+
+/// Build a `failed` response after a runtime error, logging the issue.
+fn failed_draft(tool: &str, account_id: &str, issue: ToolIssue) -> SaveDraftData {
+    let issues = vec![issue];
+    log_runtime_issues(tool, "failed", account_id, None, &issues);
+    SaveDraftData {
+        status: "failed".to_owned(),
+        issues,
+        account_id: account_id.to_owned(),
+        mailbox: None,
+        message_id: None,
+        message_uri: None,
+        message_id_header: None,
+        subject: None,
+        to: Vec::new(),
+        cc: Vec::new(),
+        size_bytes: 0,
+    }
+}
+
+fn addresses(mailboxes: &[Mailbox]) -> Vec<String> {
+    mailboxes.iter().map(display_mailbox).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::compose::Mailbox;
+    use crate::imap::FetchedMessage;
+    use crate::mime;
+    use crate::models::{
+        AttachmentMode, BodyMode, DraftAttachmentInput, ForwardStyle, ReplyDraftInput, ReplyMode,
+    };
+
+    use super::{Original, PendingAttachment, compose_request, pending_attachment, reply_draft};
+    use crate::config::AccountConfig;
+    use crate::models::SaveDraftInput;
+
+    fn original() -> Original {
+        let raw = b"From: Ann <ann@example.com>\r\nTo: me@example.com, bob@example.com\r\nCc: Carol <carol@example.com>, ME@example.com\r\nSubject: Plans\r\nDate: Sun, 01 Mar 2026 09:30:00 +0100\r\nMessage-ID: <plans@example.com>\r\nReferences: <root@example.com>\r\n\r\nSee you at noon.\r\n".to_vec();
+        let parsed = mime::parse_message(
+            &FetchedMessage {
+                raw: raw.clone(),
+                omitted_sizes: HashMap::new(),
+            },
+            10_000,
+            BodyMode::Text,
+            AttachmentMode::None,
+            0,
+            false,
+            false,
+            &Default::default(),
+        )
+        .expect("sample parses");
+        Original { raw, parsed }
+    }
+
+    fn account() -> AccountConfig {
+        AccountConfig {
+            account_id: "default".to_owned(),
+            host: "imap.example.com".to_owned(),
+            port: 993,
+            secure: true,
+            user: "me@example.com".to_owned(),
+            pass: secrecy::SecretString::new("secret".into()),
+            message_keys: Default::default(),
+            identities: vec![Mailbox::parse("Me <me@example.com>").expect("identity")],
+            smtp: None,
+            trash_mailbox: None,
+        }
+    }
+
+    fn reply_input(mode: ReplyMode) -> ReplyDraftInput {
+        ReplyDraftInput {
+            message_id: "imap:default:INBOX:1:1".to_owned(),
+            mode,
+            body: "Works for me.".to_owned(),
+            quote_original: true,
+            forward_as: ForwardStyle::Inline,
+            to: Vec::new(),
+            cc: Vec::new(),
+            from: None,
+        }
+    }
+
+    fn addresses(mailboxes: &[Mailbox]) -> Vec<&str> {
+        mailboxes.iter().map(|m| m.address.as_str()).collect()
+    }
+
+    #[test]
+    fn compose_request_requires_an_account_identity() {
+        let config = crate::server::tests::schema_test_server_config();
+        let input = |from: &str| -> SaveDraftInput {
+            serde_json::from_value(serde_json::json!({
+                "from": from,
+                "to": ["ann@example.com"],
+                "subject": "Hi",
+            }))
+            .expect("input deserializes")
+        };
+
+        let (from, _, _) = compose_request(&config, &account(), &input("Boss <ME@example.com>"))
+            .expect("identity is accepted");
+        assert_eq!(from.address, "ME@example.com");
+        let Err(error) = compose_request(&config, &account(), &input("ceo@example.com")) else {
+            panic!("foreign sender should be rejected");
+        };
+        assert!(error.to_string().contains("not an identity"));
+    }
+
+    fn attachment_input() -> DraftAttachmentInput {
+        DraftAttachmentInput {
+            path: None,
+            message_id: None,
+            part_id: None,
+            filename: None,
+            content_type: None,
+        }
+    }
+
+    #[test]
+    fn attachment_inputs_need_exactly_one_source() {
+        let err = pending_attachment(None, "default", &attachment_input())
+            .err()
+            .expect("missing source must fail");
+        assert!(err.to_string().contains("either path, or message_id"));
+
+        let input = DraftAttachmentInput {
+            path: Some("a.txt".to_owned()),
+            message_id: Some("imap:default:INBOX:1:2".to_owned()),
+            part_id: Some("2".to_owned()),
+            ..attachment_input()
+        };
+        assert!(pending_attachment(None, "default", &input).is_err());
+
+        let input = DraftAttachmentInput {
+            message_id: Some("imap:work:INBOX:1:2".to_owned()),
+            part_id: Some("2".to_owned()),
+            ..attachment_input()
+        };
+        let err = pending_attachment(None, "default", &input)
+            .err()
+            .expect("cross-account part must fail");
+        assert!(err.to_string().contains("draft's account"));
+    }
+
+    #[test]
+    fn file_attachments_stay_inside_attachment_dir() {
+        let root = std::env::temp_dir().join(format!("mail-imap-draft-{}", std::process::id()));
+        std::fs::create_dir_all(&root).expect("create temp dir");
+        let root = root.canonicalize().expect("canonical temp dir");
+        std::fs::write(root.join("notes.txt"), b"hello").expect("write attachment");
+
+        let input = DraftAttachmentInput {
+            path: Some("notes.txt".to_owned()),
+            content_type: Some("Text/Plain".to_owned()),
+            ..attachment_input()
+        };
+        let err = pending_attachment(None, "default", &input)
+            .err()
+            .expect("file attachments need a directory");
+        assert!(err.to_string().contains("MAIL_IMAP_ATTACHMENT_DIR"));
+        match pending_attachment(Some(&root), "default", &input).expect("file loads") {
+            PendingAttachment::Ready(attachment) => {
+                assert_eq!(attachment.filename, "notes.txt");
+                assert_eq!(attachment.content_type, "text/plain");
+                assert_eq!(attachment.data, b"hello");
+            }
+            PendingAttachment::Part { .. } => panic!("file attachment must be ready"),
+        }
+
+        let escape = DraftAttachmentInput {
+            path: Some("../notes.txt".to_owned()),
+            ..attachment_input()
+        };
+        assert!(pending_attachment(Some(&root), "default", &escape).is_err());
+        std::fs::remove_dir_all(&root).expect("remove temp dir");
+    }
+
+    #[test]
+    fn reply_all_threads_and_excludes_own_addresses() {
+        let me = Mailbox::parse("me@example.com").expect("identity");
+        let original = original();
+
+        let reply = reply_draft(
+            &reply_input(ReplyMode::Reply),
+            me.clone(),
+            Vec::new(),
+            Vec::new(),
+            std::slice::from_ref(&me),
+            &original,
+        )
+        .expect("reply");
+        assert_eq!(addresses(&reply.to), vec!["ann@example.com"]);
+        assert!(reply.cc.is_empty());
+        assert_eq!(reply.subject, "Re: Plans");
+        assert_eq!(reply.in_reply_to.as_deref(), Some("<plans@example.com>"));
+        assert_eq!(
+            reply.references,
+            vec!["<root@example.com>", "<plans@example.com>"]
+        );
+        assert!(reply.text.starts_with("Works for me.\n\nOn "));
+        assert!(
+            reply
+                .text
+                .contains("Ann <ann@example.com> wrote:\n> See you at noon.")
+        );
+
+        let reply_all = reply_draft(
+            &reply_input(ReplyMode::ReplyAll),
+            me.clone(),
+            Vec::new(),
+            vec![Mailbox::parse("ann@example.com").expect("dup")],
+            std::slice::from_ref(&me),
+            &original,
+        )
+        .expect("reply all");
+        assert_eq!(
+            addresses(&reply_all.to),
+            vec!["ann@example.com", "bob@example.com"]
+        );
+        assert_eq!(addresses(&reply_all.cc), vec!["carol@example.com"]);
+    }
+
+    #[test]
+    fn forwards_need_recipients_and_embed_the_original() {
+        let me = Mailbox::parse("me@example.com").expect("identity");
+        let original = original();
+        assert!(
+            reply_draft(
+                &reply_input(ReplyMode::Forward),
+                me.clone(),
+                Vec::new(),
+                Vec::new(),
+                &[],
+                &original,
+            )
+            .is_err()
+        );
+
+        let dan = vec![Mailbox::parse("dan@example.com").expect("to")];
+        let inline = reply_draft(
+            &reply_input(ReplyMode::Forward),
+            me.clone(),
+            dan.clone(),
+            Vec::new(),
+            &[],
+            &original,
+        )
+        .expect("inline forward");
+        assert_eq!(inline.subject, "Fwd: Plans");
+        assert!(inline.in_reply_to.is_none());
+        assert!(
+            inline
+                .text
+                .contains("---------- Forwarded message ---------\nFrom: Ann <ann@example.com>")
+        );
+        assert!(inline.text.contains("See you at noon."));
+
+        let input = ReplyDraftInput {
+            forward_as: ForwardStyle::Attachment,
+            ..reply_input(ReplyMode::Forward)
+        };
+        let attached =
+            reply_draft(&input, me, dan, Vec::new(), &[], &original).expect("attachment forward");
+        assert_eq!(attached.text, "Works for me.\n");
+        assert_eq!(attached.attachments[0].content_type, "message/rfc822");
+        assert_eq!(attached.attachments[0].data, original.raw);
+    }
+}
@@ -1,9 +1,13 @@
 use base64::Engine;
+use secrecy::ExposeSecret;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::time::Instant;
 
 use tokio::sync::Mutex;
+use tracing::warn;
 
+use crate::dkim;
 use crate::errors::{AppError, AppResult};
 use crate::imap;
 use crate::mailbox_codec::{decode_mailbox_name_for_display, normalize_mailbox_name};
@@ -13,7 +17,9 @@ use crate::models::{
     AccountOnlyInput, GetMessageInput, GetMessageRawInput, MailboxInfo, MessageDetail,
     MessageSummary, SearchMessagesInput,
 };
-use crate::pagination::{CursorEntry, CursorStore};
+use crate::pagination::{
+    CursorEntry, CursorStore, SignedCursor, decode_signed_cursor, encode_signed_cursor,
+};
 
 use super::session_cache::ReadSessionLease;
 use super::types::{
@@ -27,13 +33,20 @@ use super::validation::{
     build_search_query, header_value, parse_and_validate_message_id, validate_account_id,
     validate_chars, validate_mailbox, validate_search_input,
 };
-use super::{MAX_CURSOR_UIDS_STORED, MAX_SEARCH_LIMIT, MailImapServer};
+use super::{
+    MAX_CURSOR_UIDS_STORED, MAX_SEARCH_LIMIT, MailImapServer, SNIPPET_HTML_FETCH_BYTES,
+    SNIPPET_PLAIN_FETCH_BYTES,
+};
 
 struct SearchSnapshot {
     uids_desc: Arc<[u32]>,
     offset: usize,
     snippet_max_chars: Option<usize>,
     cursor_id_from_request: Option<String>,
+    /// IMAP criteria used for the search; carried into signed cursors
+    criteria: Option<String>,
+    /// Messages returned by earlier pages of a signed cursor
+    base_offset: usize,
 }
 
 struct SummaryBuildOptions<'a> {
@@ -194,7 +207,12 @@ impl MailImapServer {
                 }
             };
 
-        let snapshot = if let Some(cursor) = input.cursor.clone() {
+        let signing_key = self
+            .config
+            .cursor_signing_key
+            .as_ref()
+            .map(|key| key.expose_secret().as_bytes());
+        let snapshot = if let (Some(cursor), None) = (input.cursor.clone(), signing_key) {
             match resume_cursor_search(&self.cursors, &input, uidvalidity, cursor).await {
                 Ok(snapshot) => snapshot,
                 Err(error) => {
@@ -203,7 +221,21 @@ impl MailImapServer {
                 }
             }
         } else {
-            match start_new_search(&self.config, session.session(), &input).await {
+            let searched = match (input.cursor.as_deref(), signing_key) {
+                (Some(cursor), Some(key)) => {
+                    resume_signed_search(
+                        &self.config,
+                        session.session(),
+                        &input,
+                        uidvalidity,
+                        key,
+                        cursor,
+                    )
+                    .await
+                }
+                _ => start_new_search(&self.config, session.session(), &input).await,
+            };
+            match searched {
                 Ok(snapshot) => snapshot,
                 Err(error) if is_hard_precondition_error(&error) => {
                     let _ = release_read_session(self, session, false).await;
@@ -242,9 +274,11 @@ impl MailImapServer {
             offset,
             snippet_max_chars,
             cursor_id_from_request,
+            criteria,
+            base_offset,
         } = snapshot;
 
-        let total = uids_desc.len();
+        let total = base_offset + uids_desc.len();
         if offset > total {
             let _ = release_read_session(self, session, true).await;
             return Err(AppError::InvalidInput(
@@ -279,8 +313,33 @@ impl MailImapServer {
         .await;
 
         let next_offset = offset + page_uids.len();
-        let has_more = next_offset < total;
-        let next_cursor = if has_more {
+        let has_more = next_offset < uids_desc.len();
+        let next_cursor = if let Some(key) = signing_key {
+            match (has_more, criteria, page_uids.last()) {
+                (true, Some(criteria), Some(last_uid)) => {
+                    let cursor = SignedCursor {
+                        account_id: input.account_id.clone(),
+                        mailbox: input.mailbox.clone(),
+                        uidvalidity,
+                        criteria,
+                        last_uid: *last_uid,
+                        offset: base_offset + next_offset,
+                        snippet_max_chars,
+                        expires_at_unix: chrono::Utc::now().timestamp().saturating_add(
+                            i64::try_from(self.config.cursor_ttl_seconds).unwrap_or(i64::MAX),
+                        ),
+                    };
+                    match encode_signed_cursor(key, &cursor) {
+                        Ok(token) => Some(token),
+                        Err(error) => {
+                            let _ = release_read_session(self, session, true).await;
+                            return Err(error);
+                        }
+                    }
+                }
+                _ => None,
+            }
+        } else if has_more {
             let mut store = self.cursors.lock().await;
             if let Some(existing) = cursor_id_from_request {
                 store.update_offset(&existing, next_offset);
@@ -359,6 +418,9 @@ impl MailImapServer {
             64_000,
             "attachment_text_max_chars",
         )?;
+        if input.verify_dkim {
+            dkim::ensure_key_source(&self.config)?;
+        }
 
         let message_id = parse_and_validate_message_id(&input.message_id)?;
         let encoded_message_id = message_id.encode();
@@ -393,38 +455,53 @@ impl MailImapServer {
             return Err(error);
         }
 
-        let raw =
-            match imap::fetch_raw_message(&self.config, session.session(), message_id.uid).await {
-                Ok(raw) => raw,
-                Err(error) => {
-                    issues.push(
-                        ToolIssue::from_error("fetch_raw_message", &error)
-                            .with_uid(message_id.uid)
-                            .with_message_id(&encoded_message_id),
-                    );
-                    let _ = release_read_session(self, session, false).await;
-                    log_runtime_issues(
-                        "imap_get_message",
-                        "failed",
-                        &message_id.account_id,
-                        Some(&message_id.mailbox),
-                        &issues,
-                    );
-                    return Ok(GetMessageData {
-                        status: "failed".to_owned(),
-                        issues,
-                        account_id: message_id.account_id.clone(),
-                        message: None,
-                    });
-                }
-            };
+        let message = match fetch_message_for_parse(
+            &self.config,
+            session.session(),
+            message_id.uid,
+            input.attachment_mode,
+            input.verify_dkim,
+        )
+        .await
+        {
+            Ok(message) => message,
+            Err(error) => {
+                issues.push(
+                    ToolIssue::from_error("fetch_raw_message", &error)
+                        .with_uid(message_id.uid)
+                        .with_message_id(&encoded_message_id),
+                );
+                let _ = release_read_session(self, session, false).await;
+                log_runtime_issues(
+                    "imap_get_message",
+                    "failed",
+                    &message_id.account_id,
+                    Some(&message_id.mailbox),
+                    &issues,
+                );
+                return Ok(GetMessageData {
+                    status: "failed".to_owned(),
+                    issues,
+                    account_id: message_id.account_id.clone(),
+                    message: None,
+                });
+            }
+        };
 
+        let message_keys = self
+            .config
+            .get_account(&message_id.account_id)
+            .map(|account| Arc::clone(&account.message_keys))
+            .unwrap_or_default();
         let parsed = match mime::parse_message(
-            &raw,
+            &message,
             input.body_max_chars,
             input.body_mode,
             input.attachment_mode,
             attachment_text_max_chars,
+            input.new_content_only,
+            input.include_links,
+            &message_keys,
         ) {
             Ok(parsed) => parsed,
             Err(error) => {
@@ -465,6 +542,82 @@ impl MailImapServer {
             });
         }
 
+        if input.include_links && parsed.links_truncated {
+            issues.push(ToolIssue {
+                code: "limit_exceeded".to_owned(),
+                stage: "link_inventory_limit".to_owned(),
+                message: format!(
+                    "HTML body has more than {} links or {} remote images; only the first are returned",
+                    mime::MAX_LINKS,
+                    mime::MAX_REMOTE_IMAGES
+                ),
+                retryable: false,
+                uid: Some(message_id.uid),
+                message_id: Some(encoded_message_id.clone()),
+            });
+        }
+
+        if parsed.embedded_messages_truncated {
+            issues.push(ToolIssue {
+                code: "limit_exceeded".to_owned(),
+                stage: "embedded_message_limit".to_owned(),
+                message: format!(
+                    "embedded messages exceed the depth ({}), count ({}), body text, or attachment limits; some content is omitted",
+                    mime::MAX_EMBEDDED_DEPTH,
+                    mime::MAX_EMBEDDED_MESSAGES
+                ),
+                retryable: false,
+                uid: Some(message_id.uid),
+                message_id: Some(encoded_message_id.clone()),
+            });
+        }
+
+        issues.extend(parsed.attachment_issues.iter().map(|issue| {
+            let label = issue.filename.as_deref().unwrap_or("unnamed attachment");
+            ToolIssue {
+                code: issue.code.to_owned(),
+                stage: "extract_attachment_text".to_owned(),
+                message: format!("part {} ({label}): {}", issue.part_id, issue.message),
+                retryable: false,
+                uid: Some(message_id.uid),
+                message_id: Some(encoded_message_id.clone()),
+            }
+        }));
+        issues.extend(parsed.charset_issues.iter().map(|issue| {
+            let declared = issue.declared.as_deref().unwrap_or("no charset");
+            let mut message = format!(
+                "{}: declared {declared}, decoded as {}",
+                issue.location, issue.detected
+            );
+            if issue.replaced_chars > 0 {
+                let rate = issue.replaced_chars as f64 * 100.0 / issue.total_chars.max(1) as f64;
+                message.push_str(&format!(
+                    "; {} of {} characters ({rate:.1}%) replaced",
+                    issue.replaced_chars, issue.total_chars
+                ));
+            }
+            ToolIssue {
+                code: "charset_decoding".to_owned(),
+                stage: "decode_charset".to_owned(),
+                message,
+                retryable: false,
+                uid: Some(message_id.uid),
+                message_id: Some(encoded_message_id.clone()),
+            }
+        }));
+
+        let mut authentication = parsed.authentication;
+        if input.verify_dkim {
+            match dkim::verify_message(&self.config, &message.raw).await {
+                Ok(signatures) => mime::apply_dkim_verification(&mut authentication, signatures),
+                Err(error) => issues.push(
+                    ToolIssue::from_error("verify_dkim", &error)
+                        .with_uid(message_id.uid)
+                        .with_message_id(&encoded_message_id),
+                ),
+            }
+        }
+
         let headers = if input.include_headers || input.include_all_headers {
             Some(mime::curated_headers(
                 &parsed.headers_all,
@@ -504,15 +657,28 @@ impl MailImapServer {
             uidvalidity: message_id.uidvalidity,
             uid: message_id.uid,
             date: parsed.date,
-            from: parsed.from,
-            to: parsed.to,
-            cc: parsed.cc,
+            from: parsed.addresses.from,
+            to: parsed.addresses.to,
+            cc: parsed.addresses.cc,
+            bcc: parsed.addresses.bcc,
+            reply_to: parsed.addresses.reply_to,
+            sender: parsed.addresses.sender,
             subject: parsed.subject,
             flags,
             headers,
             body_text: parsed.body_text,
             body_html: parsed.body_html_sanitized,
+            body_markdown: parsed.body_markdown,
+            stripped_content: parsed.stripped_content,
             attachments: Some(parsed.attachments),
+            calendar_events: (!parsed.calendar_events.is_empty()).then_some(parsed.calendar_events),
+            embedded_messages: (!parsed.embedded_messages.is_empty())
+                .then_some(parsed.embedded_messages),
+            authentication: Some(authentication),
+            security: Some(parsed.security),
+            mailing_list: parsed.mailing_list,
+            links: input.include_links.then_some(parsed.links),
+            remote_images: input.include_links.then_some(parsed.remote_images),
         };
 
         let status = status_from_issue_and_counts(&issues, true);
@@ -729,7 +895,34 @@ impl MailImapServer {
     }
 }
 
-async fn release_read_session(
+/// Fetch a message for `imap_get_message`, skipping bodies it will not read.
+///
+/// Falls back to the full message when DKIM verification needs the exact
+/// bytes, when the structure rules out a partial fetch, or when the partial
+/// fetch fails.
+async fn fetch_message_for_parse(
+    config: &crate::config::ServerConfig,
+    session: &mut imap::ImapSession,
+    uid: u32,
+    attachment_mode: crate::models::AttachmentMode,
+    full_message: bool,
+) -> AppResult<imap::FetchedMessage> {
+    if !full_message {
+        let needed = |part: &imap::BodyPartRef| mime::needs_part_body(part, attachment_mode);
+        match imap::fetch_message_parts(config, session, uid, &needed).await {
+            Ok(Some(message)) => return Ok(message),
+            Ok(None) => {}
+            Err(error) => warn!(error = %error, uid, "partial message fetch failed"),
+        }
+    }
+    let raw = imap::fetch_raw_message(config, session, uid).await?;
+    Ok(imap::FetchedMessage {
+        raw,
+        omitted_sizes: HashMap::new(),
+    })
+}
+
+pub(super) async fn release_read_session(
     server: &MailImapServer,
     session: ReadSessionLease,
     reusable: bool,
@@ -743,7 +936,7 @@ async fn release_read_session(
     Ok(())
 }
 
-async fn ensure_uidvalidity_matches_readonly(
+pub(super) async fn ensure_uidvalidity_matches_readonly(
     config: &crate::config::ServerConfig,
     session: &mut imap::ImapSession,
     message_id: &MessageId,
@@ -786,9 +979,66 @@ async fn resume_cursor_search(
         offset: entry.offset,
         snippet_max_chars: entry.snippet_max_chars,
         cursor_id_from_request: Some(cursor_id),
+        criteria: None,
+        base_offset: 0,
+    })
+}
+
+/// Resume a stateless signed cursor
+///
+/// Verifies the cursor, then re-runs the original criteria restricted to
+/// UIDs below the last one returned. Results are not cached server-side, and
+/// the remaining matches are subject to the same cap as a new search.
+async fn resume_signed_search(
+    config: &crate::config::ServerConfig,
+    session: &mut imap::ImapSession,
+    input: &SearchMessagesInput,
+    uidvalidity: u32,
+    key: &[u8],
+    token: &str,
+) -> AppResult<SearchSnapshot> {
+    let cursor = decode_signed_cursor(key, token, chrono::Utc::now().timestamp())
+        .ok_or_else(|| AppError::InvalidInput("cursor is invalid or expired".to_owned()))?;
+    validate_signed_cursor(&cursor, input, uidvalidity)?;
+
+    let uids_desc = if cursor.last_uid > 1 {
+        let query = format!("{} UID 1:{}", cursor.criteria, cursor.last_uid - 1);
+        let uids = imap::uid_search(config, session, &query).await?;
+        ensure_search_within_cap(uids.len())?;
+        uids
+    } else {
+        Vec::new()
+    };
+    Ok(SearchSnapshot {
+        uids_desc: Arc::<[u32]>::from(uids_desc),
+        offset: 0,
+        snippet_max_chars: cursor.snippet_max_chars,
+        cursor_id_from_request: None,
+        criteria: Some(cursor.criteria),
+        base_offset: cursor.offset,
     })
 }
 
+fn validate_signed_cursor(
+    cursor: &SignedCursor,
+    input: &SearchMessagesInput,
+    uidvalidity: u32,
+) -> AppResult<()> {
+    if cursor.account_id != input.account_id
+        || normalize_mailbox_name(&cursor.mailbox) != normalize_mailbox_name(&input.mailbox)
+    {
+        return Err(AppError::InvalidInput(
+            "cursor does not match account/mailbox".to_owned(),
+        ));
+    }
+    if cursor.uidvalidity != uidvalidity {
+        return Err(AppError::Conflict(
+            "mailbox snapshot changed; rerun search".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
 async fn start_new_search(
     config: &crate::config::ServerConfig,
     session: &mut imap::ImapSession,
@@ -796,22 +1046,31 @@ async fn start_new_search(
 ) -> AppResult<SearchSnapshot> {
     let query = build_search_query(input)?;
     let searched_uids = imap::uid_search(config, session, &query).await?;
-    if searched_uids.len() > MAX_CURSOR_UIDS_STORED {
-        return Err(AppError::InvalidInput(format!(
-            "search matched {} messages; narrow filters to at most {} results",
-            searched_uids.len(),
-            MAX_CURSOR_UIDS_STORED
-        )));
-    }
+    ensure_search_within_cap(searched_uids.len())?;
 
     Ok(SearchSnapshot {
         uids_desc: Arc::<[u32]>::from(searched_uids),
         offset: 0,
         snippet_max_chars: input.snippet_max_chars.map(|value| value.clamp(50, 500)),
         cursor_id_from_request: None,
+        criteria: Some(query),
+        base_offset: 0,
     })
 }
 
+/// Reject searches too large to page through
+///
+/// Applies to signed cursors too: they store no snapshot, but every page
+/// re-runs the search and holds its result in memory.
+fn ensure_search_within_cap(matched: usize) -> AppResult<()> {
+    if matched > MAX_CURSOR_UIDS_STORED {
+        return Err(AppError::InvalidInput(format!(
+            "search matched {matched} messages; narrow filters to at most {MAX_CURSOR_UIDS_STORED} results"
+        )));
+    }
+    Ok(())
+}
+
 async fn build_message_summaries(
     config: &crate::config::ServerConfig,
     session: &mut imap::ImapSession,
@@ -847,6 +1106,11 @@ async fn build_message_summaries(
         }
     };
 
+    let mut body_snippets = match options.snippet_max_chars {
+        Some(max_chars) => fetch_body_snippets(config, session, &uid_set, max_chars).await,
+        None => HashMap::new(),
+    };
+
     for uid in uids {
         let Some(fetched_message) = fetched.get(uid) else {
             failed += 1;
@@ -871,7 +1135,9 @@ async fn build_message_summaries(
         };
 
         let snippet = options.snippet_max_chars.and_then(|max_chars| {
-            header_value(&headers, "subject").map(|s| mime::truncate_chars(s, max_chars))
+            body_snippets.remove(uid).or_else(|| {
+                header_value(&headers, "subject").map(|s| mime::truncate_chars(s, max_chars))
+            })
         });
 
         let message_id = MessageId {
@@ -900,10 +1166,11 @@ async fn build_message_summaries(
             uidvalidity: options.uidvalidity,
             uid: *uid,
             date: header_value(&headers, "date"),
-            from: header_value(&headers, "from"),
+            from: mime::parse_address_headers(&fetched_message.header_bytes).from,
             subject: header_value(&headers, "subject"),
             flags: Some(fetched_message.flags.clone()),
             snippet,
+            is_bulk: mime::is_bulk(&headers),
         });
     }
 
@@ -915,7 +1182,75 @@ async fn build_message_summaries(
     }
 }
 
-fn build_uid_set(uids: &[u32]) -> String {
+/// Best-effort body snippets from partial fetches of each message's text part
+///
+/// Selects the preferred text part from BODYSTRUCTURE and fetches only a
+/// prefix of it. Failures are logged and yield no snippet so callers fall back
+/// to the subject line.
+async fn fetch_body_snippets(
+    config: &crate::config::ServerConfig,
+    session: &mut imap::ImapSession,
+    uid_set: &str,
+    max_chars: usize,
+) -> HashMap<u32, String> {
+    let mut snippets = HashMap::new();
+    let part_refs = match imap::fetch_text_part_refs_by_uid_set(config, session, uid_set).await {
+        Ok(part_refs) => part_refs,
+        Err(error) => {
+            warn!(error = %error, "snippet bodystructure fetch failed");
+            return snippets;
+        }
+    };
+
+    let mut by_section: BTreeMap<(String, usize), Vec<u32>> = BTreeMap::new();
+    for (uid, part) in &part_refs {
+        let max_bytes = if part.mime_type == "text/html" {
+            SNIPPET_HTML_FETCH_BYTES
+        } else {
+            SNIPPET_PLAIN_FETCH_BYTES
+        };
+        by_section
+            .entry((part.section.clone(), max_bytes))
+            .or_default()
+            .push(*uid);
+    }
+
+    for ((section, max_bytes), section_uids) in by_section {
+        let section_uid_set = build_uid_set(&section_uids);
+        let bodies = match imap::fetch_partial_section_by_uid_set(
+            config,
+            session,
+            &section_uid_set,
+            &section,
+            max_bytes,
+        )
+        .await
+        {
+            Ok(bodies) => bodies,
+            Err(error) => {
+                warn!(error = %error, section = %section, "snippet partial body fetch failed");
+                continue;
+            }
+        };
+        for (uid, bytes) in bodies {
+            let Some(part) = part_refs.get(&uid) else {
+                continue;
+            };
+            if let Some(snippet) = mime::snippet_from_partial_part(
+                &bytes,
+                &part.mime_type,
+                part.charset.as_deref(),
+                &part.transfer_encoding,
+                max_chars,
+            ) {
+                snippets.insert(uid, snippet);
+            }
+        }
+    }
+    snippets
+}
+
+pub(super) fn build_uid_set(uids: &[u32]) -> String {
     let mut sorted = uids.to_vec();
     sorted.sort_unstable();
     let mut ranges = Vec::new();
@@ -949,9 +1284,10 @@ mod tests {
 
     use tokio::sync::Mutex;
 
-    use super::resume_cursor_search;
-    use crate::models::{MessageSummary, SearchMessagesInput};
-    use crate::pagination::{CursorEntry, CursorStore};
+    use super::{ensure_search_within_cap, resume_cursor_search, validate_signed_cursor};
+    use crate::errors::AppError;
+    use crate::models::{EmailAddress, MessageSummary, SearchMessagesInput};
+    use crate::pagination::{CursorEntry, CursorStore, SignedCursor};
     use crate::server::{MAX_CURSOR_UIDS_STORED, types::next_action_for_search_result};
 
     #[test]
@@ -959,6 +1295,14 @@ mod tests {
         assert_eq!(MAX_CURSOR_UIDS_STORED, 1_000);
     }
 
+    #[test]
+    fn search_cap_rejects_results_above_the_limit() {
+        assert!(ensure_search_within_cap(MAX_CURSOR_UIDS_STORED).is_ok());
+        let error = ensure_search_within_cap(MAX_CURSOR_UIDS_STORED + 1)
+            .expect_err("oversized search must be rejected");
+        assert!(error.to_string().contains("narrow filters"));
+    }
+
     #[tokio::test]
     async fn resume_cursor_accepts_legacy_encoded_mailbox_with_decoded_input() {
         let cursors = Arc::new(Mutex::new(CursorStore::new(600, 8)));
@@ -983,6 +1327,7 @@ mod tests {
             from: None,
             to: None,
             subject: None,
+            list_id: None,
             unread_only: None,
             last_days: None,
             start_date: None,
@@ -1022,6 +1367,7 @@ mod tests {
             from: None,
             to: None,
             subject: None,
+            list_id: None,
             unread_only: None,
             last_days: None,
             start_date: None,
@@ -1036,6 +1382,46 @@ mod tests {
         assert_eq!(snapshot.snippet_max_chars, Some(120));
     }
 
+    #[test]
+    fn signed_cursor_must_match_account_mailbox_and_uidvalidity() {
+        let cursor = SignedCursor {
+            account_id: "default".to_owned(),
+            mailbox: "&ZeVnLIqe-".to_owned(),
+            uidvalidity: 42,
+            criteria: "ALL".to_owned(),
+            last_uid: 10,
+            offset: 10,
+            snippet_max_chars: None,
+            expires_at_unix: i64::MAX,
+        };
+        let mut input = SearchMessagesInput {
+            account_id: "default".to_owned(),
+            mailbox: "日本語".to_owned(),
+            cursor: Some("s1.payload.tag".to_owned()),
+            query: None,
+            from: None,
+            to: None,
+            subject: None,
+            list_id: None,
+            unread_only: None,
+            last_days: None,
+            start_date: None,
+            end_date: None,
+            limit: 10,
+            snippet_max_chars: None,
+        };
+
+        validate_signed_cursor(&cursor, &input, 42).expect("matching cursor should resume");
+        let error = validate_signed_cursor(&cursor, &input, 43)
+            .expect_err("changed uidvalidity must be rejected");
+        assert!(matches!(error, AppError::Conflict(_)));
+
+        input.account_id = "work".to_owned();
+        let error = validate_signed_cursor(&cursor, &input, 42)
+            .expect_err("other account must be rejected");
+        assert!(matches!(error, AppError::InvalidInput(_)));
+    }
+
     #[test]
     fn search_result_next_action_for_get_message_omits_account_id() {
         let next_action = next_action_for_search_result(
@@ -1052,10 +1438,14 @@ mod tests {
                 uidvalidity: 42,
                 uid: 7,
                 date: Some("2026-01-01T00:00:00Z".to_owned()),
-                from: Some("sender@example.com".to_owned()),
+                from: Some(vec![EmailAddress {
+                    name: None,
+                    email: "sender@example.com".to_owned(),
+                }]),
                 subject: Some("subject".to_owned()),
                 flags: Some(vec!["\\Seen".to_owned()]),
                 snippet: Some("snippet".to_owned()),
+                is_bulk: false,
             }],
         );
 
@@ -0,0 +1,464 @@
+use chrono::Local;
+use mailparse::{MailHeaderMap, parse_headers};
+
+use crate::compose::{self, Draft, Mailbox};
+use crate::config::{AccountConfig, ServerConfig};
+use crate::errors::{AppError, AppResult};
+use crate::imap::{self, ImapSession, SpecialUse};
+use crate::mailbox_codec::normalize_mailbox_name;
+use crate::message_id::MessageId;
+use crate::models::{SaveDraftInput, SendMessageInput};
+use crate::smtp::{self, Envelope};
+
+use super::draft::{PendingAttachment, compose_request, require_identity};
+use super::read::ensure_uidvalidity_matches_readonly;
+use super::types::{SendMessageData, ToolIssue, log_runtime_issues};
+use super::validation::{
+    parse_and_validate_message_id, require_send_enabled, require_write_enabled, validate_account_id,
+};
+use super::{MAX_DRAFT_ATTACHMENT_BYTES, MAX_DRAFT_RECIPIENTS, MailImapServer};
+
+const SEND_TOOL: &str = "imap_send_message";
+
+/// Flags set on the copy stored in the Sent mailbox
+const SENT_FLAGS: [&str; 1] = ["\\Seen"];
+
+/// What `imap_send_message` was asked to send
+enum Source {
+    Draft(MessageId),
+    Compose {
+        from: Mailbox,
+        draft: Box<Draft>,
+        pending: Vec<PendingAttachment>,
+    },
+}
+
+/// Message ready for submission
+struct Outgoing {
+    /// Full message including `Bcc`, as stored in Sent
+    raw: Vec<u8>,
+    envelope: Envelope,
+    message_id_header: Option<String>,
+}
+
+impl MailImapServer {
+    pub(super) async fn send_message_impl(
+        &self,
+        input: SendMessageInput,
+    ) -> AppResult<SendMessageData> {
+        require_send_enabled(&self.config)?;
+        validate_account_id(&input.account_id)?;
+        let save_to_sent = follow_up_enabled(&self.config, input.save_to_sent, "save_to_sent")?;
+        let delete_draft = follow_up_enabled(&self.config, input.delete_draft, "delete_draft")?;
+        let account = self.config.get_account(&input.account_id)?;
+        let smtp_config = account.smtp.as_ref().ok_or_else(|| {
+            AppError::InvalidInput(format!(
+                "account '{}' has no SMTP server; set MAIL_IMAP_<ACCOUNT>_SMTP_HOST",
+                input.account_id
+            ))
+        })?;
+        let draft_id = input
+            .draft_message_id
+            .as_deref()
+            .map(parse_and_validate_message_id)
+            .transpose()?;
+        let source = match &draft_id {
+            Some(draft_id) => {
+                if has_compose_fields(&input) {
+                    return Err(AppError::InvalidInput(
+                        "draft_message_id cannot be combined with from, recipients, subject, body, or attachments"
+                            .to_owned(),
+                    ));
+                }
+                if draft_id.account_id != input.account_id {
+                    return Err(AppError::InvalidInput(
+                        "draft_message_id must belong to account_id".to_owned(),
+                    ));
+                }
+                Source::Draft(draft_id.clone())
+            }
+            None => {
+                let (from, draft, pending) =
+                    compose_request(&self.config, account, &compose_input(&input))?;
+                if draft.to.is_empty() && draft.cc.is_empty() && draft.bcc.is_empty() {
+                    return Err(AppError::InvalidInput(
+                        "at least one of to, cc, or bcc is required".to_owned(),
+                    ));
+                }
+                Source::Compose {
+                    from,
+                    draft: Box::new(draft),
+                    pending,
+                }
+            }
+        };
+
+        let account_lock = self.account_write_lock(&input.account_id).await;
+        let _account_guard = account_lock.lock().await;
+        let mut session = match imap::connect_authenticated(&self.config, account).await {
+            Ok(session) => session,
+            Err(error) => {
+                let issue = ToolIssue::from_error("connect_authenticated", &error);
+                return Ok(failed_send(&input.account_id, issue));
+            }
+        };
+        let outgoing = match source {
+            Source::Draft(draft_id) => {
+                self.load_draft_for_sending(&mut session, account, &draft_id)
+                    .await
+            }
+            Source::Compose {
+                from,
+                mut draft,
+                pending,
+            } => self
+                .load_attachments(&mut session, pending)
+                .await
+                .map(|attachments| {
+                    draft.attachments = attachments;
+                    let message_id_header = compose::new_message_id(&from);
+                    let raw = compose::build_message(
+                        &draft,
+                        Local::now().fixed_offset(),
+                        &message_id_header,
+                    );
+                    Outgoing {
+                        raw,
+                        envelope: envelope(&from, [&draft.to, &draft.cc, &draft.bcc]),
+                        message_id_header: Some(message_id_header),
+                    }
+                }),
+        };
+        let outgoing = match outgoing {
+            Ok(outgoing) => outgoing,
+            Err((stage, error)) => {
+                let _ = imap::logout_session_best_effort(&self.config, session).await;
+                return send_error(&input.account_id, stage, error);
+            }
+        };
+
+        let transmitted = compose::strip_header(&outgoing.raw, "Bcc");
+        if let Err(error) =
+            smtp::send_message(&self.config, smtp_config, &outgoing.envelope, &transmitted).await
+        {
+            let _ = imap::logout_session_best_effort(&self.config, session).await;
+            return send_error(&input.account_id, "smtp_send", error);
+        }
+
+        // The message is out; later failures only downgrade the result.
+        let mut issues = Vec::new();
+        let mut sent_mailbox = None;
+        let mut sent_message_id = None;
+        if save_to_sent {
+            let flags = SENT_FLAGS.map(str::to_owned);
+            match self
+                .append_to_special_use(
+                    &mut session,
+                    SpecialUse::Sent,
+                    &flags,
+                    &outgoing.raw,
+                    outgoing.message_id_header.as_deref(),
+                )
+                .await
+            {
+                Ok(saved) => {
+                    sent_message_id = saved.uid.map(|(uidvalidity, uid)| {
+                        MessageId {
+                            account_id: input.account_id.clone(),
+                            mailbox: saved.mailbox.clone(),
+                            uidvalidity,
+                            uid,
+                        }
+                        .encode()
+                    });
+                    sent_mailbox = Some(saved.mailbox);
+                }
+                Err((stage, error)) => issues.push(ToolIssue::from_error(stage, &error)),
+            }
+        }
+        let mut draft_deleted = false;
+        if let Some(draft_id) = draft_id.filter(|_| delete_draft) {
+            match self.delete_message(&mut session, &draft_id).await {
+                Ok(()) => draft_deleted = true,
+                Err((stage, error)) => issues.push(
+                    ToolIssue::from_error(stage, &error)
+                        .with_uid(draft_id.uid)
+                        .with_message_id(&draft_id.encode()),
+                ),
+            }
+        }
+        let _ = imap::logout_session_best_effort(&self.config, session).await;
+
+        let status = if issues.is_empty() { "ok" } else { "partial" };
+        if !issues.is_empty() {
+            log_runtime_issues(SEND_TOOL, status, &input.account_id, None, &issues);
+        }
+        Ok(SendMessageData {
+            status: status.to_owned(),
+            issues,
+            account_id: input.account_id,
+            sent: true,
+            message_id_header: outgoing.message_id_header,
+            envelope_from: Some(outgoing.envelope.from),
+            recipients: outgoing.envelope.recipients,
+            size_bytes: transmitted.len(),
+            sent_mailbox,
+            sent_message_id,
+            draft_deleted,
+        })
+    }
+
+    /// Fetch a stored draft and derive its envelope from the headers
+    ///
+    /// Only `\Draft`-flagged messages in the SPECIAL-USE Drafts mailbox whose
+    /// `From` is an account identity are sent. The `Date` header is
+    /// refreshed, and a `Message-ID` is added when the draft has none.
+    async fn load_draft_for_sending(
+        &self,
+        session: &mut ImapSession,
+        account: &AccountConfig,
+        draft_id: &MessageId,
+    ) -> Result<Outgoing, (&'static str, AppError)> {
+        let drafts_mailbox =
+            imap::find_special_use_mailbox(&self.config, session, SpecialUse::Drafts)
+                .await
+                .map_err(|e| ("find_drafts_mailbox", e))?
+                .ok_or_else(|| {
+                    (
+                        "find_drafts_mailbox",
+                        AppError::NotFound("no Drafts mailbox found".to_owned()),
+                    )
+                })?;
+        ensure_uidvalidity_matches_readonly(&self.config, session, draft_id)
+            .await
+            .map_err(|e| ("select_mailbox_readonly", e))?;
+        let flags = imap::fetch_flags(&self.config, session, draft_id.uid)
+            .await
+            .map_err(|e| ("fetch_flags", e))?;
+        ensure_sendable_draft(&draft_id.mailbox, &drafts_mailbox, &flags)
+            .map_err(|e| ("check_draft", e))?;
+        let size = imap::fetch_message_size(&self.config, session, draft_id.uid)
+            .await
+            .map_err(|e| ("fetch_message_size", e))?;
+        if size > MAX_DRAFT_ATTACHMENT_BYTES {
+            return Err((
+                "fetch_message_size",
+                AppError::InvalidInput(format!(
+                    "draft is {size} bytes; the maximum is {MAX_DRAFT_ATTACHMENT_BYTES}"
+                )),
+            ));
+        }
+        let raw = imap::fetch_raw_message(&self.config, session, draft_id.uid)
+            .await
+            .map_err(|e| ("fetch_raw_message", e))?;
+        let outgoing = draft_outgoing(&raw).map_err(|e| ("parse_draft", e))?;
+        let from = Mailbox::new(None, &outgoing.envelope.from).map_err(|e| ("parse_draft", e))?;
+        require_identity(account, &from).map_err(|e| ("check_draft", e))?;
+        Ok(outgoing)
+    }
+}
+
+/// Resolve a follow-up IMAP write of a send
+///
+/// Saving the Sent copy and removing the draft mutate mailboxes, so they
+/// default to on only when write tools are enabled, and asking for them
+/// explicitly requires write tools.
+fn follow_up_enabled(
+    config: &ServerConfig,
+    requested: Option<bool>,
+    name: &str,
+) -> AppResult<bool> {
+    match requested {
+        Some(true) => require_write_enabled(config).map(|()| true).map_err(|_| {
+            AppError::InvalidInput(format!(
+                "{name}=true requires write tools; set MAIL_IMAP_WRITE_ENABLED=true"
+            ))
+        }),
+        Some(false) => Ok(false),
+        None => Ok(config.write_enabled),
+    }
+}
+
+fn has_compose_fields(input: &SendMessageInput) -> bool {
+    input.from.is_some()
+        || !input.to.is_empty()
+        || !input.cc.is_empty()
+        || !input.bcc.is_empty()
+        || !input.subject.is_empty()
+        || !input.body_text.is_empty()
+        || input.body_html.is_some()
+        || !input.attachments.is_empty()
+}
+
+fn compose_input(input: &SendMessageInput) -> SaveDraftInput {
+    SaveDraftInput {
+        account_id: input.account_id.clone(),
+        from: input.from.clone(),
+        to: input.to.clone(),
+        cc: input.cc.clone(),
+        bcc: input.bcc.clone(),
+        subject: input.subject.clone(),
+        body_text: input.body_text.clone(),
+        body_html: input.body_html.clone(),
+        attachments: input.attachments.clone(),
+    }
+}
+
+/// Envelope for a sender and recipient lists, without duplicate recipients
+fn envelope(from: &Mailbox, lists: [&Vec<Mailbox>; 3]) -> Envelope {
+    let mut recipients = Vec::<Mailbox>::new();
+    for mailbox in lists.into_iter().flatten() {
+        if !recipients.iter().any(|known| known.same_address(mailbox)) {
+            recipients.push(mailbox.clone());
+        }
+    }
+    Envelope {
+        from: from.address.clone(),
+        recipients: recipients.into_iter().map(|m| m.address).collect(),
+    }
+}
+
+/// Reject a message that is not a `\Draft`-flagged message in the Drafts mailbox
+fn ensure_sendable_draft(mailbox: &str, drafts_mailbox: &str, flags: &[String]) -> AppResult<()> {
+    if normalize_mailbox_name(mailbox) != normalize_mailbox_name(drafts_mailbox) {
+        return Err(AppError::InvalidInput(format!(
+            "draft_message_id must be in the Drafts mailbox '{drafts_mailbox}'"
+        )));
+    }
+    if !flags
+        .iter()
+        .any(|flag| flag.eq_ignore_ascii_case("\\Draft"))
+    {
+        return Err(AppError::InvalidInput(
+            "draft_message_id is not flagged \\Draft".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+/// Prepare a stored draft for sending
+fn draft_outgoing(raw: &[u8]) -> AppResult<Outgoing> {
+    let from = compose::header_mailboxes(raw, "From")?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::InvalidInput("draft has no From address".to_owned()))?;
+    let to = compose::header_mailboxes(raw, "To")?;
+    let cc = compose::header_mailboxes(raw, "Cc")?;
+    let bcc = compose::header_mailboxes(raw, "Bcc")?;
+    let envelope = envelope(&from, [&to, &cc, &bcc]);
+    if envelope.recipients.is_empty() {
+        return Err(AppError::InvalidInput(
+            "draft has no To, Cc, or Bcc recipients".to_owned(),
+        ));
+    }
+    if envelope.recipients.len() > MAX_DRAFT_RECIPIENTS {
+        return Err(AppError::InvalidInput(format!(
+            "messages accept at most {MAX_DRAFT_RECIPIENTS} recipients"
+        )));
+    }
+    let (headers, _) = parse_headers(raw)
+        .map_err(|e| AppError::InvalidInput(format!("draft headers are malformed: {e}")))?;
+    let existing_id = headers
+        .get_first_value("Message-ID")
+        .and_then(|value| compose::message_ids(&value).into_iter().next());
+
+    let mut out = format!("Date: {}\r\n", Local::now().fixed_offset().to_rfc2822());
+    let message_id_header = existing_id.unwrap_or_else(|| {
+        let generated = compose::new_message_id(&from);
+        out.push_str(&format!("Message-ID: {generated}\r\n"));
+        generated
+    });
+    let mut message = out.into_bytes();
+    message.extend(compose::strip_header(raw, "Date"));
+    Ok(Outgoing {
+        raw: message,
+        envelope,
+        message_id_header: Some(message_id_header),
+    })
+}
+
+/// Return caller mistakes as errors and runtime failures as a `failed` result
+fn send_error(account_id: &str, stage: &str, error: AppError) -> AppResult<SendMessageData> {
+    match error {
+        AppError::InvalidInput(_) | AppError::NotFound(_) | AppError::Conflict(_) => Err(error),
+        error => Ok(failed_send(
+            account_id,
+            ToolIssue::from_error(stage, &error),
+        )),
+    }
+}
+
+/// Build a `failed` response for a message that was not sent.
+fn failed_send(account_id: &str, issue: ToolIssue) -> SendMessageData {
+    let issues = vec![issue];
+    log_runtime_issues(SEND_TOOL, "failed", account_id, None, &issues);
+    SendMessageData {
+        status: "failed".to_owned(),
+        issues,
+        account_id: account_id.to_owned(),
+        sent: false,
+        message_id_header: None,
+        envelope_from: None,
+        recipients: Vec::new(),
+        size_bytes: 0,
+        sent_mailbox: None,
+        sent_message_id: None,
+        draft_deleted: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{draft_outgoing, ensure_sendable_draft, follow_up_enabled};
+
+    #[test]
+    fn follow_up_writes_require_the_write_gate() {
+        let mut config = crate::server::tests::schema_test_server_config();
+        config.write_enabled = false;
+        assert!(!follow_up_enabled(&config, None, "save_to_sent").expect("defaults off"));
+        assert!(!follow_up_enabled(&config, Some(false), "save_to_sent").expect("explicit off"));
+        let error =
+            follow_up_enabled(&config, Some(true), "delete_draft").expect_err("gate is off");
+        assert!(error.to_string().contains("MAIL_IMAP_WRITE_ENABLED"));
+
+        config.write_enabled = true;
+        assert!(follow_up_enabled(&config, None, "save_to_sent").expect("defaults on"));
+        assert!(follow_up_enabled(&config, Some(true), "delete_draft").expect("allowed"));
+        assert!(!follow_up_enabled(&config, Some(false), "delete_draft").expect("explicit off"));
+    }
+
+    #[test]
+    fn draft_envelope_includes_bcc_and_refreshes_date() {
+        let raw = b"Date: Mon, 02 Mar 2026 10:00:00 +0000\r\nFrom: Me <me@example.com>\r\nTo: a@example.com, A@example.com\r\nBcc: hidden@example.com\r\nSubject: hi\r\n\r\nbody\r\n";
+        let outgoing = draft_outgoing(raw).expect("draft is sendable");
+        assert_eq!(outgoing.envelope.from, "me@example.com");
+        assert_eq!(
+            outgoing.envelope.recipients,
+            vec!["a@example.com", "hidden@example.com"]
+        );
+        let message_id = outgoing.message_id_header.expect("generated id");
+        assert!(message_id.ends_with("@example.com>"));
+        let text = String::from_utf8(outgoing.raw).expect("ascii");
+        assert!(text.starts_with("Date: "));
+        assert!(!text.contains("02 Mar 2026"));
+        assert!(text.contains(&format!("Message-ID: {message_id}\r\n")));
+        assert!(text.contains("Bcc: hidden@example.com"));
+
+        let no_recipients = b"From: me@example.com\r\nSubject: hi\r\n\r\nbody\r\n";
+        let error = draft_outgoing(no_recipients)
+            .err()
+            .expect("recipients are required");
+        assert!(error.to_string().contains("no To, Cc, or Bcc"));
+    }
+
+    #[test]
+    fn only_flagged_messages_in_drafts_are_sendable() {
+        let draft = ["\\Seen".to_owned(), "\\Draft".to_owned()];
+        assert!(ensure_sendable_draft("Drafts", "Drafts", &draft).is_ok());
+        let error = ensure_sendable_draft("INBOX", "Drafts", &draft).expect_err("wrong mailbox");
+        assert!(error.to_string().contains("Drafts mailbox"));
+        let error =
+            ensure_sendable_draft("Drafts", "Drafts", &["\\Seen".to_owned()]).expect_err("flag");
+        assert!(error.to_string().contains("not flagged"));
+    }
+}
@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+
+use crate::errors::AppResult;
+use crate::imap;
+use crate::mime;
+use crate::models::{MailboxStatsInput, SearchMessagesInput};
+
+use super::read::{build_uid_set, release_read_session};
+use super::types::{
+    FacetCount, MailboxStatsData, ToolIssue, is_hard_precondition_error, log_runtime_issues,
+    next_action_list_mailboxes, next_action_search_mailbox, status_from_issue_and_counts,
+};
+use super::validation::{
+    build_search_query, header_value, validate_account_id, validate_search_input,
+};
+use super::{MAX_STATS_DAYS, MAX_STATS_MESSAGES, MailImapServer, STATS_FETCH_BATCH};
+
+/// Size bucket upper bounds in bytes paired with their labels.
+const SIZE_BUCKETS: [(u32, &str); 4] = [
+    (10 * 1024, "<10KB"),
+    (100 * 1024, "10KB-100KB"),
+    (1024 * 1024, "100KB-1MB"),
+    (10 * 1024 * 1024, "1MB-10MB"),
+];
+
+/// Key used when a facet value cannot be derived from the message.
+const UNKNOWN_KEY: &str = "unknown";
+
+impl MailImapServer {
+    pub(super) async fn mailbox_stats_impl(
+        &self,
+        input: MailboxStatsInput,
+    ) -> AppResult<MailboxStatsData> {
+        let search_input = search_input_for_stats(&input);
+        validate_search_input(&search_input)?;
+        validate_account_id(&input.account_id)?;
+        let query = build_search_query(&search_input)?;
+        let top_n = input.top_n.clamp(1, 50);
+
+        let mut session = match self.checkout_read_session(&input.account_id).await {
+            Ok(session) => session,
+            Err(error) => {
+                let issues = vec![ToolIssue::from_error("connect_authenticated", &error)];
+                return Ok(failed_stats(input, issues));
+            }
+        };
+
+        if let Err(error) =
+            imap::select_mailbox_readonly(&self.config, session.session(), &input.mailbox).await
+        {
+            let _ = release_read_session(self, session, false).await;
+            let issues = vec![ToolIssue::from_error("select_mailbox_readonly", &error)];
+            return Ok(failed_stats(input, issues));
+        }
+
+        let uids_desc = match imap::uid_search(&self.config, session.session(), &query).await {
+            Ok(uids) => uids,
+            Err(error) if is_hard_precondition_error(&error) => {
+                let _ = release_read_session(self, session, false).await;
+                return Err(error);
+            }
+            Err(error) => {
+                let _ = release_read_session(self, session, false).await;
+                let issues = vec![ToolIssue::from_error("uid_search", &error)];
+                return Ok(failed_stats(input, issues));
+            }
+        };
+
+        let total = uids_desc.len();
+        let scan_uids = &uids_desc[..total.min(MAX_STATS_MESSAGES)];
+        let mut accumulator = StatsAccumulator::default();
+        let mut issues = Vec::new();
+        let mut failed = 0usize;
+        for batch in scan_uids.chunks(STATS_FETCH_BATCH) {
+            let uid_set = build_uid_set(batch);
+            let fetched = match imap::fetch_headers_and_flags_by_uid_set(
+                &self.config,
+                session.session(),
+                &uid_set,
+            )
+            .await
+            {
+                Ok(fetched) => fetched,
+                Err(error) => {
+                    failed += batch.len();
+                    issues.push(ToolIssue::from_error("fetch_headers_and_flags", &error));
+                    break;
+                }
+            };
+            for uid in batch {
+                let Some(message) = fetched.get(uid) else {
+                    failed += 1;
+                    continue;
+                };
+                match mime::parse_header_bytes(&message.header_bytes) {
+                    Ok(headers) => accumulator.add(&headers, &message.flags, message.size_bytes),
+                    Err(error) => {
+                        failed += 1;
+                        issues.push(
+                            ToolIssue::from_error("parse_header_bytes", &error).with_uid(*uid),
+                        );
+                    }
+                }
+            }
+        }
+        failed += scan_uids.len().saturating_sub(accumulator.scanned + failed);
+
+        let scanned = accumulator.scanned;
+        let status = status_from_issue_and_counts(&issues, scanned > 0 || total == 0).to_owned();
+        log_runtime_issues(
+            "imap_mailbox_stats",
+            &status,
+            &input.account_id,
+            Some(&input.mailbox),
+            &issues,
+        );
+        let reusable = issues.is_empty() || scanned > 0;
+        let _ = release_read_session(self, session, reusable).await;
+
+        let facets = accumulator.finish(top_n);
+        Ok(MailboxStatsData {
+            status,
+            issues,
+            next_action: next_action_search_mailbox(&input.account_id, &input.mailbox),
+            account_id: input.account_id,
+            mailbox: input.mailbox,
+            total,
+            scanned,
+            failed,
+            truncated: total > scan_uids.len(),
+            unread: facets.unread,
+            by_sender: facets.by_sender,
+            by_sender_domain: facets.by_sender_domain,
+            by_day: facets.by_day,
+            by_flag: facets.by_flag,
+            by_size: facets.by_size,
+        })
+    }
+}
+
+/// Reuse search validation and query building for stats criteria.
+fn search_input_for_stats(input: &MailboxStatsInput) -> SearchMessagesInput {
+    SearchMessagesInput {
+        account_id: input.account_id.clone(),
+        mailbox: input.mailbox.clone(),
+        cursor: None,
+        query: input.query.clone(),
+        from: input.from.clone(),
+        to: input.to.clone(),
+        subject: input.subject.clone(),
+        list_id: input.list_id.clone(),
+        unread_only: input.unread_only,
+        last_days: input.last_days,
+        start_date: input.start_date.clone(),
+        end_date: input.end_date.clone(),
+        limit: 1,
+        snippet_max_chars: None,
+    }
+}
+
+fn failed_stats(input: MailboxStatsInput, issues: Vec<ToolIssue>) -> MailboxStatsData {
+    log_runtime_issues(
+        "imap_mailbox_stats",
+        "failed",
+        &input.account_id,
+        Some(&input.mailbox),
+        &issues,
+    );
+    MailboxStatsData {
+        status: "failed".to_owned(),
+        issues,
+        next_action: next_action_list_mailboxes(&input.account_id),
+        account_id: input.account_id,
+        mailbox: input.mailbox,
+        total: 0,
+        scanned: 0,
+        failed: 0,
+        truncated: false,
+        unread: 0,
+        by_sender: Vec::new(),
+        by_sender_domain: Vec::new(),
+        by_day: Vec::new(),
+        by_flag: Vec::new(),
+        by_size: Vec::new(),
+    }
+}
+
+#[derive(Debug, Default)]
+struct StatsAccumulator {
+    scanned: usize,
+    unread: usize,
+    by_sender: HashMap<String, (usize, usize)>,
+    by_sender_domain: HashMap<String, (usize, usize)>,
+    by_day: HashMap<String, (usize, usize)>,
+    by_flag: HashMap<String, (usize, usize)>,
+    by_size: HashMap<String, (usize, usize)>,
+}
+
+#[derive(Debug)]
+struct StatsFacets {
+    unread: usize,
+    by_sender: Vec<FacetCount>,
+    by_sender_domain: Vec<FacetCount>,
+    by_day: Vec<FacetCount>,
+    by_flag: Vec<FacetCount>,
+    by_size: Vec<FacetCount>,
+}
+
+impl StatsAccumulator {
+    fn add(&mut self, headers: &[(String, String)], flags: &[String], size_bytes: Option<u32>) {
+        let unread = !flags.iter().any(|flag| flag.eq_ignore_ascii_case("\\Seen"));
+        self.scanned += 1;
+        if unread {
+            self.unread += 1;
+        }
+
+        let sender = header_value(headers, "from").and_then(|from| sender_address(&from));
+        let domain = sender
+            .as_deref()
+            .and_then(|address| address.rsplit_once('@'))
+            .map(|(_, domain)| domain.to_owned());
+        let day = header_value(headers, "date").and_then(|date| message_day(&date));
+
+        bump(&mut self.by_sender, sender, unread);
+        bump(&mut self.by_sender_domain, domain, unread);
+        bump(&mut self.by_day, day, unread);
+        bump(
+            &mut self.by_size,
+            size_bytes.map(|size| size_bucket(size).to_owned()),
+            unread,
+        );
+        for flag in flags {
+            bump(&mut self.by_flag, Some(flag.clone()), unread);
+        }
+    }
+
+    fn finish(self, top_n: usize) -> StatsFacets {
+        let mut by_day = into_facets(self.by_day);
+        by_day.sort_by(|left, right| right.key.cmp(&left.key));
+        by_day.truncate(MAX_STATS_DAYS);
+
+        let mut by_size = into_facets(self.by_size);
+        by_size.sort_by_key(|facet| size_bucket_rank(&facet.key));
+
+        StatsFacets {
+            unread: self.unread,
+            by_sender: top_facets(self.by_sender, top_n),
+            by_sender_domain: top_facets(self.by_sender_domain, top_n),
+            by_day,
+            by_flag: top_facets(self.by_flag, usize::MAX),
+            by_size,
+        }
+    }
+}
+
+fn bump(counts: &mut HashMap<String, (usize, usize)>, key: Option<String>, unread: bool) {
+    let entry = counts
+        .entry(key.unwrap_or_else(|| UNKNOWN_KEY.to_owned()))
+        .or_default();
+    entry.0 += 1;
+    if unread {
+        entry.1 += 1;
+    }
+}
+
+fn into_facets(counts: HashMap<String, (usize, usize)>) -> Vec<FacetCount> {
+    counts
+        .into_iter()
+        .map(|(key, (count, unread))| FacetCount { key, count, unread })
+        .collect()
+}
+
+/// Order facets by descending count (ties by key) and keep the top `limit`.
+fn top_facets(counts: HashMap<String, (usize, usize)>, limit: usize) -> Vec<FacetCount> {
+    let mut facets = into_facets(counts);
+    facets.sort_by(|left, right| {
+        right
+            .count
+            .cmp(&left.count)
+            .then_with(|| left.key.cmp(&right.key))
+    });
+    facets.truncate(limit);
+    facets
+}
+
+/// Extract the lowercase address of the first sender in a `From` header.
+fn sender_address(from: &str) -> Option<String> {
+    let addresses = mailparse::addrparse(from).ok()?;
+    let address = addresses.iter().find_map(|address| match address {
+        mailparse::MailAddr::Single(info) => Some(info.addr.clone()),
+        mailparse::MailAddr::Group(group) => group.addrs.first().map(|info| info.addr.clone()),
+    })?;
+    let address = address.trim().to_ascii_lowercase();
+    (!address.is_empty()).then_some(address)
+}
+
+/// Convert a `Date` header into a UTC `YYYY-MM-DD` day key.
+fn message_day(date: &str) -> Option<String> {
+    // mailparse is lenient and maps unparseable input to the epoch.
+    let timestamp = mailparse::dateparse(date).ok().filter(|ts| *ts > 0)?;
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|datetime| datetime.date_naive().format("%Y-%m-%d").to_string())
+}
+
+fn size_bucket(size_bytes: u32) -> &'static str {
+    SIZE_BUCKETS
+        .iter()
+        .find(|(limit, _)| size_bytes < *limit)
+        .map(|(_, label)| *label)
+        .unwrap_or(">=10MB")
+}
+
+fn size_bucket_rank(label: &str) -> usize {
+    SIZE_BUCKETS
+        .iter()
+        .position(|(_, bucket)| *bucket == label)
+        .unwrap_or(match label {
+            ">=10MB" => SIZE_BUCKETS.len(),
+            _ => SIZE_BUCKETS.len() + 1,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StatsAccumulator, message_day, sender_address, size_bucket};
+    use crate::server::types::FacetCount;
+
+    fn headers(from: &str, date: &str) -> Vec<(String, String)> {
+        vec![
+            ("From".to_owned(), from.to_owned()),
+            ("Date".to_owned(), date.to_owned()),
+        ]
+    }
+
+    #[test]
+    fn sender_address_and_day_are_normalized() {
+        assert_eq!(
+            sender_address("\"Alice Example\" <Alice@Example.COM>").as_deref(),
+            Some("alice@example.com")
+        );
+        assert_eq!(sender_address("").as_deref(), None);
+        assert_eq!(
+            message_day("Mon, 02 Mar 2026 23:30:00 -0500").as_deref(),
+            Some("2026-03-03")
+        );
+        assert_eq!(message_day("not a date").as_deref(), None);
+    }
+
+    #[test]
+    fn size_buckets_cover_boundaries() {
+        assert_eq!(size_bucket(0), "<10KB");
+        assert_eq!(size_bucket(10 * 1024), "10KB-100KB");
+        assert_eq!(size_bucket(2 * 1024 * 1024), "1MB-10MB");
+        assert_eq!(size_bucket(10 * 1024 * 1024), ">=10MB");
+    }
+
+    #[test]
+    fn accumulator_groups_counts_and_unread() {
+        let mut accumulator = StatsAccumulator::default();
+        let seen = vec!["\\Seen".to_owned()];
+        accumulator.add(
+            &headers("a@example.com", "Mon, 02 Mar 2026 10:00:00 +0000"),
+            &seen,
+            Some(500),
+        );
+        accumulator.add(
+            &headers("b@example.com", "Mon, 02 Mar 2026 11:00:00 +0000"),
+            &[],
+            Some(50_000),
+        );
+        accumulator.add(
+            &headers("a@example.com", "Tue, 03 Mar 2026 10:00:00 +0000"),
+            &[],
+            None,
+        );
+
+        let facets = accumulator.finish(1);
+        assert_eq!(facets.unread, 2);
+        assert_eq!(
+            facets.by_sender,
+            vec![FacetCount {
+                key: "a@example.com".to_owned(),
+                count: 2,
+                unread: 1,
+            }]
+        );
+        assert_eq!(facets.by_sender_domain[0].count, 3);
+        assert_eq!(facets.by_day[0].key, "2026-03-03");
+        assert_eq!(facets.by_day[1].count, 2);
+        assert_eq!(
+            facets
+                .by_size
+                .iter()
+                .map(|facet| facet.key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["<10KB", "10KB-100KB", "unknown"]
+        );
+        assert_eq!(facets.by_flag[0].key, "\\Seen");
+    }
+}
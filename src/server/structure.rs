@@ -0,0 +1,91 @@
+use crate::errors::AppResult;
+use crate::imap;
+use crate::message_id::MessageId;
+use crate::models::GetMessageStructureInput;
+
+use super::MailImapServer;
+use super::read::{ensure_uidvalidity_matches_readonly, release_read_session};
+use super::types::{GetMessageStructureData, ToolIssue, build_message_uri, log_runtime_issues};
+use super::validation::parse_and_validate_message_id;
+
+impl MailImapServer {
+    pub(super) async fn get_message_structure_impl(
+        &self,
+        input: GetMessageStructureInput,
+    ) -> AppResult<GetMessageStructureData> {
+        let message_id = parse_and_validate_message_id(&input.message_id)?;
+
+        let mut session = match self.checkout_read_session(&message_id.account_id).await {
+            Ok(session) => session,
+            Err(error) => {
+                let issue = ToolIssue::from_error("connect_authenticated", &error);
+                return Ok(failed_structure(&message_id, issue));
+            }
+        };
+        if let Err(error) =
+            ensure_uidvalidity_matches_readonly(&self.config, session.session(), &message_id).await
+        {
+            let _ = release_read_session(self, session, false).await;
+            return Err(error);
+        }
+
+        let structure =
+            match imap::fetch_message_structure(&self.config, session.session(), message_id.uid)
+                .await
+            {
+                Ok(structure) => structure,
+                Err(error) => {
+                    let _ = release_read_session(self, session, false).await;
+                    let issue = ToolIssue::from_error("fetch_body_structure", &error);
+                    return Ok(failed_structure(&message_id, issue));
+                }
+            };
+        let _ = release_read_session(self, session, true).await;
+
+        Ok(GetMessageStructureData {
+            status: "ok".to_owned(),
+            issues: Vec::new(),
+            account_id: message_id.account_id.clone(),
+            message_id: message_id.encode(),
+            message_uri: build_message_uri(
+                &message_id.account_id,
+                &message_id.mailbox,
+                message_id.uidvalidity,
+                message_id.uid,
+            ),
+            size_bytes: structure.size_bytes,
+            structure: Some(structure.root),
+        })
+    }
+}
+
+/// Build a `failed` response after a runtime error, logging the issue.
+fn failed_structure(message_id: &MessageId, issue: ToolIssue) -> GetMessageStructureData {
+    let encoded_message_id = message_id.encode();
+    let issues = vec![
+        issue
+            .with_uid(message_id.uid)
+            .with_message_id(&encoded_message_id),
+    ];
+    log_runtime_issues(
+        "imap_get_message_structure",
+        "failed",
+        &message_id.account_id,
+        Some(&message_id.mailbox),
+        &issues,
+    );
+    GetMessageStructureData {
+        status: "failed".to_owned(),
+        issues,
+        account_id: message_id.account_id.clone(),
+        message_id: encoded_message_id,
+        message_uri: build_message_uri(
+            &message_id.account_id,
+            &message_id.mailbox,
+            message_id.uidvalidity,
+            message_id.uid,
+        ),
+        size_bytes: None,
+        structure: None,
+    }
+}
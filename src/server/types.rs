@@ -1,4 +1,6 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Instant;
 
 use chrono::Utc;
@@ -8,8 +10,11 @@ use schemars::JsonSchema;
 use tracing::{error, warn};
 
 use crate::errors::{AppError, AppResult};
+use crate::mailstore::{SourceEntry, StoreFormat};
 use crate::message_id::MessageId;
-use crate::models::{AccountInfo, MailboxInfo, MessageDetail, MessageSummary, Meta, ToolEnvelope};
+use crate::models::{
+    AccountInfo, MailboxInfo, MessageDetail, MessageSummary, Meta, MimePart, ToolEnvelope,
+};
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
 pub(super) struct ListAccountsData {
@@ -42,6 +47,32 @@ pub(super) struct SearchResultData {
     pub(super) has_more: bool,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, JsonSchema)]
+pub(super) struct FacetCount {
+    pub(super) key: String,
+    pub(super) count: usize,
+    pub(super) unread: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
+pub(super) struct MailboxStatsData {
+    pub(super) status: String,
+    pub(super) issues: Vec<ToolIssue>,
+    pub(super) next_action: NextAction,
+    pub(super) account_id: String,
+    pub(super) mailbox: String,
+    pub(super) total: usize,
+    pub(super) scanned: usize,
+    pub(super) failed: usize,
+    pub(super) truncated: bool,
+    pub(super) unread: usize,
+    pub(super) by_sender: Vec<FacetCount>,
+    pub(super) by_sender_domain: Vec<FacetCount>,
+    pub(super) by_day: Vec<FacetCount>,
+    pub(super) by_flag: Vec<FacetCount>,
+    pub(super) by_size: Vec<FacetCount>,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
 pub(super) struct GetMessageData {
     pub(super) status: String,
@@ -66,6 +97,64 @@ pub(super) struct GetMessageRawData {
     pub(super) raw_source_encoding: Option<String>,
 }
 
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
+pub(super) struct GetAttachmentData {
+    pub(super) status: String,
+    pub(super) issues: Vec<ToolIssue>,
+    pub(super) account_id: String,
+    pub(super) message_id: String,
+    pub(super) message_uri: String,
+    pub(super) part_id: String,
+    pub(super) filename: Option<String>,
+    pub(super) content_type: Option<String>,
+    pub(super) total_size_bytes: usize,
+    pub(super) returned_bytes: usize,
+    pub(super) offset_bytes: usize,
+    pub(super) truncated: bool,
+    pub(super) content_base64: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
+pub(super) struct SaveDraftData {
+    pub(super) status: String,
+    pub(super) issues: Vec<ToolIssue>,
+    pub(super) account_id: String,
+    pub(super) mailbox: Option<String>,
+    pub(super) message_id: Option<String>,
+    pub(super) message_uri: Option<String>,
+    pub(super) message_id_header: Option<String>,
+    pub(super) subject: Option<String>,
+    pub(super) to: Vec<String>,
+    pub(super) cc: Vec<String>,
+    pub(super) size_bytes: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
+pub(super) struct SendMessageData {
+    pub(super) status: String,
+    pub(super) issues: Vec<ToolIssue>,
+    pub(super) account_id: String,
+    pub(super) sent: bool,
+    pub(super) message_id_header: Option<String>,
+    pub(super) envelope_from: Option<String>,
+    pub(super) recipients: Vec<String>,
+    pub(super) size_bytes: usize,
+    pub(super) sent_mailbox: Option<String>,
+    pub(super) sent_message_id: Option<String>,
+    pub(super) draft_deleted: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
+pub(super) struct GetMessageStructureData {
+    pub(super) status: String,
+    pub(super) issues: Vec<ToolIssue>,
+    pub(super) account_id: String,
+    pub(super) message_id: String,
+    pub(super) message_uri: String,
+    pub(super) size_bytes: Option<u32>,
+    pub(super) structure: Option<MimePart>,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
 pub(super) struct NextAction {
     pub(super) instruction: String,
@@ -157,6 +246,65 @@ pub(super) struct MailboxManagementResult {
     pub(super) destination_mailbox: Option<String>,
 }
 
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
+pub(super) struct ExportedMessageResult {
+    pub(super) message_id: String,
+    pub(super) status: String,
+    pub(super) issues: Vec<ToolIssue>,
+    pub(super) mailbox: String,
+    /// File written, relative to the export directory
+    pub(super) path: Option<String>,
+    /// SHA-256 of the message bytes as fetched from the server
+    pub(super) sha256: Option<String>,
+    pub(super) size_bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
+pub(super) struct ExportMessagesData {
+    pub(super) status: String,
+    pub(super) issues: Vec<ToolIssue>,
+    pub(super) account_id: String,
+    pub(super) format: String,
+    pub(super) destination: String,
+    pub(super) matched: usize,
+    pub(super) attempted: usize,
+    pub(super) succeeded: usize,
+    pub(super) failed: usize,
+    pub(super) results: Vec<ExportedMessageResult>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
+pub(super) struct ImportedMessageResult {
+    /// Source file, relative to the import directory
+    pub(super) source_path: String,
+    /// 1-based position of the entry within an mbox file
+    pub(super) mbox_index: Option<usize>,
+    pub(super) status: String,
+    pub(super) issues: Vec<ToolIssue>,
+    /// `Message-ID` header of the source message
+    pub(super) message_id_header: Option<String>,
+    /// Whether the message was skipped as an existing duplicate
+    pub(super) duplicate: bool,
+    pub(super) flags: Vec<String>,
+    pub(super) size_bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
+pub(super) struct ImportMessagesData {
+    pub(super) status: String,
+    pub(super) issues: Vec<ToolIssue>,
+    pub(super) account_id: String,
+    pub(super) mailbox: String,
+    pub(super) format: String,
+    pub(super) source: String,
+    pub(super) matched: usize,
+    pub(super) attempted: usize,
+    pub(super) imported: usize,
+    pub(super) skipped: usize,
+    pub(super) failed: usize,
+    pub(super) results: Vec<ImportedMessageResult>,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
 pub(super) struct OperationMetadata {
     pub(super) operation_id: String,
@@ -175,6 +323,8 @@ pub(super) struct OperationMetadata {
 pub(super) enum OperationResultData {
     BulkMessage(BulkMessageOperationData),
     MailboxManagement(MailboxManagementResult),
+    Export(ExportMessagesData),
+    Import(ImportMessagesData),
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
@@ -193,6 +343,7 @@ pub(super) enum MessageActionInput {
     Move { destination_mailbox: String },
     Copy { destination_mailbox: String },
     Delete,
+    PermanentDelete,
 }
 
 #[derive(Debug, Clone)]
@@ -317,6 +468,8 @@ pub(super) enum StoredOperationSpec {
     ApplyMessages(ApplyMessagesOperation),
     UpdateFlags(UpdateFlagsOperation),
     ManageMailbox(ManageMailboxOperation),
+    ExportMessages(ExportMessagesOperation),
+    ImportMessages(ImportMessagesOperation),
 }
 
 #[derive(Debug, Clone)]
@@ -347,6 +500,33 @@ pub(super) struct ManageMailboxOperation {
     pub(super) result: Option<MailboxManagementResult>,
 }
 
+#[derive(Debug, Clone)]
+pub(super) struct ExportMessagesOperation {
+    pub(super) account_id: String,
+    pub(super) format: StoreFormat,
+    /// Destination as given, relative to the export directory
+    pub(super) destination: String,
+    pub(super) destination_path: PathBuf,
+    pub(super) message_ids: Vec<MessageId>,
+    pub(super) results: Vec<ExportedMessageResult>,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct ImportMessagesOperation {
+    pub(super) account_id: String,
+    pub(super) mailbox: String,
+    pub(super) uidvalidity: u32,
+    pub(super) format: StoreFormat,
+    /// Source as given, relative to the import directory
+    pub(super) source: String,
+    pub(super) entries: Vec<SourceEntry>,
+    /// `Message-ID` values already imported or found by this operation
+    ///
+    /// Shared with each import step so handing it out does not copy the set.
+    pub(super) seen_message_ids: Arc<HashSet<String>>,
+    pub(super) results: Vec<ImportedMessageResult>,
+}
+
 #[derive(Debug, Clone)]
 pub(super) enum OperationStep {
     ApplyMessagesGroup {
@@ -363,6 +543,20 @@ pub(super) enum OperationStep {
         account_id: String,
         action: MailboxAction,
     },
+    ExportMessage {
+        account_id: String,
+        format: StoreFormat,
+        destination_path: PathBuf,
+        message_id: MessageId,
+    },
+    ImportMessage {
+        account_id: String,
+        mailbox: String,
+        uidvalidity: u32,
+        entry: SourceEntry,
+        mbox_index: Option<usize>,
+        seen_message_ids: Arc<HashSet<String>>,
+    },
 }
 
 impl OperationStep {
@@ -370,7 +564,9 @@ impl OperationStep {
         match self {
             Self::ApplyMessagesGroup { account_id, .. }
             | Self::UpdateFlagsGroup { account_id, .. }
-            | Self::ManageMailbox { account_id, .. } => account_id,
+            | Self::ManageMailbox { account_id, .. }
+            | Self::ExportMessage { account_id, .. }
+            | Self::ImportMessage { account_id, .. } => account_id,
         }
     }
 }
@@ -379,6 +575,8 @@ impl OperationStep {
 pub(super) enum OperationStepOutcome {
     MessageResults(Vec<MessageMutationResult>),
     MailboxResult(MailboxManagementResult),
+    ExportResult(ExportedMessageResult),
+    ImportResult(ImportedMessageResult),
 }
 
 pub(super) fn duration_ms(started: Instant) -> u64 {
@@ -596,6 +794,22 @@ where
     }
 }
 
+pub(super) fn draft_summary(data: &SaveDraftData) -> String {
+    match data.status.as_str() {
+        "ok" => "Draft saved".to_owned(),
+        "partial" => "Draft saved with issues".to_owned(),
+        _ => "Draft not saved".to_owned(),
+    }
+}
+
+pub(super) fn send_summary(data: &SendMessageData) -> String {
+    match data.status.as_str() {
+        "ok" => "Message sent".to_owned(),
+        "partial" => "Message sent with issues".to_owned(),
+        _ => "Message not sent".to_owned(),
+    }
+}
+
 pub(super) fn operation_summary(status: &str, kind: &str) -> String {
     let label = kind.strip_prefix("imap_").unwrap_or(kind);
     match status {
@@ -614,6 +828,8 @@ pub(super) fn operation_kind_label(spec: &StoredOperationSpec) -> &'static str {
         StoredOperationSpec::ApplyMessages(_) => "imap_apply_to_messages",
         StoredOperationSpec::UpdateFlags(_) => "imap_update_message_flags",
         StoredOperationSpec::ManageMailbox(_) => "imap_manage_mailbox",
+        StoredOperationSpec::ExportMessages(_) => "imap_export_messages",
+        StoredOperationSpec::ImportMessages(_) => "imap_import_messages",
     }
 }
 
@@ -622,6 +838,8 @@ pub(super) fn operation_total_units(spec: &StoredOperationSpec) -> usize {
         StoredOperationSpec::ApplyMessages(spec) => spec.groups.len(),
         StoredOperationSpec::UpdateFlags(spec) => spec.groups.len(),
         StoredOperationSpec::ManageMailbox(_) => 1,
+        StoredOperationSpec::ExportMessages(spec) => spec.message_ids.len(),
+        StoredOperationSpec::ImportMessages(spec) => spec.entries.len(),
     }
 }
 
@@ -665,6 +883,31 @@ pub(super) fn next_operation_step(operation: &mut StoredOperation) -> Option<Ope
                 action: spec.action.clone(),
             })
         }
+        StoredOperationSpec::ExportMessages(spec) => {
+            let message_id = spec.message_ids.get(spec.results.len())?.clone();
+            operation.progress.current_mailbox = Some(message_id.mailbox.clone());
+            operation.progress.phase = format!("exporting_{}", spec.format.as_str());
+            Some(OperationStep::ExportMessage {
+                account_id: spec.account_id.clone(),
+                format: spec.format,
+                destination_path: spec.destination_path.clone(),
+                message_id,
+            })
+        }
+        StoredOperationSpec::ImportMessages(spec) => {
+            let index = spec.results.len();
+            let entry = spec.entries.get(index)?.clone();
+            operation.progress.current_mailbox = Some(spec.mailbox.clone());
+            operation.progress.phase = format!("importing_{}", spec.format.as_str());
+            Some(OperationStep::ImportMessage {
+                account_id: spec.account_id.clone(),
+                mailbox: spec.mailbox.clone(),
+                uidvalidity: spec.uidvalidity,
+                mbox_index: (spec.format == StoreFormat::Mbox).then_some(index + 1),
+                entry,
+                seen_message_ids: Arc::clone(&spec.seen_message_ids),
+            })
+        }
     }
 }
 
@@ -673,6 +916,7 @@ pub(super) fn message_action_name(action: &MessageActionInput) -> &'static str {
         MessageActionInput::Move { .. } => "move",
         MessageActionInput::Copy { .. } => "copy",
         MessageActionInput::Delete => "delete",
+        MessageActionInput::PermanentDelete => "permanent_delete",
     }
 }
 
@@ -692,7 +936,7 @@ pub(super) fn destination_mailbox_for_action(action: &MessageActionInput) -> Opt
         | MessageActionInput::Copy {
             destination_mailbox,
         } => Some(destination_mailbox.as_str()),
-        MessageActionInput::Delete => None,
+        MessageActionInput::Delete | MessageActionInput::PermanentDelete => None,
     }
 }
 
@@ -4,7 +4,9 @@ use chrono::{Duration as ChronoDuration, NaiveDate, Utc};
 
 use crate::config::ServerConfig;
 use crate::errors::{AppError, AppResult};
+use crate::imap;
 use crate::message_id::MessageId;
+use crate::mime;
 use crate::models::{
     ApplyToMessagesInput, ManageMailboxInput, SearchMessagesInput, UpdateMessageFlagsInput,
 };
@@ -18,6 +20,20 @@ pub(super) fn parse_and_validate_message_id(message_id: &str) -> AppResult<Messa
     Ok(message_id)
 }
 
+/// Map an `imap_get_message` attachment `part_id` to its IMAP section.
+pub(super) fn attachment_section(part_id: &str) -> AppResult<String> {
+    if mime::is_unwrapped_part_id(part_id) {
+        return Err(AppError::InvalidInput(format!(
+            "part_id {part_id} is inside decrypted, opaque-signed, or TNEF content and has no IMAP section; read it with imap_get_message"
+        )));
+    }
+    mime::imap_section_for_part_id(part_id).ok_or_else(|| {
+        AppError::InvalidInput(
+            "part_id must be an attachment part id reported by imap_get_message".to_owned(),
+        )
+    })
+}
+
 pub(super) fn build_message_action(input: &ApplyToMessagesInput) -> AppResult<MessageActionInput> {
     match input.action.as_str() {
         "move" => Ok(MessageActionInput::Move {
@@ -42,8 +58,16 @@ pub(super) fn build_message_action(input: &ApplyToMessagesInput) -> AppResult<Me
             )?;
             Ok(MessageActionInput::Delete)
         }
+        "permanent_delete" => {
+            reject_field_for_action(
+                input.destination_mailbox.as_ref(),
+                "destination_mailbox",
+                "permanent_delete",
+            )?;
+            Ok(MessageActionInput::PermanentDelete)
+        }
         _ => Err(AppError::InvalidInput(format!(
-            "action must be one of move, copy, delete; got '{}'",
+            "action must be one of move, copy, delete, permanent_delete; got '{}'",
             input.action
         ))),
     }
@@ -132,7 +156,7 @@ pub(super) fn validate_message_action(action: &MessageActionInput) -> AppResult<
         | MessageActionInput::Copy {
             destination_mailbox,
         } => validate_mailbox(destination_mailbox),
-        MessageActionInput::Delete => Ok(()),
+        MessageActionInput::Delete | MessageActionInput::PermanentDelete => Ok(()),
     }
 }
 
@@ -263,9 +287,15 @@ pub(super) fn validate_search_input(input: &SearchMessagesInput) -> AppResult<()
         validate_chars(snippet_max_chars, 50, 500, "snippet_max_chars")?;
     }
 
-    for text in [&input.query, &input.from, &input.to, &input.subject]
-        .into_iter()
-        .flatten()
+    for text in [
+        &input.query,
+        &input.from,
+        &input.to,
+        &input.subject,
+        &input.list_id,
+    ]
+    .into_iter()
+    .flatten()
     {
         validate_search_text(text)?;
     }
@@ -312,6 +342,9 @@ pub(super) fn build_search_query(input: &SearchMessagesInput) -> AppResult<Strin
     if let Some(value) = &input.subject {
         parts.push(format!("SUBJECT \"{}\"", escape_imap_quoted(value)?));
     }
+    if let Some(value) = &input.list_id {
+        parts.push(format!("HEADER List-Id \"{}\"", escape_imap_quoted(value)?));
+    }
     if input.unread_only.unwrap_or(false) {
         parts.push("UNSEEN".to_owned());
     }
@@ -336,7 +369,7 @@ pub(super) fn build_search_query(input: &SearchMessagesInput) -> AppResult<Strin
 
 pub(super) fn escape_imap_quoted(input: &str) -> AppResult<String> {
     validate_search_text(input)?;
-    Ok(input.replace('\\', "\\\\").replace('"', "\\\""))
+    imap::escape_quoted(input)
 }
 
 fn validate_flags(flags: &[String], field: &str) -> AppResult<()> {
@@ -403,18 +436,65 @@ pub(super) fn require_write_enabled(config: &ServerConfig) -> AppResult<()> {
     Ok(())
 }
 
+pub(super) fn require_permanent_delete_enabled(config: &ServerConfig) -> AppResult<()> {
+    if !config.permanent_delete_enabled {
+        return Err(AppError::InvalidInput(
+            "permanent deletion is disabled; set MAIL_IMAP_PERMANENT_DELETE_ENABLED=true"
+                .to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+pub(super) fn require_send_enabled(config: &ServerConfig) -> AppResult<()> {
+    if !config.send_enabled {
+        return Err(AppError::InvalidInput(
+            "sending is disabled; set MAIL_IMAP_SEND_ENABLED=true".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        FlagOperation, FlagUpdateRequest, build_flag_update_request, build_mailbox_action,
-        build_message_action, dedupe_and_parse_message_ids, escape_imap_quoted,
-        parse_bulk_message_ids, validate_flag, validate_flag_update_request, validate_mailbox,
-        validate_search_input, validate_search_text,
+        FlagOperation, FlagUpdateRequest, MessageActionInput, attachment_section,
+        build_flag_update_request, build_mailbox_action, build_message_action, build_search_query,
+        dedupe_and_parse_message_ids, escape_imap_quoted, parse_bulk_message_ids,
+        require_permanent_delete_enabled, validate_flag, validate_flag_update_request,
+        validate_mailbox, validate_search_input, validate_search_text,
     };
     use crate::models::{
         ApplyToMessagesInput, ManageMailboxInput, SearchMessagesInput, UpdateMessageFlagsInput,
     };
 
+    #[test]
+    fn permanent_delete_requires_its_own_gate() {
+        let mut config = crate::server::tests::schema_test_server_config();
+        config.write_enabled = true;
+        let error = require_permanent_delete_enabled(&config).expect_err("gate is off");
+        assert!(
+            error
+                .to_string()
+                .contains("MAIL_IMAP_PERMANENT_DELETE_ENABLED")
+        );
+        config.permanent_delete_enabled = true;
+        assert!(require_permanent_delete_enabled(&config).is_ok());
+    }
+
+    #[test]
+    fn attachment_section_rejects_unwrapped_parts() {
+        assert_eq!(attachment_section("1.2").expect("real part"), "2");
+        let error = attachment_section("1.2.u.1").expect_err("decrypted part");
+        assert!(
+            error
+                .to_string()
+                .contains("decrypted, opaque-signed, or TNEF")
+        );
+        assert!(attachment_section("1.3.u.2").is_err());
+        assert!(attachment_section("2").is_err());
+    }
+
     #[test]
     fn control_character_validation_rejects_invalid_inputs() {
         let cases = [
@@ -530,6 +610,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn build_message_action_parses_permanent_delete_without_destination() {
+        let mut input = ApplyToMessagesInput {
+            message_ids: vec!["imap:default:INBOX:42:7".to_owned()],
+            action: "permanent_delete".to_owned(),
+            destination_mailbox: None,
+        };
+        assert!(matches!(
+            build_message_action(&input),
+            Ok(MessageActionInput::PermanentDelete)
+        ));
+
+        input.destination_mailbox = Some("Trash".to_owned());
+        let err = build_message_action(&input).expect_err("destination must be rejected");
+        assert!(
+            err.to_string()
+                .contains("destination_mailbox is not allowed for action=permanent_delete")
+        );
+    }
+
     #[test]
     fn build_mailbox_action_rejects_destination_for_delete() {
         let input = ManageMailboxInput {
@@ -556,6 +656,7 @@ mod tests {
             from: Some(".*".to_owned()),
             to: Some(".*".to_owned()),
             subject: Some(".*".to_owned()),
+            list_id: Some(".*".to_owned()),
             unread_only: Some(false),
             last_days: Some(365),
             start_date: Some("2025-01-01".to_owned()),
@@ -577,6 +678,7 @@ mod tests {
             from: None,
             to: None,
             subject: None,
+            list_id: None,
             unread_only: None,
             last_days: Some(30),
             start_date: Some("2025-01-01".to_owned()),
@@ -602,6 +704,7 @@ mod tests {
             from: None,
             to: None,
             subject: None,
+            list_id: None,
             unread_only: None,
             last_days: None,
             start_date: None,
@@ -612,4 +715,29 @@ mod tests {
 
         validate_search_input(&input).expect("snippet_max_chars alone should enable snippets");
     }
+
+    #[test]
+    fn build_search_query_filters_by_list_id_header() {
+        let input = SearchMessagesInput {
+            account_id: "default".to_owned(),
+            mailbox: "INBOX".to_owned(),
+            cursor: None,
+            query: None,
+            from: None,
+            to: None,
+            subject: None,
+            list_id: Some("news.example.com".to_owned()),
+            unread_only: Some(true),
+            last_days: None,
+            start_date: None,
+            end_date: None,
+            limit: 10,
+            snippet_max_chars: None,
+        };
+
+        assert_eq!(
+            build_search_query(&input).expect("query should build"),
+            "HEADER List-Id \"news.example.com\" UNSEEN"
+        );
+    }
 }
@@ -1,35 +1,42 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use secrecy::ExposeSecret;
 use tokio::sync::Mutex;
 use tracing::error;
 
 use crate::errors::{AppError, AppResult};
-use crate::imap;
+use crate::imap::{self, SpecialUse};
 use crate::mailbox_codec::normalize_mailbox_name;
+use crate::mailstore::{self, SourceEntry, StoreFormat, StoreMessage};
 use crate::message_id::MessageId;
 use crate::models::{
-    ApplyToMessagesInput, GetOperationInput, ManageMailboxInput, OperationIdInput,
-    UpdateMessageFlagsInput,
+    ApplyToMessagesInput, ExportMessagesInput, GetOperationInput, ImportMessagesInput,
+    ManageMailboxInput, OperationIdInput, UpdateMessageFlagsInput,
 };
+use crate::pagination::decode_signed_cursor;
 
 use super::types::{
-    ApplyMessagesOperation, BulkMessageOperationData, FlagUpdateRequest, MailboxAction,
-    MailboxManagementResult, MessageActionInput, MessageMutationGroup, MessageMutationResult,
-    OperationMetadata, OperationProgress, OperationResultData, OperationState, OperationStatusData,
-    OperationStep, OperationStepOutcome, StoredOperation, StoredOperationSpec, ToolIssue,
-    UpdateFlagsOperation, canceled_tool_issue, destination_mailbox_for_action, flag_operation_name,
-    mailbox_action_display, mailbox_action_stage, message_action_name, next_action_get_operation,
+    ApplyMessagesOperation, BulkMessageOperationData, ExportMessagesData, ExportMessagesOperation,
+    ExportedMessageResult, FlagUpdateRequest, ImportMessagesData, ImportMessagesOperation,
+    ImportedMessageResult, MailboxAction, MailboxManagementResult, MessageActionInput,
+    MessageMutationGroup, MessageMutationResult, OperationMetadata, OperationProgress,
+    OperationResultData, OperationState, OperationStatusData, OperationStep, OperationStepOutcome,
+    StoredOperation, StoredOperationSpec, ToolIssue, UpdateFlagsOperation, canceled_tool_issue,
+    destination_mailbox_for_action, flag_operation_name, mailbox_action_display,
+    mailbox_action_stage, message_action_name, next_action_get_operation,
     next_action_get_operation_with_result, next_operation_step, now_utc_string,
     operation_kind_label, operation_total_units, status_from_issue_and_counts,
 };
 use super::validation::{
-    build_flag_update_request, build_mailbox_action, build_message_action, parse_bulk_message_ids,
-    require_write_enabled, validate_account_id, validate_flag_update_request, validate_mailbox,
-    validate_message_action, validate_operation_id,
+    build_flag_update_request, build_mailbox_action, build_message_action, escape_imap_quoted,
+    parse_bulk_message_ids, require_permanent_delete_enabled, require_write_enabled,
+    validate_account_id, validate_flag_update_request, validate_mailbox, validate_message_action,
+    validate_operation_id,
 };
-use super::{MailImapServer, WRITE_INLINE_BUDGET_MS};
+use super::{MAX_EXPORT_MESSAGES, MAX_IMPORT_MESSAGES, MailImapServer, WRITE_INLINE_BUDGET_MS};
 
 #[derive(Default)]
 struct OperationExecutionContext {
@@ -39,6 +46,7 @@ struct OperationExecutionContext {
     selected_readonly: Option<bool>,
     selected_uidvalidity: Option<u32>,
     supports_move: Option<bool>,
+    trash_mailbox: Option<String>,
 }
 
 impl MailImapServer {
@@ -49,6 +57,9 @@ impl MailImapServer {
         require_write_enabled(&self.config)?;
         let action = build_message_action(&input)?;
         validate_message_action(&action)?;
+        if matches!(action, MessageActionInput::PermanentDelete) {
+            require_permanent_delete_enabled(&self.config)?;
+        }
         let (account_id, message_ids) = parse_bulk_message_ids(&input.message_ids)?;
         let spec = self
             .preflight_apply_message_operation(&account_id, action, message_ids)
@@ -83,6 +94,82 @@ impl MailImapServer {
         self.start_write_operation(spec).await
     }
 
+    pub(super) async fn export_messages_impl(
+        &self,
+        input: ExportMessagesInput,
+    ) -> AppResult<OperationStatusData> {
+        require_write_enabled(&self.config)?;
+        let export_dir = self.config.export_dir.as_deref().ok_or_else(|| {
+            AppError::InvalidInput("exports are disabled; set MAIL_IMAP_EXPORT_DIR".to_owned())
+        })?;
+        let format = StoreFormat::parse(&input.format)?;
+        let destination_path = mailstore::resolve_sandbox_path(export_dir, &input.destination)?;
+        let (account_id, message_ids) = match (&input.message_ids, input.cursor.as_deref()) {
+            (Some(message_ids), None) => parse_bulk_message_ids(message_ids)?,
+            (None, Some(cursor)) => self.resolve_export_cursor(cursor).await?,
+            _ => {
+                return Err(AppError::InvalidInput(
+                    "provide exactly one of message_ids or cursor".to_owned(),
+                ));
+            }
+        };
+        let groups = group_message_ids(&message_ids);
+        let account = self.config.get_account(&account_id)?;
+        let mut session = imap::connect_authenticated(&self.config, account).await?;
+        self.validate_group_uidvalidities(&mut session, &groups, true)
+            .await?;
+        mailstore::prepare_export(format, &destination_path)?;
+        let spec = StoredOperationSpec::ExportMessages(ExportMessagesOperation {
+            account_id,
+            format,
+            destination: input.destination.trim().to_owned(),
+            destination_path,
+            message_ids,
+            results: Vec::new(),
+        });
+        self.start_write_operation(spec).await
+    }
+
+    pub(super) async fn import_messages_impl(
+        &self,
+        input: ImportMessagesInput,
+    ) -> AppResult<OperationStatusData> {
+        require_write_enabled(&self.config)?;
+        let import_dir = self.config.import_dir.as_deref().ok_or_else(|| {
+            AppError::InvalidInput("imports are disabled; set MAIL_IMAP_IMPORT_DIR".to_owned())
+        })?;
+        validate_account_id(&input.account_id)?;
+        validate_mailbox(&input.mailbox)?;
+        let format = StoreFormat::parse(&input.format)?;
+        let source_path = mailstore::resolve_sandbox_path(import_dir, &input.source)?;
+        if !source_path.exists() {
+            return Err(AppError::NotFound(
+                "import source does not exist".to_owned(),
+            ));
+        }
+        let entries = mailstore::list_source(format, &source_path, MAX_IMPORT_MESSAGES)?;
+        if entries.is_empty() {
+            return Err(AppError::InvalidInput(
+                "import source contains no messages".to_owned(),
+            ));
+        }
+        let account = self.config.get_account(&input.account_id)?;
+        let mut session = imap::connect_authenticated(&self.config, account).await?;
+        let uidvalidity =
+            imap::select_mailbox_readonly(&self.config, &mut session, &input.mailbox).await?;
+        let spec = StoredOperationSpec::ImportMessages(ImportMessagesOperation {
+            account_id: input.account_id,
+            mailbox: input.mailbox,
+            uidvalidity,
+            format,
+            source: input.source.trim().to_owned(),
+            entries,
+            seen_message_ids: Arc::default(),
+            results: Vec::new(),
+        });
+        self.start_write_operation(spec).await
+    }
+
     pub(super) async fn get_operation_impl(
         &self,
         input: GetOperationInput,
@@ -135,7 +222,15 @@ impl MailImapServer {
         }
         let account = self.config.get_account(account_id)?;
         let mut session = imap::connect_authenticated(&self.config, account).await?;
-        if let Some(destination_mailbox) = destination_mailbox_for_action(&action) {
+        let trash_mailbox = match action {
+            MessageActionInput::Delete => {
+                Some(self.resolve_trash_mailbox(&mut session, account_id).await?)
+            }
+            _ => None,
+        };
+        if let Some(destination_mailbox) =
+            destination_mailbox_for_action(&action).or(trash_mailbox.as_deref())
+        {
             imap::select_mailbox_readonly(&self.config, &mut session, destination_mailbox).await?;
         }
         self.validate_group_uidvalidities(&mut session, &groups, false)
@@ -208,6 +303,73 @@ impl MailImapServer {
         ))
     }
 
+    /// Resolve the messages a search cursor has not returned yet
+    ///
+    /// Stored cursors yield their remaining UID snapshot; signed cursors
+    /// re-run their criteria below the last returned UID. Messages are
+    /// exported in ascending UID order.
+    async fn resolve_export_cursor(&self, cursor: &str) -> AppResult<(String, Vec<MessageId>)> {
+        let signing_key = self
+            .config
+            .cursor_signing_key
+            .as_ref()
+            .map(|key| key.expose_secret().as_bytes());
+        let (account_id, mailbox, uidvalidity, mut uids) = if let Some(key) = signing_key {
+            let cursor = decode_signed_cursor(key, cursor, chrono::Utc::now().timestamp())
+                .ok_or_else(|| AppError::InvalidInput("cursor is invalid or expired".to_owned()))?;
+            let account = self.config.get_account(&cursor.account_id)?;
+            let mut session = imap::connect_authenticated(&self.config, account).await?;
+            let uidvalidity =
+                imap::select_mailbox_readonly(&self.config, &mut session, &cursor.mailbox).await?;
+            if uidvalidity != cursor.uidvalidity {
+                return Err(AppError::Conflict(
+                    "mailbox snapshot changed; rerun search".to_owned(),
+                ));
+            }
+            let uids = if cursor.last_uid > 1 {
+                let query = format!("{} UID 1:{}", cursor.criteria, cursor.last_uid - 1);
+                imap::uid_search(&self.config, &mut session, &query).await?
+            } else {
+                Vec::new()
+            };
+            (cursor.account_id, cursor.mailbox, uidvalidity, uids)
+        } else {
+            let entry =
+                self.cursors.lock().await.get(cursor).ok_or_else(|| {
+                    AppError::InvalidInput("cursor is invalid or expired".to_owned())
+                })?;
+            let remaining = entry.uids_desc.get(entry.offset..).unwrap_or_default();
+            (
+                entry.account_id,
+                entry.mailbox,
+                entry.uidvalidity,
+                remaining.to_vec(),
+            )
+        };
+        if uids.is_empty() {
+            return Err(AppError::InvalidInput(
+                "cursor has no remaining messages to export".to_owned(),
+            ));
+        }
+        if uids.len() > MAX_EXPORT_MESSAGES {
+            return Err(AppError::InvalidInput(format!(
+                "cursor has {} remaining messages; narrow the search to at most {MAX_EXPORT_MESSAGES}",
+                uids.len()
+            )));
+        }
+        uids.sort_unstable();
+        let message_ids = uids
+            .into_iter()
+            .map(|uid| MessageId {
+                account_id: account_id.clone(),
+                mailbox: mailbox.clone(),
+                uidvalidity,
+                uid,
+            })
+            .collect();
+        Ok((account_id, message_ids))
+    }
+
     async fn start_write_operation(
         &self,
         spec: StoredOperationSpec,
@@ -316,6 +478,9 @@ impl MailImapServer {
                 return Ok(());
             }
             let outcome = self.execute_operation_step(&mut execution_ctx, &step).await;
+            // Release the step's share of state such as the import dedupe set,
+            // so recording the outcome can update it in place.
+            drop(step);
             self.apply_operation_step_outcome(operation_id, outcome)
                 .await?;
         }
@@ -367,6 +532,10 @@ impl MailImapServer {
                         self.execute_delete_group(execution_ctx, account_id, group)
                             .await
                     }
+                    MessageActionInput::PermanentDelete => {
+                        self.execute_expunge_group(execution_ctx, account_id, group)
+                            .await
+                    }
                 };
                 OperationStepOutcome::MessageResults(results)
             }
@@ -384,6 +553,46 @@ impl MailImapServer {
                         .await,
                 )
             }
+            OperationStep::ExportMessage {
+                account_id,
+                format,
+                destination_path,
+                message_id,
+            } => OperationStepOutcome::ExportResult(
+                self.execute_export_message(
+                    execution_ctx,
+                    account_id,
+                    *format,
+                    destination_path,
+                    message_id,
+                )
+                .await,
+            ),
+            OperationStep::ImportMessage {
+                account_id,
+                mailbox,
+                uidvalidity,
+                entry,
+                mbox_index,
+                seen_message_ids,
+            } => {
+                let group = MessageMutationGroup {
+                    mailbox: mailbox.clone(),
+                    uidvalidity: *uidvalidity,
+                    entries: Vec::new(),
+                };
+                OperationStepOutcome::ImportResult(
+                    self.execute_import_message(
+                        execution_ctx,
+                        account_id,
+                        &group,
+                        entry,
+                        *mbox_index,
+                        seen_message_ids,
+                    )
+                    .await,
+                )
+            }
         }
     }
 
@@ -439,6 +648,30 @@ impl MailImapServer {
                     operation.progress.failed_units = 1;
                 }
             }
+            (
+                StoredOperationSpec::ExportMessages(spec),
+                OperationStepOutcome::ExportResult(result),
+            ) => {
+                operation.issues.extend(result.issues.iter().cloned());
+                operation.progress.completed_units += 1;
+                if result.status != "ok" {
+                    operation.progress.failed_units += 1;
+                }
+                spec.results.push(result);
+            }
+            (
+                StoredOperationSpec::ImportMessages(spec),
+                OperationStepOutcome::ImportResult(result),
+            ) => {
+                operation.issues.extend(result.issues.iter().cloned());
+                operation.progress.completed_units += 1;
+                if result.status != "ok" {
+                    operation.progress.failed_units += 1;
+                } else if let Some(message_id) = &result.message_id_header {
+                    Arc::make_mut(&mut spec.seen_message_ids).insert(message_id.clone());
+                }
+                spec.results.push(result);
+            }
             _ => {
                 return Err(AppError::Internal(
                     "operation step outcome did not match stored operation type".to_owned(),
@@ -506,6 +739,28 @@ impl MailImapServer {
                     AppError::Internal("missing mailbox operation result".to_owned())
                 })?)
             }
+            StoredOperationSpec::ExportMessages(spec) => {
+                if was_cancel_requested {
+                    let remaining = spec.message_ids[spec.results.len()..].to_vec();
+                    spec.results.extend(remaining.iter().map(|message_id| {
+                        failed_export_result(
+                            message_id,
+                            canceled_tool_issue(Some(message_id.uid), Some(message_id.encode())),
+                        )
+                    }));
+                }
+                build_export_response(spec)
+            }
+            StoredOperationSpec::ImportMessages(spec) => {
+                if was_cancel_requested {
+                    append_remaining_import_results(
+                        spec,
+                        self.config.import_dir.as_deref(),
+                        &canceled_tool_issue(None, None),
+                    );
+                }
+                build_import_response(spec)
+            }
         };
 
         operation.state = if was_cancel_requested {
@@ -573,7 +828,7 @@ impl MailImapServer {
         })
     }
 
-    async fn account_write_lock(&self, account_id: &str) -> Arc<Mutex<()>> {
+    pub(super) async fn account_write_lock(&self, account_id: &str) -> Arc<Mutex<()>> {
         let mut locks = self.account_write_locks.lock().await;
         locks
             .entry(account_id.to_owned())
@@ -668,6 +923,27 @@ impl MailImapServer {
                     AppError::Internal("missing mailbox operation result".to_owned())
                 })?)
             }
+            StoredOperationSpec::ExportMessages(spec) => {
+                let remaining = spec.message_ids[spec.results.len()..].to_vec();
+                spec.results.extend(remaining.iter().map(|message_id| {
+                    failed_export_result(
+                        message_id,
+                        failure_issue
+                            .clone()
+                            .with_uid(message_id.uid)
+                            .with_message_id(&message_id.encode()),
+                    )
+                }));
+                build_export_response(spec)
+            }
+            StoredOperationSpec::ImportMessages(spec) => {
+                append_remaining_import_results(
+                    spec,
+                    self.config.import_dir.as_deref(),
+                    &failure_issue,
+                );
+                build_import_response(spec)
+            }
         };
 
         operation.state = OperationState::Failed;
@@ -766,6 +1042,169 @@ impl MailImapServer {
         }
     }
 
+    async fn execute_export_message(
+        &self,
+        execution_ctx: &mut OperationExecutionContext,
+        account_id: &str,
+        format: StoreFormat,
+        destination_path: &Path,
+        message_id: &MessageId,
+    ) -> ExportedMessageResult {
+        let encoded = message_id.encode();
+        let group = MessageMutationGroup {
+            mailbox: message_id.mailbox.clone(),
+            uidvalidity: message_id.uidvalidity,
+            entries: vec![message_id.clone()],
+        };
+        let issue = |stage: &str, error: &AppError| {
+            ToolIssue::from_error(stage, error)
+                .with_uid(message_id.uid)
+                .with_message_id(&encoded)
+        };
+        if let Err(error) = self
+            .ensure_selected_group(execution_ctx, account_id, &group, true)
+            .await
+        {
+            return failed_export_result(message_id, issue("select_mailbox_readonly", &error));
+        }
+        let Some(session) = execution_ctx.session.as_mut() else {
+            return failed_export_result(
+                message_id,
+                issue(
+                    "connect_authenticated",
+                    &AppError::Internal("execution session unavailable".to_owned()),
+                ),
+            );
+        };
+        let stored = match imap::fetch_stored_message(&self.config, session, message_id.uid).await {
+            Ok(stored) => stored,
+            Err(error) => {
+                return failed_export_result(message_id, issue("fetch_raw_message", &error));
+            }
+        };
+        let key = format!(
+            "{}_{}_{}",
+            mailstore::file_name_component(&message_id.mailbox),
+            message_id.uidvalidity,
+            message_id.uid
+        );
+        let message = StoreMessage {
+            key: &key,
+            raw: &stored.raw,
+            flags: &stored.flags,
+            internal_date: stored.internal_date,
+        };
+        let written = match mailstore::write_message(format, destination_path, &message) {
+            Ok(written) => written,
+            Err(error) => return failed_export_result(message_id, issue("write_export", &error)),
+        };
+        let path = sandbox_display_path(self.config.export_dir.as_deref(), &written);
+        ExportedMessageResult {
+            message_id: encoded,
+            status: "ok".to_owned(),
+            issues: Vec::new(),
+            mailbox: message_id.mailbox.clone(),
+            path: Some(path),
+            sha256: Some(mailstore::sha256_hex(&stored.raw)),
+            size_bytes: Some(stored.raw.len()),
+        }
+    }
+
+    async fn execute_import_message(
+        &self,
+        execution_ctx: &mut OperationExecutionContext,
+        account_id: &str,
+        target: &MessageMutationGroup,
+        entry: &SourceEntry,
+        mbox_index: Option<usize>,
+        seen_message_ids: &HashSet<String>,
+    ) -> ImportedMessageResult {
+        let mut result = ImportedMessageResult {
+            source_path: sandbox_display_path(self.config.import_dir.as_deref(), &entry.path),
+            mbox_index,
+            status: "failed".to_owned(),
+            issues: Vec::new(),
+            message_id_header: None,
+            duplicate: false,
+            flags: Vec::new(),
+            size_bytes: None,
+        };
+        let message = match mailstore::read_source_message(entry) {
+            Ok(message) => message,
+            Err(error) => {
+                result
+                    .issues
+                    .push(ToolIssue::from_error("read_import_source", &error));
+                return result;
+            }
+        };
+        result.message_id_header = mailstore::message_id_header(&message.raw);
+        result.flags = message.flags.clone();
+        result.size_bytes = Some(message.raw.len());
+        if let Err(error) = self
+            .ensure_selected_group(execution_ctx, account_id, target, true)
+            .await
+        {
+            result
+                .issues
+                .push(ToolIssue::from_error("select_mailbox_readonly", &error));
+            return result;
+        }
+        let Some(session) = execution_ctx.session.as_mut() else {
+            result.issues.push(ToolIssue::from_error(
+                "connect_authenticated",
+                &AppError::Internal("execution session unavailable".to_owned()),
+            ));
+            return result;
+        };
+
+        if let Some(message_id) = &result.message_id_header {
+            let duplicate = if seen_message_ids.contains(message_id) {
+                Ok(true)
+            } else {
+                match escape_imap_quoted(message_id) {
+                    Ok(quoted) => imap::uid_search(
+                        &self.config,
+                        session,
+                        &format!("HEADER Message-ID \"{quoted}\""),
+                    )
+                    .await
+                    .map(|uids| !uids.is_empty()),
+                    Err(error) => Err(error),
+                }
+            };
+            match duplicate {
+                Ok(true) => {
+                    result.status = "ok".to_owned();
+                    result.duplicate = true;
+                    return result;
+                }
+                Ok(false) => {}
+                Err(error) => {
+                    result
+                        .issues
+                        .push(ToolIssue::from_error("search_duplicate", &error));
+                    return result;
+                }
+            }
+        }
+
+        match imap::append(
+            &self.config,
+            session,
+            &target.mailbox,
+            &message.flags,
+            message.internal_date,
+            &message.raw,
+        )
+        .await
+        {
+            Ok(()) => result.status = "ok".to_owned(),
+            Err(error) => result.issues.push(ToolIssue::from_error("append", &error)),
+        }
+        result
+    }
+
     async fn execute_copy_group(
         &self,
         execution_ctx: &mut OperationExecutionContext,
@@ -899,11 +1338,33 @@ impl MailImapServer {
         finalize_group_results(group, issues, Some(destination_mailbox), None, true)
     }
 
+    /// Move a group to Trash, or expunge it when it is already there
     async fn execute_delete_group(
         &self,
         execution_ctx: &mut OperationExecutionContext,
         account_id: &str,
         group: &MessageMutationGroup,
+    ) -> Vec<MessageMutationResult> {
+        let trash_mailbox = match self.trash_mailbox(execution_ctx, account_id).await {
+            Ok(trash_mailbox) => trash_mailbox,
+            Err(error) => {
+                return failed_group_results(group, "find_trash_mailbox", &error, None, None);
+            }
+        };
+        if delete_moves_to_trash(&group.mailbox, &trash_mailbox) {
+            return self
+                .execute_move_group(execution_ctx, account_id, group, &trash_mailbox)
+                .await;
+        }
+        self.execute_expunge_group(execution_ctx, account_id, group)
+            .await
+    }
+
+    async fn execute_expunge_group(
+        &self,
+        execution_ctx: &mut OperationExecutionContext,
+        account_id: &str,
+        group: &MessageMutationGroup,
     ) -> Vec<MessageMutationResult> {
         let uid_set = build_uid_set(&group.entries);
         if let Err(error) = self
@@ -1087,6 +1548,7 @@ impl MailImapServer {
         execution_ctx.selected_readonly = None;
         execution_ctx.selected_uidvalidity = None;
         execution_ctx.supports_move = None;
+        execution_ctx.trash_mailbox = None;
         Ok(())
     }
 
@@ -1146,6 +1608,100 @@ impl MailImapServer {
         execution_ctx.supports_move = Some(supports_move);
         Ok(supports_move)
     }
+
+    async fn trash_mailbox(
+        &self,
+        execution_ctx: &mut OperationExecutionContext,
+        account_id: &str,
+    ) -> AppResult<String> {
+        self.ensure_execution_session(execution_ctx, account_id)
+            .await?;
+        if let Some(trash_mailbox) = &execution_ctx.trash_mailbox {
+            return Ok(trash_mailbox.clone());
+        }
+        let session = execution_ctx
+            .session
+            .as_mut()
+            .ok_or_else(|| AppError::Internal("execution session unavailable".to_owned()))?;
+        let trash_mailbox = self.resolve_trash_mailbox(session, account_id).await?;
+        execution_ctx.trash_mailbox = Some(trash_mailbox.clone());
+        Ok(trash_mailbox)
+    }
+
+    /// Apply the `delete` policy to one message outside a tracked operation
+    ///
+    /// Moves the message to Trash, or expunges it when it is already there.
+    pub(super) async fn delete_message(
+        &self,
+        session: &mut imap::ImapSession,
+        message_id: &MessageId,
+    ) -> Result<(), (&'static str, AppError)> {
+        let trash_mailbox = self
+            .resolve_trash_mailbox(session, &message_id.account_id)
+            .await
+            .map_err(|e| ("find_trash_mailbox", e))?;
+        let uidvalidity =
+            imap::select_mailbox_readwrite(&self.config, session, &message_id.mailbox)
+                .await
+                .map_err(|e| ("select_mailbox_readwrite", e))?;
+        if uidvalidity != message_id.uidvalidity {
+            return Err((
+                "select_mailbox_readwrite",
+                AppError::Conflict("message mailbox UIDVALIDITY changed".to_owned()),
+            ));
+        }
+        if delete_moves_to_trash(&message_id.mailbox, &trash_mailbox) {
+            let supports_move = imap::capabilities(&self.config, session)
+                .await
+                .map_err(|e| ("capabilities", e))?
+                .has_str("MOVE");
+            if supports_move {
+                return imap::uid_move(&self.config, session, message_id.uid, &trash_mailbox)
+                    .await
+                    .map_err(|e| ("uid_move", e));
+            }
+            imap::uid_copy(&self.config, session, message_id.uid, &trash_mailbox)
+                .await
+                .map_err(|e| ("uid_copy", e))?;
+        }
+        imap::uid_store(
+            &self.config,
+            session,
+            message_id.uid,
+            "+FLAGS.SILENT (\\Deleted)",
+        )
+        .await
+        .map_err(|e| ("uid_store_deleted", e))?;
+        imap::uid_expunge(&self.config, session, message_id.uid)
+            .await
+            .map_err(|e| ("uid_expunge", e))
+    }
+
+    /// Configured Trash mailbox, else the SPECIAL-USE `\Trash` mailbox
+    async fn resolve_trash_mailbox(
+        &self,
+        session: &mut imap::ImapSession,
+        account_id: &str,
+    ) -> AppResult<String> {
+        let account = self.config.get_account(account_id)?;
+        if let Some(trash_mailbox) = &account.trash_mailbox {
+            return Ok(trash_mailbox.clone());
+        }
+        imap::find_special_use_mailbox(&self.config, session, SpecialUse::Trash)
+            .await?
+            .ok_or_else(|| {
+                AppError::NotFound(
+                    "no Trash mailbox found; set MAIL_IMAP_<ACCOUNT>_TRASH_MAILBOX or use permanent_delete"
+                        .to_owned(),
+                )
+            })
+    }
+}
+
+/// Whether `delete` moves messages in `mailbox` to Trash rather than
+/// expunging them, which it does only for messages already in Trash
+pub(super) fn delete_moves_to_trash(mailbox: &str, trash_mailbox: &str) -> bool {
+    normalize_mailbox_name(mailbox) != normalize_mailbox_name(trash_mailbox)
 }
 
 fn cached_uidvalidity(selected_uidvalidity: &Option<u32>) -> AppResult<u32> {
@@ -1316,6 +1872,8 @@ fn operation_result_status(result: &OperationResultData) -> &str {
     match result {
         OperationResultData::BulkMessage(result) => result.status.as_str(),
         OperationResultData::MailboxManagement(result) => result.status.as_str(),
+        OperationResultData::Export(result) => result.status.as_str(),
+        OperationResultData::Import(result) => result.status.as_str(),
     }
 }
 
@@ -1323,6 +1881,8 @@ fn operation_result_issues(result: &OperationResultData) -> &[ToolIssue] {
     match result {
         OperationResultData::BulkMessage(result) => &result.issues,
         OperationResultData::MailboxManagement(result) => &result.issues,
+        OperationResultData::Export(result) => &result.issues,
+        OperationResultData::Import(result) => &result.issues,
     }
 }
 
@@ -1376,6 +1936,108 @@ fn append_failed_message_results(
     }
 }
 
+fn failed_export_result(message_id: &MessageId, issue: ToolIssue) -> ExportedMessageResult {
+    ExportedMessageResult {
+        message_id: message_id.encode(),
+        status: "failed".to_owned(),
+        issues: vec![issue],
+        mailbox: message_id.mailbox.clone(),
+        path: None,
+        sha256: None,
+        size_bytes: None,
+    }
+}
+
+fn build_export_response(spec: &ExportMessagesOperation) -> OperationResultData {
+    let succeeded = spec
+        .results
+        .iter()
+        .filter(|result| result.status == "ok")
+        .count();
+    let issues = spec
+        .results
+        .iter()
+        .flat_map(|result| result.issues.iter().cloned())
+        .collect::<Vec<_>>();
+    let status = status_from_issue_and_counts(&issues, succeeded > 0).to_owned();
+    OperationResultData::Export(ExportMessagesData {
+        status,
+        issues,
+        account_id: spec.account_id.clone(),
+        format: spec.format.as_str().to_owned(),
+        destination: spec.destination.clone(),
+        matched: spec.message_ids.len(),
+        attempted: spec.results.len(),
+        succeeded,
+        failed: spec.results.len().saturating_sub(succeeded),
+        results: spec.results.clone(),
+    })
+}
+
+fn append_remaining_import_results(
+    spec: &mut ImportMessagesOperation,
+    import_dir: Option<&Path>,
+    issue: &ToolIssue,
+) {
+    let next_index = spec.results.len();
+    let mbox = spec.format == StoreFormat::Mbox;
+    let remaining = spec.entries[next_index..]
+        .iter()
+        .enumerate()
+        .map(|(offset, entry)| ImportedMessageResult {
+            source_path: sandbox_display_path(import_dir, &entry.path),
+            mbox_index: mbox.then_some(next_index + offset + 1),
+            status: "failed".to_owned(),
+            issues: vec![issue.clone()],
+            message_id_header: None,
+            duplicate: false,
+            flags: Vec::new(),
+            size_bytes: None,
+        })
+        .collect::<Vec<_>>();
+    spec.results.extend(remaining);
+}
+
+/// Show `path` relative to its sandbox directory
+fn sandbox_display_path(root: Option<&Path>, path: &Path) -> String {
+    root.and_then(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn build_import_response(spec: &ImportMessagesOperation) -> OperationResultData {
+    let mut imported = 0usize;
+    let mut skipped = 0usize;
+    for result in spec.results.iter().filter(|result| result.status == "ok") {
+        if result.duplicate {
+            skipped += 1;
+        } else {
+            imported += 1;
+        }
+    }
+    let issues = spec
+        .results
+        .iter()
+        .flat_map(|result| result.issues.iter().cloned())
+        .collect::<Vec<_>>();
+    let status = status_from_issue_and_counts(&issues, imported + skipped > 0).to_owned();
+    OperationResultData::Import(ImportMessagesData {
+        status,
+        issues,
+        account_id: spec.account_id.clone(),
+        mailbox: spec.mailbox.clone(),
+        format: spec.format.as_str().to_owned(),
+        source: spec.source.clone(),
+        matched: spec.entries.len(),
+        attempted: spec.results.len(),
+        imported,
+        skipped,
+        failed: spec.results.len().saturating_sub(imported + skipped),
+        results: spec.results.clone(),
+    })
+}
+
 fn canceled_mailbox_result(account_id: &str, action: &MailboxAction) -> MailboxManagementResult {
     let (action_name, mailbox, destination_mailbox) = mailbox_action_display(action);
     MailboxManagementResult {
@@ -1475,12 +2137,16 @@ fn order_group_results(
 mod tests {
     use std::collections::BTreeMap;
 
-    use super::{cached_uidvalidity, evict_completed_operations};
+    use std::sync::Arc;
+
+    use super::{cached_uidvalidity, delete_moves_to_trash, evict_completed_operations};
     use crate::config::ServerConfig;
     use crate::errors::AppError;
+    use crate::mailstore::{SourceEntry, StoreFormat};
     use crate::server::types::{
-        MailboxAction, MailboxManagementResult, ManageMailboxOperation, OperationResultData,
-        OperationState, StoredOperation, StoredOperationSpec,
+        ImportMessagesOperation, MailboxAction, MailboxManagementResult, ManageMailboxOperation,
+        OperationResultData, OperationState, OperationStep, StoredOperation, StoredOperationSpec,
+        next_operation_step,
     };
 
     fn completed_operation(operation_id: &str, finished_at: &str) -> StoredOperation {
@@ -1545,18 +2211,72 @@ mod tests {
             accounts: BTreeMap::new(),
             trusted_ca_certs: Vec::new(),
             write_enabled: false,
+            send_enabled: false,
+            permanent_delete_enabled: false,
             connect_timeout_ms: 30_000,
             greeting_timeout_ms: 15_000,
             socket_timeout_ms: 300_000,
             cursor_ttl_seconds: 600,
             cursor_max_entries: 512,
+            cursor_signing_key: None,
             read_session_cache_ttl_seconds: 120,
             read_session_cache_max_per_account: 4,
             operation_max_entries: 256,
+            dkim_keys: BTreeMap::new(),
+            dkim_dns_server: None,
+            export_dir: None,
+            import_dir: None,
+            attachment_dir: None,
         };
         assert_eq!(config.operation_max_entries, 256);
     }
 
+    #[test]
+    fn delete_expunges_only_messages_already_in_trash() {
+        assert!(delete_moves_to_trash("INBOX", "Trash"));
+        assert!(delete_moves_to_trash("Archive/Trash", "Trash"));
+        assert!(!delete_moves_to_trash("Trash", "Trash"));
+        assert!(!delete_moves_to_trash(
+            "&AMk-l&AOk-ments supprim&AOk-s",
+            "Éléments supprimés"
+        ));
+    }
+
+    #[test]
+    fn import_steps_share_the_dedupe_set() {
+        let mut operation = completed_operation("op-import", "2026-01-01T00:00:00Z");
+        operation.state = OperationState::Running;
+        operation.spec = StoredOperationSpec::ImportMessages(ImportMessagesOperation {
+            account_id: "default".to_owned(),
+            mailbox: "INBOX".to_owned(),
+            uidvalidity: 1,
+            format: StoreFormat::Eml,
+            source: "mail".to_owned(),
+            entries: vec![SourceEntry {
+                path: "mail/a.eml".into(),
+                range: None,
+                flags: Vec::new(),
+                internal_date: None,
+            }],
+            seen_message_ids: Arc::new(["<a@example.com>".to_owned()].into()),
+            results: Vec::new(),
+        });
+
+        let step = next_operation_step(&mut operation).expect("import step");
+        let (
+            OperationStep::ImportMessage {
+                seen_message_ids, ..
+            },
+            StoredOperationSpec::ImportMessages(spec),
+        ) = (&step, &mut operation.spec)
+        else {
+            panic!("expected an import step");
+        };
+        assert!(Arc::ptr_eq(seen_message_ids, &spec.seen_message_ids));
+        drop(step);
+        assert_eq!(Arc::strong_count(&spec.seen_message_ids), 1);
+    }
+
     #[test]
     fn cached_uidvalidity_requires_server_selected_value() {
         let error = cached_uidvalidity(&None).expect_err("missing cached uidvalidity must fail");
@@ -0,0 +1,553 @@
+//! SMTP submission client
+//!
+//! Sends finished messages through an account's submission server over
+//! implicit TLS or STARTTLS, authenticating with `AUTH PLAIN` or `AUTH LOGIN`.
+//! Credentials and message content never cross an unencrypted connection, and
+//! every network step is bounded by the configured timeouts.
+
+use std::time::Duration;
+
+use base64::Engine;
+use rustls_pki_types::ServerName;
+use secrecy::ExposeSecret;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_rustls::TlsConnector;
+
+use crate::config::{ServerConfig, SmtpConfig, SmtpSecurity};
+use crate::errors::{AppError, AppResult};
+use crate::imap::tls_connector;
+use crate::mailstore::normalize_crlf;
+
+/// Longest reply line accepted from the server, including CRLF
+const MAX_REPLY_LINE_BYTES: u64 = 4_096;
+/// Most lines accepted in one multiline reply
+const MAX_REPLY_LINES: usize = 100;
+
+/// Sender and recipients handed to the server, separate from the headers
+#[derive(Debug, Clone)]
+pub struct Envelope {
+    /// `MAIL FROM` address
+    pub from: String,
+    /// `RCPT TO` addresses, including blind copies
+    pub recipients: Vec<String>,
+}
+
+/// Submit a message through the account's SMTP server.
+///
+/// `message` is sent as-is apart from line-ending normalization and dot
+/// stuffing, so `Bcc` must already be removed.
+///
+/// # Errors
+///
+/// - `InvalidInput` if the host is invalid for TLS SNI or the message exceeds
+///   the server's `SIZE` limit
+/// - `Timeout` if any connection phase or command times out
+/// - `AuthFailed` if the server rejects the credentials
+/// - `Internal` for connection failures, a missing `STARTTLS` offer, or
+///   rejected commands
+pub async fn send_message(
+    server: &ServerConfig,
+    smtp: &SmtpConfig,
+    envelope: &Envelope,
+    message: &[u8],
+) -> AppResult<()> {
+    send_message_with(server, smtp, &tls_connector(server), envelope, message).await
+}
+
+/// Submit a message using a caller-supplied TLS connector.
+///
+/// # Errors
+///
+/// Same as [`send_message`].
+pub async fn send_message_with(
+    server: &ServerConfig,
+    smtp: &SmtpConfig,
+    connector: &TlsConnector,
+    envelope: &Envelope,
+    message: &[u8],
+) -> AppResult<()> {
+    let connect_duration = Duration::from_millis(server.connect_timeout_ms);
+    let greeting_duration = Duration::from_millis(server.greeting_timeout_ms);
+    let socket_duration = Duration::from_millis(server.socket_timeout_ms);
+    let server_name = ServerName::try_from(smtp.host.clone())
+        .map_err(|_| AppError::InvalidInput("invalid SMTP host for TLS SNI".to_owned()))?;
+    let helo = helo_name(&envelope.from);
+
+    let mut tcp = timeout(
+        connect_duration,
+        TcpStream::connect((smtp.host.as_str(), smtp.port)),
+    )
+    .await
+    .map_err(|_| AppError::Timeout("SMTP tcp connect timeout".to_owned()))
+    .and_then(|r| r.map_err(|e| AppError::Internal(format!("SMTP tcp connect failed: {e}"))))?;
+
+    if smtp.security == SmtpSecurity::StartTls {
+        let mut plain = SmtpStream::new(tcp, greeting_duration);
+        plain.expect_greeting().await?;
+        let capabilities = plain.ehlo(&helo).await?;
+        if capabilities.get("STARTTLS").is_none() {
+            return Err(AppError::Internal(
+                "SMTP server does not offer STARTTLS".to_owned(),
+            ));
+        }
+        plain.command("STARTTLS", "STARTTLS", 2).await?;
+        tcp = plain.into_inner()?;
+    }
+
+    let tls = timeout(greeting_duration, connector.connect(server_name, tcp))
+        .await
+        .map_err(|_| AppError::Timeout("SMTP TLS handshake timeout".to_owned()))
+        .and_then(|r| {
+            r.map_err(|e| AppError::Internal(format!("SMTP TLS handshake failed: {e}")))
+        })?;
+    let mut stream = SmtpStream::new(tls, greeting_duration);
+    if smtp.security == SmtpSecurity::Tls {
+        stream.expect_greeting().await?;
+    }
+    stream.io_timeout = socket_duration;
+    submit(
+        &mut stream,
+        &helo,
+        &smtp.user,
+        smtp.pass.expose_secret(),
+        envelope,
+        message,
+    )
+    .await
+}
+
+/// Run `EHLO`, `AUTH`, and the mail transaction on an established connection
+async fn submit<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut SmtpStream<S>,
+    helo: &str,
+    user: &str,
+    pass: &str,
+    envelope: &Envelope,
+    message: &[u8],
+) -> AppResult<()> {
+    let capabilities = stream.ehlo(helo).await?;
+    authenticate(stream, &capabilities, user, pass).await?;
+
+    let data = dot_stuff(message);
+    let mut mail_from = format!("MAIL FROM:<{}>", envelope.from);
+    if !data.is_ascii() {
+        if capabilities.get("8BITMIME").is_none() {
+            return Err(AppError::InvalidInput(
+                "message contains 8-bit data but the SMTP server does not advertise 8BITMIME"
+                    .to_owned(),
+            ));
+        }
+        mail_from.push_str(" BODY=8BITMIME");
+    }
+    if let Some(limit) = capabilities.get("SIZE") {
+        if let Ok(limit) = limit.trim().parse::<usize>()
+            && limit > 0
+            && message.len() > limit
+        {
+            return Err(AppError::InvalidInput(format!(
+                "message is {} bytes; the SMTP server accepts at most {limit}",
+                message.len()
+            )));
+        }
+        mail_from.push_str(&format!(" SIZE={}", message.len()));
+    }
+    stream.command("MAIL FROM", &mail_from, 2).await?;
+    for recipient in &envelope.recipients {
+        stream
+            .command("RCPT TO", &format!("RCPT TO:<{recipient}>"), 2)
+            .await?;
+    }
+    stream.command("DATA", "DATA", 3).await?;
+    stream.write(&data).await?;
+    let reply = stream.read_reply().await?;
+    if reply.code / 100 != 2 {
+        return Err(rejected("DATA", &reply));
+    }
+    let _ = stream.command("QUIT", "QUIT", 2).await;
+    Ok(())
+}
+
+/// Authenticate with the first supported mechanism
+///
+/// Credentials are always configured, so a server that does not offer `AUTH`
+/// is an error rather than a reason to submit unauthenticated.
+async fn authenticate<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut SmtpStream<S>,
+    capabilities: &Capabilities,
+    user: &str,
+    pass: &str,
+) -> AppResult<()> {
+    let Some(mechanisms) = capabilities.get("AUTH") else {
+        return Err(AppError::Internal(
+            "SMTP server does not offer AUTH; refusing to submit unauthenticated".to_owned(),
+        ));
+    };
+    let mechanisms = mechanisms.to_ascii_uppercase();
+    let offers = |name: &str| mechanisms.split_whitespace().any(|m| m == name);
+    let b64 = |value: &str| base64::engine::general_purpose::STANDARD.encode(value);
+    if offers("PLAIN") {
+        stream
+            .command(
+                "AUTH",
+                &format!("AUTH PLAIN {}", b64(&format!("\0{user}\0{pass}"))),
+                2,
+            )
+            .await?;
+    } else if offers("LOGIN") {
+        stream.command("AUTH", "AUTH LOGIN", 3).await?;
+        stream.command("AUTH", &b64(user), 3).await?;
+        stream.command("AUTH", &b64(pass), 2).await?;
+    } else {
+        return Err(AppError::Internal(format!(
+            "SMTP server offers no supported AUTH mechanism (PLAIN or LOGIN): {mechanisms}"
+        )));
+    }
+    Ok(())
+}
+
+/// One complete server reply
+#[derive(Debug)]
+struct Reply {
+    code: u16,
+    lines: Vec<String>,
+}
+
+/// `EHLO` extension keywords with their parameters
+struct Capabilities(Vec<(String, String)>);
+
+impl Capabilities {
+    fn get(&self, keyword: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(keyword))
+            .map(|(_, params)| params.as_str())
+    }
+}
+
+/// Line-oriented SMTP connection with per-step timeouts
+struct SmtpStream<S> {
+    reader: BufReader<S>,
+    io_timeout: Duration,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> SmtpStream<S> {
+    fn new(stream: S, io_timeout: Duration) -> Self {
+        Self {
+            reader: BufReader::new(stream),
+            io_timeout,
+        }
+    }
+
+    /// Release the socket for a TLS upgrade, refusing data sent ahead of it
+    fn into_inner(self) -> AppResult<S> {
+        if !self.reader.buffer().is_empty() {
+            return Err(AppError::Internal(
+                "SMTP server sent data before the TLS handshake".to_owned(),
+            ));
+        }
+        Ok(self.reader.into_inner())
+    }
+
+    async fn expect_greeting(&mut self) -> AppResult<()> {
+        let reply = self.read_reply().await?;
+        if reply.code != 220 {
+            return Err(rejected("greeting", &reply));
+        }
+        Ok(())
+    }
+
+    async fn ehlo(&mut self, helo: &str) -> AppResult<Capabilities> {
+        let reply = self.command("EHLO", &format!("EHLO {helo}"), 2).await?;
+        Ok(Capabilities(
+            reply
+                .lines
+                .iter()
+                .skip(1)
+                .map(|line| match line.split_once(' ') {
+                    Some((name, params)) => (name.to_owned(), params.to_owned()),
+                    None => (line.clone(), String::new()),
+                })
+                .collect(),
+        ))
+    }
+
+    /// Send one command line and require a reply in the given class (2 or 3)
+    ///
+    /// `step` names the command in errors, so `line` itself is never echoed.
+    async fn command(&mut self, step: &str, line: &str, class: u16) -> AppResult<Reply> {
+        self.write(format!("{line}\r\n").as_bytes()).await?;
+        let reply = self.read_reply().await?;
+        if reply.code / 100 != class {
+            return Err(rejected(step, &reply));
+        }
+        Ok(reply)
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> AppResult<()> {
+        let stream = self.reader.get_mut();
+        timeout(self.io_timeout, async {
+            stream.write_all(bytes).await?;
+            stream.flush().await
+        })
+        .await
+        .map_err(|_| AppError::Timeout("SMTP write timed out".to_owned()))
+        .and_then(|r| r.map_err(|e| AppError::Internal(format!("SMTP write failed: {e}"))))
+    }
+
+    async fn read_reply(&mut self) -> AppResult<Reply> {
+        let mut lines = Vec::new();
+        loop {
+            let mut buf = Vec::new();
+            let read = timeout(
+                self.io_timeout,
+                (&mut self.reader)
+                    .take(MAX_REPLY_LINE_BYTES)
+                    .read_until(b'\n', &mut buf),
+            )
+            .await
+            .map_err(|_| AppError::Timeout("SMTP reply timed out".to_owned()))
+            .and_then(|r| r.map_err(|e| AppError::Internal(format!("SMTP read failed: {e}"))))?;
+            if read == 0 {
+                return Err(AppError::Internal(
+                    "SMTP server closed the connection".to_owned(),
+                ));
+            }
+            if !buf.ends_with(b"\n") {
+                return Err(AppError::Internal("SMTP reply line too long".to_owned()));
+            }
+            let line = String::from_utf8_lossy(&buf);
+            let line = line.trim_end_matches(['\r', '\n']);
+            let code = line
+                .get(..3)
+                .and_then(|code| code.parse::<u16>().ok())
+                .filter(|code| (200..600).contains(code))
+                .ok_or_else(|| AppError::Internal(format!("malformed SMTP reply: {line}")))?;
+            lines.push(line.get(4..).unwrap_or_default().to_owned());
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok(Reply { code, lines });
+            }
+            if lines.len() >= MAX_REPLY_LINES {
+                return Err(AppError::Internal(
+                    "SMTP reply has too many lines".to_owned(),
+                ));
+            }
+        }
+    }
+}
+
+/// Error for a reply outside the expected class
+fn rejected(step: &str, reply: &Reply) -> AppError {
+    let text = reply.lines.join(" ");
+    if step == "AUTH" && matches!(reply.code, 530 | 534 | 535 | 538) {
+        AppError::AuthFailed(format!(
+            "SMTP server rejected credentials: {} {text}",
+            reply.code
+        ))
+    } else {
+        AppError::Internal(format!("SMTP {step} rejected: {} {text}", reply.code))
+    }
+}
+
+/// Domain announced in `EHLO`: the sender's domain, else `localhost`
+fn helo_name(from: &str) -> String {
+    from.rsplit_once('@')
+        .map(|(_, domain)| domain)
+        .filter(|domain| {
+            !domain.is_empty()
+                && domain
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-.[]:".contains(c))
+        })
+        .unwrap_or("localhost")
+        .to_owned()
+}
+
+/// Message body for `DATA`: CRLF line endings, leading dots doubled, and the
+/// terminating `.` line appended
+fn dot_stuff(message: &[u8]) -> Vec<u8> {
+    let normalized = normalize_crlf(message);
+    let mut out = Vec::with_capacity(normalized.len() + 64);
+    let mut line_start = true;
+    for &byte in &normalized {
+        if line_start && byte == b'.' {
+            out.push(b'.');
+        }
+        out.push(byte);
+        line_start = byte == b'\n';
+    }
+    if !out.is_empty() && !out.ends_with(b"\r\n") {
+        out.extend_from_slice(b"\r\n");
+    }
+    out.extend_from_slice(b".\r\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream, duplex};
+
+    use super::{Envelope, SmtpStream, dot_stuff, submit};
+    use crate::errors::AppError;
+
+    /// Minimal SMTP server answering one session; returns the lines it received
+    async fn fake_server(stream: DuplexStream, capabilities: &'static str) -> Vec<String> {
+        let mut reader = BufReader::new(stream);
+        let mut received = Vec::new();
+        let mut in_data = false;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await.expect("read") == 0 {
+                return received;
+            }
+            let line = line.trim_end().to_owned();
+            received.push(line.clone());
+            let reply = if in_data {
+                if line != "." {
+                    continue;
+                }
+                in_data = false;
+                "250 queued\r\n".to_owned()
+            } else if line.starts_with("EHLO") {
+                format!("250-fake.example.com\r\n{capabilities}")
+            } else if line.starts_with("AUTH PLAIN") {
+                "235 ok\r\n".to_owned()
+            } else if line.starts_with("RCPT") && line.contains("reject") {
+                "550 no such user\r\n".to_owned()
+            } else if line == "DATA" {
+                in_data = true;
+                "354 go ahead\r\n".to_owned()
+            } else if line == "QUIT" {
+                reader
+                    .get_mut()
+                    .write_all(b"221 bye\r\n")
+                    .await
+                    .expect("write");
+                return received;
+            } else {
+                "250 ok\r\n".to_owned()
+            };
+            reader
+                .get_mut()
+                .write_all(reply.as_bytes())
+                .await
+                .expect("write");
+        }
+    }
+
+    fn envelope(recipients: &[&str]) -> Envelope {
+        Envelope {
+            from: "me@example.com".to_owned(),
+            recipients: recipients.iter().map(|r| (*r).to_owned()).collect(),
+        }
+    }
+
+    #[test]
+    fn dot_stuffing_doubles_leading_dots_and_terminates() {
+        assert_eq!(
+            dot_stuff(b"Subject: x\n\n.hidden\nend"),
+            b"Subject: x\r\n\r\n..hidden\r\nend\r\n.\r\n".to_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn submits_with_auth_plain_and_size() {
+        let (client, server) = duplex(64 * 1024);
+        let server = tokio::spawn(fake_server(
+            server,
+            "250-AUTH LOGIN PLAIN\r\n250 SIZE 1000\r\n",
+        ));
+        let mut stream = SmtpStream::new(client, Duration::from_secs(5));
+        submit(
+            &mut stream,
+            "example.com",
+            "user",
+            "pass",
+            &envelope(&["a@example.com", "b@example.com"]),
+            b"Subject: hi\r\n\r\n.dot\r\n",
+        )
+        .await
+        .expect("submission succeeds");
+        drop(stream);
+
+        let received = server.await.expect("server task");
+        assert_eq!(received[0], "EHLO example.com");
+        assert_eq!(received[1], "AUTH PLAIN AHVzZXIAcGFzcw==");
+        assert_eq!(received[2], "MAIL FROM:<me@example.com> SIZE=21");
+        assert_eq!(received[3], "RCPT TO:<a@example.com>");
+        assert_eq!(received[4], "RCPT TO:<b@example.com>");
+        assert!(received.contains(&"..dot".to_owned()));
+        assert_eq!(received.last().map(String::as_str), Some("QUIT"));
+    }
+
+    #[tokio::test]
+    async fn rejected_recipient_fails_without_data() {
+        let (client, server) = duplex(64 * 1024);
+        let server = tokio::spawn(fake_server(server, "250-8BITMIME\r\n250 AUTH PLAIN\r\n"));
+        let mut stream = SmtpStream::new(client, Duration::from_secs(5));
+        let error = submit(
+            &mut stream,
+            "example.com",
+            "user",
+            "pass",
+            &envelope(&["reject@example.com"]),
+            b"Subject: hi\r\n\r\nbody\r\n",
+        )
+        .await
+        .expect_err("recipient is rejected");
+        assert!(
+            matches!(&error, AppError::Internal(message) if message.contains("RCPT TO rejected: 550"))
+        );
+        drop(stream);
+
+        let received = server.await.expect("server task");
+        assert!(!received.contains(&"DATA".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn refuses_to_submit_without_auth() {
+        let (client, server) = duplex(64 * 1024);
+        let server = tokio::spawn(fake_server(server, "250 8BITMIME\r\n"));
+        let mut stream = SmtpStream::new(client, Duration::from_secs(5));
+        let error = submit(
+            &mut stream,
+            "example.com",
+            "user",
+            "pass",
+            &envelope(&["a@example.com"]),
+            b"Subject: hi\r\n\r\nbody\r\n",
+        )
+        .await
+        .expect_err("server offers no AUTH");
+        assert!(matches!(&error, AppError::Internal(message) if message.contains("AUTH")));
+        drop(stream);
+
+        let received = server.await.expect("server task");
+        assert!(!received.iter().any(|line| line.starts_with("MAIL FROM")));
+    }
+
+    #[tokio::test]
+    async fn rejects_8bit_data_without_8bitmime() {
+        let (client, server) = duplex(64 * 1024);
+        let server = tokio::spawn(fake_server(server, "250 AUTH PLAIN\r\n"));
+        let mut stream = SmtpStream::new(client, Duration::from_secs(5));
+        let error = submit(
+            &mut stream,
+            "example.com",
+            "user",
+            "pass",
+            &envelope(&["a@example.com"]),
+            "Subject: hi\r\n\r\ncaf\u{e9}\r\n".as_bytes(),
+        )
+        .await
+        .expect_err("8-bit data needs 8BITMIME");
+        assert!(matches!(&error, AppError::InvalidInput(message) if message.contains("8BITMIME")));
+        drop(stream);
+
+        let received = server.await.expect("server task");
+        assert!(!received.iter().any(|line| line.starts_with("MAIL FROM")));
+    }
+}